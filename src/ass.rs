@@ -0,0 +1,369 @@
+use std::path::Path;
+use std::fs;
+use anyhow::{Result, anyhow};
+use crate::srt_merger;
+use crate::subtitle::SubtitleEntry;
+
+/// 缺少 Format 行时 [Events] 使用的默认字段顺序
+const DEFAULT_EVENT_FORMAT: &[&str] = &[
+    "Layer", "Start", "End", "Style", "Name", "MarginL", "MarginR", "MarginV", "Effect", "Text",
+];
+
+/// ASS 时间精度为百分之一秒，小于该差值的时间修改不写回
+const TIME_TOLERANCE: f64 = 0.005;
+
+/// 保留全部样式信息的 ASS 文件
+/// 
+/// 除 [Events] 中的 `Dialogue` 行外，其余内容（脚本信息、[V4+ Styles]、注释、字体等）原样保存。
+/// 对外只暴露对白的纯文本和时间，写回时保留样式引用、边距、特效和未改动部分的覆盖标签
+#[derive(Debug, Clone)]
+pub struct AssDocument {
+    lines: Vec<AssLine>,
+    bom: bool,
+    line_ending: &'static str,
+}
+
+#[derive(Debug, Clone)]
+enum AssLine {
+    Raw(String),
+    Dialogue(Dialogue),
+}
+
+/// 一条对白，`fields` 为按 Format 顺序排列的全部字段
+#[derive(Debug, Clone)]
+struct Dialogue {
+    prefix: String,  // 字段之前的部分，如 `Dialogue: `
+    fields: Vec<String>,
+    start: usize,  // 各字段在 `fields` 中的位置
+    end: usize,
+    text: usize,
+}
+
+impl Dialogue {
+    fn text(&self) -> &str {
+        &self.fields[self.text]
+    }
+}
+
+impl AssDocument {
+    pub fn parse(content: &str) -> Result<Self> {
+        let bom = content.starts_with('\u{feff}');
+        let content = content.trim_start_matches('\u{feff}');
+        let line_ending = if content.contains("\r\n") { "\r\n" } else { "\n" };
+        
+        let mut lines = Vec::new();
+        let mut in_events = false;
+        let mut format: Vec<String> = DEFAULT_EVENT_FORMAT.iter().map(|s| s.to_string()).collect();
+        
+        for line in content.lines() {
+            let trimmed = line.trim();
+            if trimmed.starts_with('[') {
+                in_events = trimmed.eq_ignore_ascii_case("[Events]");
+            } else if in_events {
+                if let Some(fields) = trimmed.strip_prefix("Format:") {
+                    format = fields.split(',').map(|f| f.trim().to_string()).collect();
+                } else if let Some(fields) = trimmed.strip_prefix("Dialogue:") {
+                    let fields_start = trimmed.len() - fields.trim_start().len();
+                    let prefix = trimmed[..fields_start].to_string();
+                    lines.push(AssLine::Dialogue(parse_dialogue(prefix, fields.trim_start(), &format)?));
+                    continue;
+                }
+            }
+            lines.push(AssLine::Raw(line.to_string()));
+        }
+        
+        Ok(Self { lines, bom, line_ending })
+    }
+    
+    /// 读取 ASS 文件，与 SRT 一样用 [`srt_merger::decode_text`] 识别 GBK、UTF-16 和带 BOM 的文件
+    /// 
+    /// 保存时统一写为 UTF-8，原文件带 BOM 时保留 BOM
+    pub fn load(path: &Path) -> Result<Self> {
+        let bytes = fs::read(path)?;
+        let mut document = Self::parse(&srt_merger::decode_text(&bytes))?;
+        document.bom |= encoding_rs::Encoding::for_bom(&bytes).is_some();
+        Ok(document)
+    }
+    
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_string())?;
+        Ok(())
+    }
+    
+    fn dialogues(&self) -> impl Iterator<Item = &Dialogue> {
+        self.lines.iter().filter_map(|line| match line {
+            AssLine::Dialogue(dialogue) => Some(dialogue),
+            AssLine::Raw(_) => None,
+        })
+    }
+    
+    /// 按文件顺序返回对白，文本去掉覆盖标签，`\N` 转为换行
+    pub fn subtitles(&self) -> Vec<SubtitleEntry> {
+        self.dialogues()
+            .enumerate()
+            .map(|(i, dialogue)| SubtitleEntry {
+                index: i + 1,
+                start_time: parse_ass_time(&dialogue.fields[dialogue.start]).unwrap_or(0.0),
+                end_time: parse_ass_time(&dialogue.fields[dialogue.end]).unwrap_or(0.0),
+                text: plain_text(dialogue.text()),
+                locked: false,
+                words: Vec::new(),
+            })
+            .collect()
+    }
+    
+    /// 把编辑后的文本和时间写回对白，返回改动的对白数
+    /// 
+    /// `subtitles` 必须与 `subtitles()` 一一对应（不能增删字幕）。
+    /// 文本只替换改动的部分，改动范围之外的覆盖标签保持不变。
+    /// 原时间无法解析的字段保持原样，不会被 `subtitles()` 给出的 0 覆盖
+    pub fn apply_subtitles(&mut self, subtitles: &[SubtitleEntry]) -> Result<usize> {
+        let count = self.dialogues().count();
+        if subtitles.len() != count {
+            return Err(anyhow!(
+                "ASS file has {} dialogue lines but {} subtitles were given (only text and timing edits can be written back)",
+                count,
+                subtitles.len()
+            ));
+        }
+        
+        let mut changed = 0;
+        let dialogues = self.lines.iter_mut().filter_map(|line| match line {
+            AssLine::Dialogue(dialogue) => Some(dialogue),
+            AssLine::Raw(_) => None,
+        });
+        
+        for (dialogue, entry) in dialogues.zip(subtitles) {
+            let mut modified = false;
+            
+            for (field, time) in [(dialogue.start, entry.start_time), (dialogue.end, entry.end_time)] {
+                let Some(old) = parse_ass_time(&dialogue.fields[field]) else { continue };
+                if (old - time).abs() >= TIME_TOLERANCE {
+                    dialogue.fields[field] = format_ass_time(time);
+                    modified = true;
+                }
+            }
+            
+            let text = replace_plain_text(dialogue.text(), &entry.text);
+            if text != dialogue.text() {
+                dialogue.fields[dialogue.text] = text;
+                modified = true;
+            }
+            
+            if modified {
+                changed += 1;
+            }
+        }
+        
+        Ok(changed)
+    }
+}
+
+impl std::fmt::Display for AssDocument {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.bom {
+            write!(f, "\u{feff}")?;
+        }
+        for line in &self.lines {
+            match line {
+                AssLine::Raw(raw) => write!(f, "{}", raw)?,
+                AssLine::Dialogue(dialogue) => write!(f, "{}{}", dialogue.prefix, dialogue.fields.join(","))?,
+            }
+            write!(f, "{}", self.line_ending)?;
+        }
+        Ok(())
+    }
+}
+
+/// 按 Format 拆分对白字段，最后的 Text 字段可以包含逗号
+fn parse_dialogue(prefix: String, fields: &str, format: &[String]) -> Result<Dialogue> {
+    let position = |name: &str| format.iter()
+        .position(|f| f.eq_ignore_ascii_case(name))
+        .ok_or_else(|| anyhow!("ASS event format has no {} field", name));
+    
+    let fields: Vec<String> = fields
+        .splitn(format.len(), ',')
+        .map(|f| f.to_string())
+        .collect();
+    if fields.len() != format.len() {
+        return Err(anyhow!("Invalid Dialogue line: expected {} fields, found {}", format.len(), fields.len()));
+    }
+    
+    Ok(Dialogue {
+        prefix,
+        fields,
+        start: position("Start")?,
+        end: position("End")?,
+        text: position("Text")?,
+    })
+}
+
+/// 解析 ASS 时间（H:MM:SS.cc）为秒数
+fn parse_ass_time(time_str: &str) -> Option<f64> {
+    let parts: Vec<&str> = time_str.trim().split(':').collect();
+    if parts.len() != 3 {
+        return None;
+    }
+    
+    let hours: f64 = parts[0].parse().ok()?;
+    let minutes: f64 = parts[1].parse().ok()?;
+    let seconds: f64 = parts[2].parse().ok()?;
+    
+    Some(hours * 3600.0 + minutes * 60.0 + seconds)
+}
+
+/// 将秒数转换为 ASS 时间格式（H:MM:SS.cc）
+fn format_ass_time(seconds: f64) -> String {
+    let centis = (seconds.max(0.0) * 100.0).round() as u64;
+    format!("{}:{:02}:{:02}.{:02}", centis / 360000, centis / 6000 % 60, centis / 100 % 60, centis % 100)
+}
+
+/// 对白文本中的每个可见字符及其在原文中的字节范围，覆盖标签 `{...}` 不计入
+fn visible_chars(raw: &str) -> Vec<(char, usize, usize)> {
+    let mut chars = Vec::new();
+    let mut iter = raw.char_indices().peekable();
+    
+    while let Some((i, c)) = iter.next() {
+        match c {
+            '{' => {
+                // 跳过整个覆盖标签
+                for (_, c) in iter.by_ref() {
+                    if c == '}' {
+                        break;
+                    }
+                }
+            }
+            '\\' => match iter.peek() {
+                Some(&(j, 'N' | 'n')) => {
+                    iter.next();
+                    chars.push(('\n', i, j + 1));
+                }
+                Some(&(j, 'h')) => {
+                    iter.next();
+                    chars.push(('\u{a0}', i, j + 1));
+                }
+                _ => chars.push((c, i, i + 1)),
+            },
+            _ => chars.push((c, i, i + c.len_utf8())),
+        }
+    }
+    
+    chars
+}
+
+/// 去掉覆盖标签后的纯文本
+fn plain_text(raw: &str) -> String {
+    visible_chars(raw).into_iter().map(|(c, _, _)| c).collect()
+}
+
+/// 把纯文本写成 ASS 文本（换行为 `\N`，不换行空格为 `\h`）
+fn escape_text(text: &str) -> String {
+    text.replace('\n', "\\N").replace('\u{a0}', "\\h")
+}
+
+/// 用新的纯文本替换对白文本，只改动与原文不同的部分
+/// 
+/// 比较新旧纯文本的公共前缀和后缀，只替换中间改动的范围，
+/// 该范围之外（包括紧邻改动处）的覆盖标签都保留
+fn replace_plain_text(raw: &str, new_text: &str) -> String {
+    let old = visible_chars(raw);
+    let new: Vec<char> = new_text.chars().collect();
+    
+    let prefix = old.iter().zip(&new).take_while(|((a, _, _), b)| a == *b).count();
+    let max_suffix = old.len().min(new.len()) - prefix;
+    let suffix = old.iter().rev().zip(new.iter().rev())
+        .take(max_suffix)
+        .take_while(|((a, _, _), b)| a == *b)
+        .count();
+    
+    if prefix == old.len() && prefix == new.len() {
+        return raw.to_string();
+    }
+    
+    // 原文中被替换的字节范围
+    let (raw_start, raw_end) = if prefix + suffix < old.len() {
+        (old[prefix].1, old[old.len() - suffix - 1].2)
+    } else if prefix < old.len() {
+        (old[prefix].1, old[prefix].1)  // 只有插入，插到下一个字符之前
+    } else {
+        (raw.len(), raw.len())  // 追加到末尾
+    };
+    
+    let replacement: String = new[prefix..new.len() - suffix].iter().collect();
+    format!("{}{}{}", &raw[..raw_start], escape_text(&replacement), &raw[raw_end..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const SAMPLE: &str = "[Script Info]\r\nTitle: test\r\n\r\n[V4+ Styles]\r\nFormat: Name, Fontname, Fontsize\r\nStyle: Default,Arial,20\r\n\r\n[Events]\r\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\r\nComment: 0,0:00:00.00,0:00:01.00,Default,,0,0,0,,note\r\nDialogue: 0,0:00:01.00,0:00:03.50,Default,,0,0,0,,{\\pos(10,20)}Helo, world\\Nsecond line\r\nDialogue: 0,0:00:04.00,0:00:05.00,Sign,,0,0,0,,{\\k20}ka{\\k30}ra{\\k40}oke\r\n";
+    
+    #[test]
+    fn test_round_trip_preserves_everything() {
+        let doc = AssDocument::parse(SAMPLE).unwrap();
+        assert_eq!(doc.to_string(), SAMPLE);
+        
+        let subs = doc.subtitles();
+        assert_eq!(subs.len(), 2);
+        assert_eq!(subs[0].text, "Helo, world\nsecond line");
+        assert!((subs[0].end_time - 3.5).abs() < 1e-9);
+        assert_eq!(subs[1].text, "karaoke");
+    }
+    
+    #[test]
+    fn test_apply_text_edits_keeps_tags() {
+        let mut doc = AssDocument::parse(SAMPLE).unwrap();
+        let mut subs = doc.subtitles();
+        subs[0].text = "Hello, world\nsecond line".to_string();
+        subs[1].text = "karaOKE".to_string();
+        
+        assert_eq!(doc.apply_subtitles(&subs).unwrap(), 2);
+        let output = doc.to_string();
+        assert!(output.contains("Dialogue: 0,0:00:01.00,0:00:03.50,Default,,0,0,0,,{\\pos(10,20)}Hello, world\\Nsecond line\r\n"));
+        assert!(output.contains("Dialogue: 0,0:00:04.00,0:00:05.00,Sign,,0,0,0,,{\\k20}ka{\\k30}ra{\\k40}OKE\r\n"));
+        
+        // 只改时间
+        subs[1].start_time = 4.25;
+        assert_eq!(doc.apply_subtitles(&subs).unwrap(), 1);
+        assert!(doc.to_string().contains("Dialogue: 0,0:00:04.25,0:00:05.00,Sign"));
+        
+        // 不能增删字幕
+        subs.pop();
+        assert!(doc.apply_subtitles(&subs).is_err());
+    }
+    
+    #[test]
+    fn test_unparsable_time_left_alone() {
+        let content = "[Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\nDialogue: 0,bad,0:00:02.00,Default,,0,0,0,,typo\n";
+        let mut doc = AssDocument::parse(content).unwrap();
+        let mut subs = doc.subtitles();
+        subs[0].text = "fixed".to_string();
+        
+        assert_eq!(doc.apply_subtitles(&subs).unwrap(), 1);
+        assert!(doc.to_string().contains("Dialogue: 0,bad,0:00:02.00,Default,,0,0,0,,fixed\n"));
+    }
+    
+    #[test]
+    fn test_load_non_utf8() {
+        let dir = std::env::temp_dir().join(format!("whisper_ass_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("styled.ass");
+        let content = "[Events]\r\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\r\nDialogue: 0,0:00:01.00,0:00:02.00,Default,,0,0,0,,{\\an8}你好，世界\r\n";
+        
+        let (gbk, _, _) = encoding_rs::GBK.encode(content);
+        fs::write(&path, &gbk).unwrap();
+        assert_eq!(AssDocument::load(&path).unwrap().subtitles()[0].text, "你好，世界");
+        
+        // UTF-16 带 BOM：保存为带 BOM 的 UTF-8
+        let mut utf16 = vec![0xff, 0xfe];
+        utf16.extend(content.encode_utf16().flat_map(|unit| unit.to_le_bytes()));
+        fs::write(&path, &utf16).unwrap();
+        let doc = AssDocument::load(&path).unwrap();
+        assert_eq!(doc.subtitles()[0].text, "你好，世界");
+        doc.save(&path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), format!("\u{feff}{}", content));
+        
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -2,10 +2,232 @@ use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink, Source};
 use std::fs::{self, File};
 use std::io::BufReader;
 use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use std::process::Command;
 use anyhow::Result;
+use crate::error::{command_output, WhisperError};
+use crate::{config, ffmpeg};
+
+/// 判定爆音的采样幅度（接近 0 dBFS）
+const CLIP_THRESHOLD: i16 = i16::MAX - 1;
+/// 电平表每次更新包含的样本数
+const METER_WINDOW: usize = 2048;
+/// 检测到爆音后保持警告的时长
+const CLIP_HOLD: Duration = Duration::from_secs(1);
+/// 普通 seek 每次预处理的音频时长（秒）
+const SEEK_WINDOW_SECONDS: f64 = 30.0;
+/// 保持音高变速时每段预处理的原始音频时长（秒），播放一段时在后台准备下一段
+const STRETCH_WINDOW_SECONDS: f64 = 120.0;
+/// 精确 seek 时输入端粗略定位比目标提前的秒数，剩下的部分逐帧解码到目标位置
+const ACCURATE_SEEK_MARGIN: f64 = 5.0;
+/// 支持的播放速度范围（FFmpeg atempo 单级滤镜的范围）
+pub const MIN_SPEED: f32 = 0.5;
+pub const MAX_SPEED: f32 = 2.0;
+
+/// 播放电平（线性幅度 0~1）
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AudioLevels {
+    pub peak: f32,
+    pub rms: f32,
+    pub clipping: bool,  // 最近是否出现爆音
+}
+
+#[derive(Default)]
+struct LevelMeter {
+    peak: f32,
+    rms: f32,
+    last_clip: Option<std::time::Instant>,
+}
+
+/// 统计播放样本电平的音源包装
+struct MeteredSource<S> {
+    inner: S,
+    meter: Arc<Mutex<LevelMeter>>,
+    peak: i16,
+    sum_squares: f64,
+    count: usize,
+    clipped: bool,
+}
+
+impl<S> MeteredSource<S> {
+    fn new(inner: S, meter: Arc<Mutex<LevelMeter>>) -> Self {
+        MeteredSource { inner, meter, peak: 0, sum_squares: 0.0, count: 0, clipped: false }
+    }
+}
+
+impl<S: Source<Item = i16>> Iterator for MeteredSource<S> {
+    type Item = i16;
+    
+    fn next(&mut self) -> Option<i16> {
+        let sample = self.inner.next()?;
+        let magnitude = sample.saturating_abs();
+        self.peak = self.peak.max(magnitude);
+        self.clipped |= magnitude >= CLIP_THRESHOLD;
+        let value = sample as f64 / i16::MAX as f64;
+        self.sum_squares += value * value;
+        self.count += 1;
+        
+        // 每个窗口发布一次，避免逐样本加锁
+        if self.count == METER_WINDOW {
+            if let Ok(mut meter) = self.meter.lock() {
+                meter.peak = self.peak as f32 / i16::MAX as f32;
+                meter.rms = (self.sum_squares / self.count as f64).sqrt() as f32;
+                if self.clipped {
+                    meter.last_clip = Some(std::time::Instant::now());
+                }
+            }
+            self.peak = 0;
+            self.sum_squares = 0.0;
+            self.count = 0;
+            self.clipped = false;
+        }
+        
+        Some(sample)
+    }
+}
+
+impl<S: Source<Item = i16>> Source for MeteredSource<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
+    
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+    
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+    
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+}
+
+/// 依次播放的多段音源，开始播放一段时就在后台准备下一段
+/// 
+/// `load(i)` 返回第 i 段（从 1 开始，第 0 段在创建时给出），返回 None 表示已到结尾。
+/// 各段的声道数和采样率必须相同。下一段还没准备好时等待它完成
+struct ChainedSource<S> {
+    current: S,
+    next: Option<Receiver<Option<S>>>,
+    load: Arc<dyn Fn(usize) -> Option<S> + Send + Sync>,
+    index: usize,
+}
+
+impl<S: Source + Send + 'static> ChainedSource<S>
+where
+    S::Item: rodio::Sample,
+{
+    fn new(first: S, load: impl Fn(usize) -> Option<S> + Send + Sync + 'static) -> Self {
+        let mut source = ChainedSource { current: first, next: None, load: Arc::new(load), index: 0 };
+        source.prefetch();
+        source
+    }
+    
+    fn prefetch(&mut self) {
+        let (tx, rx) = channel();
+        let load = self.load.clone();
+        let index = self.index + 1;
+        std::thread::spawn(move || {
+            let _ = tx.send(load(index));
+        });
+        self.next = Some(rx);
+    }
+}
+
+impl<S: Source + Send + 'static> Iterator for ChainedSource<S>
+where
+    S::Item: rodio::Sample,
+{
+    type Item = S::Item;
+    
+    fn next(&mut self) -> Option<S::Item> {
+        loop {
+            if let Some(sample) = self.current.next() {
+                return Some(sample);
+            }
+            self.current = self.next.take()?.recv().ok().flatten()?;
+            self.index += 1;
+            self.prefetch();
+        }
+    }
+}
+
+impl<S: Source + Send + 'static> Source for ChainedSource<S>
+where
+    S::Item: rodio::Sample,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.current.current_frame_len()
+    }
+    
+    fn channels(&self) -> u16 {
+        self.current.channels()
+    }
+    
+    fn sample_rate(&self) -> u32 {
+        self.current.sample_rate()
+    }
+    
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// FFmpeg 预处理出的临时 WAV，播放完（音源被丢弃）后删除
+struct TempWindow {
+    decoder: Option<Decoder<BufReader<File>>>,
+    path: PathBuf,
+}
+
+impl TempWindow {
+    fn open(path: PathBuf) -> Result<Self> {
+        match File::open(&path).map_err(anyhow::Error::from).and_then(|file| Ok(Decoder::new(BufReader::new(file))?)) {
+            Ok(decoder) => Ok(TempWindow { decoder: Some(decoder), path }),
+            Err(e) => {
+                let _ = fs::remove_file(&path);
+                Err(e)
+            }
+        }
+    }
+}
+
+impl Iterator for TempWindow {
+    type Item = i16;
+    
+    fn next(&mut self) -> Option<i16> {
+        self.decoder.as_mut()?.next()
+    }
+}
+
+impl Source for TempWindow {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.decoder.as_ref()?.current_frame_len()
+    }
+    
+    fn channels(&self) -> u16 {
+        self.decoder.as_ref().map_or(2, |decoder| decoder.channels())
+    }
+    
+    fn sample_rate(&self) -> u32 {
+        self.decoder.as_ref().map_or(44100, |decoder| decoder.sample_rate())
+    }
+    
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+impl Drop for TempWindow {
+    fn drop(&mut self) {
+        // 先关闭文件，Windows 上打开的文件无法删除
+        self.decoder.take();
+        let _ = fs::remove_file(&self.path);
+    }
+}
 
 pub struct AudioPlayer {
     audio_path: PathBuf,
@@ -13,10 +235,15 @@ pub struct AudioPlayer {
     stream_handle: OutputStreamHandle,
     sink: Arc<Mutex<Sink>>,
     duration: f64,
-    start_time: Arc<Mutex<std::time::Instant>>,
+    base_position: Arc<Mutex<f64>>,  // 当前 sink 中音源起点对应的文件位置（秒）
     paused_at: Arc<Mutex<Option<f64>>>,
     is_playing: Arc<Mutex<bool>>,
     temp_seek_file: Arc<Mutex<Option<PathBuf>>>,  // 临时seek文件路径
+    meter: Arc<Mutex<LevelMeter>>,  // 播放电平
+    speed: f32,  // 播放速度
+    preserve_pitch: bool,  // 变速时保持音高
+    time_scale: f64,  // 当前音源每秒对应的原文件秒数（保持音高时音源已被拉伸）
+    accurate_seek: bool,  // seek 时先粗略定位再逐帧解码到目标位置
 }
 
 impl AudioPlayer {
@@ -29,12 +256,15 @@ impl AudioPlayer {
         let source = Decoder::new(BufReader::new(file))?;
         let duration = source.total_duration()
             .map(|d| d.as_secs_f64())
+            .filter(|&d| d > 0.0)
+            .or_else(|| ffmpeg::get_audio_duration(path).ok())
             .unwrap_or(0.0);
         
         // 重新加载音频用于播放
         let file = File::open(path)?;
         let source = Decoder::new(BufReader::new(file))?;
-        sink.append(source);
+        let meter = Arc::new(Mutex::new(LevelMeter::default()));
+        sink.append(MeteredSource::new(source, meter.clone()));
         sink.pause();
         
         Ok(AudioPlayer {
@@ -43,32 +273,30 @@ impl AudioPlayer {
             stream_handle,
             sink: Arc::new(Mutex::new(sink)),
             duration,
-            start_time: Arc::new(Mutex::new(std::time::Instant::now())),
+            base_position: Arc::new(Mutex::new(0.0)),
             paused_at: Arc::new(Mutex::new(Some(0.0))),
             is_playing: Arc::new(Mutex::new(false)),
             temp_seek_file: Arc::new(Mutex::new(None)),
+            meter,
+            speed: 1.0,
+            preserve_pitch: false,
+            time_scale: 1.0,
+            accurate_seek: false,
         })
     }
     
     pub fn play(&mut self) {
+        let sink_empty = self.sink.lock().map(|sink| sink.empty()).unwrap_or(false);
+        if sink_empty {
+            // 如果 sink 为空（播放结束），从暂停位置重新加载
+            // 使用新的 sink，使播放位置从新音源的起点开始计数
+            let current_pos = self.paused_at.lock().unwrap().unwrap_or(0.0);
+            self.seek(current_pos);
+        }
+        
         if let Ok(sink) = self.sink.lock() {
-            if sink.empty() {
-                // 如果 sink 为空（可能因为 seek 操作），重新加载
-                if let Ok(file) = File::open(&self.audio_path) {
-                    if let Ok(source) = Decoder::new(BufReader::new(file)) {
-                        let current_pos = self.paused_at.lock().unwrap().unwrap_or(0.0);
-                        // 跳过前面的部分
-                        let source = source.skip_duration(Duration::from_secs_f64(current_pos));
-                        sink.append(source);
-                    }
-                }
-            }
-            
             sink.play();
             
-            // 更新开始时间
-            let paused_position = self.paused_at.lock().unwrap().unwrap_or(0.0);
-            *self.start_time.lock().unwrap() = std::time::Instant::now() - Duration::from_secs_f64(paused_position);
             *self.paused_at.lock().unwrap() = None;
             *self.is_playing.lock().unwrap() = true;
         }
@@ -85,42 +313,58 @@ impl AudioPlayer {
         }
     }
     
-    /// 使用FFmpeg创建快速seek文件
-    /// 这样可以避免rodio的skip_duration性能问题
-    fn create_seek_segment(&self, position: f64) -> Result<PathBuf> {
-        let temp_dir = std::env::temp_dir();
-        let temp_file = temp_dir.join(format!("whisper_seek_{}.wav", 
-            std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_millis()));
-        
-        // 使用FFmpeg从目标位置开始提取音频
-        // 只提取接下来的一段（比如30秒），这样文件更小，加载更快
-        let duration_to_extract = (self.duration - position).min(30.0);
-        
-        let output = Command::new("ffmpeg")
-            .arg("-ss")
-            .arg(position.to_string())
-            .arg("-i")
-            .arg(&self.audio_path)
-            .arg("-t")
-            .arg(duration_to_extract.to_string())
-            .arg("-acodec")
-            .arg("pcm_s16le")
-            .arg("-ar")
-            .arg("44100")
-            .arg("-ac")
-            .arg("2")
-            .arg("-y")
-            .arg(&temp_file)
-            .output()?;
-        
-        if !output.status.success() {
-            return Err(anyhow::anyhow!("FFmpeg seek failed"));
-        }
-        
-        Ok(temp_file)
+    /// 设置播放速度（0.5 ~ 2.0）
+    /// 
+    /// 默认由 rodio 直接变速，音高随速度变化。`preserve_pitch` 为 true 时改用 FFmpeg 的
+    /// `atempo` 滤镜逐段（每段 2 分钟）预先处理为临时文件再播放：音高不变，
+    /// 但每次 seek 或改变速度都要等待 FFmpeg 处理完第一段（通常几百毫秒），之后各段在后台处理
+    pub fn set_speed(&mut self, speed: f32, preserve_pitch: bool) {
+        let position = self.position();
+        self.speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+        self.preserve_pitch = preserve_pitch;
+        self.seek(position);
+    }
+    
+    /// 设置精确 seek，下次 seek 时生效
+    /// 
+    /// 只影响解码器不支持直接定位、改用 FFmpeg 预处理的情况（见 [`AudioPlayer::seek`]）。
+    /// 默认只把 `-ss` 放在 `-i` 前面，FFmpeg 按索引快速定位。没有索引的 VBR MP3 等格式
+    /// 定位可能有偏差，听到的内容和显示的位置对不上；精确模式在 `-i` 前先定位到目标前
+    /// `ACCURATE_SEEK_MARGIN` 秒，再用 `-i` 后的 `-ss` 逐帧解码到目标位置，稍慢但位置准确
+    pub fn set_accurate_seek(&mut self, accurate: bool) {
+        self.accurate_seek = accurate;
+    }
+    
+    /// 变速时是否需要预先拉伸音频
+    fn stretching(&self) -> bool {
+        self.preserve_pitch && self.speed != 1.0
+    }
+    
+    /// 使用FFmpeg创建快速seek文件，见 [`create_seek_segment`]
+    fn create_seek_segment(&self, position: f64, tempo: Option<f32>) -> Result<PathBuf> {
+        create_seek_segment(&self.audio_path, self.duration, position, tempo, self.accurate_seek)
+    }
+    
+    /// 保持音高变速的音源：从 `position` 开始每 `STRETCH_WINDOW_SECONDS` 秒拉伸为一段，
+    /// 第一段在当前线程处理（失败时返回错误），之后各段在播放前一段时于后台处理
+    fn stretched_source(&self, position: f64, tempo: f32) -> Result<ChainedSource<TempWindow>> {
+        let first = TempWindow::open(self.create_seek_segment(position, Some(tempo))?)?;
+        let audio_path = self.audio_path.clone();
+        let duration = self.duration;
+        let accurate = self.accurate_seek;
+        Ok(ChainedSource::new(first, move |index| {
+            let start = position + index as f64 * STRETCH_WINDOW_SECONDS;
+            if start >= duration {
+                return None;
+            }
+            match create_seek_segment(&audio_path, duration, start, Some(tempo), accurate).and_then(TempWindow::open) {
+                Ok(window) => Some(window),
+                Err(e) => {
+                    eprintln!("保持音高变速预处理下一段失败: {}", e);
+                    None
+                }
+            }
+        }))
     }
     
     /// 清理旧的临时seek文件
@@ -132,6 +376,36 @@ impl AudioPlayer {
         }
     }
     
+    /// 用新的 sink 替换当前 sink，保持原来的播放/暂停状态
+    fn install_sink(&mut self, sink: Sink, position: f64, time_scale: f64) {
+        *self.base_position.lock().unwrap() = position;
+        self.time_scale = time_scale;
+        
+        let was_playing = *self.is_playing.lock().unwrap();
+        if was_playing {
+            sink.play();
+            *self.paused_at.lock().unwrap() = None;
+        } else {
+            sink.pause();
+            *self.paused_at.lock().unwrap() = Some(position);
+        }
+        
+        *self.sink.lock().unwrap() = sink;
+    }
+    
+    /// 直接解码原文件并跳到指定位置（位置较大时较慢）
+    /// 
+    /// 只在解码器不支持定位、FFmpeg 也失败时使用
+    fn open_from_start(&self, position: f64) -> Option<impl Source<Item = i16> + Send + 'static> {
+        let file = File::open(&self.audio_path).ok()?;
+        let source = Decoder::new(BufReader::new(file)).ok()?;
+        Some(source.skip_duration(Duration::from_secs_f64(position)))
+    }
+    
+    /// 跳到 `position`（秒），保持原来的播放/暂停状态
+    /// 
+    /// 优先用解码器自身的定位（[`open_seeked`]）直接从原文件播放，耗时与位置无关；
+    /// 解码器不支持定位的格式和保持音高变速时用 FFmpeg 预处理接下来的一段
     pub fn seek(&mut self, position: f64) {
         // 限制position在有效范围内
         let position = position.max(0.0).min(self.duration);
@@ -142,95 +416,233 @@ impl AudioPlayer {
         }
         
         // 创建新的 sink
-        if let Ok(new_sink) = Sink::try_new(&self.stream_handle) {
-            // 对于接近开头的位置，直接使用原文件
-            if position < 1.0 {
-                if let Ok(file) = File::open(&self.audio_path) {
+        let Ok(new_sink) = Sink::try_new(&self.stream_handle) else { return };
+        
+        // 保持音高变速：播放 FFmpeg 拉伸后的音频，sink 本身不变速
+        if self.stretching() {
+            match self.stretched_source(position, self.speed) {
+                Ok(source) => {
+                    self.cleanup_temp_seek_file();
+                    new_sink.append(MeteredSource::new(source, self.meter.clone()));
+                    self.install_sink(new_sink, position, self.speed as f64);
+                    return;
+                }
+                Err(e) => {
+                    eprintln!("保持音高变速失败，回退到普通变速: {}", e);
+                }
+            }
+        }
+        
+        // rodio 直接变速，`get_pos` 是输出时间（2 倍速下 5 秒对应原文件 10 秒），需乘以速度换算为原文件时间
+        new_sink.set_speed(self.speed);
+        
+        match open_seeked(&self.audio_path, position) {
+            Ok(source) => {
+                self.cleanup_temp_seek_file();
+                new_sink.append(MeteredSource::new(source, self.meter.clone()));
+                self.install_sink(new_sink, position, self.speed as f64);
+                return;
+            }
+            Err(e) => {
+                eprintln!("解码器无法直接定位，改用 FFmpeg: {}", e);
+            }
+        }
+        
+        // 对于接近开头的位置，直接使用原文件
+        if position < 1.0 {
+            if let Some(source) = self.open_from_start(position) {
+                new_sink.append(MeteredSource::new(source, self.meter.clone()));
+                self.install_sink(new_sink, position, self.speed as f64);
+            }
+            return;
+        }
+        
+        // 对于较大的seek，使用FFmpeg预先处理
+        // 这样可以避免rodio的skip_duration性能问题
+        match self.create_seek_segment(position, None) {
+            Ok(seek_file) => {
+                // 先清理旧的临时文件
+                self.cleanup_temp_seek_file();
+                
+                if let Ok(file) = File::open(&seek_file) {
                     if let Ok(source) = Decoder::new(BufReader::new(file)) {
-                        let source = source.skip_duration(Duration::from_secs_f64(position));
-                        new_sink.append(source);
-                        
-                        let was_playing = *self.is_playing.lock().unwrap();
-                        if was_playing {
-                            new_sink.play();
-                            *self.start_time.lock().unwrap() = std::time::Instant::now() - Duration::from_secs_f64(position);
-                            *self.paused_at.lock().unwrap() = None;
-                        } else {
-                            new_sink.pause();
-                            *self.paused_at.lock().unwrap() = Some(position);
-                        }
+                        new_sink.append(MeteredSource::new(source, self.meter.clone()));
+                        self.install_sink(new_sink, position, self.speed as f64);
                         
-                        *self.sink.lock().unwrap() = new_sink;
+                        // 保存临时文件路径以便后续清理
+                        *self.temp_seek_file.lock().unwrap() = Some(seek_file);
                     }
                 }
-            } else {
-                // 对于较大的seek，使用FFmpeg预先处理
-                // 这样可以避免rodio的skip_duration性能问题
-                match self.create_seek_segment(position) {
-                    Ok(seek_file) => {
-                        // 先清理旧的临时文件
-                        self.cleanup_temp_seek_file();
-                        
-                        if let Ok(file) = File::open(&seek_file) {
-                            if let Ok(source) = Decoder::new(BufReader::new(file)) {
-                                new_sink.append(source);
-                                
-                                let was_playing = *self.is_playing.lock().unwrap();
-                                if was_playing {
-                                    new_sink.play();
-                                    *self.start_time.lock().unwrap() = std::time::Instant::now() - Duration::from_secs_f64(position);
-                                    *self.paused_at.lock().unwrap() = None;
-                                } else {
-                                    new_sink.pause();
-                                    *self.paused_at.lock().unwrap() = Some(position);
-                                }
-                                
-                                *self.sink.lock().unwrap() = new_sink;
-                                
-                                // 保存临时文件路径以便后续清理
-                                *self.temp_seek_file.lock().unwrap() = Some(seek_file);
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!("快速seek失败，回退到慢速模式: {}", e);
-                        // 如果FFmpeg失败，回退到原来的方法
-                        if let Ok(file) = File::open(&self.audio_path) {
-                            if let Ok(source) = Decoder::new(BufReader::new(file)) {
-                                let source = source.skip_duration(Duration::from_secs_f64(position));
-                                new_sink.append(source);
-                                
-                                let was_playing = *self.is_playing.lock().unwrap();
-                                if was_playing {
-                                    new_sink.play();
-                                    *self.start_time.lock().unwrap() = std::time::Instant::now() - Duration::from_secs_f64(position);
-                                    *self.paused_at.lock().unwrap() = None;
-                                } else {
-                                    new_sink.pause();
-                                    *self.paused_at.lock().unwrap() = Some(position);
-                                }
-                                
-                                *self.sink.lock().unwrap() = new_sink;
-                            }
-                        }
-                    }
+            }
+            Err(e) => {
+                eprintln!("快速seek失败，回退到慢速模式: {}", e);
+                // 如果FFmpeg失败，回退到原来的方法
+                if let Some(source) = self.open_from_start(position) {
+                    new_sink.append(MeteredSource::new(source, self.meter.clone()));
+                    self.install_sink(new_sink, position, self.speed as f64);
                 }
             }
         }
     }
     
+    /// 当前播放位置
+    /// 
+    /// 由 sink 实际已输出的样本数计算（`Sink::get_pos`），不受缓冲和系统负载影响
     pub fn position(&self) -> f64 {
-        if let Some(paused) = *self.paused_at.lock().unwrap() {
-            paused
-        } else {
-            let elapsed = self.start_time.lock().unwrap().elapsed().as_secs_f64();
-            elapsed.min(self.duration)
-        }
+        let played = self.sink.lock().unwrap().get_pos();
+        playback_position(
+            *self.base_position.lock().unwrap(),
+            played.mul_f64(self.time_scale),
+            *self.paused_at.lock().unwrap(),
+            self.duration,
+        )
     }
     
     pub fn duration(&self) -> f64 {
         self.duration
     }
+    
+    /// 最近播放样本的电平，暂停时为 0
+    pub fn levels(&self) -> AudioLevels {
+        if self.paused_at.lock().unwrap().is_some() {
+            return AudioLevels::default();
+        }
+        
+        let meter = self.meter.lock().unwrap();
+        AudioLevels {
+            peak: meter.peak,
+            rms: meter.rms,
+            clipping: meter.last_clip.is_some_and(|t| t.elapsed() < CLIP_HOLD),
+        }
+    }
+}
+
+/// 扫描整个文件，返回爆音（达到 0 dBFS）样本所占比例
+pub fn scan_clipping(path: &Path) -> Result<f64> {
+    let file = File::open(path)?;
+    let source = Decoder::new(BufReader::new(file))?;
+    
+    let mut total = 0u64;
+    let mut clipped = 0u64;
+    for sample in source {
+        total += 1;
+        if sample.saturating_abs() >= CLIP_THRESHOLD {
+            clipped += 1;
+        }
+    }
+    
+    if total == 0 {
+        return Ok(0.0);
+    }
+    
+    Ok(clipped as f64 / total as f64)
+}
+
+/// 计算整个文件的波形概览：把音频按时间平均分成 `buckets` 段，返回每段的峰值（线性幅度 0~1）
+/// 
+/// 需要解码整个文件，应在后台线程调用并缓存结果。MP3 等没有时长头信息的格式用 ffprobe 获取时长
+pub fn compute_waveform(path: &Path, buckets: usize) -> Result<Vec<f32>> {
+    let file = File::open(path)?;
+    let source = Decoder::new(BufReader::new(file))?;
+    let duration = match source.total_duration() {
+        Some(duration) => duration.as_secs_f64(),
+        None => ffmpeg::get_audio_duration(path)?,
+    };
+    let total_samples = (duration * source.sample_rate() as f64 * source.channels() as f64).ceil() as usize;
+    Ok(bucket_peaks(source, total_samples, buckets))
+}
+
+/// 把约 `total_samples` 个样本按顺序平均分到 `buckets` 段，每段取峰值；超出的样本计入最后一段
+fn bucket_peaks(samples: impl Iterator<Item = i16>, total_samples: usize, buckets: usize) -> Vec<f32> {
+    let mut peaks = vec![0.0f32; buckets];
+    if buckets == 0 {
+        return peaks;
+    }
+    let per_bucket = total_samples.div_ceil(buckets).max(1);
+    for (i, sample) in samples.enumerate() {
+        let bucket = (i / per_bucket).min(buckets - 1);
+        peaks[bucket] = peaks[bucket].max(sample.saturating_abs() as f32 / i16::MAX as f32);
+    }
+    peaks
+}
+
+/// 打开音频文件并用解码器自身的定位跳到 `position`（秒）
+/// 
+/// WAV 按样本偏移直接定位，MP3 等由 symphonia 按帧头定位后精确到样本，都不需要解码前面的内容，
+/// 长文件的末尾也能立即开始播放。解码器不支持定位时返回错误
+fn open_seeked(path: &Path, position: f64) -> Result<Decoder<BufReader<File>>> {
+    let mut source = Decoder::new(BufReader::new(File::open(path)?))?;
+    source.try_seek(Duration::from_secs_f64(position.max(0.0)))
+        .map_err(|e| anyhow::anyhow!("Seek failed: {}", e))?;
+    Ok(source)
+}
+
+/// 使用FFmpeg从 `position` 开始提取一段音频到临时文件
+/// 这样可以避免rodio的skip_duration性能问题
+/// 
+/// `tempo` 不为 None 时同时用 atempo 滤镜变速（保持音高）
+fn create_seek_segment(audio_path: &Path, duration: f64, position: f64, tempo: Option<f32>, accurate: bool) -> Result<PathBuf> {
+    let temp_dir = std::env::temp_dir();
+    // 后台可能同时在准备下一段，文件名加上序号避免冲突
+    static COUNTER: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+    let temp_file = temp_dir.join(format!("whisper_seek_{}_{}_{}.wav",
+        std::process::id(),
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis(),
+        COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed)));
+    
+    // 使用FFmpeg从目标位置开始提取音频
+    // 只提取接下来的一段（比如30秒），这样文件更小，加载更快
+    let window = if tempo.is_some() { STRETCH_WINDOW_SECONDS } else { SEEK_WINDOW_SECONDS };
+    let duration_to_extract = (duration - position).min(window);
+    
+    let (input_seek, output_seek) = seek_offsets(position, accurate);
+    let mut cmd = Command::new(config::ffmpeg());
+    cmd.arg("-ss")
+        .arg(input_seek.to_string())
+        .arg("-i")
+        .arg(audio_path);
+    match tempo {
+        // 输出端的 -ss/-t 按拉伸后的时间计算，改为在拉伸前按原文件时间截取，相邻各段首尾相接
+        Some(tempo) => {
+            cmd.arg("-filter:a").arg(format!(
+                "atrim=start={}:duration={},asetpts=PTS-STARTPTS,atempo={}",
+                output_seek.unwrap_or(0.0), duration_to_extract, tempo,
+            ));
+        }
+        None => {
+            if let Some(output_seek) = output_seek {
+                cmd.arg("-ss").arg(output_seek.to_string());
+            }
+            cmd.arg("-t").arg(duration_to_extract.to_string());
+        }
+    }
+    cmd.arg("-acodec")
+        .arg("pcm_s16le")
+        .arg("-ar")
+        .arg("44100")
+        .arg("-ac")
+        .arg("2")
+        .arg("-y")
+        .arg(&temp_file);
+    let output = command_output(&mut cmd)?;
+    
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(WhisperError::process_failed("FFmpeg seek failed", stderr).into());
+    }
+    
+    Ok(temp_file)
+}
+
+/// 根据音源起点和已播放时长计算文件中的播放位置
+fn playback_position(base_position: f64, played: Duration, paused_at: Option<f64>, duration: f64) -> f64 {
+    match paused_at {
+        Some(paused) => paused,
+        None => (base_position + played.as_secs_f64()).min(duration),
+    }
 }
 
 impl Drop for AudioPlayer {
@@ -240,3 +652,156 @@ impl Drop for AudioPlayer {
     }
 }
 
+/// seek 到 `position` 时放在 `-i` 前后的 `-ss` 值
+/// 
+/// 精确模式下输入端只定位到目标前 `ACCURATE_SEEK_MARGIN` 秒，输出端的 `-ss` 补上剩下的部分
+fn seek_offsets(position: f64, accurate: bool) -> (f64, Option<f64>) {
+    if !accurate {
+        return (position, None);
+    }
+    let coarse = (position - ACCURATE_SEEK_MARGIN).max(0.0);
+    (coarse, Some(position - coarse))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::source::SineWave;
+    
+    #[test]
+    fn test_seek_long_file() {
+        // 1 小时 8kHz 单声道 WAV，数据部分是稀疏文件，不占用实际磁盘空间
+        let sample_rate: u32 = 8000;
+        let data_len = sample_rate * 2 * 3600;
+        let mut header = Vec::new();
+        header.extend_from_slice(b"RIFF");
+        header.extend_from_slice(&(36 + data_len).to_le_bytes());
+        header.extend_from_slice(b"WAVEfmt ");
+        header.extend_from_slice(&16u32.to_le_bytes());
+        header.extend_from_slice(&1u16.to_le_bytes());  // PCM
+        header.extend_from_slice(&1u16.to_le_bytes());  // 单声道
+        header.extend_from_slice(&sample_rate.to_le_bytes());
+        header.extend_from_slice(&(sample_rate * 2).to_le_bytes());
+        header.extend_from_slice(&2u16.to_le_bytes());
+        header.extend_from_slice(&16u16.to_le_bytes());
+        header.extend_from_slice(b"data");
+        header.extend_from_slice(&data_len.to_le_bytes());
+        
+        let path = std::env::temp_dir().join(format!("whisper_seek_test_{}.wav", std::process::id()));
+        let file = File::create(&path).unwrap();
+        std::io::Write::write_all(&mut &file, &header).unwrap();
+        file.set_len(header.len() as u64 + data_len as u64).unwrap();
+        
+        let start = std::time::Instant::now();
+        let mut source = open_seeked(&path, 3599.0).unwrap();
+        // 定位后只剩最后 1 秒
+        assert!(source.next().is_some());
+        let elapsed = start.elapsed();
+        assert_eq!(source.count() + 1, sample_rate as usize);
+        fs::remove_file(&path).unwrap();
+        
+        assert!(elapsed < Duration::from_millis(100), "seek took {:?}", elapsed);
+    }
+    
+    #[test]
+    fn test_bucket_peaks() {
+        // 前半段安静、后半段响亮的 1 秒音频
+        let tone = |amplitude: f32| SineWave::new(440.0).take_duration(Duration::from_millis(500)).amplify(amplitude).convert_samples::<i16>();
+        let quiet: Vec<i16> = tone(0.1).collect();
+        let loud: Vec<i16> = tone(1.0).collect();
+        let samples: Vec<i16> = quiet.iter().chain(&loud).copied().collect();
+        
+        let peaks = bucket_peaks(samples.iter().copied(), samples.len(), 4);
+        assert_eq!(peaks.len(), 4);
+        assert!(peaks[0] < 0.15 && peaks[1] < 0.15);
+        assert!(peaks[2] > 0.9 && peaks[3] > 0.9);
+        
+        // 实际样本比预计的多时计入最后一段，不会越界
+        assert_eq!(bucket_peaks([i16::MIN; 10].into_iter(), 4, 2), [1.0, 1.0]);
+        assert!(bucket_peaks(std::iter::empty(), 0, 0).is_empty());
+    }
+    
+    #[test]
+    fn test_position_follows_played_samples() {
+        // 不依赖音频设备：手动消费 sink 的输出，模拟播放 1.5 秒
+        let (sink, mut output) = Sink::new_idle();
+        let source = SineWave::new(440.0).take_duration(Duration::from_secs(5));
+        let sample_rate = source.sample_rate() as usize;
+        let channels = source.channels() as usize;
+        sink.append(source);
+        
+        for _ in 0..(sample_rate * channels * 3 / 2) {
+            output.next();
+        }
+        
+        // 从 10 秒处 seek 后播放了 1.5 秒
+        let position = playback_position(10.0, sink.get_pos(), None, 60.0);
+        assert!((position - 11.5).abs() < 0.1, "position = {}", position);
+        
+        // 暂停时返回暂停位置，且不超过总时长
+        assert_eq!(playback_position(10.0, sink.get_pos(), Some(3.0), 60.0), 3.0);
+        assert_eq!(playback_position(59.0, sink.get_pos(), None, 60.0), 60.0);
+        
+        // 音源播完后输出设备继续取样（静音），位置停在音源结尾，不会继续前进
+        for _ in 0..(sample_rate * channels * 10) {
+            output.next();
+        }
+        let position = playback_position(10.0, sink.get_pos(), None, 60.0);
+        assert!((position - 15.0).abs() < 0.1, "position = {}", position);
+    }
+    
+    #[test]
+    fn test_position_at_sink_speed() {
+        // rodio 变速时 `get_pos` 是输出时间，乘以速度才是原文件中的位置
+        let (sink, mut output) = Sink::new_idle();
+        let source = SineWave::new(440.0).take_duration(Duration::from_secs(10));
+        let sample_rate = source.sample_rate() as usize;
+        let channels = source.channels() as usize;
+        sink.set_speed(2.0);
+        sink.append(source);
+        
+        // 空闲 sink 的输出不重采样：播放 1.5 秒会消耗原文件 3 秒的样本
+        for _ in 0..(sample_rate * channels * 3) {
+            output.next();
+        }
+        
+        assert!((sink.get_pos().as_secs_f64() - 1.5).abs() < 0.1);
+        let position = playback_position(10.0, sink.get_pos().mul_f64(2.0), None, 60.0);
+        assert!((position - 13.0).abs() < 0.1, "position = {}", position);
+    }
+    
+    #[test]
+    fn test_chained_windows_play_past_boundary() {
+        // 每段 1 秒，共 3 段，模拟保持音高变速时逐段预处理的音频
+        let window = |_| SineWave::new(440.0).take_duration(Duration::from_secs(1));
+        let source = ChainedSource::new(window(0), move |index| (index < 3).then(|| window(index)));
+        let sample_rate = source.sample_rate() as usize;
+        let channels = source.channels() as usize;
+        
+        let (sink, mut output) = Sink::new_idle();
+        sink.append(source);
+        
+        // 播放到第二段中间：越过第一段的结尾后继续播放，位置连续
+        for _ in 0..(sample_rate * channels * 3 / 2) {
+            output.next();
+        }
+        assert!(!sink.empty());
+        let position = playback_position(10.0, sink.get_pos(), None, 60.0);
+        assert!((position - 11.5).abs() < 0.1, "position = {}", position);
+        
+        // 三段都播完后结束
+        for _ in 0..(sample_rate * channels * 2) {
+            output.next();
+        }
+        assert!(sink.empty());
+        assert!((sink.get_pos().as_secs_f64() - 3.0).abs() < 0.1);
+    }
+    
+    #[test]
+    fn test_seek_offsets() {
+        assert_eq!(seek_offsets(125.5, false), (125.5, None));
+        assert_eq!(seek_offsets(125.5, true), (120.5, Some(5.0)));
+        // 离开头不足 5 秒时全部在输出端定位
+        assert_eq!(seek_offsets(3.0, true), (0.0, Some(3.0)));
+    }
+}
@@ -0,0 +1,96 @@
+//! 外部程序（ffmpeg、ffprobe、whisper）的路径
+//! 
+//! 默认从 PATH 中查找。可以用环境变量 `WHISPER_FFMPEG`/`WHISPER_FFPROBE`/`WHISPER_BIN` 指定，
+//! 程序中设置的路径优先于环境变量
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+const FFMPEG_ENV_VAR: &str = "WHISPER_FFMPEG";
+const FFPROBE_ENV_VAR: &str = "WHISPER_FFPROBE";
+const WHISPER_ENV_VAR: &str = "WHISPER_BIN";
+
+/// 界面中设置的 ffmpeg 路径，None 时使用环境变量或 PATH
+static FFMPEG_PATH: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// 设置的 whisper 路径，None 时使用环境变量或 PATH
+static WHISPER_PATH: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// 设置 ffmpeg 路径，空路径或 None 恢复默认
+pub fn set_ffmpeg_path(path: Option<PathBuf>) {
+    *FFMPEG_PATH.write().unwrap() = path.filter(|p| !p.as_os_str().is_empty());
+}
+
+/// 启动 ffmpeg 使用的程序：界面设置 → `WHISPER_FFMPEG` → PATH 中的 `ffmpeg`
+pub fn ffmpeg() -> PathBuf {
+    configured_ffmpeg().unwrap_or_else(|| PathBuf::from("ffmpeg"))
+}
+
+/// 设置 whisper 路径，空路径或 None 恢复默认
+pub fn set_whisper_path(path: Option<PathBuf>) {
+    *WHISPER_PATH.write().unwrap() = path.filter(|p| !p.as_os_str().is_empty());
+}
+
+/// 启动 Whisper 使用的程序：设置的路径 → `WHISPER_BIN` → PATH 中的 `whisper`
+pub fn whisper() -> PathBuf {
+    WHISPER_PATH.read().unwrap().clone()
+        .or_else(|| env_path(WHISPER_ENV_VAR))
+        .unwrap_or_else(|| PathBuf::from("whisper"))
+}
+
+/// 启动 ffprobe 使用的程序：`WHISPER_FFPROBE` → 指定的 ffmpeg 旁边的 ffprobe → PATH 中的 `ffprobe`
+/// 
+/// Homebrew 等安装方式下两者在同一目录，只设置 ffmpeg 路径即可
+pub fn ffprobe() -> PathBuf {
+    env_path(FFPROBE_ENV_VAR)
+        .or_else(|| configured_ffmpeg().and_then(|ffmpeg| sibling_ffprobe(&ffmpeg)))
+        .unwrap_or_else(|| PathBuf::from("ffprobe"))
+}
+
+/// 把指定的 ffmpeg 所在目录加到 PATH 最前面，供 Whisper 等自己调用 ffmpeg 的程序使用
+/// 
+/// 没有指定路径（或路径不含目录）时返回 None，子进程沿用当前的 PATH
+pub fn search_path() -> Option<OsString> {
+    let ffmpeg = configured_ffmpeg()?;
+    let dir = ffmpeg.parent().filter(|dir| !dir.as_os_str().is_empty())?.to_path_buf();
+    let current = std::env::var_os("PATH").unwrap_or_default();
+    std::env::join_paths(std::iter::once(dir).chain(std::env::split_paths(&current))).ok()
+}
+
+fn configured_ffmpeg() -> Option<PathBuf> {
+    FFMPEG_PATH.read().unwrap().clone().or_else(|| env_path(FFMPEG_ENV_VAR))
+}
+
+fn env_path(name: &str) -> Option<PathBuf> {
+    std::env::var_os(name).filter(|v| !v.is_empty()).map(PathBuf::from)
+}
+
+/// 与 ffmpeg 同目录、同扩展名（Windows 上的 `.exe`）的 ffprobe，文件不存在时返回 None
+fn sibling_ffprobe(ffmpeg: &Path) -> Option<PathBuf> {
+    let dir = ffmpeg.parent().filter(|dir| !dir.as_os_str().is_empty())?;
+    let mut name = OsString::from("ffprobe");
+    if let Some(extension) = ffmpeg.extension() {
+        name.push(".");
+        name.push(extension);
+    }
+    Some(dir.join(name)).filter(|path| path.exists())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_sibling_ffprobe() {
+        let dir = std::env::temp_dir().join(format!("whisper_config_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("ffprobe.exe"), b"").unwrap();
+        
+        assert_eq!(sibling_ffprobe(&dir.join("ffmpeg.exe")), Some(dir.join("ffprobe.exe")));
+        assert_eq!(sibling_ffprobe(&dir.join("ffmpeg")), None);
+        assert_eq!(sibling_ffprobe(Path::new("ffmpeg")), None);
+        
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
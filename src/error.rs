@@ -0,0 +1,122 @@
+use std::fmt;
+use std::io;
+use std::process::{Command, Output};
+
+/// 核心流程（`pipeline`）的返回类型
+pub type Result<T> = std::result::Result<T, WhisperError>;
+
+/// 核心流程的错误类型
+/// 
+/// 界面和命令行可以按类型分别处理：缺少 FFmpeg/Whisper 时提示安装，
+/// 外部程序出错时可以重试，输入有误时不必重试
+#[derive(Debug)]
+pub enum WhisperError {
+    /// 外部程序（ffmpeg、ffprobe、whisper 等）不在 PATH 中
+    ToolNotFound { tool: String },
+    /// 外部程序存在但无法启动
+    SpawnFailed { tool: String, source: io::Error },
+    /// 外部程序运行失败，`stderr` 为其错误输出或原因
+    ProcessFailed { action: String, stderr: String },
+    /// 无法解析外部程序的输出或字幕文件
+    ParseError(String),
+    /// 读写文件失败
+    Io(io::Error),
+    /// 输入参数或文件不符合要求
+    InvalidInput(String),
+    /// 用户取消
+    Cancelled,
+}
+
+impl WhisperError {
+    /// 启动外部程序失败：程序不存在时为 `ToolNotFound`，否则为 `SpawnFailed`
+    pub fn spawn(tool: &str, source: io::Error) -> Self {
+        if source.kind() == io::ErrorKind::NotFound {
+            WhisperError::ToolNotFound { tool: tool.to_string() }
+        } else {
+            WhisperError::SpawnFailed { tool: tool.to_string(), source }
+        }
+    }
+    
+    /// 外部程序以失败状态退出
+    pub fn process_failed(action: impl Into<String>, stderr: impl Into<String>) -> Self {
+        WhisperError::ProcessFailed { action: action.into(), stderr: stderr.into() }
+    }
+    
+    /// 再试一次可能成功（外部程序偶发失败、读写失败），输入有误、缺少程序或已取消时为 false
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, WhisperError::ProcessFailed { .. } | WhisperError::SpawnFailed { .. } | WhisperError::Io(_))
+    }
+}
+
+impl fmt::Display for WhisperError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WhisperError::ToolNotFound { tool } => write!(f, "{} not found, please install it and make sure it is on PATH", tool),
+            WhisperError::SpawnFailed { tool, source } => write!(f, "Failed to start {}: {}", tool, source),
+            WhisperError::ProcessFailed { action, stderr } => write!(f, "{}: {}", action, stderr.trim()),
+            WhisperError::ParseError(message) => write!(f, "Parse error: {}", message),
+            WhisperError::Io(e) => write!(f, "{}", e),
+            WhisperError::InvalidInput(message) => write!(f, "{}", message),
+            WhisperError::Cancelled => write!(f, "Recognition cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for WhisperError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            WhisperError::SpawnFailed { source, .. } => Some(source),
+            WhisperError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for WhisperError {
+    fn from(e: io::Error) -> Self {
+        WhisperError::Io(e)
+    }
+}
+
+/// 各模块内部仍使用 `anyhow`：根因是 `WhisperError` 或 `io::Error` 时保留原类型，
+/// 其余的错误（参数检查等）视为输入有误
+impl From<anyhow::Error> for WhisperError {
+    fn from(e: anyhow::Error) -> Self {
+        let e = match e.downcast::<WhisperError>() {
+            Ok(whisper_error) => return whisper_error,
+            Err(e) => e,
+        };
+        match e.downcast::<io::Error>() {
+            Ok(io_error) => WhisperError::Io(io_error),
+            Err(e) => WhisperError::InvalidInput(e.to_string()),
+        }
+    }
+}
+
+/// 运行外部命令并等待结束，启动失败时按 [`WhisperError::spawn`] 分类
+pub fn command_output(command: &mut Command) -> Result<Output> {
+    let tool = command.get_program().to_string_lossy().to_string();
+    command.output().map_err(|e| WhisperError::spawn(&tool, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_error_kinds() {
+        let missing = command_output(&mut Command::new("surely-not-an-installed-tool")).unwrap_err();
+        assert!(matches!(&missing, WhisperError::ToolNotFound { tool } if tool == "surely-not-an-installed-tool"));
+        assert!(!missing.is_retryable());
+        
+        // 经过 anyhow 传递后仍能还原类型
+        let wrapped = anyhow::Error::new(WhisperError::process_failed("FFmpeg failed", "boom"));
+        let restored = WhisperError::from(wrapped);
+        assert!(restored.is_retryable());
+        assert_eq!(restored.to_string(), "FFmpeg failed: boom");
+        
+        let io = WhisperError::from(anyhow::Error::new(io::Error::other("disk")));
+        assert!(matches!(io, WhisperError::Io(_)));
+        assert!(matches!(WhisperError::from(anyhow::anyhow!("bad range")), WhisperError::InvalidInput(_)));
+    }
+}
@@ -1,43 +1,336 @@
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{Command, Stdio};
+use std::io::{BufRead, BufReader, Read};
 use std::fs;
+use std::sync::mpsc::Sender;
 use anyhow::{Result, anyhow};
+use serde::{Serialize, Deserialize};
+use crate::{config, ProgressMessage};
+use crate::error::{command_output, WhisperError};
+
+/// libmp3lame 的起始延迟（priming）样本数
+/// 
+/// LAME 编码器固定插入 576 个样本的编码延迟，解码端 MDCT 再引入 529 个样本，共 1105 个样本
+/// （44.1kHz 下约 25ms）。只有不带 LAME/Info 标签的 MP3 才需要扣除，见 `priming_delay`
+pub const LAME_PRIMING_SAMPLES: u32 = 1105;
+
+/// Whisper 内部使用的采样率，`extract_audio_for_whisper` 直接提取为该格式
+const WHISPER_SAMPLE_RATE: &str = "16000";
+
+/// 音频包之间的空隙超过该值（秒）视为时间戳不连续
+const PTS_GAP_SECONDS: f64 = 0.1;
+/// 平均帧率与标称帧率相差超过该比例视为可变帧率
+const VFR_TOLERANCE: f64 = 0.01;
 
 /// 使用 FFmpeg 检测并提取音频
 pub fn extract_audio(video_path: &Path) -> Result<PathBuf> {
+    extract_audio_with(video_path, false, false)
+}
+
+/// 提取音频并按时间戳重建时间轴：重新生成缺失的时间戳，空隙补静音、重叠处丢弃样本
+/// 
+/// 用于可变帧率或时间戳不连续的源文件（常见于手机录制），见 `timestamp_issues`。
+/// 直接提取会把不连续的音频首尾相接，切割和字幕时间都会逐渐偏离视频
+pub fn extract_audio_retimed(video_path: &Path) -> Result<PathBuf> {
+    extract_audio_with(video_path, true, false)
+}
+
+/// 提取为 Whisper 实际使用的 16kHz 单声道 WAV，`retime` 同 `extract_audio_retimed`
+/// 
+/// 文件只有 44.1kHz 立体声的约 1/5，提取和切割更快，识别结果不变；播放音质较差
+pub fn extract_audio_for_whisper(video_path: &Path, retime: bool) -> Result<PathBuf> {
+    extract_audio_with(video_path, retime, true)
+}
+
+fn extract_audio_with(video_path: &Path, retime: bool, whisper_format: bool) -> Result<PathBuf> {
     // 直接转换为 WAV 格式以确保最大兼容性
     let wav_path = video_path.with_extension("wav");
     
-    let output = Command::new("ffmpeg")
-        .arg("-i")
-        .arg(video_path)
+    let mut command = Command::new(config::ffmpeg());
+    if retime {
+        command.args(["-fflags", "+genpts"]);
+    }
+    command.arg("-i").arg(video_path);
+    if retime {
+        command.args(["-af", "aresample=async=1"]);
+    }
+    
+    let (sample_rate, channels) = if whisper_format { (WHISPER_SAMPLE_RATE, "1") } else { ("44100", "2") };
+    let output = command_output(command
         .arg("-vn")            // 不处理视频
         .arg("-acodec")
         .arg("pcm_s16le")      // 转换为 WAV PCM 16-bit
         .arg("-ar")
-        .arg("44100")          // 采样率 44.1kHz (标准音质)
+        .arg(sample_rate)      // 采样率 44.1kHz (标准音质) 或 16kHz
         .arg("-ac")
-        .arg("2")              // 立体声
+        .arg(channels)         // 立体声或单声道
         .arg("-y")             // 覆盖输出文件
-        .arg(&wav_path)
-        .output()?;
+        .arg(&wav_path))?;
     
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(anyhow!("FFmpeg failed to extract audio: {}", stderr));
+        return Err(WhisperError::process_failed("FFmpeg failed to extract audio", stderr).into());
     }
     
     Ok(wav_path)
 }
 
-/// 根据切割点切割音频文件
+/// 生成自检用的合成媒体文件：1 秒 440Hz 提示音，接着用 FFmpeg 的 `flite` 语音合成朗读 `text`
 /// 
-/// 注意：切割后会将 WAV 片段转换为 MP3 格式，并删除 WAV 片段
-/// 完整的 WAV 文件会保留用于播放
-pub fn cut_audio(audio_path: &Path, cut_points: &[f64]) -> Result<Vec<PathBuf>> {
+/// FFmpeg 编译时没有启用 libflite 时返回错误
+pub fn synthesize_test_media(text: &str, output_path: &Path) -> Result<()> {
+    let text = text.replace(['\'', ':', ',', ';', '\\'], " ");
+    let output = command_output(Command::new(config::ffmpeg())
+        .args(["-f", "lavfi", "-i", "sine=frequency=440:duration=1"])
+        .args(["-f", "lavfi", "-i", &format!("flite=text='{}'", text)])
+        .arg("-filter_complex")
+        .arg("[0:a]aresample=16000,aformat=channel_layouts=mono[tone];\
+              [1:a]aresample=16000,aformat=channel_layouts=mono[speech];\
+              [tone][speech]concat=n=2:v=0:a=1")
+        .arg("-y")
+        .arg(output_path))?;
+    
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(WhisperError::process_failed("FFmpeg failed to synthesize test speech", stderr).into());
+    }
+    
+    Ok(())
+}
+
+/// 网络读取超时（微秒），超时后 FFmpeg 报错退出而不是一直等待
+const STREAM_TIMEOUT_US: &str = "15000000";
+
+/// 检查 URL 是否为支持的网络流（http/https，包括 HLS `.m3u8`）
+pub fn validate_stream_url(url: &str) -> Result<()> {
+    let lower = url.trim().to_lowercase();
+    if !(lower.starts_with("http://") || lower.starts_with("https://")) {
+        return Err(anyhow!("Unsupported URL: {} (only http:// and https:// are supported)", url));
+    }
+    if lower.len() <= "https://".len() || lower.contains(char::is_whitespace) {
+        return Err(anyhow!("Invalid URL: {}", url));
+    }
+    Ok(())
+}
+
+/// 由 URL 推算本地文件名（取路径最后一段，去掉扩展名和非法字符）
+pub fn stream_file_stem(url: &str) -> String {
+    let without_query = url.split(['?', '#']).next().unwrap_or(url);
+    let without_scheme = without_query.split_once("://").map_or(without_query, |(_, rest)| rest);
+    let path = without_scheme.split_once('/').map_or("", |(_, path)| path);
+    let last = path.trim_end_matches('/').rsplit('/').next().unwrap_or("");
+    
+    let stem: String = Path::new(last).file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default()
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    
+    if stem.is_empty() {
+        "stream".to_string()
+    } else {
+        stem
+    }
+}
+
+/// 从网络流提取音频到 `output_path`（WAV）
+/// 
+/// 先用 ffprobe 探测：直播流没有时长，必须给出 `max_seconds` 限制录制长度。
+/// 探测和提取都设置了读取超时（`STREAM_TIMEOUT_US`），主机无法连接时报错而不是一直等待。
+/// 网络错误时返回 FFmpeg 输出的最后几行。`output_path` 已存在时报错，不会覆盖
+pub fn extract_audio_from_url(url: &str, output_path: &Path, max_seconds: Option<f64>) -> Result<PathBuf> {
+    validate_stream_url(url)?;
+    
+    let info = probe(url.as_ref(), &["-rw_timeout", STREAM_TIMEOUT_US])
+        .map_err(|e| WhisperError::process_failed("Cannot open stream", e.to_string()))?;
+    if info.duration.is_none() && max_seconds.is_none() {
+        return Err(WhisperError::InvalidInput("This looks like a live stream with no known duration, please set a max length".to_string()).into());
+    }
+    
+    let mut cmd = Command::new(config::ffmpeg());
+    cmd.args(["-rw_timeout", STREAM_TIMEOUT_US, "-i", url]);
+    if let Some(seconds) = max_seconds {
+        cmd.arg("-t").arg(seconds.to_string());
+    }
+    let output = command_output(cmd
+        .args(["-vn", "-acodec", "pcm_s16le", "-ar", "44100", "-ac", "2", "-n"])
+        .arg(output_path))?;
+    
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let tail: Vec<&str> = stderr.lines().rev().take(3).collect();
+        let message: Vec<&str> = tail.into_iter().rev().collect();
+        return Err(WhisperError::process_failed("FFmpeg failed to read stream", message.join(" | ")).into());
+    }
+    
+    Ok(output_path.to_path_buf())
+}
+
+/// 把音频的 `[start_time, end_time)` 区间提取为 WAV，用于不保留片段文件的时间段识别
+pub fn extract_range(audio_path: &Path, start_time: f64, end_time: f64, output_path: &Path) -> Result<()> {
+    if start_time >= end_time {
+        return Err(anyhow!("Start time must be less than end time"));
+    }
+    
+    let output = command_output(Command::new(config::ffmpeg())
+        .arg("-ss")
+        .arg(start_time.to_string())
+        .arg("-i")
+        .arg(audio_path)
+        .arg("-t")
+        .arg((end_time - start_time).to_string())
+        .args(["-vn", "-acodec", "pcm_s16le", "-y"])
+        .arg(output_path))?;
+    
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(WhisperError::process_failed("FFmpeg failed to extract range", stderr).into());
+    }
+    
+    Ok(())
+}
+
+/// 把音频的一个声道（0 为左声道，1 为右声道）提取为单声道 WAV
+/// 
+/// 使用 `pan` 滤镜而不是 `-map_channel`（后者已在 FFmpeg 7 中移除）
+pub fn extract_channel(audio_path: &Path, channel: usize, output_path: &Path) -> Result<()> {
+    let output = command_output(Command::new(config::ffmpeg())
+        .arg("-i")
+        .arg(audio_path)
+        .arg("-vn")
+        .arg("-af")
+        .arg(format!("pan=mono|c0=c{}", channel))
+        .args(["-acodec", "pcm_s16le", "-y"])
+        .arg(output_path))?;
+    
+    check_written(&output, output_path, "提取声道失败")
+}
+
+/// FFmpeg 输出中表示磁盘已满的错误信息
+const DISK_FULL_MARKERS: &[&str] = &["No space left on device", "Disk quota exceeded", "ENOSPC"];
+
+/// FFmpeg 写文件失败时删除不完整的输出，并把磁盘已满的情况单独报告
+fn write_failure(output_path: &Path, context: &str, stderr: &str) -> anyhow::Error {
+    let _ = fs::remove_file(output_path);
+    
+    if DISK_FULL_MARKERS.iter().any(|marker| stderr.contains(marker)) {
+        WhisperError::process_failed(context, format!("磁盘空间不足，无法写入 {:?}，请清理磁盘后重试", output_path)).into()
+    } else {
+        WhisperError::process_failed(context, stderr).into()
+    }
+}
+
+/// 检查 FFmpeg 的输出文件：命令失败或生成了空文件时删除输出并返回错误
+fn check_written(output: &std::process::Output, output_path: &Path, context: &str) -> Result<()> {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() {
+        return Err(write_failure(output_path, context, &stderr));
+    }
+    
+    let size = fs::metadata(output_path).map(|m| m.len()).unwrap_or(0);
+    if size == 0 {
+        return Err(write_failure(output_path, context, "输出文件为空"));
+    }
+    
+    Ok(())
+}
+
+/// 删除切割或转换中途失败时已经生成的片段
+fn remove_files(paths: &[PathBuf]) {
+    for path in paths {
+        let _ = fs::remove_file(path);
+    }
+}
+
+/// 切割得到的片段及其在原音频中的时间范围（秒）
+/// 
+/// 合并字幕时直接使用切割时的起始时间作为偏移，不再由切割点重新推算；随工作区保存
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Segment {
+    pub path: PathBuf,
+    pub start: f64,
+    /// 最后一段为 None，表示一直到音频结尾
+    pub end: Option<f64>,
+}
+
+/// 按切割点给各片段标上时间范围，`paths` 必须与切割点划分出的片段一一对应，数量不符时返回错误
+pub fn segments_from_cut_points(paths: Vec<PathBuf>, cut_points: &[f64]) -> Result<Vec<Segment>> {
+    if paths.len() != cut_points.len() + 1 {
+        return Err(anyhow!("{} segments do not match {} cut points", paths.len(), cut_points.len()));
+    }
+    
+    let starts = std::iter::once(0.0).chain(cut_points.iter().copied());
+    let ends = cut_points.iter().copied().map(Some).chain(std::iter::once(None));
+    Ok(paths.into_iter()
+        .zip(starts.zip(ends))
+        .map(|(path, (start, end))| Segment { path, start, end })
+        .collect())
+}
+
+/// 由切出的各段实际时长推算实际的切割点
+/// 
+/// 直接复制音频流时只能在编码帧边界切开，片段的实际长度与请求的不同，
+/// 依次累加前面各段的时长才是每段在原音频中真正的起点。无法读取时长的片段按请求的切割点计算
+fn measured_cut_points(cut_points: &[f64], durations: impl IntoIterator<Item = Option<f64>>) -> Vec<f64> {
+    let mut start = 0.0;
+    cut_points.iter()
+        .zip(durations)
+        .map(|(&requested, duration)| {
+            start = duration.map_or(requested, |duration| start + duration);
+            start
+        })
+        .collect()
+}
+
+/// 根据切割点切割音频文件，返回各片段及其时间范围
+/// 
+/// 注意：切割后会将 WAV 片段转换为 MP3 格式，并删除 WAV 片段；`lossless` 为 true 时跳过转换，
+/// 直接返回与原音频格式相同的片段（无损模式，片段没有编码延迟）。
+/// 完整的 WAV 文件会保留用于播放。任何一步失败都会删除已生成的片段，
+/// 避免不完整的文件被当作有效片段。`accurate` 见 [`cut_codec_args`]
+pub fn cut_audio(audio_path: &Path, cut_points: &[f64], lossless: bool, accurate: bool) -> Result<Vec<Segment>> {
+    cut_audio_with_progress(audio_path, cut_points, lossless, accurate, None)
+}
+
+/// 切割时的编码参数
+/// 
+/// `accurate` 为 false 时直接复制音频流，速度快，但 MP3、AAC 等压缩格式只能在编码帧边界切开，
+/// 片段会比请求的时间早开始；为 true 时重新编码（按输出扩展名选择默认编码器），
+/// 配合放在 `-i` 之后的 `-ss` 逐样本解码定位，切割点精确到样本。WAV 源两种方式结果相同
+pub fn cut_codec_args(accurate: bool) -> &'static [&'static str] {
+    if accurate {
+        &[]
+    } else {
+        &["-acodec", "copy"]
+    }
+}
+
+/// 同 [`cut_audio`]，并把每段的切割和转换进度发送到 `progress`
+/// 
+/// 切割和转换各算一步，共 `2 × 片段数` 步（无损模式不转换，共 `片段数` 步）：
+/// 每步开始时发送 `RealtimeOutput` 描述，完成后发送 `Progress`
+pub fn cut_audio_with_progress(
+    audio_path: &Path,
+    cut_points: &[f64],
+    lossless: bool,
+    accurate: bool,
+    progress: Option<&Sender<ProgressMessage>>,
+) -> Result<Vec<Segment>> {
+    let segment_count = cut_points.len() + 1;
+    let total_steps = if lossless { segment_count } else { segment_count * 2 };
+    let report = |message: Option<String>, step: usize| {
+        if let Some(tx) = progress {
+            let _ = tx.send(match message {
+                Some(message) => ProgressMessage::RealtimeOutput(message),
+                None => ProgressMessage::Progress { current: step, total: total_steps },
+            });
+        }
+    };
+    
     if cut_points.is_empty() {
         // 如果没有切割点，返回原始文件
-        return Ok(vec![audio_path.to_path_buf()]);
+        return Ok(vec![Segment { path: audio_path.to_path_buf(), start: 0.0, end: None }]);
     }
     
     let mut wav_segments = Vec::new();
@@ -57,51 +350,60 @@ pub fn cut_audio(audio_path: &Path, cut_points: &[f64]) -> Result<Vec<PathBuf>>
         let duration = cut_point - start_time;
         
         println!("   切割片段 {} ({:.2}s - {:.2}s)...", i + 1, start_time, cut_point);
+        report(Some(format!("Cutting segment {}/{}", i + 1, segment_count)), i);
         
-        let output = Command::new("ffmpeg")
+        let output = command_output(Command::new(config::ffmpeg())
             .arg("-i")
             .arg(audio_path)
             .arg("-ss")
             .arg(start_time.to_string())
             .arg("-t")
             .arg(duration.to_string())
-            .arg("-acodec")
-            .arg("copy")
+            .args(cut_codec_args(accurate))
             .arg("-y")
-            .arg(&output_path)
-            .output()?;
+            .arg(&output_path))?;
         
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(anyhow!("切割音频失败: {}", stderr));
+        if let Err(e) = check_written(&output, &output_path, "切割音频失败") {
+            remove_files(&wav_segments);
+            return Err(e);
         }
         
         wav_segments.push(output_path);
         start_time = cut_point;
+        report(None, i + 1);
     }
     
     // 最后一段：从最后一个切割点到结束
     let output_path = parent.join(format!("{}_{:03}.{}", stem, cut_points.len(), extension));
     
     println!("   切割片段 {} ({:.2}s - 结束)...", cut_points.len() + 1, start_time);
+    report(Some(format!("Cutting segment {}/{}", segment_count, segment_count)), cut_points.len());
     
-    let output = Command::new("ffmpeg")
+    let output = command_output(Command::new(config::ffmpeg())
         .arg("-i")
         .arg(audio_path)
         .arg("-ss")
         .arg(start_time.to_string())
-        .arg("-acodec")
-        .arg("copy")
+        .args(cut_codec_args(accurate))
         .arg("-y")
-        .arg(&output_path)
-        .output()?;
+        .arg(&output_path))?;
     
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(anyhow!("切割最后一段音频失败: {}", stderr));
+    if let Err(e) = check_written(&output, &output_path, "切割最后一段音频失败") {
+        remove_files(&wav_segments);
+        return Err(e);
     }
     
     wav_segments.push(output_path);
+    report(None, segment_count);
+    
+    // 转换 MP3 前读取切出的各段时长，得到实际的时间范围（最后一段的时长不影响切割点）
+    let durations = wav_segments[..cut_points.len()].iter().map(|path| get_audio_duration(path).ok());
+    let actual_cut_points = measured_cut_points(cut_points, durations);
+    
+    if lossless {
+        println!("✅ 音频切割完成，共 {} 个无损片段（未转换为 MP3）", wav_segments.len());
+        return segments_from_cut_points(wav_segments, &actual_cut_points);
+    }
     
     // 将所有 WAV 片段转换为 MP3
     println!("🎵 转换片段为 MP3 格式...");
@@ -109,13 +411,17 @@ pub fn cut_audio(audio_path: &Path, cut_points: &[f64]) -> Result<Vec<PathBuf>>
     
     for (i, wav_path) in wav_segments.iter().enumerate() {
         println!("   转换片段 {} 为 MP3...", i + 1);
+        report(Some(format!("Converting segment {}/{} to MP3", i + 1, segment_count)), segment_count + i);
         match convert_wav_to_mp3(wav_path) {
             Ok(mp3_path) => {
                 mp3_segments.push(mp3_path);
                 println!("   ✅ 片段 {} 转换完成", i + 1);
+                report(None, segment_count + i + 1);
             }
             Err(e) => {
                 eprintln!("   ❌ 片段 {} 转换失败: {}", i + 1, e);
+                remove_files(&mp3_segments);
+                remove_files(&wav_segments[i..]);
                 return Err(anyhow!("转换片段 {} 为 MP3 失败: {}", i + 1, e));
             }
         }
@@ -123,7 +429,171 @@ pub fn cut_audio(audio_path: &Path, cut_points: &[f64]) -> Result<Vec<PathBuf>>
     
     println!("✅ 音频切割和转换完成，共 {} 个 MP3 片段", mp3_segments.len());
     
-    Ok(mp3_segments)
+    segments_from_cut_points(mp3_segments, &actual_cut_points)
+}
+
+/// 用 FFmpeg 的 concat demuxer 把片段按顺序无损拼接成一个文件，用于核对切割有没有丢失内容
+/// 
+/// 片段的编码、采样率或声道不一致时拼接结果可能损坏，先用 [`segment_format_mismatch`] 检查
+pub fn concat_segments(segments: &[PathBuf], output_path: &Path) -> Result<PathBuf> {
+    if segments.is_empty() {
+        return Err(anyhow!("No segments to join"));
+    }
+    
+    let list_path = output_path.with_extension("concat.txt");
+    fs::write(&list_path, concat_list(segments))?;
+    
+    let output = command_output(Command::new(config::ffmpeg())
+        .args(["-f", "concat", "-safe", "0", "-i"])
+        .arg(&list_path)
+        .args(["-c", "copy", "-y"])
+        .arg(output_path));
+    let _ = fs::remove_file(&list_path);
+    
+    check_written(&output?, output_path, "拼接片段失败")?;
+    Ok(output_path.to_path_buf())
+}
+
+/// concat demuxer 的文件列表，路径中的单引号按 `'\''` 转义
+fn concat_list(segments: &[PathBuf]) -> String {
+    segments.iter()
+        .map(|segment| format!("file '{}'\n", segment.to_string_lossy().replace('\'', "'\\''")))
+        .collect()
+}
+
+/// 检查片段的编码、采样率和声道是否一致，不一致时返回说明
+/// 
+/// 无法读取的片段也会报告
+pub fn segment_format_mismatch(segments: &[PathBuf]) -> Option<String> {
+    let mut formats = Vec::new();
+    for segment in segments {
+        match media_info(segment) {
+            Ok(info) => formats.push((segment, (info.audio_codec, info.sample_rate, info.channels))),
+            Err(e) => return Some(format!("Cannot read {:?}: {}", segment.file_name().unwrap_or_default(), e)),
+        }
+    }
+    
+    let (_, first) = formats.first()?;
+    formats.iter()
+        .find(|(_, format)| format != first)
+        .map(|(segment, (codec, rate, channels))| format!(
+            "{:?} is {} {} Hz {} ch, unlike the first segment ({} {} Hz {} ch)",
+            segment.file_name().unwrap_or_default(),
+            codec.as_deref().unwrap_or("?"), rate.unwrap_or(0), channels.unwrap_or(0),
+            first.0.as_deref().unwrap_or("?"), first.1.unwrap_or(0), first.2.unwrap_or(0),
+        ))
+}
+
+/// 字幕嵌入视频的方式
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum MuxMode {
+    #[default]
+    Soft,  // 作为字幕轨封装，不重新编码视频，播放器中可以开关
+    Burn,  // 烧录进画面（硬字幕），需要重新编码视频
+}
+
+impl MuxMode {
+    pub fn as_str(&self) -> &str {
+        match self {
+            MuxMode::Soft => "Soft subtitles (track)",
+            MuxMode::Burn => "Burn in (hardsub)",
+        }
+    }
+    
+    pub fn all() -> Vec<MuxMode> {
+        vec![MuxMode::Soft, MuxMode::Burn]
+    }
+}
+
+/// 输出容器支持的软字幕编码，不支持软字幕的容器（如 AVI、FLV）返回 None
+pub fn soft_subtitle_codec(output_path: &Path) -> Option<&'static str> {
+    let extension = output_path.extension()
+        .and_then(|s| s.to_str())
+        .map(|s| s.to_lowercase())
+        .unwrap_or_default();
+    
+    match extension.as_str() {
+        "mp4" | "m4v" | "mov" => Some("mov_text"),
+        "mkv" => Some("srt"),
+        "webm" => Some("webvtt"),
+        _ => None,
+    }
+}
+
+/// 把字幕文件嵌入视频，写到 `output_path`
+/// 
+/// 软字幕复制音视频流，只增加一条字幕轨，容器不支持软字幕时返回错误；
+/// 硬字幕用 `subtitles` 滤镜烧录，视频重新编码，音频原样复制。
+/// 按 FFmpeg 的 `-progress` 输出把已处理的毫秒数作为 `Progress` 发送到 `progress`
+pub fn mux_subtitles(
+    video_path: &Path,
+    srt_path: &Path,
+    output_path: &Path,
+    mode: MuxMode,
+    progress: Option<&Sender<ProgressMessage>>,
+) -> Result<()> {
+    let mut command = Command::new(config::ffmpeg());
+    command.args(["-nostats", "-progress", "pipe:1", "-loglevel", "error", "-y", "-i"]).arg(video_path);
+    match mode {
+        MuxMode::Soft => {
+            let codec = soft_subtitle_codec(output_path).ok_or_else(|| WhisperError::InvalidInput(format!(
+                "{:?} does not support soft subtitles; save as .mkv or .mp4, or burn them in",
+                output_path.extension().unwrap_or_default())))?;
+            command.arg("-i").arg(srt_path)
+                .args(["-map", "0:v?", "-map", "0:a?", "-map", "1:0", "-c", "copy", "-c:s", codec]);
+        }
+        MuxMode::Burn => {
+            command.arg("-vf").arg(format!("subtitles={}", filter_path(srt_path)))
+                .args(["-c:a", "copy"]);
+        }
+    }
+    command.arg(output_path).stdout(Stdio::piped()).stderr(Stdio::piped());
+    
+    let total_ms = media_info(video_path).ok().and_then(|info| info.duration).map(|d| (d * 1000.0) as usize);
+    let mut child = command.spawn().map_err(|e| WhisperError::spawn("ffmpeg", e))?;
+    
+    // 另开线程读 stderr，避免管道写满后 FFmpeg 阻塞
+    let stderr = child.stderr.take();
+    let stderr_reader = std::thread::spawn(move || {
+        let mut text = String::new();
+        if let Some(mut stderr) = stderr {
+            let _ = stderr.read_to_string(&mut text);
+        }
+        text
+    });
+    
+    if let Some(stdout) = child.stdout.take() {
+        for line in BufReader::new(stdout).lines().map_while(|line| line.ok()) {
+            if let (Some(seconds), Some(total), Some(tx)) = (parse_progress_time(&line), total_ms, progress) {
+                let current = ((seconds * 1000.0) as usize).min(total);
+                let _ = tx.send(ProgressMessage::Progress { current, total });
+            }
+        }
+    }
+    
+    let status = child.wait()?;
+    let stderr = stderr_reader.join().unwrap_or_default();
+    let output = std::process::Output { status, stdout: Vec::new(), stderr: stderr.into_bytes() };
+    check_written(&output, output_path, "Failed to export video with subtitles")
+}
+
+/// `-progress` 输出中已处理的时长（秒），如 `out_time_us=1500000`
+fn parse_progress_time(line: &str) -> Option<f64> {
+    let micros: f64 = line.trim().strip_prefix("out_time_us=")?.parse().ok()?;
+    Some(micros / 1_000_000.0)
+}
+
+/// 滤镜参数中的文件路径：先按选项值转义 `\ : '`，再按滤镜图转义 `\ ' [ ] , ;`
+/// 
+/// Windows 路径的反斜杠先换成斜杠
+fn filter_path(path: &Path) -> String {
+    let escape = |text: &str, special: &[char]| -> String {
+        text.chars()
+            .flat_map(|c| if special.contains(&c) { vec!['\\', c] } else { vec![c] })
+            .collect()
+    };
+    let path = path.to_string_lossy().replace('\\', "/");
+    escape(&escape(&path, &['\\', ':', '\'']), &['\\', '\'', '[', ']', ',', ';'])
 }
 
 /// 将 WAV 音频文件转换为 MP3 格式
@@ -139,7 +609,7 @@ pub fn convert_wav_to_mp3(wav_path: &Path) -> Result<PathBuf> {
     
     // 使用 ffmpeg 转换为 MP3
     // 使用较高的比特率以保证质量
-    let output = Command::new("ffmpeg")
+    let output = command_output(Command::new(config::ffmpeg())
         .arg("-i")
         .arg(wav_path)
         .arg("-codec:a")
@@ -147,18 +617,10 @@ pub fn convert_wav_to_mp3(wav_path: &Path) -> Result<PathBuf> {
         .arg("-b:a")
         .arg("192k")  // 192 kbps 比特率，平衡质量和文件大小
         .arg("-y")
-        .arg(&mp3_path)
-        .output()?;
-    
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(anyhow!("转换为 MP3 失败: {}", stderr));
-    }
+        .arg(&mp3_path))?;
     
-    // 验证 MP3 文件是否生成成功
-    if !mp3_path.exists() {
-        return Err(anyhow!("MP3 文件未生成"));
-    }
+    // 失败或生成空文件时删除不完整的 MP3，保留 WAV
+    check_written(&output, &mp3_path, "转换为 MP3 失败")?;
     
     // 删除原始 WAV 文件
     if let Err(e) = fs::remove_file(wav_path) {
@@ -169,26 +631,419 @@ pub fn convert_wav_to_mp3(wav_path: &Path) -> Result<PathBuf> {
     Ok(mp3_path)
 }
 
-/// 获取音频文件的时长
-#[allow(dead_code)]
-fn get_audio_duration(audio_path: &Path) -> Result<f64> {
-    let output = Command::new("ffprobe")
+/// 根据片段的编码格式推算每段的 priming 延迟（秒）
+/// 
+/// ffmpeg 写出的 MP3（`convert_wav_to_mp3` 等）带 LAME/Info 标签，记录了编码延迟，
+/// ffmpeg 的 MP3 解码器（Whisper 用它读取音频）据此裁掉开头的延迟样本，因此返回 0。
+/// 只有不带标签的 MP3 才按自身的采样率返回 libmp3lame 的 priming 延迟；
+/// WAV 等无编码延迟的格式以及读不到采样率的片段返回 0
+pub fn priming_delay(segment_path: &Path) -> f64 {
+    let extension = segment_path.extension()
+        .and_then(|s| s.to_str())
+        .map(|s| s.to_lowercase())
+        .unwrap_or_default();
+    
+    match extension.as_str() {
+        "mp3" if !read_mp3_head(segment_path).is_some_and(|head| has_encoder_delay_tag(&head)) => {
+            media_info(segment_path).ok()
+                .and_then(|info| info.sample_rate)
+                .map_or(0.0, lame_priming_delay)
+        }
+        _ => 0.0,
+    }
+}
+
+/// 读取 MP3 第一帧附近的数据，跳过开头的 ID3v2 标签
+fn read_mp3_head(path: &Path) -> Option<Vec<u8>> {
+    use std::io::{Seek, SeekFrom};
+    
+    let mut file = fs::File::open(path).ok()?;
+    let mut header = [0u8; 10];
+    file.read_exact(&mut header).ok()?;
+    if &header[..3] == b"ID3" {
+        // 标签大小为 4 个 7 位字节（不含 10 字节的头），标志位 0x10 表示还有 10 字节的尾
+        let size = header[6..10].iter().fold(0u64, |size, &byte| (size << 7) | (byte & 0x7f) as u64);
+        let footer = if header[5] & 0x10 != 0 { 10 } else { 0 };
+        file.seek(SeekFrom::Start(10 + size + footer)).ok()?;
+    } else {
+        file.seek(SeekFrom::Start(0)).ok()?;
+    }
+    
+    let mut head = Vec::new();
+    file.take(4096).read_to_end(&mut head).ok()?;
+    Some(head)
+}
+
+/// 第一帧是否为带编码延迟信息的 Xing/Info 标签帧（LAME 或 ffmpeg 写出）
+/// 
+/// 标签位于帧头和 side info 之后，LAME 扩展部分以编码器名称开头
+fn has_encoder_delay_tag(head: &[u8]) -> bool {
+    let Some(tag) = head.windows(4).take(64).position(|w| w == b"Xing" || w == b"Info") else {
+        return false;
+    };
+    head[tag..].windows(4).take(200).any(|w| w == b"LAME" || w == b"Lavf" || w == b"Lavc")
+}
+
+/// 指定采样率下 libmp3lame 的 priming 延迟（秒）
+pub fn lame_priming_delay(sample_rate: u32) -> f64 {
+    if sample_rate == 0 {
+        return 0.0;
+    }
+    LAME_PRIMING_SAMPLES as f64 / sample_rate as f64
+}
+
+/// 媒体文件的容器与编码信息
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MediaInfo {
+    pub container: String,
+    pub duration: Option<f64>,
+    pub video_codec: Option<String>,
+    pub audio_codec: Option<String>,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u32>,
+    pub bit_rate: Option<u64>,  // 音频流码率，缺失时使用整个文件的码率（bit/s）
+    pub audio_start_time: Option<f64>,  // 音频流第一个样本的时间戳（秒），缺失时使用文件的起始时间
+    pub variable_frame_rate: bool,  // 视频流的平均帧率与标称帧率不同
+}
+
+impl MediaInfo {
+    /// 用于界面显示的单行摘要
+    pub fn summary(&self) -> String {
+        let mut parts = vec![self.container.clone()];
+        if let Some(codec) = &self.video_codec {
+            parts.push(format!("video: {}", codec));
+        }
+        if let Some(codec) = &self.audio_codec {
+            parts.push(format!("audio: {}", codec));
+        }
+        if let Some(rate) = self.sample_rate {
+            parts.push(format!("{} Hz", rate));
+        }
+        if let Some(channels) = self.channels {
+            parts.push(format!("{} ch", channels));
+        }
+        if let Some(bit_rate) = self.bit_rate {
+            parts.push(format!("{} kbps", bit_rate / 1000));
+        }
+        parts.join(" | ")
+    }
+}
+
+/// ffmpeg 能否启动（`ffmpeg -version` 成功退出），用于启动时提示安装
+pub fn check_available() -> bool {
+    Command::new(config::ffmpeg())
+        .arg("-version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// 使用 ffprobe 读取媒体文件的容器、编码、采样率、声道数和码率
+pub fn media_info(path: &Path) -> Result<MediaInfo> {
+    probe(path.as_os_str(), &[])
+}
+
+/// 运行 ffprobe，`options` 为放在输入前的额外选项（如网络流的超时）
+fn probe(input: &std::ffi::OsStr, options: &[&str]) -> Result<MediaInfo> {
+    let output = command_output(Command::new(config::ffprobe())
+        .args(["-v", "error", "-show_streams", "-show_format", "-of", "json"])
+        .args(options)
+        .arg(input))?;
+    
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(WhisperError::process_failed("ffprobe failed", stderr).into());
+    }
+    
+    parse_media_info(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// 解析 ffprobe 的 JSON 输出，取第一个视频流和第一个音频流
+fn parse_media_info(json: &str) -> Result<MediaInfo> {
+    let value: serde_json::Value = serde_json::from_str(json)
+        .map_err(|e| WhisperError::ParseError(format!("Invalid ffprobe output: {}", e)))?;
+    
+    // ffprobe 的数值字段大多以字符串输出
+    let number = |v: &serde_json::Value, key: &str| -> Option<f64> {
+        match &v[key] {
+            serde_json::Value::String(s) => s.parse().ok(),
+            other => other.as_f64(),
+        }
+    };
+    
+    let streams = value["streams"].as_array().cloned().unwrap_or_default();
+    let find_stream = |codec_type: &str| {
+        streams.iter().find(|s| s["codec_type"].as_str() == Some(codec_type))
+    };
+    let video = find_stream("video");
+    let audio = find_stream("audio");
+    let format = &value["format"];
+    
+    Ok(MediaInfo {
+        container: format["format_name"].as_str().unwrap_or("unknown").to_string(),
+        duration: number(format, "duration"),
+        video_codec: video.and_then(|s| s["codec_name"].as_str()).map(String::from),
+        audio_codec: audio.and_then(|s| s["codec_name"].as_str()).map(String::from),
+        sample_rate: audio.and_then(|s| number(s, "sample_rate")).map(|r| r as u32),
+        channels: audio.and_then(|s| number(s, "channels")).map(|c| c as u32),
+        bit_rate: audio.and_then(|s| number(s, "bit_rate"))
+            .or_else(|| number(format, "bit_rate"))
+            .map(|b| b as u64),
+        audio_start_time: audio.and_then(|s| number(s, "start_time"))
+            .or_else(|| number(format, "start_time")),
+        variable_frame_rate: video.is_some_and(|s| {
+            let rate = |key: &str| s[key].as_str().and_then(parse_frame_rate);
+            match (rate("r_frame_rate"), rate("avg_frame_rate")) {
+                (Some(nominal), Some(average)) => (nominal - average).abs() / nominal > VFR_TOLERANCE,
+                _ => false,
+            }
+        }),
+    })
+}
+
+/// 检查源文件的时间戳是否异常：视频为可变帧率，或音频时间戳倒退、有空隙
+/// 
+/// 返回问题描述，正常时返回 None。需要读取所有音频包的时间戳，长文件要几秒钟
+pub fn timestamp_issues(path: &Path) -> Result<Option<String>> {
+    let mut issues = Vec::new();
+    if media_info(path)?.variable_frame_rate {
+        issues.push("variable frame rate".to_string());
+    }
+    
+    let output = command_output(Command::new(config::ffprobe())
+        .args(["-v", "error", "-select_streams", "a:0", "-show_entries", "packet=pts_time,duration_time", "-of", "csv=p=0"])
+        .arg(path))?;
+    if !output.status.success() {
+        return Err(WhisperError::process_failed("ffprobe failed to read packets", String::from_utf8_lossy(&output.stderr)).into());
+    }
+    
+    let packets = parse_packet_times(&String::from_utf8_lossy(&output.stdout));
+    let (backwards, gaps) = pts_anomalies(&packets);
+    if backwards > 0 {
+        issues.push(format!("{} non-monotonic audio timestamps", backwards));
+    }
+    if gaps > 0 {
+        issues.push(format!("{} audio timestamp gaps", gaps));
+    }
+    
+    Ok((!issues.is_empty()).then(|| issues.join(", ")))
+}
+
+/// 解析 ffprobe 输出的 `pts_time,duration_time`，缺少时间戳的包跳过
+fn parse_packet_times(csv: &str) -> Vec<(f64, f64)> {
+    csv.lines()
+        .filter_map(|line| {
+            let mut fields = line.split(',');
+            let pts = fields.next()?.trim().parse().ok()?;
+            let duration = fields.next().and_then(|d| d.trim().parse().ok()).unwrap_or(0.0);
+            Some((pts, duration))
+        })
+        .collect()
+}
+
+/// 统计时间戳倒退和空隙（上一包结束后超过 `PTS_GAP_SECONDS` 才开始）的次数
+fn pts_anomalies(packets: &[(f64, f64)]) -> (usize, usize) {
+    let mut backwards = 0;
+    let mut gaps = 0;
+    for pair in packets.windows(2) {
+        let ((previous, duration), (pts, _)) = (pair[0], pair[1]);
+        if pts < previous {
+            backwards += 1;
+        } else if pts - (previous + duration) > PTS_GAP_SECONDS {
+            gaps += 1;
+        }
+    }
+    (backwards, gaps)
+}
+
+/// 解析 ffprobe 的分数形式帧率（如 `30000/1001`），0/0 等无效值返回 None
+fn parse_frame_rate(rate: &str) -> Option<f64> {
+    let (numerator, denominator) = rate.split_once('/')?;
+    let (numerator, denominator): (f64, f64) = (numerator.parse().ok()?, denominator.parse().ok()?);
+    (numerator > 0.0 && denominator > 0.0).then(|| numerator / denominator)
+}
+
+/// 使用 ffprobe 获取音频文件的时长（秒）
+/// 
+/// 没有时长头信息的 MP3 等格式 rodio 无法给出时长，播放器以此为准
+pub fn get_audio_duration(audio_path: &Path) -> Result<f64> {
+    let output = command_output(Command::new(config::ffprobe())
         .arg("-v")
         .arg("error")
         .arg("-show_entries")
         .arg("format=duration")
         .arg("-of")
         .arg("default=noprint_wrappers=1:nokey=1")
-        .arg(audio_path)
-        .output()?;
+        .arg(audio_path))?;
     
     if !output.status.success() {
-        return Err(anyhow!("获取音频时长失败"));
+        return Err(WhisperError::process_failed("获取音频时长失败", String::from_utf8_lossy(&output.stderr)).into());
     }
     
     let duration_str = String::from_utf8_lossy(&output.stdout).trim().to_string();
-    let duration: f64 = duration_str.parse()?;
+    let duration: f64 = duration_str.parse()
+        .map_err(|_| WhisperError::ParseError(format!("Invalid duration: {}", duration_str)))?;
     
     Ok(duration)
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_encoder_delay_tag() {
+        // 帧头 + 32 字节 side info 后是 Info 标签，LAME 扩展在标签后 120 字节处
+        let mut frame = vec![0xff, 0xfb, 0x90, 0x64];
+        frame.extend([0u8; 32]);
+        frame.extend(b"Info");
+        frame.extend([0u8; 116]);
+        frame.extend(b"Lavc61.19");
+        assert!(has_encoder_delay_tag(&frame));
+        assert!(!has_encoder_delay_tag(&frame[..150]));
+        
+        // 普通音频帧没有标签
+        let mut plain = vec![0xff, 0xfb, 0x90, 0x64];
+        plain.extend([0x55u8; 400]);
+        assert!(!has_encoder_delay_tag(&plain));
+        
+        // 跳过开头的 ID3v2 标签后再查找
+        let path = std::env::temp_dir().join(format!("ffmpeg_tag_test_{}.mp3", std::process::id()));
+        let mut file = b"ID3\x04\x00\x00\x00\x00\x01\x00".to_vec();
+        file.extend([0u8; 128]);
+        file.extend(&frame);
+        fs::write(&path, &file).unwrap();
+        assert_eq!(read_mp3_head(&path).unwrap(), frame);
+        assert_eq!(priming_delay(&path), 0.0);
+        fs::remove_file(&path).unwrap();
+    }
+    
+    #[test]
+    fn test_segment_ranges() {
+        let paths: Vec<PathBuf> = (0..3).map(|i| PathBuf::from(format!("/tmp/talk_{:03}.mp3", i))).collect();
+        let segments = segments_from_cut_points(paths.clone(), &[10.0, 25.5]).unwrap();
+        assert_eq!(segments[0], Segment { path: paths[0].clone(), start: 0.0, end: Some(10.0) });
+        assert_eq!(segments[1], Segment { path: paths[1].clone(), start: 10.0, end: Some(25.5) });
+        assert_eq!(segments[2], Segment { path: paths[2].clone(), start: 25.5, end: None });
+        
+        // 片段数与切割点不符
+        assert!(segments_from_cut_points(paths[..2].to_vec(), &[10.0, 25.5]).is_err());
+        
+        // 按帧切开的片段比请求的长或短，后面各段的起点随之移动；读不到时长时用请求的切割点
+        assert_eq!(measured_cut_points(&[10.0, 25.5], [Some(10.026), Some(15.5)]), vec![10.026, 25.526]);
+        assert_eq!(measured_cut_points(&[10.0, 25.5], [Some(10.026), None]), vec![10.026, 25.5]);
+    }
+    
+    #[test]
+    fn test_concat_list() {
+        let segments = vec![PathBuf::from("/tmp/talk_000.mp3"), PathBuf::from("/tmp/it's_001.mp3")];
+        assert_eq!(concat_list(&segments), "file '/tmp/talk_000.mp3'\nfile '/tmp/it'\\''s_001.mp3'\n");
+    }
+    
+    #[test]
+    fn test_mux_helpers() {
+        assert_eq!(soft_subtitle_codec(Path::new("/tmp/out.MP4")), Some("mov_text"));
+        assert_eq!(soft_subtitle_codec(Path::new("/tmp/out.mkv")), Some("srt"));
+        assert_eq!(soft_subtitle_codec(Path::new("/tmp/out.avi")), None);
+        
+        assert_eq!(parse_progress_time("out_time_us=1500000"), Some(1.5));
+        assert_eq!(parse_progress_time("out_time_us=N/A"), None);
+        assert_eq!(parse_progress_time("progress=continue"), None);
+        
+        assert_eq!(filter_path(Path::new("/tmp/a.srt")), "/tmp/a.srt");
+        assert_eq!(filter_path(Path::new("C:/Users/o'neil/a,b.srt")), "C\\\\:/Users/o\\\\\\'neil/a\\,b.srt");
+    }
+    
+    #[test]
+    fn test_stream_url() {
+        assert!(validate_stream_url("https://example.com/live/index.m3u8").is_ok());
+        assert!(validate_stream_url("HTTP://example.com/a.mp4").is_ok());
+        assert!(validate_stream_url("file:///etc/passwd").is_err());
+        assert!(validate_stream_url("rtmp://example.com/live").is_err());
+        assert!(validate_stream_url("https://").is_err());
+        
+        assert_eq!(stream_file_stem("https://example.com/live/index.m3u8?token=abc"), "index");
+        assert_eq!(stream_file_stem("https://example.com/talk%20one.mp4"), "talk_20one");
+        assert_eq!(stream_file_stem("https://example.com/"), "stream");
+    }
+    
+    #[test]
+    fn test_parse_media_info() {
+        let json = r#"{
+            "streams": [
+                {"codec_type": "video", "codec_name": "h264", "r_frame_rate": "30000/1001", "avg_frame_rate": "30000/1001"},
+                {"codec_type": "audio", "codec_name": "aac", "sample_rate": "48000", "channels": 2, "bit_rate": "128000", "start_time": "0.021333"}
+            ],
+            "format": {"format_name": "mov,mp4,m4a,3gp,3g2,mj2", "duration": "62.500000", "bit_rate": "1500000"}
+        }"#;
+        
+        let info = parse_media_info(json).unwrap();
+        assert_eq!(info.video_codec.as_deref(), Some("h264"));
+        assert_eq!(info.audio_codec.as_deref(), Some("aac"));
+        assert_eq!(info.sample_rate, Some(48000));
+        assert_eq!(info.channels, Some(2));
+        assert_eq!(info.bit_rate, Some(128000));
+        assert_eq!(info.duration, Some(62.5));
+        assert_eq!(info.audio_start_time, Some(0.021333));
+        assert!(!info.variable_frame_rate);
+        
+        // 纯音频文件且音频流没有码率时使用文件码率
+        let json = r#"{"streams": [{"codec_type": "audio", "codec_name": "flac"}], "format": {"format_name": "flac", "bit_rate": "900000"}}"#;
+        let info = parse_media_info(json).unwrap();
+        assert_eq!(info.video_codec, None);
+        assert_eq!(info.bit_rate, Some(900000));
+        assert_eq!(info.audio_start_time, None);
+        assert!(!info.variable_frame_rate);
+        
+        // 手机录制的可变帧率视频
+        let json = r#"{"streams": [{"codec_type": "video", "r_frame_rate": "30/1", "avg_frame_rate": "16987/582"}], "format": {}}"#;
+        assert!(parse_media_info(json).unwrap().variable_frame_rate);
+    }
+    
+    #[test]
+    #[ignore = "requires ffmpeg"]
+    fn test_unreachable_stream_times_out() {
+        assert!(check_available(), "ffmpeg not found");
+        // 不可路由的地址：连接既不成功也不被拒绝，只能等超时
+        let output = std::env::temp_dir().join(format!("whisper_stream_test_{}.wav", std::process::id()));
+        let start = std::time::Instant::now();
+        assert!(extract_audio_from_url("http://10.255.255.1/stream.mp3", &output, Some(10.0)).is_err());
+        assert!(start.elapsed() < std::time::Duration::from_secs(60), "took {:?}", start.elapsed());
+    }
+    
+    #[test]
+    #[ignore = "requires ffmpeg"]
+    fn test_accurate_cut_duration() {
+        assert!(check_available(), "ffmpeg not found");
+        let dir = std::env::temp_dir().join(format!("whisper_cut_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("tone.mp3");
+        let output = command_output(Command::new(config::ffmpeg())
+            .args(["-f", "lavfi", "-i", "sine=frequency=440:duration=5", "-y"])
+            .arg(&source)).unwrap();
+        assert!(output.status.success());
+        
+        let segments = cut_audio(&source, &[1.337, 3.5], true, true).unwrap();
+        for segment in &segments {
+            let requested = segment.end.unwrap_or(5.0) - segment.start;
+            let actual = get_audio_duration(&segment.path).unwrap();
+            assert!((actual - requested).abs() < 0.05, "{:?}: requested {}, got {}", segment.path, requested, actual);
+        }
+        
+        fs::remove_dir_all(&dir).unwrap();
+    }
+    
+    #[test]
+    fn test_pts_anomalies() {
+        let packets = parse_packet_times("0.000000,0.021333\n0.021333,0.021333\nN/A,0.021333\n0.042667,0.021333\n0.500000,0.021333\n0.400000,0.021333\n");
+        assert_eq!(packets.len(), 5);
+        // 0.064 之后跳到 0.5 是空隙，0.5 之后回到 0.4 是倒退
+        assert_eq!(pts_anomalies(&packets), (1, 1));
+        assert_eq!(pts_anomalies(&packets[..3]), (0, 0));
+        
+        assert_eq!(parse_frame_rate("0/0"), None);
+        assert_eq!(parse_frame_rate("25/1"), Some(25.0));
+    }
+}
@@ -0,0 +1,159 @@
+use eframe::egui;
+use std::path::{Path, PathBuf};
+
+/// 自定义 CJK 字体路径的环境变量
+const FONT_ENV_VAR: &str = "WHISPER_GUI_CJK_FONT";
+
+/// 各平台常见的 CJK 字体位置，按优先级排列
+const SYSTEM_FONT_PATHS: &[&str] = &[
+    // macOS
+    "/System/Library/Fonts/PingFang.ttc",
+    "/System/Library/Fonts/Hiragino Sans GB.ttc",
+    "/System/Library/Fonts/STHeiti Medium.ttc",
+    // Windows
+    "C:\\Windows\\Fonts\\msyh.ttc",
+    "C:\\Windows\\Fonts\\simhei.ttf",
+    "C:\\Windows\\Fonts\\simsun.ttc",
+    // Linux
+    "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/google-noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/truetype/wqy/wqy-microhei.ttc",
+    "/usr/share/fonts/wqy-microhei/wqy-microhei.ttc",
+    "/usr/share/fonts/truetype/droid/DroidSansFallbackFull.ttf",
+];
+
+/// 后备字体：Noto Sans SC 子集（简体中文常用字 + 假名，SIL OFL 许可）
+#[cfg(feature = "cjk-font-download")]
+const FALLBACK_FONT_URL: &str =
+    "https://github.com/notofonts/noto-cjk/raw/main/Sans/SubsetOTF/SC/NotoSansSC-Regular.otf";
+
+/// 后备字体在配置目录中的文件名
+const FALLBACK_FONT_FILE: &str = "NotoSansSC-Regular.otf";
+
+/// 加载 CJK 字体作为 egui 的后备字体，返回所用字体的来源
+/// 
+/// 查找顺序：环境变量 `WHISPER_GUI_CJK_FONT` → 系统字体 → 配置目录中下载过的后备字体。
+/// 都找不到时，启用 `cjk-font-download` 特性的版本会在后台下载后备字体，下载完成后立即生效；
+/// 否则保持默认字体，中文/日文会显示为方块
+pub fn setup_fonts(ctx: &egui::Context, config_dir: Option<PathBuf>) -> Option<String> {
+    let cached = config_dir.as_deref().map(cached_font_path);
+    match load_font_data(cached.as_deref()) {
+        Some((source, data)) => {
+            apply_font(ctx, data);
+            Some(source)
+        }
+        None => {
+            if let Some(path) = cached {
+                download_fallback_font(ctx, path);
+            }
+            None
+        }
+    }
+}
+
+fn apply_font(ctx: &egui::Context, data: egui::FontData) {
+    let mut fonts = egui::FontDefinitions::default();
+    fonts.font_data.insert("cjk".to_string(), data);
+    
+    // 放在默认字体之后，拉丁字母仍使用默认字体
+    for family in [egui::FontFamily::Proportional, egui::FontFamily::Monospace] {
+        fonts.families.entry(family).or_default().push("cjk".to_string());
+    }
+    
+    ctx.set_fonts(fonts);
+}
+
+fn cached_font_path(config_dir: &Path) -> PathBuf {
+    config_dir.join("fonts").join(FALLBACK_FONT_FILE)
+}
+
+fn load_font_data(cached: Option<&Path>) -> Option<(String, egui::FontData)> {
+    let env_path = std::env::var_os(FONT_ENV_VAR).map(PathBuf::from);
+    let candidates = env_path.into_iter()
+        .chain(SYSTEM_FONT_PATHS.iter().map(PathBuf::from))
+        .chain(cached.map(Path::to_path_buf));
+    
+    for path in candidates {
+        if let Ok(bytes) = std::fs::read(&path) {
+            return Some((path.display().to_string(), egui::FontData::from_owned(bytes)));
+        }
+    }
+    
+    None
+}
+
+/// 文件头是否为 TrueType/OpenType 字体（避免把下载到的错误页面当成字体）
+#[cfg(feature = "cjk-font-download")]
+fn is_font_file(bytes: &[u8]) -> bool {
+    matches!(bytes.get(..4), Some(b"OTTO" | b"\x00\x01\x00\x00" | b"true" | b"ttcf"))
+}
+
+/// 在后台用 curl 下载后备字体到 `path`，成功后替换界面字体
+/// 
+/// 先下载到临时文件再改名，中途失败不会留下不完整的字体
+#[cfg(feature = "cjk-font-download")]
+fn download_fallback_font(ctx: &egui::Context, path: PathBuf) {
+    let ctx = ctx.clone();
+    std::thread::spawn(move || {
+        println!("No CJK font found, downloading fallback font to {}", path.display());
+        match fetch_font(&path) {
+            Ok(bytes) => {
+                apply_font(&ctx, egui::FontData::from_owned(bytes));
+                ctx.request_repaint();
+                println!("Loaded CJK font: {}", path.display());
+            }
+            Err(e) => println!("Failed to download CJK font: {}", e),
+        }
+    });
+}
+
+#[cfg(not(feature = "cjk-font-download"))]
+fn download_fallback_font(_ctx: &egui::Context, _path: PathBuf) {}
+
+#[cfg(feature = "cjk-font-download")]
+fn fetch_font(path: &Path) -> anyhow::Result<Vec<u8>> {
+    use anyhow::Context;
+    
+    let dir = path.parent().context("Invalid font path")?;
+    std::fs::create_dir_all(dir)?;
+    let partial = path.with_extension("part");
+    
+    let output = whisper_gui::error::command_output(std::process::Command::new("curl")
+        .args(["-fsSL", "--connect-timeout", "15", "--max-time", "600", "-o"])
+        .arg(&partial)
+        .arg(FALLBACK_FONT_URL))?;
+    if !output.status.success() {
+        let _ = std::fs::remove_file(&partial);
+        anyhow::bail!("curl failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    
+    let bytes = std::fs::read(&partial)?;
+    if !is_font_file(&bytes) {
+        let _ = std::fs::remove_file(&partial);
+        anyhow::bail!("Downloaded file is not a font");
+    }
+    std::fs::rename(&partial, path)?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[cfg(feature = "cjk-font-download")]
+    #[test]
+    fn test_font_magic() {
+        assert!(is_font_file(b"OTTO\x00\x0b"));
+        assert!(is_font_file(b"\x00\x01\x00\x00\x00\x10"));
+        assert!(is_font_file(b"ttcf\x00\x01"));
+        assert!(!is_font_file(b"<!DOCTYPE html>"));
+        assert!(!is_font_file(b"OT"));
+    }
+    
+    #[test]
+    fn test_cached_font_path() {
+        let dir = Path::new("config");
+        assert_eq!(cached_font_path(dir), dir.join("fonts").join(FALLBACK_FONT_FILE));
+    }
+}
@@ -0,0 +1,65 @@
+//! 系统级全局快捷键：窗口没有焦点时（如在 ffplay 中看视频）也能标记切割点
+//! 
+//! 需要启用 `global-hotkey` 特性编译；未启用或注册失败时只能使用窗口内的 `M` 键
+
+/// 全局快捷键的说明文字
+pub const HOTKEY_LABEL: &str = "Ctrl+Alt+M";
+
+#[cfg(feature = "global-hotkey")]
+pub use imp::MarkHotkey;
+
+#[cfg(feature = "global-hotkey")]
+mod imp {
+    use global_hotkey::hotkey::{Code, HotKey, Modifiers};
+    use global_hotkey::{GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState};
+    
+    /// 已注册的"标记切割点"全局快捷键，drop 时注销
+    pub struct MarkHotkey {
+        manager: GlobalHotKeyManager,
+        hotkey: HotKey,
+    }
+    
+    impl MarkHotkey {
+        /// 向系统注册快捷键，已被其他程序占用或平台不支持（如 Wayland）时返回错误
+        pub fn register() -> Result<Self, String> {
+            let manager = GlobalHotKeyManager::new().map_err(|e| e.to_string())?;
+            let hotkey = HotKey::new(Some(Modifiers::CONTROL | Modifiers::ALT), Code::KeyM);
+            manager.register(hotkey).map_err(|e| e.to_string())?;
+            Ok(Self { manager, hotkey })
+        }
+        
+        /// 取出上次调用以来的按键事件，按下过快捷键时返回 true
+        pub fn pressed(&self) -> bool {
+            let mut pressed = false;
+            while let Ok(event) = GlobalHotKeyEvent::receiver().try_recv() {
+                pressed |= event.id == self.hotkey.id() && event.state == HotKeyState::Pressed;
+            }
+            pressed
+        }
+    }
+    
+    impl Drop for MarkHotkey {
+        fn drop(&mut self) {
+            let _ = self.manager.unregister(self.hotkey);
+        }
+    }
+}
+
+#[cfg(not(feature = "global-hotkey"))]
+pub use fallback::MarkHotkey;
+
+#[cfg(not(feature = "global-hotkey"))]
+mod fallback {
+    /// 未启用 `global-hotkey` 特性时的占位实现，注册总是失败
+    pub struct MarkHotkey;
+    
+    impl MarkHotkey {
+        pub fn register() -> Result<Self, String> {
+            Err("this build has no global hotkey support (build with --features global-hotkey)".to_string())
+        }
+        
+        pub fn pressed(&self) -> bool {
+            false
+        }
+    }
+}
@@ -0,0 +1,135 @@
+//! 长音频 Whisper 识别的核心流程：提取音频 → 切割 → 识别 → 合并字幕
+//! 
+//! 与界面无关，图形界面（`main.rs`）和其他程序都通过 `pipeline` 模块调用
+
+pub mod audio_player;
+pub mod config;
+pub mod ffmpeg;
+pub mod whisper;
+pub mod srt_merger;
+pub mod pipeline;
+pub mod manual_cut;
+pub mod workspace;
+pub mod vad_recognition;
+pub mod subtitle;
+pub mod ass;
+pub mod error;
+pub mod translate;
+
+pub use error::WhisperError;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone)]
+pub enum ProgressMessage {
+    Progress { current: usize, total: usize },
+    Result { segment: usize, text: String },
+    RealtimeOutput(String),  // 实时输出信息
+    Completed,
+    Error(String),
+    SegmentFailed { segment: usize, error: String },  // 片段识别失败（序号从 1 开始）及原因
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum WhisperModel {
+    Tiny,
+    #[default]
+    Base,
+    Small,
+    Medium,
+    Large,
+    Turbo,
+}
+
+impl WhisperModel {
+    pub fn as_str(&self) -> &str {
+        match self {
+            WhisperModel::Tiny => "tiny",
+            WhisperModel::Base => "base",
+            WhisperModel::Small => "small",
+            WhisperModel::Medium => "medium",
+            WhisperModel::Large => "large",
+            WhisperModel::Turbo => "turbo",
+        }
+    }
+    
+    pub fn all() -> Vec<WhisperModel> {
+        vec![
+            WhisperModel::Tiny,
+            WhisperModel::Base,
+            WhisperModel::Small,
+            WhisperModel::Medium,
+            WhisperModel::Large,
+            WhisperModel::Turbo,
+        ]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub enum WhisperLanguage {
+    #[default]
+    Unknown,
+    Japanese,
+    English,
+    Chinese,
+    French,
+    German,
+    Spanish,
+    Italian,
+    Russian,
+    Custom,
+}
+
+impl WhisperLanguage {
+    pub fn as_str(&self) -> &str {
+        match self {
+            WhisperLanguage::Unknown => "Auto Detect",
+            WhisperLanguage::Japanese => "Japanese",
+            WhisperLanguage::English => "English",
+            WhisperLanguage::Chinese => "Chinese",
+            WhisperLanguage::French => "French",
+            WhisperLanguage::German => "German",
+            WhisperLanguage::Spanish => "Spanish",
+            WhisperLanguage::Italian => "Italian",
+            WhisperLanguage::Russian => "Russian",
+            WhisperLanguage::Custom => "Custom (Manual Input)",
+        }
+    }
+    
+    pub fn all() -> Vec<WhisperLanguage> {
+        vec![
+            WhisperLanguage::Unknown,
+            WhisperLanguage::English,
+            WhisperLanguage::Chinese,
+            WhisperLanguage::Japanese,
+            WhisperLanguage::French,
+            WhisperLanguage::German,
+            WhisperLanguage::Spanish,
+            WhisperLanguage::Italian,
+            WhisperLanguage::Russian,
+            WhisperLanguage::Custom,
+        ]
+    }
+    
+    /// 传给 Whisper 的语言代码，自动检测或自定义代码为空时返回 None
+    pub fn code<'a>(&'a self, custom_language: &'a str) -> Option<&'a str> {
+        match self {
+            WhisperLanguage::Unknown => None,
+            WhisperLanguage::Japanese => Some("ja"),
+            WhisperLanguage::English => Some("en"),
+            WhisperLanguage::Chinese => Some("zh"),
+            WhisperLanguage::French => Some("fr"),
+            WhisperLanguage::German => Some("de"),
+            WhisperLanguage::Spanish => Some("es"),
+            WhisperLanguage::Italian => Some("it"),
+            WhisperLanguage::Russian => Some("ru"),
+            WhisperLanguage::Custom => {
+                if custom_language.is_empty() {
+                    None
+                } else {
+                    Some(custom_language)
+                }
+            }
+        }
+    }
+}
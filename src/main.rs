@@ -1,39 +1,173 @@
-mod audio_player;
-mod ffmpeg;
-mod whisper;
-mod srt_merger;
-mod recognition;
-mod manual_cut;
-mod workspace;
+mod fonts;
+mod hotkey;
+mod templates;
 
 use eframe::egui;
-use std::path::PathBuf;
-use std::sync::mpsc::{channel, Receiver};
+use whisper_gui::{
+    ass, audio_player, config, ffmpeg, manual_cut, pipeline, srt_merger, subtitle, translate, vad_recognition, whisper, workspace,
+    ProgressMessage, WhisperLanguage, WhisperModel,
+};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
-use std::process::Command;
+
+/// 窗口标题，同时决定配置目录的位置
+const APP_NAME: &str = "Whisper Speech Recognition";
+
+/// 窗口宽度低于该值时左右面板改为上下堆叠
+const MIN_SIDE_BY_SIDE_WIDTH: f32 = 900.0;
+
+/// 持久化存储中“拖入视频后自动提取音频”设置的键
+const AUTO_EXTRACT_KEY: &str = "auto_extract";
+const KEEP_PREVIOUS_SRT_KEY: &str = "keep_previous_srt";
+const SETTINGS_TEMPLATES_KEY: &str = "settings_templates";
+const TRANSLATE_COMMAND_KEY: &str = "translate_command";
+const TRANSLATE_TIMEOUT_KEY: &str = "translate_timeout";
+const LOSSLESS_KEY: &str = "lossless";
+const WHISPER_AUDIO_KEY: &str = "whisper_audio";
+const ACCURATE_CUT_KEY: &str = "accurate_cut";
+const RECOGNITION_WORKERS_KEY: &str = "recognition_workers";
+const STRIP_NONSPEECH_KEY: &str = "strip_nonspeech";
+const NONSPEECH_PATTERNS_KEY: &str = "nonspeech_patterns";
+const FFMPEG_PATH_KEY: &str = "ffmpeg_path";
+const WHISPER_OPTIONS_KEY: &str = "whisper_options";
+
+/// 试听单条字幕时，播放位置超出范围多少秒视为用户跳转（而不是正常播放到结尾）
+const PLAY_UNTIL_TOLERANCE: f64 = 1.0;
+
+/// 播放速度选项
+const PLAYBACK_SPEEDS: [f32; 6] = [0.5, 0.75, 1.0, 1.25, 1.5, 2.0];
+
+/// 识别完成后字幕末尾距媒体结尾超过该时长（秒）时提示可能识别失败
+const TAIL_GAP_WARNING_SECONDS: f64 = 60.0;
+
+/// 预估识别耗时超过该值（秒）时开始前先请用户确认
+const LONG_RUN_WARNING_SECONDS: f64 = 3.0 * 3600.0;
+/// 播放进度条（及下方语音区域条）的宽度（像素）
+const TIMELINE_WIDTH: f32 = 640.0;
+/// 波形概览的段数，绘制时按进度条轨道的宽度缩放
+const WAVEFORM_BUCKETS: usize = 640;
+
+/// 时间段识别结果：开始、结束时间（秒）和换算成全局时间的字幕
+type RangeResult = Result<(f64, f64, Vec<subtitle::SubtitleEntry>), String>;
+
+/// 最响区域查找结果，完全静音时为 None
+type LoudestResult = Result<Option<(f64, f64)>, String>;
+
+/// 音频提取结果：音频路径和源文件时间戳异常、已修正时的提示
+type ExtractionResult = Result<(PathBuf, Option<String>), String>;
+
+/// 片段拼接结果：输出路径和片段格式不一致时的警告
+type JoinResult = Result<(PathBuf, Option<String>), String>;
+
+/// 波形概览结果：音频路径和各段峰值
+type WaveformResult = (PathBuf, Result<Vec<f32>, String>);
+
+/// 模型下载线程发给界面的消息
+enum ModelDownloadMessage {
+    Progress(f32),
+    Finished(Result<(), String>),
+}
+
+/// 片段的识别状态
+#[derive(Debug, Clone, PartialEq)]
+enum SegmentStatus {
+    NotRecognized,
+    Failed(String),  // 失败原因
+    Completed,
+}
+
+/// 字幕右键菜单中的操作
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CueAction {
+    Delete,
+    MergeWithNext,
+    SplitAtPlayhead,
+    ToggleItalic,
+    ToggleBold,
+    Rerecognize,
+}
 
 fn main() -> Result<(), eframe::Error> {
+    // `--self-test [file]`：不打开窗口，端到端检查 FFmpeg 和 Whisper 是否可用
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|arg| arg == "--self-test") {
+        std::process::exit(run_self_test(args.get(1).map(Path::new)));
+    }
+    
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_inner_size([1200.0, 700.0])
+            .with_min_inner_size([480.0, 360.0])
             .with_drag_and_drop(true),
         ..Default::default()
     };
 
     eframe::run_native(
-        "Whisper Speech Recognition",
+        APP_NAME,
         options,
-        Box::new(|_cc| Ok(Box::new(WhisperApp::default()))),
+        Box::new(|cc| {
+            match fonts::setup_fonts(&cc.egui_ctx, eframe::storage_dir(APP_NAME)) {
+                Some(source) => println!("Loaded CJK font: {}", source),
+                None => println!("No CJK font found, CJK text will render as boxes"),
+            }
+            let mut app = WhisperApp::new();
+            if let Some(storage) = cc.storage {
+                app.auto_extract = eframe::get_value(storage, AUTO_EXTRACT_KEY).unwrap_or(true);
+                app.keep_previous_srt = eframe::get_value(storage, KEEP_PREVIOUS_SRT_KEY).unwrap_or(false);
+                app.settings_templates = eframe::get_value(storage, SETTINGS_TEMPLATES_KEY).unwrap_or_default();
+                app.translate_command = eframe::get_value(storage, TRANSLATE_COMMAND_KEY).unwrap_or_default();
+                if let Some(timeout) = eframe::get_value(storage, TRANSLATE_TIMEOUT_KEY) {
+                    app.translate_timeout = timeout;
+                }
+                app.lossless = eframe::get_value(storage, LOSSLESS_KEY).unwrap_or(false);
+                app.whisper_audio = eframe::get_value(storage, WHISPER_AUDIO_KEY).unwrap_or(false);
+                app.accurate_cut = eframe::get_value(storage, ACCURATE_CUT_KEY).unwrap_or(false);
+                app.recognition_workers = eframe::get_value(storage, RECOGNITION_WORKERS_KEY).unwrap_or(0);
+                app.strip_nonspeech = eframe::get_value(storage, STRIP_NONSPEECH_KEY).unwrap_or(false);
+                if let Some(patterns) = eframe::get_value(storage, NONSPEECH_PATTERNS_KEY) {
+                    app.nonspeech_patterns = patterns;
+                }
+                app.ffmpeg_path = eframe::get_value(storage, FFMPEG_PATH_KEY).unwrap_or_default();
+                app.whisper_options = eframe::get_value(storage, WHISPER_OPTIONS_KEY).unwrap_or_default();
+                app.apply_ffmpeg_path();
+            }
+            app.ffmpeg_missing = !ffmpeg::check_available();
+            Ok(Box::new(app))
+        }),
     )
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+/// 运行自检并打印结果，返回进程退出码
+fn run_self_test(sample: Option<&Path>) -> i32 {
+    match pipeline::self_test(sample) {
+        Ok(count) => {
+            println!("✅ Self-test passed: {} cues recognized and merged", count);
+            0
+        }
+        Err(e) => {
+            eprintln!("❌ Self-test failed: {}", e);
+            1
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
 enum AppState {
+    #[default]
     Idle,
     AudioExtracted,
     Processing,
 }
 
+/// 正在编辑的外部 ASS 文件：对白与 `AssDocument::subtitles()` 一一对应
+struct AssEditor {
+    path: PathBuf,
+    document: ass::AssDocument,
+    subtitles: Vec<subtitle::SubtitleEntry>,
+}
+
 #[derive(Default)]
 struct WhisperApp {
     // 文件路径
@@ -48,18 +182,72 @@ struct WhisperApp {
     audio_player: Option<audio_player::AudioPlayer>,
     is_playing: bool,
     current_position: f64, // 秒
+    scrub_position: Option<f64>,  // 按住进度条拖动期间的位置，松开后才 seek
     total_duration: f64,   // 秒
     
     // 切割点
     cut_points: Vec<f64>,  // 时间点（秒）
+    editing_cut_point: Option<(usize, String)>,  // 正在列表中编辑的切割点及输入的时间
+    cut_progress_receiver: Option<Receiver<ProgressMessage>>,
+    cut_receiver: Option<Receiver<Result<Vec<ffmpeg::Segment>, String>>>,
+    cut_progress: f32,
+    cut_status: String,
+    join_receiver: Option<Receiver<JoinResult>>,  // 片段拼接结果
+    mux_receiver: Option<Receiver<Result<PathBuf, String>>>,  // 导出带字幕视频的结果
+    mux_progress_receiver: Option<Receiver<ProgressMessage>>,
+    mux_progress: f32,
     
     // Whisper 参数
     whisper_model: WhisperModel,
+    downloaded_models: Vec<WhisperModel>,  // Whisper 缓存目录中已有的模型
+    model_download_receiver: Option<Receiver<ModelDownloadMessage>>,
+    model_download_progress: f32,
+    show_estimate: bool,  // 显示耗时和磁盘占用预估窗口
+    long_run_warning: Option<String>,  // 预估耗时过长，等待用户确认后才开始识别
+    benchmark_factor: Option<(WhisperModel, f64)>,  // 基准测试得到的每秒音频识别耗时
+    benchmark_receiver: Option<Receiver<Result<(WhisperModel, f64), String>>>,
+    show_quick_transcribe: bool,  // 快速识别窗口：短音频直接识别，不走工作区流程
+    quick_path: String,
+    quick_receiver: Option<Receiver<Result<String, String>>>,
+    quick_cancel: whisper::CancelToken,
+    ass_editor: Option<AssEditor>,  // 外部 ASS 文件的对白编辑窗口
+    preview_seconds: f64,  // 预览识别的开头时长（秒）
+    preview_receiver: Option<Receiver<Result<String, String>>>,
+    preview_result: Option<Result<String, String>>,  // 预览识别的带时间文本，不写入字幕
+    preview_cancel: whisper::CancelToken,
+    quick_result: Option<Result<String, String>>,
     whisper_language: WhisperLanguage,
     custom_language_code: String,
+    priming_delay_ms: String,  // 编码延迟修正（毫秒），留空则根据每个片段的格式自动推算
+    lossless: bool,  // 无损模式：自动切割和手动切割都保留 WAV 片段，不转换为 MP3
+    whisper_audio: bool,  // 从视频提取为 16kHz 单声道 WAV（Whisper 实际使用的格式）
+    accurate_cut: bool,  // 精确切割：重新编码而不是复制音频流，MP3/AAC 源的切割点不会提前
+    whisper_threads: usize,  // Whisper 推理线程数，0 表示使用全部核心
+    recognition_workers: usize,  // 同时识别的片段数，0 表示 CPU 核心数的一半
+    precise_timing: bool,  // 用 Whisper 的 JSON 输出保留未取整的时间
+    whisper_options: whisper::WhisperOptions,  // 束搜索、温度、提示词等解码参数
+    output_encoding: srt_merger::OutputEncoding,  // 字幕/文本输出编码
+    keep_previous_srt: bool,  // 不覆盖之前的字幕，写到 video.1.srt、video.2.srt……
+    session_srt_path: Option<PathBuf>,  // 本次打开后识别写出的字幕文件
+    export_segment_subtitles: bool,  // 同时导出每段字幕
+    export_fps: String,  // 时间戳取整的帧率，留空则保持毫秒精度
+    export_start_index: String,  // 字幕起始编号，留空则从 1 开始
+    dedupe_boundaries: bool,  // 合并时去掉切割点两侧重复的字幕
+    plaintext_per_cue: bool,  // 导出纯文本时每条字幕一行，不合并同一句话
+    strip_nonspeech: bool,  // 合并时去掉 [音乐]、(笑) 等非语音标注
+    nonspeech_patterns: String,  // 非语音标注的模式，空格分隔，`*` 匹配任意文字
+    media_transfer: workspace::MediaTransfer,  // 保存工作区时如何处理媒体文件
+    ffmpeg_path: String,  // ffmpeg 程序路径，留空则使用环境变量 WHISPER_FFMPEG 或 PATH
+    ffmpeg_missing: bool,  // 启动时找不到 ffmpeg，显示提示横幅直到关闭或找到
+    
+    // 设置模板
+    settings_templates: Vec<templates::SettingsTemplate>,
+    selected_template: usize,
+    template_name: String,  // 保存为模板时使用的名称
+    template_pattern: String,  // 自动应用的文件名通配符
     
     // 切割后的音频文件
-    audio_segments: Vec<PathBuf>,
+    audio_segments: Vec<ffmpeg::Segment>,  // 各片段及其在原音频中的时间范围
     
     // 进度信息
     processing_progress: f32,
@@ -70,6 +258,7 @@ struct WhisperApp {
     
     // 消息通道
     progress_receiver: Option<Receiver<ProgressMessage>>,
+    cancel_token: whisper::CancelToken,  // 停止识别时终止 Whisper 进程
     
     // 重新识别
     selected_segment_index: usize,
@@ -78,148 +267,273 @@ struct WhisperApp {
     manual_start_time: String,
     manual_end_time: String,
     manual_segment: Option<PathBuf>,
+    range_receiver: Option<Receiver<RangeResult>>,  // 时间段识别结果
+    channel_receiver: Option<Receiver<Result<Vec<subtitle::SubtitleEntry>, String>>>,  // 分声道识别结果
     
     // 工作区
     workspace_dir: Option<PathBuf>,
     can_resume: bool,  // 是否可以恢复识别
-    missing_segments: Vec<usize>,  // 缺失字幕的片段索引
+    tail_gap_warning: Option<String>,  // 识别完成后末尾缺少字幕的提示
+    missing_segments: Vec<usize>,  // 还没有识别的片段索引
+    failed_segments: Vec<usize>,  // 识别失败的片段索引，恢复识别时不自动重试
+    segment_failures: BTreeMap<usize, String>,  // 片段识别失败的原因，随工作区保存
     completed_segments: Vec<usize>,  // 已完成的片段索引
+    
+    // 语音区域预览
+    vad_threshold: f32,
+    speech_regions: Vec<(f64, f64)>,  // 检测到的语音区域（秒）
+    target_segment_minutes: f64,  // 按目标时长切割时每段的目标时长（分钟）
+    snap_window_seconds: f64,  // 切割点吸附静音的范围（前后秒数）
+    vad_preview_receiver: Option<Receiver<Result<vad_recognition::SpeechRegions, String>>>,
+    
+    // 波形概览：每个音频文件只计算一次
+    waveforms: BTreeMap<PathBuf, Vec<f32>>,
+    waveform_receiver: Option<Receiver<WaveformResult>>,
+    
+    // 字幕整理
+    max_cue_seconds: f64,  // 单条字幕最大时长（秒）
+    max_cue_chars: usize,  // 单条字幕最大字数
+    merge_max_gap: f64,  // 合并短字幕时相邻字幕的最大间隔（秒）
+    pad_lead: f64,  // 字幕开始提前的秒数
+    pad_tail: f64,  // 字幕结束推迟的秒数
+    locked_subtitles: Vec<subtitle::SubtitleEntry>,  // 锁定的字幕，重新识别后恢复
+    subtitle_standard: subtitle::SubtitleStandard,  // 字幕规范
+    standard_violations: Vec<subtitle::Violation>,  // 上次检查的结果
+    overlaps: Vec<(subtitle::SubtitleEntry, subtitle::SubtitleEntry)>,  // 时间重叠的字幕对，编辑后更新
+    normalize_rules: subtitle::NormalizeRules,  // 文本规范化规则
+    
+    // 字幕搜索
+    subtitle_search: String,
+    search_results: Vec<subtitle::SubtitleEntry>,  // 匹配的字幕，没有搜索时为全部字幕
+    search_selected: Option<usize>,  // 当前选中的匹配
+    search_marked: BTreeSet<usize>,  // 勾选的匹配，用于批量删除
+    delete_range_start: String,  // 删除时间段内字幕的开始时间
+    delete_range_end: String,
+    export_range_start: String,  // 导出片段字幕的时间范围
+    export_range_end: String,
+    export_range_rebase: bool,  // 导出的时间从 0 开始
+    export_range_clip: bool,  // 跨越边界的字幕截到范围内
+    sync_anchors: [(String, String); 2],  // 两个同步点：字幕现在的时间、应该在的时间
+    
+    // 字幕翻译
+    translate_command: String,  // 外部翻译命令，从标准输入读取原文
+    translate_suffix: String,  // 译文文件名后缀，如 video.zh.srt 中的 zh
+    translate_timeout: f64,  // 翻译一条字幕的超时时间（秒）
+    translate_receiver: Option<Receiver<Result<translate::Translation, String>>>,
+    
+    // 媒体信息（ffprobe）
+    media_info: Option<Result<ffmpeg::MediaInfo, String>>,
+    
+    // 播放速度
+    playback_speed: f32,
+    preserve_pitch: bool,  // 变速时保持音高（FFmpeg atempo）
+    accurate_seek: bool,  // 播放器 seek 时逐帧解码到目标位置
+    
+    // A/B 循环
+    loop_a: Option<f64>,  // 循环起点（秒）
+    loop_b: Option<f64>,  // 循环终点（秒）
+    loop_enabled: bool,
+    play_until: Option<(f64, f64)>,  // 试听单条字幕的时间范围，播放到结尾时暂停
+    
+    // 标记切割点的全局快捷键，未启用时为 None
+    global_hotkey: Option<hotkey::MarkHotkey>,
+    
+    // 爆音检测
+    clip_scan_receiver: Option<Receiver<Result<f64, String>>>,
+    loudest_receiver: Option<Receiver<LoudestResult>>,  // 最响区域查找结果
+    
+    // 音频提取
+    auto_extract: bool,  // 拖入视频后立即提取音频
+    stream_url: String,  // URL 输入框
+    stream_max_seconds: String,  // 录制长度上限（秒），直播流必填
+    stream_source: Option<String>,  // 当前媒体来自网络流时的 URL
+    extraction_receiver: Option<Receiver<ExtractionResult>>,
 }
 
-#[derive(Debug, Clone)]
-enum ProgressMessage {
-    Progress { current: usize, total: usize },
-    Result { segment: usize, text: String },
-    RealtimeOutput(String),  // 实时输出信息
-    Completed,
-    Error(String),
-}
-
-#[derive(Debug, Clone, Copy, PartialEq)]
-enum WhisperModel {
-    Tiny,
-    Base,
-    Small,
-    Medium,
-    Large,
-    Turbo,
-}
-
-impl Default for WhisperModel {
-    fn default() -> Self {
-        WhisperModel::Base
+impl WhisperApp {
+    fn new() -> Self {
+        Self {
+            vad_threshold: vad_recognition::DEFAULT_THRESHOLD,
+            max_cue_seconds: 7.0,
+            max_cue_chars: 40,
+            merge_max_gap: 0.3,
+            translate_suffix: "translated".to_string(),
+            translate_timeout: translate::DEFAULT_TIMEOUT.as_secs_f64(),
+            preview_seconds: pipeline::PREVIEW_SECONDS,
+            target_segment_minutes: 10.0,
+            snap_window_seconds: 15.0,
+            export_range_rebase: true,
+            export_range_clip: true,
+            auto_extract: true,
+            playback_speed: 1.0,
+            downloaded_models: whisper::downloaded_models(),
+            dedupe_boundaries: false,
+            strip_nonspeech: false,
+            nonspeech_patterns: subtitle::DEFAULT_NONSPEECH_PATTERNS.join(" "),
+            ..Default::default()
+        }
     }
-}
-
-impl WhisperModel {
-    fn as_str(&self) -> &str {
-        match self {
-            WhisperModel::Tiny => "tiny",
-            WhisperModel::Base => "base",
-            WhisperModel::Small => "small",
-            WhisperModel::Medium => "medium",
-            WhisperModel::Large => "large",
-            WhisperModel::Turbo => "turbo",
-        }
-    }
-    
-    fn all() -> Vec<WhisperModel> {
-        vec![
-            WhisperModel::Tiny,
-            WhisperModel::Base,
-            WhisperModel::Small,
-            WhisperModel::Medium,
-            WhisperModel::Large,
-            WhisperModel::Turbo,
-        ]
+    
+    fn handle_dropped_file(&mut self, path: PathBuf) {
+        if self.media_switch_blocked() {
+            return;
+        }
+        self.reset_for_new_media(path.clone());
+        self.status_message = format!("File loaded: {:?}", path.file_name().unwrap());
+        
+        // 文件名匹配模板时自动应用
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        if let Some(template) = templates::find_for_file(&self.settings_templates, &file_name).cloned() {
+            self.apply_template(&template);
+            self.status_message.push_str(&format!(" (template \"{}\" applied)", template.name));
+        }
+        self.media_info = Some(ffmpeg::media_info(&path).map_err(|e| e.to_string()));
+        
+        // 检查文件类型：如果是音频文件，直接使用；如果是视频，提取音频
+        if pipeline::is_audio_file(&path) {
+            // 直接使用音频文件
+            self.load_audio_file(path);
+        } else if self.auto_extract {
+            // 从视频中提取音频
+            self.extract_audio();
+        }
     }
-}
-
-#[derive(Debug, Clone, PartialEq)]
-enum WhisperLanguage {
-    Unknown,
-    Japanese,
-    English,
-    Chinese,
-    French,
-    German,
-    Spanish,
-    Italian,
-    Russian,
-    Custom,
-}
-
-impl Default for WhisperLanguage {
-    fn default() -> Self {
-        WhisperLanguage::Unknown
+    
+    /// 把界面中填写的 ffmpeg 路径交给 `config`，之后启动的 ffmpeg/ffprobe 都使用该路径
+    fn apply_ffmpeg_path(&self) {
+        config::set_ffmpeg_path(Some(PathBuf::from(self.ffmpeg_path.trim())));
     }
-}
-
-impl WhisperLanguage {
-    fn as_str(&self) -> &str {
-        match self {
-            WhisperLanguage::Unknown => "Auto Detect",
-            WhisperLanguage::Japanese => "Japanese",
-            WhisperLanguage::English => "English",
-            WhisperLanguage::Chinese => "Chinese",
-            WhisperLanguage::French => "French",
-            WhisperLanguage::German => "German",
-            WhisperLanguage::Spanish => "Spanish",
-            WhisperLanguage::Italian => "Italian",
-            WhisperLanguage::Russian => "Russian",
-            WhisperLanguage::Custom => "Custom (Manual Input)",
-        }
-    }
-    
-    fn all() -> Vec<WhisperLanguage> {
-        vec![
-            WhisperLanguage::Unknown,
-            WhisperLanguage::English,
-            WhisperLanguage::Chinese,
-            WhisperLanguage::Japanese,
-            WhisperLanguage::French,
-            WhisperLanguage::German,
-            WhisperLanguage::Spanish,
-            WhisperLanguage::Italian,
-            WhisperLanguage::Russian,
-            WhisperLanguage::Custom,
-        ]
+    
+    /// 当前设置保存为模板
+    fn capture_template(&self, name: &str, file_pattern: &str) -> templates::SettingsTemplate {
+        templates::SettingsTemplate {
+            name: name.to_string(),
+            file_pattern: file_pattern.to_string(),
+            model: self.whisper_model,
+            language: self.whisper_language.clone(),
+            custom_language_code: self.custom_language_code.clone(),
+            threads: self.whisper_threads,
+            precise_timing: self.precise_timing,
+            whisper_options: self.whisper_options.clone(),
+            vad_threshold: self.vad_threshold,
+            subtitle_standard: self.subtitle_standard,
+            normalize_rules: self.normalize_rules,
+            max_cue_seconds: self.max_cue_seconds,
+            output_encoding: self.output_encoding,
+            dedupe_boundaries: self.dedupe_boundaries,
+        }
     }
-}
-
-impl Default for AppState {
-    fn default() -> Self {
-        AppState::Idle
+    
+    fn apply_template(&mut self, template: &templates::SettingsTemplate) {
+        self.whisper_model = template.model;
+        self.whisper_language = template.language.clone();
+        self.custom_language_code = template.custom_language_code.clone();
+        self.whisper_threads = template.threads;
+        self.precise_timing = template.precise_timing;
+        self.whisper_options = template.whisper_options.clone();
+        self.vad_threshold = template.vad_threshold;
+        self.subtitle_standard = template.subtitle_standard;
+        self.normalize_rules = template.normalize_rules;
+        self.max_cue_seconds = template.max_cue_seconds;
+        self.output_encoding = template.output_encoding;
+        self.dedupe_boundaries = template.dedupe_boundaries;
     }
-}
-
-impl WhisperApp {
-    fn handle_dropped_file(&mut self, path: PathBuf) {
-        self.video_path = Some(path.clone());
+    
+    /// 把当前设置保存为模板，同名模板会被替换
+    fn save_template(&mut self) {
+        let name = self.template_name.trim().to_string();
+        if name.is_empty() {
+            self.status_message = "Enter a template name first".to_string();
+            return;
+        }
+        
+        let template = self.capture_template(&name, self.template_pattern.trim());
+        match self.settings_templates.iter().position(|t| t.name == name) {
+            Some(i) => {
+                self.settings_templates[i] = template;
+                self.selected_template = i;
+            }
+            None => {
+                self.settings_templates.push(template);
+                self.selected_template = self.settings_templates.len() - 1;
+            }
+        }
+        self.status_message = format!("Template \"{}\" saved", name);
+    }
+    
+    /// 打开 http(s) 网络流，音频保存到用户选择的文件夹后再加载
+    fn open_stream_url(&mut self) {
+        if self.media_switch_blocked() {
+            return;
+        }
+        let url = self.stream_url.trim().to_string();
+        if let Err(e) = ffmpeg::validate_stream_url(&url) {
+            self.status_message = e.to_string();
+            return;
+        }
+        
+        if !self.stream_max_seconds.trim().is_empty() && self.stream_max_seconds.trim().parse::<f64>().is_err() {
+            self.status_message = "Invalid max length, enter seconds or leave empty".to_string();
+            return;
+        }
+        
+        if let Some(folder) = rfd::FileDialog::new().set_title("Choose where to save the stream audio").pick_folder() {
+            // 文件夹中已有同名音频或字幕时改用 `{stem}_1.wav` 等，不覆盖之前下载的内容
+            let file_name = format!("{}.wav", ffmpeg::stream_file_stem(&url));
+            let output_path = match workspace::unique_target(&folder, Path::new(&file_name)) {
+                Ok(path) => path,
+                Err(e) => {
+                    self.status_message = format!("Failed to choose output file: {}", e);
+                    return;
+                }
+            };
+            self.reset_for_new_media(output_path);
+            self.stream_source = Some(url);
+            self.extract_audio();
+        }
+    }
+    
+    /// 正在识别或切割时不允许换文件，并在状态栏说明原因
+    /// 
+    /// 后台任务的进度、失败记录和完成后的检查都针对当前文件，换文件会让这些结果落到新文件上或丢失
+    fn media_switch_blocked(&mut self) -> bool {
+        if self.state != AppState::Processing {
+            return false;
+        }
+        self.status_message = "A job is running for the current file; wait for it or press Stop before opening another file".to_string();
+        true
+    }
+    
+    /// 切换到新的媒体文件，清空上一个文件的状态
+    fn reset_for_new_media(&mut self, path: PathBuf) {
+        self.video_path = Some(path);
         self.state = AppState::Idle;
-        self.status_message = format!("File loaded: {:?}", path.file_name().unwrap());
         self.audio_path = None;
         self.audio_player = None;
         self.cut_points.clear();
+        self.editing_cut_point = None;
         self.audio_segments.clear();
         self.recognition_results.clear();
+        self.speech_regions.clear();
+        self.locked_subtitles.clear();
+        self.segment_failures.clear();
+        self.failed_segments.clear();
+        self.standard_violations.clear();
+        self.overlaps.clear();
+        self.search_results.clear();
+        self.search_selected = None;
+        self.search_marked.clear();
+        self.media_info = None;
+        self.stream_source = None;
+        self.tail_gap_warning = None;
+        self.clear_loop();
+        self.session_srt_path = None;
+        self.preview_cancel.cancel();
+        self.preview_receiver = None;
+        self.preview_result = None;
         
         // 重置工作区（新视频需要新工作区）
         self.workspace_dir = None;
-        
-        // 检查文件类型：如果是音频文件，直接使用；如果是视频，提取音频
-        let extension = path.extension()
-            .and_then(|s| s.to_str())
-            .map(|s| s.to_lowercase())
-            .unwrap_or_default();
-        
-        if matches!(extension.as_str(), "wav" | "mp3" | "m4a" | "flac" | "ogg" | "opus") {
-            // 直接使用音频文件
-            self.load_audio_file(path);
-        } else {
-            // 从视频中提取音频
-            self.extract_audio();
-        }
     }
     
     fn load_audio_file(&mut self, audio_path: PathBuf) {
@@ -232,485 +546,1138 @@ impl WhisperApp {
             Ok(player) => {
                 self.total_duration = player.duration();
                 self.audio_player = Some(player);
+                self.apply_playback_speed();
             }
             Err(e) => {
                 self.status_message = format!("Failed to load audio: {}", e);
             }
         }
+        
+        // 视频旁边已有字幕时直接列出
+        self.update_subtitle_search();
     }
     
-    fn extract_audio(&mut self) {
-        if let Some(video_path) = &self.video_path {
-            self.status_message = "Extracting audio...".to_string();
-            
-            match ffmpeg::extract_audio(video_path) {
-                Ok(audio_path) => {
-                    self.audio_path = Some(audio_path.clone());
-                    self.status_message = "Audio extracted successfully!".to_string();
-                    self.state = AppState::AudioExtracted;
-                    
-                    // Load audio player
-                    match audio_player::AudioPlayer::new(&audio_path) {
-                        Ok(player) => {
-                            self.total_duration = player.duration();
-                            self.audio_player = Some(player);
-                        }
-                        Err(e) => {
-                            self.status_message = format!("Failed to load audio: {}", e);
-                        }
-                    }
-                }
-                Err(e) => {
-                    self.status_message = format!("Failed to extract audio: {}", e);
-                }
-            }
+    /// 当前音频还没有波形概览时在后台计算，结果按文件缓存
+    fn request_waveform(&mut self) {
+        let Some(audio_path) = self.audio_path.clone() else { return };
+        if self.waveform_receiver.is_some() || self.waveforms.contains_key(&audio_path) {
+            return;
         }
+        
+        let (tx, rx) = channel();
+        self.waveform_receiver = Some(rx);
+        std::thread::spawn(move || {
+            let result = audio_player::compute_waveform(&audio_path, WAVEFORM_BUCKETS).map_err(|e| e.to_string());
+            let _ = tx.send((audio_path, result));
+        });
     }
     
-    fn add_cut_point(&mut self) {
-        if !self.cut_points.contains(&self.current_position) {
-            self.cut_points.push(self.current_position);
-            self.cut_points.sort_by(|a, b| a.partial_cmp(b).unwrap());
-        }
+    /// 在后台下载当前选择的模型
+    fn download_model(&mut self) {
+        let model = self.whisper_model;
+        self.status_message = format!("Downloading model {}...", model.as_str());
+        self.model_download_progress = 0.0;
+        
+        let (tx, rx) = channel();
+        self.model_download_receiver = Some(rx);
+        
+        std::thread::spawn(move || {
+            let progress_tx = tx.clone();
+            let result = whisper::download_model(model, |progress| {
+                let _ = progress_tx.send(ModelDownloadMessage::Progress(progress));
+            });
+            let _ = tx.send(ModelDownloadMessage::Finished(result.map_err(|e| e.to_string())));
+        });
     }
     
-    fn remove_cut_point(&mut self, index: usize) {
-        if index < self.cut_points.len() {
-            self.cut_points.remove(index);
+    /// 媒体时长：优先使用 ffprobe 的结果，其次是播放器的时长
+    fn media_duration(&self) -> Option<f64> {
+        match &self.media_info {
+            Some(Ok(info)) if info.duration.is_some() => info.duration,
+            _ => self.known_duration(),
         }
     }
     
-    fn cut_audio(&mut self) {
-        if let Some(audio_path) = &self.audio_path {
-            self.status_message = "Cutting audio...".to_string();
-            self.state = AppState::Processing;
-            
-            match ffmpeg::cut_audio(audio_path, &self.cut_points) {
-                Ok(segments) => {
-                    self.audio_segments = segments;
-                    self.status_message = format!("Audio cut completed, {} segments", self.audio_segments.len());
-                    self.state = AppState::AudioExtracted;
-                }
-                Err(e) => {
-                    self.status_message = format!("Failed to cut audio: {}", e);
-                    self.state = AppState::AudioExtracted;
-                }
-            }
+    /// 在后台识别 30 秒样本并计时，结果用于预估
+    fn run_benchmark(&mut self) {
+        let (Some(audio_path), Some(duration)) = (self.audio_path.clone(), self.known_duration()) else { return };
+        let model = self.whisper_model;
+        let recognition = self.recognition_options();
+        self.status_message = format!("Benchmarking {} on a {:.0} s sample...", model.as_str(), pipeline::BENCHMARK_SECONDS);
+        
+        let (tx, rx) = channel();
+        self.benchmark_receiver = Some(rx);
+        
+        std::thread::spawn(move || {
+            let result = pipeline::benchmark(&audio_path, duration, &recognition, &whisper::CancelToken::default())
+                .map(|factor| (model, factor))
+                .map_err(|e| e.to_string());
+            let _ = tx.send(result);
+        });
+    }
+    
+    /// 耗时和磁盘占用预估窗口
+    /// 当前模型的基准测试结果（每秒音频的识别耗时），没有测过时为 None
+    fn measured_factor(&self) -> Option<f64> {
+        self.benchmark_factor
+            .filter(|(model, _)| *model == self.whisper_model)
+            .map(|(_, factor)| factor)
+    }
+    
+    /// 预估耗时过长时请用户确认后再开始识别
+    fn long_run_window(&mut self, ctx: &egui::Context) {
+        let Some(warning) = self.long_run_warning.clone() else { return };
+        
+        let mut start = false;
+        let mut cancel = false;
+        egui::Window::new("⏳ Long Recognition")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(warning);
+                ui.label("Consider a smaller model (e.g. turbo), a GPU, or recognizing only part of the media.");
+                ui.horizontal(|ui| {
+                    start = ui.button("Start Anyway").clicked();
+                    cancel = ui.button("Cancel").clicked();
+                });
+            });
+        
+        if start {
+            self.long_run_warning = None;
+            self.spawn_recognition();
+        } else if cancel {
+            self.long_run_warning = None;
         }
     }
     
-    fn start_recognition(&mut self) {
-        if self.audio_segments.is_empty() {
-            self.status_message = "Please cut audio first!".to_string();
+    fn estimate_window(&mut self, ctx: &egui::Context) {
+        if !self.show_estimate {
             return;
         }
         
-        self.state = AppState::Processing;
-        self.processing_progress = 0.0;
-        self.processing_status = "Starting recognition...".to_string();
-        self.recognition_results.clear();
-        
-        let segments = self.audio_segments.clone();
-        let model = self.whisper_model;
-        let language = self.whisper_language.clone();
-        let custom_lang = self.custom_language_code.clone();
-        let cut_points = self.cut_points.clone();
-        let video_path = self.video_path.clone().unwrap();
-        
-        // 创建消息通道
-        let (tx, rx) = channel();
-        self.progress_receiver = Some(rx);
-        
-        std::thread::spawn(move || {
-            let total = segments.len();
-            let mut srt_files = Vec::new();
-            
-            for (i, segment) in segments.iter().enumerate() {
-                // 确定要使用的语言代码
-                let lang_code = match language {
-                    WhisperLanguage::Unknown => None,
-                    WhisperLanguage::Japanese => Some("ja"),
-                    WhisperLanguage::English => Some("en"),
-                    WhisperLanguage::Chinese => Some("zh"),
-                    WhisperLanguage::French => Some("fr"),
-                    WhisperLanguage::German => Some("de"),
-                    WhisperLanguage::Spanish => Some("es"),
-                    WhisperLanguage::Italian => Some("it"),
-                    WhisperLanguage::Russian => Some("ru"),
-                    WhisperLanguage::Custom => {
-                        if custom_lang.is_empty() {
-                            None
-                        } else {
-                            Some(custom_lang.as_str())
-                        }
-                    }
+        let mut open = true;
+        egui::Window::new("📊 Estimate")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let Some(duration) = self.media_duration() else {
+                    ui.label("Load a video or audio file first");
+                    return;
                 };
+                let estimate = pipeline::estimate(duration, self.whisper_model, self.measured_factor());
                 
-                // 使用新的实时输出版本
-                match whisper::recognize_audio_realtime(segment, model, lang_code, tx.clone(), i + 1, total) {
-                    Ok((srt_path, text)) => {
-                        srt_files.push(srt_path);
-                        // 发送识别结果
-                        let _ = tx.send(ProgressMessage::Result { 
-                            segment: i + 1, 
-                            text 
-                        });
-                        // 发送进度（识别完成后）
-                        let _ = tx.send(ProgressMessage::Progress { 
-                            current: i + 1, 
-                            total 
-                        });
-                    }
-                    Err(e) => {
-                        let error_msg = format!("Failed to recognize segment {}: {}", i + 1, e);
-                        eprintln!("{}", error_msg);
-                        let _ = tx.send(ProgressMessage::Error(error_msg));
-                    }
-                }
-            }
-            
-            // Merge subtitles
-            if !srt_files.is_empty() {
-                let output_path = video_path.with_extension("srt");
-                match srt_merger::merge_srt_files(&srt_files, &cut_points, &output_path) {
-                    Ok(_) => {
-                        println!("Subtitles merged successfully: {:?}", output_path);
-                    }
-                    Err(e) => {
-                        eprintln!("Failed to merge subtitles: {}", e);
-                        let _ = tx.send(ProgressMessage::Error(format!("Failed to merge: {}", e)));
+                egui::Grid::new("estimate_grid").num_columns(2).show(ui, |ui| {
+                    ui.label("Media duration:");
+                    ui.label(Self::format_time(duration));
+                    ui.end_row();
+                    ui.label("Recognition time:");
+                    ui.label(format!("~{} with {}", format_eta(estimate.recognition_seconds), self.whisper_model.as_str()));
+                    ui.end_row();
+                    ui.label("Extracted WAV:");
+                    ui.label(format_bytes(estimate.wav_bytes));
+                    ui.end_row();
+                    ui.label("MP3 segments:");
+                    ui.label(format_bytes(estimate.segment_bytes));
+                    ui.end_row();
+                    ui.label("Peak while cutting:");
+                    ui.label(format_bytes(estimate.peak_bytes));
+                    ui.end_row();
+                });
+                
+                ui.add_space(5.0);
+                match self.measured_factor() {
+                    Some(factor) => ui.label(format!("Based on a benchmark: {:.2} s per second of audio", factor)),
+                    None => ui.label("Based on rough CPU figures; run a benchmark for this machine"),
+                };
+                
+                if self.benchmark_receiver.is_some() {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Benchmarking...");
+                    });
+                } else {
+                    let can_benchmark = self.audio_path.is_some() && self.state != AppState::Processing;
+                    if ui.add_enabled(can_benchmark, egui::Button::new(format!("⏱ Benchmark {:.0} s Sample", pipeline::BENCHMARK_SECONDS)))
+                        .on_disabled_hover_text("Extract the audio first")
+                        .clicked() {
+                        self.run_benchmark();
                     }
                 }
-            }
-            
-            // 发送完成消息
-            let _ = tx.send(ProgressMessage::Completed);
+            });
+        self.show_estimate = open;
+    }
+    
+    /// 在后台只识别开头 `preview_seconds` 秒，用于确认语言和模型设置，结果只显示在设置面板中
+    /// 
+    /// 还没提取音频时直接从视频中截取
+    fn preview_recognition(&mut self) {
+        let Some(media_path) = self.audio_path.clone().or_else(|| self.video_path.clone()) else { return };
+        let seconds = self.preview_seconds;
+        let duration = self.known_duration();
+        let recognition = self.recognition_options();
+        self.preview_cancel = whisper::CancelToken::default();
+        let cancel = self.preview_cancel.clone();
+        let (tx, rx) = channel();
+        self.preview_receiver = Some(rx);
+        self.preview_result = None;
+        
+        std::thread::spawn(move || {
+            let result = pipeline::preview(&media_path, seconds, duration, &recognition, &cancel)
+                .map(|subtitles| subtitles.iter()
+                    .map(|entry| format!("[{}] {}", Self::format_time(entry.start_time), entry.text.replace('\n', " ")))
+                    .collect::<Vec<_>>()
+                    .join("\n"))
+                .map_err(|e| e.to_string());
+            let _ = tx.send(result);
         });
     }
     
-    fn format_time(seconds: f64) -> String {
-        let hours = (seconds / 3600.0).floor() as u32;
-        let minutes = ((seconds % 3600.0) / 60.0).floor() as u32;
-        let secs = (seconds % 60.0).floor() as u32;
-        let millis = ((seconds % 1.0) * 1000.0).floor() as u32;
+    /// 快速识别 `quick_path` 指向的音频文件，结果只显示在快速识别窗口中
+    fn quick_transcribe(&mut self) {
+        let path = PathBuf::from(self.quick_path.trim().trim_matches('"'));
+        if !path.is_file() {
+            self.quick_result = Some(Err(format!("File not found: {}", path.display())));
+            return;
+        }
         
-        format!("{:02}:{:02}:{:02}.{:03}", hours, minutes, secs, millis)
+        let recognition = self.recognition_options();
+        self.quick_cancel = whisper::CancelToken::default();
+        let cancel = self.quick_cancel.clone();
+        let (tx, rx) = channel();
+        self.quick_receiver = Some(rx);
+        self.quick_result = None;
+        
+        std::thread::spawn(move || {
+            // 快速识别不显示逐行进度
+            let (progress_tx, _progress_rx) = channel();
+            let result = pipeline::quick_transcribe(&path, &recognition, &cancel, &progress_tx)
+                .map_err(|e| e.to_string());
+            let _ = tx.send(result);
+        });
     }
     
-    fn cleanup_temp_files(&mut self) {
-        // 删除提取的音频文件
-        if let Some(audio_path) = &self.audio_path {
-            if audio_path.exists() {
-                let _ = fs::remove_file(audio_path);
+    /// 预览识别：用当前模型和语言识别开头几秒，确认设置无误后再识别整个文件
+    fn preview_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if self.preview_receiver.is_some() {
+                ui.spinner();
+                ui.label(format!("Previewing first {:.0} s...", self.preview_seconds));
+                if ui.button("✖ Cancel").clicked() {
+                    self.preview_cancel.cancel();
+                }
+            } else {
+                let can_preview = (self.audio_path.is_some() || self.video_path.is_some()) && self.state != AppState::Processing;
+                if ui.add_enabled(can_preview, egui::Button::new(format!("🔍 Preview First {:.0} s", self.preview_seconds)))
+                    .on_hover_text("Recognize only the beginning with the current model and language to check the settings. No segments are created and the subtitles are not changed")
+                    .clicked() {
+                    self.preview_recognition();
+                }
+                ui.add(egui::DragValue::new(&mut self.preview_seconds)
+                    .range(5.0..=600.0)
+                    .speed(1.0)
+                    .suffix(" s"));
             }
-        }
+        });
         
-        // 删除切割的音频片段和对应的字幕文件
-        for segment in &self.audio_segments {
-            if segment.exists() {
-                let _ = fs::remove_file(segment);
+        let mut close = false;
+        match &self.preview_result {
+            Some(Ok(text)) => {
+                let text = if text.is_empty() { "(no speech recognized)" } else { text.as_str() };
+                egui::ScrollArea::vertical().id_salt("preview_result").max_height(120.0).show(ui, |ui| {
+                    ui.label(text);
+                });
+                close = ui.small_button("Close preview").clicked();
             }
-            
-            // 删除对应的 SRT 文件
-            let srt_path = segment.with_extension("srt");
-            if srt_path.exists() {
-                let _ = fs::remove_file(&srt_path);
+            Some(Err(e)) => {
+                ui.colored_label(egui::Color32::RED, format!("❌ Preview failed: {}", e));
+                close = ui.small_button("Close preview").clicked();
             }
+            None => {}
+        }
+        if close {
+            self.preview_result = None;
+        }
+    }
+    
+    /// 快速识别窗口：拖入或粘贴短音频的路径，识别结果显示在窗口中
+    fn quick_transcribe_window(&mut self, ctx: &egui::Context) {
+        if !self.show_quick_transcribe {
+            return;
         }
         
-        // 删除手动切割的片段
-        if let Some(manual_seg) = &self.manual_segment {
-            if manual_seg.exists() {
-                let _ = fs::remove_file(manual_seg);
+        let mut open = true;
+        egui::Window::new("⚡ Quick Transcribe")
+            .open(&mut open)
+            .collapsible(false)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                ui.label("Drop a short audio file here or paste its path. The project is not changed.");
+                ui.horizontal(|ui| {
+                    ui.add(egui::TextEdit::singleline(&mut self.quick_path)
+                        .hint_text("/path/to/clip.mp3")
+                        .desired_width(280.0));
+                    if self.quick_receiver.is_some() {
+                        ui.spinner();
+                        if ui.button("✖ Cancel").clicked() {
+                            self.quick_cancel.cancel();
+                        }
+                    } else if ui.add_enabled(!self.quick_path.trim().is_empty(), egui::Button::new("▶ Transcribe")).clicked() {
+                        self.quick_transcribe();
+                    }
+                });
+                ui.label(format!("Model: {}", self.whisper_model.as_str()));
+                
+                match &mut self.quick_result {
+                    Some(Ok(text)) => {
+                        ui.add_space(5.0);
+                        ui.add(egui::TextEdit::multiline(text).desired_rows(6).desired_width(f32::INFINITY));
+                        if ui.button("📋 Copy").clicked() {
+                            ui.ctx().copy_text(text.clone());
+                        }
+                    }
+                    Some(Err(e)) => {
+                        ui.colored_label(egui::Color32::RED, format!("❌ {}", e));
+                    }
+                    None => {}
+                }
+            });
+        self.show_quick_transcribe = open;
+    }
+    
+    /// 把界面上的播放速度和精确 seek 设置应用到播放器（新加载的播放器默认为原速）
+    fn apply_playback_speed(&mut self) {
+        if let Some(player) = &mut self.audio_player {
+            player.set_accurate_seek(self.accurate_seek);
+            player.set_speed(self.playback_speed, self.preserve_pitch);
+        }
+    }
+    
+    /// 把当前播放位置设为循环起点 A（晚于 B 时两者交换）
+    fn set_loop_a(&mut self) {
+        self.loop_a = Some(self.current_position);
+        self.order_loop_points();
+    }
+    
+    /// 把当前播放位置设为循环终点 B（早于 A 时两者交换）
+    fn set_loop_b(&mut self) {
+        self.loop_b = Some(self.current_position);
+        self.order_loop_points();
+    }
+    
+    fn order_loop_points(&mut self) {
+        if let (Some(a), Some(b)) = (self.loop_a, self.loop_b) {
+            if a > b {
+                self.loop_a = Some(b);
+                self.loop_b = Some(a);
             }
-            let srt_path = manual_seg.with_extension("srt");
-            if srt_path.exists() {
-                let _ = fs::remove_file(&srt_path);
+        }
+    }
+    
+    fn clear_loop(&mut self) {
+        self.loop_a = None;
+        self.loop_b = None;
+        self.loop_enabled = false;
+    }
+    
+    /// A、B 都已设置且区间有效时返回循环区间
+    fn loop_range(&self) -> Option<(f64, f64)> {
+        match (self.loop_a, self.loop_b) {
+            (Some(a), Some(b)) if b > a => Some((a, b)),
+            _ => None,
+        }
+    }
+    
+    /// 循环开启且播放超过 B 时跳回 A
+    fn enforce_loop(&mut self) {
+        if !self.loop_enabled || !self.is_playing {
+            return;
+        }
+        let Some((a, b)) = self.loop_range() else { return };
+        if self.current_position >= b {
+            if let Some(player) = &mut self.audio_player {
+                player.seek(a);
+                self.current_position = a;
             }
         }
-        
-        self.status_message = "Temporary files cleaned up.".to_string();
     }
     
-    fn rerecognize_segment(&mut self) {
-        if self.audio_segments.is_empty() || self.selected_segment_index >= self.audio_segments.len() {
-            self.status_message = "Invalid segment selection!".to_string();
+    /// 只播放一条字幕的时间范围，到结束时间自动暂停
+    fn play_cue(&mut self, start: f64, end: f64) {
+        let Some(player) = &mut self.audio_player else { return };
+        player.seek(start);
+        player.play();
+        self.is_playing = true;
+        self.current_position = start;
+        self.play_until = Some((start, end));
+    }
+    
+    /// 试听单条字幕时播放到结束时间后暂停
+    /// 
+    /// 暂停或跳到范围以外（拖动进度条、跳转到其他字幕）视为恢复正常播放，不再自动暂停
+    fn enforce_play_until(&mut self) {
+        let Some((start, end)) = self.play_until else { return };
+        let position = self.current_position;
+        if !self.is_playing || position < start - PLAY_UNTIL_TOLERANCE || position > end + PLAY_UNTIL_TOLERANCE {
+            self.play_until = None;
+        } else if position >= end {
+            if let Some(player) = &mut self.audio_player {
+                player.pause();
+            }
+            self.is_playing = false;
+            self.play_until = None;
+        }
+    }
+    
+    /// 播放器快捷键：`M` 标记切割点，`[` 设置 A，`]` 设置 B，`L` 开关循环（输入框有焦点时不响应）
+    /// 
+    /// 启用了全局快捷键时，窗口没有焦点也能用它标记切割点
+    fn handle_player_shortcuts(&mut self, ctx: &egui::Context) {
+        // 没有加载音频时也要取出事件，避免之后一次性触发
+        let global_mark = self.global_hotkey.as_ref().is_some_and(|hotkey| hotkey.pressed());
+        if self.audio_player.is_none() {
+            return;
+        }
+        if global_mark {
+            self.add_cut_point();
+        }
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+        let (mark, set_a, set_b, toggle) = ctx.input(|i| (
+            i.key_pressed(egui::Key::M),
+            i.key_pressed(egui::Key::OpenBracket),
+            i.key_pressed(egui::Key::CloseBracket),
+            i.key_pressed(egui::Key::L),
+        ));
+        if mark {
+            self.add_cut_point();
+        }
+        if set_a {
+            self.set_loop_a();
+        }
+        if set_b {
+            self.set_loop_b();
+        }
+        if toggle {
+            self.loop_enabled = !self.loop_enabled;
+        }
+    }
+    
+    /// 在后台线程提取音频，完成后在 update 中加载播放器
+    fn extract_audio(&mut self) {
+        if let Some(video_path) = self.video_path.clone() {
+            let (tx, rx) = channel();
+            self.extraction_receiver = Some(rx);
+            
+            if let Some(url) = self.stream_source.clone() {
+                // 网络流：下载音频到 video_path
+                self.status_message = format!("Downloading audio from {}...", url);
+                let max_seconds = self.stream_max_seconds.trim().parse::<f64>().ok();
+                
+                std::thread::spawn(move || {
+                    let result = ffmpeg::extract_audio_from_url(&url, &video_path, max_seconds)
+                        .map(|audio_path| (audio_path, None))
+                        .map_err(|e| e.to_string());
+                    let _ = tx.send(result);
+                });
+            } else {
+                self.status_message = "Extracting audio...".to_string();
+                let whisper_audio = self.whisper_audio;
+                
+                std::thread::spawn(move || {
+                    let result = pipeline::prepare_audio(&video_path, whisper_audio)
+                        .map_err(|e| e.to_string());
+                    let _ = tx.send(result);
+                });
+            }
+        }
+    }
+    
+    fn preview_speech_regions(&mut self) {
+        if let Some(audio_path) = self.audio_path.clone() {
+            self.status_message = "Detecting speech regions...".to_string();
+            self.speech_regions.clear();
+            
+            let threshold = self.vad_threshold;
+            let (tx, rx) = channel();
+            self.vad_preview_receiver = Some(rx);
+            
+            std::thread::spawn(move || {
+                let result = vad_recognition::preview_speech_regions(&audio_path, threshold)
+                    .map_err(|e| e.to_string());
+                let _ = tx.send(result);
+            });
+        }
+    }
+    
+    /// 在后台查找最响的区域，找到后跳转播放，用于快速确认文件加载正确
+    fn jump_to_loudest(&mut self) {
+        if let Some(audio_path) = self.audio_path.clone() {
+            self.status_message = "Finding loudest region...".to_string();
+            
+            let (tx, rx) = channel();
+            self.loudest_receiver = Some(rx);
+            
+            std::thread::spawn(move || {
+                let result = vad_recognition::loudest_region(&audio_path)
+                    .map_err(|e| e.to_string());
+                let _ = tx.send(result);
+            });
+        }
+    }
+    
+    fn scan_clipping(&mut self) {
+        if let Some(audio_path) = self.audio_path.clone() {
+            self.status_message = "Scanning for clipping...".to_string();
+            
+            let (tx, rx) = channel();
+            self.clip_scan_receiver = Some(rx);
+            
+            std::thread::spawn(move || {
+                let result = audio_player::scan_clipping(&audio_path)
+                    .map_err(|e| e.to_string());
+                let _ = tx.send(result);
+            });
+        }
+    }
+    
+    /// 开关全局快捷键，注册失败（未编译该功能、已被占用、平台不支持）时回到窗口内的 `M` 键
+    fn set_global_hotkey(&mut self, enabled: bool) {
+        if !enabled {
+            self.global_hotkey = None;
             return;
         }
+        match hotkey::MarkHotkey::register() {
+            Ok(registered) => {
+                self.global_hotkey = Some(registered);
+                self.status_message = format!("Global hotkey {} marks a cut point", hotkey::HOTKEY_LABEL);
+            }
+            Err(e) => {
+                self.status_message = format!("Global hotkey unavailable: {}; press M in this window instead", e);
+            }
+        }
+    }
+    
+    /// 按目标时长生成切割点（替换已有的切割点），每个切割点吸附到附近的静音
+    /// 
+    /// 静音来自"Preview Speech"检测的语音区域，没有检测过时均匀切割
+    fn cut_by_target_length(&mut self) {
+        let Some(total) = self.known_duration() else {
+            self.status_message = "Cannot place cut points: the media duration is unknown".to_string();
+            return;
+        };
+        let silences = vad_recognition::silence_regions(&self.speech_regions, total);
+        let cut_points = vad_recognition::target_cut_points(total, self.target_segment_minutes * 60.0, self.snap_window_seconds, &silences);
         
-        self.state = AppState::Processing;
-        self.processing_progress = 0.0;
-        self.processing_status = "Re-recognizing segment...".to_string();
-        self.recognition_results.clear();
+        self.cut_points = pipeline::valid_cut_points(&cut_points, Some(total));
+        self.editing_cut_point = None;
+        self.status_message = format!("Placed {} cut points every ~{} min{}", self.cut_points.len(), self.target_segment_minutes,
+            if self.speech_regions.is_empty() { " (run Preview Speech first to snap them to silences)" } else { ", snapped to silences" });
+    }
+    
+    fn add_cut_point(&mut self) {
+        let mut points = self.cut_points.clone();
+        points.push(self.current_position);
+        let points = pipeline::valid_cut_points(&points, self.known_duration());
+        if points.len() > self.cut_points.len() {
+            self.cut_points = points;
+        } else {
+            self.status_message = "Cut point ignored: too close to the start, the end or another cut point".to_string();
+        }
+    }
+    
+    /// 播放器给出的总时长，未加载时为 None
+    fn known_duration(&self) -> Option<f64> {
+        (self.total_duration > 0.0).then_some(self.total_duration)
+    }
+    
+    /// 把第 `index` 个切割点改为输入的时间并重新排序
+    /// 
+    /// 时间格式错误，或离开头、结尾、其他切割点太近时保持原样
+    fn move_cut_point(&mut self, index: usize, input: &str) {
+        let time = match manual_cut::parse_time_string(input) {
+            Ok(time) => time,
+            Err(e) => {
+                self.status_message = format!("Invalid time: {}", e);
+                return;
+            }
+        };
         
-        let segment = self.audio_segments[self.selected_segment_index].clone();
-        let segment_index = self.selected_segment_index;
-        let all_segments = self.audio_segments.clone();
-        let model = self.whisper_model;
-        let language = self.whisper_language.clone();
-        let custom_lang = self.custom_language_code.clone();
-        let cut_points = self.cut_points.clone();
-        let video_path = self.video_path.clone().unwrap();
+        let mut points = self.cut_points.clone();
+        let Some(point) = points.get_mut(index) else { return };
+        *point = time;
+        let points_len = points.len();
+        let valid = pipeline::valid_cut_points(&points, self.known_duration());
+        if valid.len() == points_len {
+            self.cut_points = valid;
+            self.status_message = format!("Cut point moved to {}", Self::format_time(time));
+        } else {
+            self.status_message = "Cut point not moved: too close to the start, the end or another cut point".to_string();
+        }
+    }
+    
+    fn remove_cut_point(&mut self, index: usize) {
+        if index < self.cut_points.len() {
+            self.cut_points.remove(index);
+        }
+    }
+    
+    /// 在后台线程切割音频，进度和结果在 update 中处理
+    fn cut_audio(&mut self) {
+        if let Some(audio_path) = self.audio_path.clone() {
+            self.status_message = "Cutting audio...".to_string();
+            self.state = AppState::Processing;
+            self.audio_segments.clear();  // 旧片段会被覆盖
+            self.cut_progress = 0.0;
+            self.cut_status = "Cutting audio...".to_string();
+            
+            // 工作区中保存的旧切割点也可能落在开头或结尾
+            self.cut_points = pipeline::valid_cut_points(&self.cut_points, self.known_duration());
+            let cut_points = self.cut_points.clone();
+            let lossless = self.lossless;
+            let accurate = self.accurate_cut;
+            
+            let (progress_tx, progress_rx) = channel();
+            let (tx, rx) = channel();
+            self.cut_progress_receiver = Some(progress_rx);
+            self.cut_receiver = Some(rx);
+            
+            std::thread::spawn(move || {
+                let result = pipeline::cut_audio_with_progress(&audio_path, &cut_points, lossless, accurate, &progress_tx)
+                    .map_err(|e| e.to_string());
+                let _ = tx.send(result);
+            });
+        }
+    }
+    
+    /// 在后台把所有片段按顺序拼接为一个音频文件
+    fn join_segments(&mut self) {
+        let Some(first) = self.audio_segments.first().map(|segment| segment.path.clone()) else { return };
+        let stem = first.file_stem().unwrap_or_default().to_string_lossy();
+        let extension = first.extension().unwrap_or_default().to_string_lossy();
+        let mut dialog = rfd::FileDialog::new()
+            .set_file_name(format!("{}_joined.{}", stem.trim_end_matches("_000"), extension));
+        if let Some(dir) = first.parent() {
+            dialog = dialog.set_directory(dir);
+        }
+        let Some(output_path) = dialog.save_file() else { return };
         
-        // 创建消息通道
+        let segments = self.segment_paths();
+        self.status_message = format!("Joining {} segments...", segments.len());
         let (tx, rx) = channel();
-        self.progress_receiver = Some(rx);
+        self.join_receiver = Some(rx);
         
         std::thread::spawn(move || {
-            // 重新识别单个片段
-            match recognition::recognize_single_segment(
-                &segment,
-                segment_index,
-                all_segments.len(),
-                model,
-                &language,
-                &custom_lang,
-                tx.clone(),
-            ) {
-                Ok((_srt_path, text)) => {
-                    let _ = tx.send(ProgressMessage::Result { 
-                        segment: segment_index + 1, 
-                        text 
-                    });
-                    let _ = tx.send(ProgressMessage::Progress { 
-                        current: 1, 
-                        total: 1 
-                    });
-                    
-                    // 收集所有字幕文件并重新合并
-                    let mut srt_files = Vec::new();
-                    for seg in &all_segments {
-                        let srt = seg.with_extension("srt");
-                        if srt.exists() {
-                            srt_files.push(srt);
-                        }
-                    }
-                    
-                    // 重新合并字幕
-                    if !srt_files.is_empty() {
-                        let output_path = video_path.with_extension("srt");
-                        match recognition::remerge_subtitles(&srt_files, &cut_points, &output_path) {
-                            Ok(_) => {
-                                println!("Subtitles remerged successfully: {:?}", output_path);
-                            }
-                            Err(e) => {
-                                eprintln!("Failed to remerge subtitles: {}", e);
-                                let _ = tx.send(ProgressMessage::Error(format!("Failed to remerge: {}", e)));
-                            }
-                        }
-                    }
-                }
-                Err(e) => {
-                    let error_msg = format!("Failed to re-recognize segment {}: {}", segment_index + 1, e);
-                    eprintln!("{}", error_msg);
-                    let _ = tx.send(ProgressMessage::Error(error_msg));
-                }
-            }
-            
-            let _ = tx.send(ProgressMessage::Completed);
+            let warning = ffmpeg::segment_format_mismatch(&segments);
+            let result = ffmpeg::concat_segments(&segments, &output_path)
+                .map(|path| (path, warning))
+                .map_err(|e| e.to_string());
+            let _ = tx.send(result);
         });
     }
     
-    fn cut_manual_segment(&mut self) {
-        if let Some(audio_path) = &self.audio_path {
-            // 解析时间
-            let start_time = match manual_cut::parse_time_string(&self.manual_start_time) {
-                Ok(t) => t,
-                Err(_) => {
-                    self.status_message = "Invalid start time format!".to_string();
-                    return;
-                }
-            };
-            
-            let end_time = match manual_cut::parse_time_string(&self.manual_end_time) {
-                Ok(t) => t,
-                Err(_) => {
-                    self.status_message = "Invalid end time format!".to_string();
-                    return;
-                }
-            };
-            
-            // 切割片段
-            match manual_cut::cut_audio_segment(audio_path, start_time, end_time) {
-                Ok(segment_path) => {
-                    self.manual_segment = Some(segment_path);
-                    self.status_message = format!("Manual segment cut: {:.2}s - {:.2}s", start_time, end_time);
-                }
-                Err(e) => {
-                    self.status_message = format!("Failed to cut segment: {}", e);
-                }
+    /// 在后台把当前字幕（含锁定的字幕）嵌入视频，按 `mode` 封装为字幕轨或烧录进画面
+    /// 
+    /// 字幕先写到临时 SRT（UTF-8），不受输出编码设置影响
+    fn export_subtitled_video(&mut self, mode: ffmpeg::MuxMode) {
+        let (Some(video_path), Some(srt_path)) = (self.video_path.clone(), self.merged_srt_path()) else { return };
+        let mut subtitles = match subtitle::load_srt_file(&srt_path) {
+            Ok(subtitles) => subtitles,
+            Err(e) => {
+                self.status_message = format!("Failed to load subtitles: {}", e);
+                return;
             }
+        };
+        subtitle::apply_locked(&mut subtitles, &self.locked_subtitles);
+        
+        // 软字幕默认用原容器，原容器不支持时改为 MKV
+        let stem = video_path.file_stem().unwrap_or_default().to_string_lossy();
+        let extension = match mode {
+            ffmpeg::MuxMode::Soft if ffmpeg::soft_subtitle_codec(&video_path).is_none() => "mkv".to_string(),
+            _ => video_path.extension().unwrap_or_default().to_string_lossy().to_string(),
+        };
+        let mut dialog = rfd::FileDialog::new()
+            .set_file_name(format!("{}_subtitled.{}", stem, extension));
+        if let Some(dir) = video_path.parent() {
+            dialog = dialog.set_directory(dir);
+        }
+        let Some(output_path) = dialog.save_file() else { return };
+        if output_path == video_path {
+            self.status_message = "Choose a different file name, the source video would be overwritten".to_string();
+            return;
+        }
+        
+        let temp_srt = std::env::temp_dir().join(format!("whisper_mux_{}.srt", std::process::id()));
+        if let Err(e) = std::fs::write(&temp_srt, subtitle::to_srt_string(&subtitles)) {
+            self.status_message = format!("Failed to write temporary subtitles: {}", e);
+            return;
         }
+        
+        self.status_message = format!("Exporting video with subtitles ({})...", mode.as_str());
+        self.mux_progress = 0.0;
+        let (progress_tx, progress_rx) = channel();
+        let (tx, rx) = channel();
+        self.mux_progress_receiver = Some(progress_rx);
+        self.mux_receiver = Some(rx);
+        
+        std::thread::spawn(move || {
+            let result = ffmpeg::mux_subtitles(&video_path, &temp_srt, &output_path, mode, Some(&progress_tx))
+                .map(|_| output_path)
+                .map_err(|e| e.to_string());
+            let _ = std::fs::remove_file(&temp_srt);
+            let _ = tx.send(result);
+        });
     }
     
-    fn recognize_manual_segment(&mut self) {
-        if self.manual_segment.is_none() {
-            self.status_message = "No manual segment to recognize!".to_string();
+    /// 检查设置后开始识别所有片段
+    /// 
+    /// 时长未知（探测失败）时拒绝开始；按当前模型预估耗时超过 `LONG_RUN_WARNING_SECONDS` 时先请用户确认
+    fn start_recognition(&mut self) {
+        if self.audio_segments.is_empty() {
+            self.status_message = "Please cut audio first!".to_string();
+            return;
+        }
+        if self.total_duration <= 0.0 {
+            self.status_message = "Cannot start: the media duration is unknown (probing failed). Reload the file or check it with ffprobe".to_string();
+            return;
+        }
+        
+        let estimate = pipeline::estimate(self.total_duration, self.whisper_model, self.measured_factor());
+        if estimate.recognition_seconds > LONG_RUN_WARNING_SECONDS {
+            let basis = if self.measured_factor().is_some() { "measured by the benchmark" } else { "on a typical CPU" };
+            self.long_run_warning = Some(format!("Recognizing {} of audio with the {} model will take about {} ({}).",
+                Self::format_time(self.total_duration), self.whisper_model.as_str(), format_eta(estimate.recognition_seconds), basis));
             return;
         }
         
+        self.spawn_recognition();
+    }
+    
+    fn spawn_recognition(&mut self) {
+        let Some(output_path) = self.output_srt_path() else { return };
+        
         self.state = AppState::Processing;
         self.processing_progress = 0.0;
-        self.processing_status = "Recognizing manual segment...".to_string();
+        self.processing_status = "Starting recognition...".to_string();
         self.recognition_results.clear();
         
-        let segment = self.manual_segment.clone().unwrap();
-        let model = self.whisper_model;
-        let language = self.whisper_language.clone();
-        let custom_lang = self.custom_language_code.clone();
-        let video_path = self.video_path.clone().unwrap();
-        let all_segments = self.audio_segments.clone();
-        let cut_points = self.cut_points.clone();
-        
-        // 解析手动片段的起始时间
-        let start_time = match manual_cut::parse_time_string(&self.manual_start_time) {
-            Ok(t) => t,
-            Err(_) => 0.0,
-        };
+        let segments = self.segment_paths();
+        let offsets = pipeline::cut_offsets(&self.audio_segments, self.stream_start());
+        let recognition = self.recognition_options();
+        let merge = self.merge_options();
         
         // 创建消息通道
         let (tx, rx) = channel();
         self.progress_receiver = Some(rx);
+        let cancel = self.new_cancel_token();
         
         std::thread::spawn(move || {
-            // 识别手动片段
-            match recognition::recognize_single_segment(
-                &segment,
-                0,
-                1,
-                model,
-                &language,
-                &custom_lang,
-                tx.clone(),
-            ) {
-                Ok((_srt_path, text)) => {
-                    let _ = tx.send(ProgressMessage::Result { 
-                        segment: 0, 
-                        text 
-                    });
-                    let _ = tx.send(ProgressMessage::Progress { 
-                        current: 1, 
-                        total: 1 
-                    });
-                    
-                    // 收集所有字幕文件（包括手动片段）
-                    let mut srt_files = Vec::new();
-                    let mut segment_times = Vec::new();
-                    
-                    // 添加所有自动切割的片段
-                    for (i, seg) in all_segments.iter().enumerate() {
-                        let srt = seg.with_extension("srt");
-                        if srt.exists() {
-                            srt_files.push(srt);
-                            // 计算每段的起始时间
-                            if i == 0 {
-                                segment_times.push((0.0, srt_files.len() - 1));
-                            } else if i - 1 < cut_points.len() {
-                                segment_times.push((cut_points[i - 1], srt_files.len() - 1));
-                            }
-                        }
-                    }
-                    
-                    // 添加手动片段
-                    let manual_srt = segment.with_extension("srt");
-                    if manual_srt.exists() {
-                        srt_files.push(manual_srt);
-                        segment_times.push((start_time, srt_files.len() - 1));
-                    }
-                    
-                    // 按时间排序
-                    segment_times.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
-                    
-                    // 重新排列 srt_files
-                    let mut sorted_srt_files = Vec::new();
-                    let mut sorted_cut_points = Vec::new();
-                    
-                    for (time, idx) in segment_times {
-                        sorted_srt_files.push(srt_files[idx].clone());
-                        if time > 0.0 {
-                            sorted_cut_points.push(time);
-                        }
-                    }
-                    
-                    // 合并字幕
-                    if !sorted_srt_files.is_empty() {
-                        let output_path = video_path.with_extension("srt");
-                        match recognition::remerge_subtitles(&sorted_srt_files, &sorted_cut_points, &output_path) {
-                            Ok(_) => {
-                                println!("Subtitles merged successfully: {:?}", output_path);
-                            }
-                            Err(e) => {
-                                eprintln!("Failed to merge subtitles: {}", e);
-                                let _ = tx.send(ProgressMessage::Error(format!("Failed to merge: {}", e)));
-                            }
-                        }
-                    }
-                }
-                Err(e) => {
-                    let error_msg = format!("Failed to recognize manual segment: {}", e);
-                    eprintln!("{}", error_msg);
-                    let _ = tx.send(ProgressMessage::Error(error_msg));
-                }
+            let indices: Vec<usize> = (0..segments.len()).collect();
+            pipeline::recognize_segments(&segments, &indices, &recognition, &cancel, &tx);
+            
+            // Merge subtitles（停止后保留原有的合并结果）
+            if !cancel.is_cancelled() {
+                merge_in_background(&offsets, recognition.subtitles_dir.as_deref(), &merge, &output_path, &tx);
             }
             
+            // 发送完成消息
             let _ = tx.send(ProgressMessage::Completed);
         });
     }
     
-    fn stop_recognition(&mut self) {
-        // 终止所有 whisper 和 python 进程
-        Self::kill_whisper_processes();
-        
-        // 重置状态
-        self.state = AppState::AudioExtracted;
-        self.status_message = "Recognition stopped and all processes killed.".to_string();
-        self.progress_receiver = None;
-        self.processing_progress = 0.0;
-        self.processing_status = String::new();
+    /// 当前设置对应的识别参数
+    fn recognition_options(&self) -> pipeline::RecognitionOptions {
+        pipeline::RecognitionOptions {
+            model: self.whisper_model,
+            language: self.whisper_language.code(&self.custom_language_code).map(String::from),
+            threads: self.whisper_threads,
+            precise_timing: self.precise_timing,
+            subtitles_dir: self.segment_subtitles_dir(),
+            lossless: self.lossless,
+            whisper_audio: self.whisper_audio,
+            accurate_cut: self.accurate_cut,
+            workers: self.recognition_workers,
+            whisper: self.whisper_options.clone(),
+        }
     }
     
-    fn kill_whisper_processes() {
-        // 查找并终止所有 whisper 相关进程
-        if let Ok(output) = Command::new("ps")
-            .args(&["aux"])
-            .output()
-        {
-            let output_str = String::from_utf8_lossy(&output.stdout);
+    /// 各片段的文件路径
+    fn segment_paths(&self) -> Vec<PathBuf> {
+        self.audio_segments.iter().map(|segment| segment.path.clone()).collect()
+    }
+    
+    /// 原音频的起始时间戳，用于把片段和时间段的偏移修正到播放时间轴
+    fn stream_start(&self) -> f64 {
+        self.audio_path.as_deref().map_or(0.0, pipeline::stream_start_time)
+    }
+    
+    /// 片段字幕的目录：有工作区时为工作区的 `subtitles/`，否则写在片段旁边
+    fn segment_subtitles_dir(&self) -> Option<PathBuf> {
+        self.workspace_dir.as_ref().map(|dir| dir.join("subtitles"))
+    }
+    
+    /// 把旧项目中片段旁边的字幕迁移到工作区的 `subtitles/`
+    fn migrate_segment_subtitles(&self) {
+        let Some(subtitles_dir) = self.segment_subtitles_dir() else { return };
+        let mut segments = self.segment_paths();
+        segments.extend(self.manual_segment.clone());
+        match workspace::migrate_segment_subtitles(&segments, &subtitles_dir) {
+            Ok(0) => {}
+            Ok(count) => println!("Migrated {} segment subtitles to {:?}", count, subtitles_dir),
+            Err(e) => eprintln!("Failed to migrate segment subtitles: {}", e),
+        }
+    }
+    
+    /// 当前设置对应的合并参数
+    fn merge_options(&self) -> pipeline::MergeOptions {
+        // 每段字幕导出到工作区的 subtitles/，没有工作区时放在合并字幕旁边
+        let export_segments_to = self.export_segment_subtitles.then(|| {
+            self.workspace_dir.clone()
+                .or_else(|| self.video_path.as_ref().and_then(|p| p.parent()).map(|p| p.to_path_buf()))
+                .unwrap_or_default()
+                .join("subtitles")
+        });
+        
+        pipeline::MergeOptions {
+            priming_delay: self.priming_delay(),
+            encoding: self.output_encoding,
+            export_segments_to,
+            fps: self.export_fps(),
+            start_index: self.export_start_index(),
+            dedupe_boundaries: self.dedupe_boundaries,
+            nonspeech_patterns: if self.strip_nonspeech {
+                subtitle::parse_nonspeech_patterns(&self.nonspeech_patterns)
+            } else {
+                Vec::new()
+            },
+        }
+    }
+    
+    /// 输出时间戳取整使用的帧率，未填写或无效时返回 None
+    fn export_fps(&self) -> Option<f64> {
+        self.export_fps.trim().parse::<f64>().ok().filter(|fps| *fps > 0.0)
+    }
+    
+    /// 输出字幕的起始编号，未填写或无效时返回 None（从 1 开始）
+    fn export_start_index(&self) -> Option<usize> {
+        self.export_start_index.trim().parse::<usize>().ok().filter(|start| *start > 0)
+    }
+    
+    /// 用户填写的编码延迟（秒），所有片段统一扣除
+    /// 
+    /// 未填写或无效时返回 None，合并时按每个片段的格式推算，见 `ffmpeg::priming_delay`
+    fn priming_delay(&self) -> Option<f64> {
+        self.priming_delay_ms.trim().parse::<f64>().ok().map(|ms| ms / 1000.0)
+    }
+    
+    fn format_time(seconds: f64) -> String {
+        // 先四舍五入到整毫秒再拆分，59.9996 进位为 00:01:00.000 而不是截断成 59.999
+        let total_millis = (seconds.max(0.0) * 1000.0).round() as u64;
+        let hours = total_millis / 3_600_000;
+        let minutes = total_millis / 60_000 % 60;
+        let secs = total_millis / 1000 % 60;
+        let millis = total_millis % 1000;
+        
+        format!("{:02}:{:02}:{:02}.{:03}", hours, minutes, secs, millis)
+    }
+    
+    fn cleanup_temp_files(&mut self) {
+        // 删除提取的音频文件
+        if let Some(audio_path) = &self.audio_path {
+            if audio_path.exists() {
+                let _ = fs::remove_file(audio_path);
+            }
+        }
+        
+        // 删除切割的音频片段和对应的字幕文件
+        let subtitles_dir = self.segment_subtitles_dir();
+        for segment in self.segment_paths() {
+            if segment.exists() {
+                let _ = fs::remove_file(&segment);
+            }
             
-            for line in output_str.lines() {
-                // 查找包含 whisper 的进程
-                if line.contains("whisper") && !line.contains("grep") {
-                    if let Some(pid) = Self::extract_pid_from_ps_line(line) {
-                        let _ = Command::new("kill")
-                            .args(&["-9", &pid.to_string()])
-                            .output();
-                    }
+            // 删除对应的 SRT 文件（及精确时间的 JSON）
+            let srt_path = pipeline::segment_srt_path(&segment, subtitles_dir.as_deref());
+            if srt_path.exists() {
+                let _ = fs::remove_file(&srt_path);
+            }
+            let _ = fs::remove_file(srt_path.with_extension("json"));
+        }
+        
+        // 删除手动切割的片段
+        if let Some(manual_seg) = &self.manual_segment {
+            if manual_seg.exists() {
+                let _ = fs::remove_file(manual_seg);
+            }
+            let srt_path = pipeline::segment_srt_path(manual_seg, subtitles_dir.as_deref());
+            if srt_path.exists() {
+                let _ = fs::remove_file(&srt_path);
+            }
+            let _ = fs::remove_file(srt_path.with_extension("json"));
+        }
+        
+        self.status_message = "Temporary files cleaned up.".to_string();
+    }
+    
+    fn rerecognize_segment(&mut self) {
+        if self.audio_segments.is_empty() || self.selected_segment_index >= self.audio_segments.len() {
+            self.status_message = "Invalid segment selection!".to_string();
+            return;
+        }
+        
+        let Some(output_path) = self.output_srt_path() else { return };
+        
+        self.state = AppState::Processing;
+        self.processing_progress = 0.0;
+        self.processing_status = "Re-recognizing segment...".to_string();
+        self.recognition_results.clear();
+        
+        let segment_index = self.selected_segment_index;
+        let all_segments = self.segment_paths();
+        let offsets = pipeline::cut_offsets(&self.audio_segments, self.stream_start());
+        let recognition = self.recognition_options();
+        let merge = self.merge_options();
+        
+        // 创建消息通道
+        let (tx, rx) = channel();
+        self.progress_receiver = Some(rx);
+        let cancel = self.new_cancel_token();
+        
+        std::thread::spawn(move || {
+            // 重新识别单个片段，成功后重新合并所有字幕
+            if pipeline::recognize_segments(&all_segments, &[segment_index], &recognition, &cancel, &tx) > 0 {
+                merge_in_background(&offsets, recognition.subtitles_dir.as_deref(), &merge, &output_path, &tx);
+            }
+            
+            let _ = tx.send(ProgressMessage::Completed);
+        });
+    }
+    
+    /// 解析手动输入的开始和结束时间，格式错误时设置状态信息并返回 None
+    fn parse_manual_range(&mut self) -> Option<(f64, f64)> {
+        let start_time = match manual_cut::parse_time_string(&self.manual_start_time) {
+            Ok(t) => t,
+            Err(_) => {
+                self.status_message = "Invalid start time format!".to_string();
+                return None;
+            }
+        };
+        
+        let end_time = match manual_cut::parse_time_string(&self.manual_end_time) {
+            Ok(t) => t,
+            Err(_) => {
+                self.status_message = "Invalid end time format!".to_string();
+                return None;
+            }
+        };
+        
+        Some((start_time, end_time))
+    }
+    
+    fn cut_manual_segment(&mut self) {
+        let Some((start_time, end_time)) = self.parse_manual_range() else { return };
+        
+        if let Some(audio_path) = &self.audio_path {
+            // 切割片段
+            match manual_cut::cut_audio_segment(audio_path, start_time, end_time, self.lossless, self.accurate_cut) {
+                Ok(segment_path) => {
+                    self.manual_segment = Some(segment_path);
+                    self.status_message = format!("Manual segment cut: {:.2}s - {:.2}s", start_time, end_time);
                 }
-                
-                // 查找包含 python 且包含 whisper 的进程
-                if line.contains("python") && line.contains("whisper") && !line.contains("grep") {
-                    if let Some(pid) = Self::extract_pid_from_ps_line(line) {
-                        let _ = Command::new("kill")
-                            .args(&["-9", &pid.to_string()])
-                            .output();
-                    }
+                Err(e) => {
+                    self.status_message = format!("Failed to cut segment: {}", e);
                 }
             }
         }
     }
     
-    fn extract_pid_from_ps_line(line: &str) -> Option<u32> {
-        // ps aux 输出格式：USER PID ...
-        // 提取第二列（PID）
-        let parts: Vec<&str> = line.split_whitespace().collect();
-        if parts.len() >= 2 {
-            parts[1].parse::<u32>().ok()
-        } else {
-            None
+    fn recognize_manual_segment(&mut self) {
+        if self.manual_segment.is_none() {
+            self.status_message = "No manual segment to recognize!".to_string();
+            return;
+        }
+        
+        let Some(output_path) = self.output_srt_path() else { return };
+        
+        self.state = AppState::Processing;
+        self.processing_progress = 0.0;
+        self.processing_status = "Recognizing manual segment...".to_string();
+        self.recognition_results.clear();
+        
+        let segment = self.manual_segment.clone().unwrap();
+        let recognition = self.recognition_options();
+        let merge = self.merge_options();
+        
+        // 解析手动片段的起始时间（按原音频的起始时间戳修正），和自动切割的片段一起按时间合并
+        let start_time = manual_cut::parse_time_string(&self.manual_start_time).unwrap_or(0.0);
+        let stream_start = self.stream_start();
+        let start_time = pipeline::range_offset(stream_start, start_time);
+        let mut offsets = pipeline::cut_offsets(&self.audio_segments, stream_start);
+        offsets.push((segment.clone(), start_time));
+        
+        // 创建消息通道
+        let (tx, rx) = channel();
+        self.progress_receiver = Some(rx);
+        let cancel = self.new_cancel_token();
+        
+        std::thread::spawn(move || {
+            // 识别手动片段
+            match pipeline::recognize_segment(&segment, 0, 1, &recognition, &cancel, &tx) {
+                Ok((_srt_path, text)) => {
+                    let _ = tx.send(ProgressMessage::Result { 
+                        segment: 0, 
+                        text 
+                    });
+                    let _ = tx.send(ProgressMessage::Progress { 
+                        current: 1, 
+                        total: 1 
+                    });
+                    
+                    // 合并字幕（包括手动片段）
+                    merge_in_background(&offsets, recognition.subtitles_dir.as_deref(), &merge, &output_path, &tx);
+                }
+                Err(e) => {
+                    let error_msg = format!("Failed to recognize manual segment: {}", e);
+                    eprintln!("{}", error_msg);
+                    let _ = tx.send(ProgressMessage::Error(error_msg));
+                }
+            }
+            
+            let _ = tx.send(ProgressMessage::Completed);
+        });
+    }
+    
+    /// 直接识别完整音频中的一个时间段，不生成片段文件
+    /// 
+    /// 结果替换合并字幕中该时间段内未锁定的字幕
+    fn recognize_range(&mut self) {
+        let Some((start_time, end_time)) = self.parse_manual_range() else { return };
+        let Some(audio_path) = self.audio_path.clone() else { return };
+        if start_time >= end_time {
+            self.status_message = "Start time must be less than end time".to_string();
+            return;
+        }
+        
+        self.state = AppState::Processing;
+        self.processing_progress = 0.0;
+        self.processing_status = format!("Recognizing {} - {}...", Self::format_time(start_time), Self::format_time(end_time));
+        self.recognition_results.clear();
+        
+        let recognition = self.recognition_options();
+        let cancel = self.new_cancel_token();
+        
+        // 实时输出走进度通道，字幕通过单独的通道返回
+        let (tx, rx) = channel();
+        self.progress_receiver = Some(rx);
+        let (result_tx, result_rx) = channel();
+        self.range_receiver = Some(result_rx);
+        
+        std::thread::spawn(move || {
+            let result = pipeline::recognize_range(&audio_path, start_time, end_time, &recognition, &cancel, &tx)
+                .map(|subtitles| (start_time, end_time, subtitles))
+                .map_err(|e| e.to_string());
+            let _ = result_tx.send(result);
+        });
+    }
+    
+    /// 媒体是否为立体声（ffprobe 检测）
+    fn is_stereo(&self) -> bool {
+        matches!(&self.media_info, Some(Ok(info)) if info.channels == Some(2))
+    }
+    
+    /// 左右声道分别识别整段音频，结果带说话人标签写入合并字幕
+    fn recognize_channels(&mut self) {
+        let Some(audio_path) = self.audio_path.clone() else { return };
+        
+        self.state = AppState::Processing;
+        self.processing_progress = 0.0;
+        self.processing_status = "Recognizing left and right channels...".to_string();
+        self.recognition_results.clear();
+        self.tail_gap_warning = None;
+        
+        let recognition = self.recognition_options();
+        let cancel = self.new_cancel_token();
+        
+        // 实时输出走进度通道，字幕通过单独的通道返回
+        let (tx, rx) = channel();
+        self.progress_receiver = Some(rx);
+        let (result_tx, result_rx) = channel();
+        self.channel_receiver = Some(result_rx);
+        
+        std::thread::spawn(move || {
+            let result = pipeline::recognize_channels(&audio_path, &recognition, &cancel, &tx)
+                .map_err(|e| e.to_string());
+            let _ = result_tx.send(result);
+        });
+    }
+    
+    /// 保存分声道识别的结果为合并字幕（替换原有内容，锁定的字幕保留）
+    fn save_channel_subtitles(&mut self, mut subtitles: Vec<subtitle::SubtitleEntry>) {
+        let Some(srt_path) = self.output_srt_path() else { return };
+        
+        subtitle::apply_locked(&mut subtitles, &self.locked_subtitles);
+        if let Some(fps) = self.export_fps() {
+            subtitle::quantize_to_fps(&mut subtitles, fps);
+        }
+        if let Some(start) = self.export_start_index() {
+            subtitle::reindex_from(&mut subtitles, start);
+        }
+        
+        self.status_message = match subtitle::save_srt_file(&srt_path, &subtitles, self.output_encoding) {
+            Ok(_) => {
+                self.update_subtitle_search();
+                format!("Per-channel recognition completed! {} cues saved to {:?}", subtitles.len(), srt_path)
+            }
+            Err(e) => format!("Failed to save subtitles: {}", e),
+        };
+    }
+    
+    /// 把时间段识别的结果拼接进合并字幕
+    fn splice_range_subtitles(&mut self, start: f64, end: f64, new_subtitles: Vec<subtitle::SubtitleEntry>) {
+        let Some(srt_path) = self.merged_srt_path() else { return };
+        if !srt_path.exists() {
+            if let Err(e) = subtitle::save_srt_file(&srt_path, &[], self.output_encoding) {
+                self.status_message = format!("Failed to create subtitles: {}", e);
+                return;
+            }
+        }
+        
+        if new_subtitles.is_empty() {
+            self.status_message = "No speech recognized in the range".to_string();
+            return;
         }
+        
+        self.edit_merged_subtitles(|subtitles| {
+            let removed = subtitle::remove_subtitles_in_range(subtitles, start, end);
+            let inserted = subtitle::insert_subtitles(subtitles, new_subtitles);
+            format!("Range recognized: replaced {} cues with {}", removed, inserted)
+        });
     }
     
-    fn save_plain_text(&self) {
+    /// 为新的识别任务创建取消令牌
+    fn new_cancel_token(&mut self) -> whisper::CancelToken {
+        self.cancel_token = whisper::CancelToken::default();
+        self.cancel_token.clone()
+    }
+    
+    fn stop_recognition(&mut self) {
+        // 终止本程序启动的 Whisper 进程，识别线程不再启动后续片段
+        self.cancel_token.cancel();
+        
+        // 重置状态
+        self.state = AppState::AudioExtracted;
+        self.status_message = "Recognition stopped and Whisper process killed.".to_string();
+        self.progress_receiver = None;
+        self.range_receiver = None;
+        self.channel_receiver = None;
+        self.translate_receiver = None;
+        self.processing_progress = 0.0;
+        self.processing_status = String::new();
+    }
+    
+    fn save_plain_text(&mut self) {
         // 过滤出 Segment 开头的结果
         let mut plain_text = String::new();
         for result in &self.recognition_results {
@@ -734,295 +1701,2633 @@ impl WhisperApp {
         
         // 使用文件对话框保存
         if let Some(path) = rfd::FileDialog::new()
-            .set_file_name(&format!("{}_transcript.txt", video_name))
+            .set_file_name(format!("{}_transcript.txt", video_name))
             .add_filter("Text", &["txt"])
             .save_file()
         {
-            let _ = fs::write(path, plain_text);
+            match srt_merger::encode_text(&plain_text, self.output_encoding) {
+                Ok(bytes) => {
+                    let _ = fs::write(path, bytes);
+                }
+                Err(e) => {
+                    self.status_message = format!("Failed to save text: {}", e);
+                }
+            }
         }
     }
     
-    fn open_workspace(&mut self) {
-        if let Some(folder) = rfd::FileDialog::new().pick_folder() {
-            println!("Selected folder: {:?}", folder);
-            
-            // 检查是否存在工作区状态文件
-            if workspace::WorkspaceState::exists(&folder) {
-                println!("Found workspace_state.json, loading...");
-                
-                // 加载工作区
-                match workspace::WorkspaceState::load(&folder) {
-                    Ok(state) => {
-                        println!("Workspace loaded successfully!");
-                        println!("Video path: {:?}", state.video_path);
-                        println!("Audio segments: {}", state.audio_segments.len());
-                        println!("Completed segments: {:?}", state.completed_segments);
-                        
-                        self.workspace_dir = Some(folder.clone());
-                        self.video_path = state.video_path.clone();
-                        self.audio_path = state.audio_path.clone();
-                        self.cut_points = state.cut_points.clone();
-                        self.audio_segments = state.audio_segments.clone();
-                        self.manual_segment = state.manual_segment.clone();
-                        self.manual_start_time = state.manual_start_time.clone();
-                        self.manual_end_time = state.manual_end_time.clone();
-                        self.total_duration = state.total_duration;
-                        
-                        // 重新加载音频播放器
-                        if let Some(audio_path) = &state.audio_path {
-                            println!("Loading audio player from: {:?}", audio_path);
-                            if audio_path.exists() {
-                                match audio_player::AudioPlayer::new(audio_path) {
-                                    Ok(player) => {
-                                        println!("Audio player loaded successfully!");
-                                        self.audio_player = Some(player);
-                                        self.state = AppState::AudioExtracted;
-                                    }
-                                    Err(e) => {
-                                        println!("Failed to load audio player: {}", e);
-                                        self.state = AppState::AudioExtracted;  // 仍然设置状态
-                                    }
-                                }
-                            } else {
-                                println!("Audio file not found: {:?}", audio_path);
-                                self.state = AppState::AudioExtracted;  // 即使音频不存在也设置状态
-                            }
-                        }
-                        
-                        // 检测哪些片段缺失字幕
-                        self.check_missing_subtitles();
+    /// 只合并已有的片段字幕，不重新识别
+    fn merge_existing_subtitles(&mut self) {
+        let offsets = pipeline::cut_offsets(&self.audio_segments, self.stream_start());
+        
+        if let Some(output_path) = self.output_srt_path() {
+            match pipeline::merge_subtitles(&offsets, self.segment_subtitles_dir().as_deref(), &self.merge_options(), &output_path) {
+                Ok(count) => {
+                    self.restore_locked_subtitles();
+                    self.update_subtitle_search();
+                    self.status_message = format!("Merged {}/{} segment subtitles into {:?}",
+                        count, self.audio_segments.len(), output_path);
+                }
+                Err(e) => {
+                    self.status_message = format!("Failed to merge subtitles: {}", e);
+                }
+            }
+        }
+    }
+    
+    /// 崩溃后从音频旁边遗留的片段恢复：按片段时长推算切割点，重建片段列表并合并已有字幕
+    /// 
+    /// 不重新识别；没有字幕的片段可以之后用 Resume 继续识别
+    fn recover_from_segments(&mut self) {
+        let Some(audio_path) = self.audio_path.clone() else { return };
+        
+        match pipeline::recover_segments(&audio_path) {
+            Ok(segments) => {
+                self.cut_points = pipeline::cut_points_of(&segments);
+                self.audio_segments = segments;
+                self.check_missing_subtitles();
+                self.merge_existing_subtitles();
+                self.status_message = format!("Recovered {} segments ({} with subtitles). {}",
+                    self.audio_segments.len(), self.completed_segments.len(), self.status_message);
+            }
+            Err(e) => {
+                self.status_message = format!("Failed to recover from segments: {}", e);
+            }
+        }
+    }
+    
+    /// 合并后的字幕文件路径：本次识别写出过字幕时为该文件，否则为 `video.srt`
+    fn merged_srt_path(&self) -> Option<PathBuf> {
+        self.session_srt_path.clone()
+            .or_else(|| self.video_path.as_ref().map(|p| p.with_extension("srt")))
+    }
+    
+    /// 识别结果的输出路径
+    /// 
+    /// 本次已经写出过字幕时继续覆盖该文件；否则勾选了保留旧字幕且 `video.srt` 已存在时
+    /// 写到下一个空闲的 `video.N.srt`，避免试验参数时丢掉之前较好的结果
+    fn output_srt_path(&mut self) -> Option<PathBuf> {
+        if let Some(path) = &self.session_srt_path {
+            return Some(path.clone());
+        }
+        
+        let path = self.video_path.as_ref()?.with_extension("srt");
+        let path = if self.keep_previous_srt { pipeline::versioned_path(&path) } else { path };
+        self.session_srt_path = Some(path.clone());
+        Some(path)
+    }
+    
+    /// 读取合并后的字幕，处理后写回
+    /// 
+    /// `edit` 返回的文字作为状态信息显示
+    fn edit_merged_subtitles<F>(&mut self, edit: F)
+    where
+        F: FnOnce(&mut Vec<subtitle::SubtitleEntry>) -> String,
+    {
+        if let Some(srt_path) = self.merged_srt_path() {
+            match subtitle::load_srt_file(&srt_path) {
+                Ok(mut subtitles) => {
+                    subtitle::apply_locked(&mut subtitles, &self.locked_subtitles);
+                    let message = edit(&mut subtitles);
+                    if let Some(fps) = self.export_fps() {
+                        subtitle::quantize_to_fps(&mut subtitles, fps);
+                    }
+                    if let Some(start) = self.export_start_index() {
+                        subtitle::reindex_from(&mut subtitles, start);
+                    }
+                    match subtitle::save_srt_file(&srt_path, &subtitles, self.output_encoding) {
+                        Ok(_) => {
+                            self.status_message = message;
+                            self.overlaps = Self::overlapping_pairs(&subtitles);
+                            self.update_subtitle_search();
+                        }
+                        Err(e) => {
+                            self.status_message = format!("Failed to save subtitles: {}", e);
+                        }
+                    }
+                }
+                Err(e) => {
+                    self.status_message = format!("Failed to load subtitles: {}", e);
+                }
+            }
+        }
+    }
+    
+    fn split_long_duration_cues(&mut self) {
+        let max_seconds = self.max_cue_seconds;
+        let silences = vad_recognition::silence_regions(&self.speech_regions, self.total_duration);
+        
+        self.edit_merged_subtitles(|subtitles| {
+            let count = subtitle::split_long_duration(subtitles, max_seconds, &silences);
+            format!("Split {} cues longer than {:.1}s", count, max_seconds)
+        });
+    }
+    
+    fn split_long_text_cues(&mut self) {
+        let max_chars = self.max_cue_chars;
+        
+        self.edit_merged_subtitles(|subtitles| {
+            let count = subtitle::split_long_cues(subtitles, max_chars);
+            format!("Split {} cues longer than {} characters", count, max_chars)
+        });
+    }
+    
+    /// 合并间隔很短的相邻字幕，合并后不超过单条字幕最大时长
+    fn merge_short_cues(&mut self) {
+        let (max_gap, max_duration) = (self.merge_max_gap, self.max_cue_seconds);
+        
+        self.edit_merged_subtitles(|subtitles| {
+            let count = subtitle::merge_short_cues(subtitles, max_gap, max_duration);
+            format!("Merged {} short cues (gap < {:.2}s, up to {:.1}s)", count, max_gap, max_duration)
+        });
+    }
+    
+    fn pad_cues(&mut self) {
+        let (lead, tail) = (self.pad_lead, self.pad_tail);
+        
+        self.edit_merged_subtitles(|subtitles| {
+            let count = subtitle::pad(subtitles, lead, tail);
+            format!("Padded {} cues ({:.2}s lead, {:.2}s tail)", count, lead, tail)
+        });
+    }
+    
+    fn split_cue_at_playhead(&mut self) {
+        let position = self.current_position;
+        
+        self.edit_merged_subtitles(|subtitles| {
+            match subtitle::find_at(subtitles, position) {
+                Some(index) => match subtitle::split_at(subtitles, index, position) {
+                    Ok(_) => format!("Split cue {} at {}", index + 1, Self::format_time(position)),
+                    Err(e) => format!("Failed to split cue: {}", e),
+                },
+                None => format!("No cue at {}", Self::format_time(position)),
+            }
+        });
+    }
+    
+    fn check_subtitle_standard(&mut self) {
+        let Some(srt_path) = self.merged_srt_path() else { return };
+        match subtitle::load_srt_file(&srt_path) {
+            Ok(subtitles) => {
+                self.standard_violations = subtitle::check_standard(&subtitles, self.subtitle_standard);
+                self.status_message = format!("{} issues found for {} standard",
+                    self.standard_violations.len(), self.subtitle_standard.as_str());
+            }
+            Err(e) => {
+                self.status_message = format!("Failed to load subtitles: {}", e);
+            }
+        }
+    }
+    
+    /// 查找合并字幕中时间重叠的字幕
+    fn check_overlaps(&mut self) {
+        let Some(srt_path) = self.merged_srt_path() else { return };
+        match subtitle::load_srt_file(&srt_path) {
+            Ok(subtitles) => {
+                self.overlaps = Self::overlapping_pairs(&subtitles);
+                self.status_message = format!("{} overlapping cue pairs found", self.overlaps.len());
+            }
+            Err(e) => {
+                self.status_message = format!("Failed to load subtitles: {}", e);
+            }
+        }
+    }
+    
+    fn overlapping_pairs(subtitles: &[subtitle::SubtitleEntry]) -> Vec<(subtitle::SubtitleEntry, subtitle::SubtitleEntry)> {
+        subtitle::find_overlaps(subtitles).into_iter()
+            .map(|(i, j)| (subtitles[i].clone(), subtitles[j].clone()))
+            .collect()
+    }
+    
+    /// 截短较早的字幕，消除与下一条的重叠
+    fn fix_overlaps(&mut self) {
+        self.edit_merged_subtitles(|subtitles| {
+            let count = subtitle::fix_overlaps(subtitles);
+            format!("Trimmed {} overlapping cues", count)
+        });
+    }
+    
+    fn apply_standard_line_breaks(&mut self) {
+        let standard = self.subtitle_standard;
+        
+        self.edit_merged_subtitles(|subtitles| {
+            let count = subtitle::apply_line_breaks(subtitles, standard);
+            format!("Re-wrapped {} cues to {} chars per line", count, standard.limits().max_chars_per_line)
+        });
+        
+        // 折行后重新检查，保留折行的结果信息
+        let message = self.status_message.clone();
+        self.check_subtitle_standard();
+        self.status_message = format!("{}, {} issues remaining", message, self.standard_violations.len());
+    }
+    
+    fn normalize_subtitle_text(&mut self) {
+        let rules = self.normalize_rules;
+        
+        self.edit_merged_subtitles(|subtitles| {
+            let count = subtitle::normalize_text(subtitles, rules);
+            format!("Normalized text of {} cues", count)
+        });
+    }
+    
+    /// 去掉已合并字幕中的非语音标注，模式为空时使用默认模式
+    fn strip_nonspeech_tags(&mut self) {
+        let mut patterns = subtitle::parse_nonspeech_patterns(&self.nonspeech_patterns);
+        if patterns.is_empty() {
+            patterns = subtitle::DEFAULT_NONSPEECH_PATTERNS.iter().map(|p| p.to_string()).collect();
+        }
+        
+        self.edit_merged_subtitles(|subtitles| {
+            let (cleaned, removed) = subtitle::strip_nonspeech(subtitles, &patterns);
+            format!("Removed non-speech tags from {} cues, {} cues deleted", cleaned, removed)
+        });
+    }
+    
+    /// 对外部带样式的 ASS 文件做文本规范化，样式、位置和未改动部分的覆盖标签保持不变
+    fn normalize_ass_file(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("ASS subtitles", &["ass", "ssa"])
+            .pick_file() else { return };
+        
+        let result = ass::AssDocument::load(&path).and_then(|mut document| {
+            let mut subtitles = document.subtitles();
+            subtitle::normalize_text(&mut subtitles, self.normalize_rules);
+            let changed = document.apply_subtitles(&subtitles)?;
+            document.save(&path)?;
+            Ok(changed)
+        });
+        
+        self.status_message = match result {
+            Ok(count) => format!("Normalized text of {} dialogue lines in {:?}", count, path.file_name().unwrap_or_default()),
+            Err(e) => format!("Failed to normalize ASS file: {}", e),
+        };
+    }
+    
+    /// 打开外部 ASS 文件的对白编辑窗口
+    fn open_ass_editor(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("ASS subtitles", &["ass", "ssa"])
+            .pick_file() else { return };
+        
+        match ass::AssDocument::load(&path) {
+            Ok(document) => {
+                let subtitles = document.subtitles();
+                self.ass_editor = Some(AssEditor { path, document, subtitles });
+            }
+            Err(e) => self.status_message = format!("Failed to load ASS file: {}", e),
+        }
+    }
+    
+    /// 把编辑后的对白写回 ASS 文件，样式和未改动部分的覆盖标签保持不变
+    fn save_ass_editor(&mut self) {
+        let Some(editor) = &mut self.ass_editor else { return };
+        let result = editor.document.apply_subtitles(&editor.subtitles)
+            .and_then(|changed| editor.document.save(&editor.path).map(|_| changed));
+        
+        self.status_message = match result {
+            Ok(count) => format!("Saved {} changed dialogue lines to {:?}", count, editor.path.file_name().unwrap_or_default()),
+            Err(e) => format!("Failed to save ASS file: {}", e),
+        };
+    }
+    
+    /// ASS 对白编辑窗口：逐条修改文本和时间
+    fn ass_editor_window(&mut self, ctx: &egui::Context) {
+        let Some(editor) = &mut self.ass_editor else { return };
+        
+        let mut open = true;
+        let mut save = false;
+        let title = format!("✏ {}", editor.path.file_name().unwrap_or_default().to_string_lossy());
+        egui::Window::new(title)
+            .id(egui::Id::new("ass_editor"))
+            .open(&mut open)
+            .collapsible(false)
+            .default_width(520.0)
+            .show(ctx, |ui| {
+                ui.label(format!("{} dialogue lines. Styles and override tags outside edited text are kept.", editor.subtitles.len()));
+                egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                    for entry in &mut editor.subtitles {
+                        ui.horizontal(|ui| {
+                            ui.add(egui::DragValue::new(&mut entry.start_time).speed(0.01).range(0.0..=f64::MAX).max_decimals(2));
+                            ui.label("→");
+                            ui.add(egui::DragValue::new(&mut entry.end_time).speed(0.01).range(0.0..=f64::MAX).max_decimals(2));
+                            ui.add(egui::TextEdit::multiline(&mut entry.text).desired_rows(1).desired_width(f32::INFINITY));
+                        });
+                    }
+                });
+                ui.separator();
+                if ui.button("💾 Save").clicked() {
+                    save = true;
+                }
+            });
+        
+        if save {
+            self.save_ass_editor();
+        }
+        if !open {
+            self.ass_editor = None;
+        }
+    }
+    
+    /// 用外部命令翻译合并后的字幕，时间不变，译文另存为 `video.<后缀>.srt`
+    fn translate_subtitles(&mut self) {
+        let Some(srt_path) = self.merged_srt_path() else { return };
+        let subtitles = match subtitle::load_srt_file(&srt_path) {
+            Ok(subtitles) if !subtitles.is_empty() => subtitles,
+            Ok(_) => {
+                self.status_message = "No subtitles to translate".to_string();
+                return;
+            }
+            Err(e) => {
+                self.status_message = format!("Failed to load subtitles: {}", e);
+                return;
+            }
+        };
+        let options = translate::TranslateOptions {
+            command: self.translate_command.trim().to_string(),
+            timeout: std::time::Duration::from_secs_f64(self.translate_timeout.max(1.0)),
+            ..Default::default()
+        };
+        if options.command.is_empty() {
+            self.status_message = "Enter a translation command first".to_string();
+            return;
+        }
+        
+        self.state = AppState::Processing;
+        self.processing_progress = 0.0;
+        self.processing_status = format!("Translating {} cues...", subtitles.len());
+        self.recognition_results.clear();
+        
+        let cancel = self.new_cancel_token();
+        let (tx, rx) = channel();
+        self.progress_receiver = Some(rx);
+        let (result_tx, result_rx) = channel();
+        self.translate_receiver = Some(result_rx);
+        
+        std::thread::spawn(move || {
+            let result = translate::translate_subtitles(&subtitles, &options, &cancel, &tx)
+                .map_err(|e| e.to_string());
+            let _ = result_tx.send(result);
+        });
+    }
+    
+    /// 保存译文到合并字幕旁边，失败的字幕保留原文
+    fn save_translation(&mut self, translation: translate::Translation) {
+        let translate::Translation { subtitles, failures } = translation;
+        let Some(srt_path) = self.merged_srt_path() else { return };
+        let suffix = match self.translate_suffix.trim() {
+            "" => "translated",
+            suffix => suffix,
+        };
+        let path = srt_path.with_extension(format!("{}.srt", suffix));
+        
+        self.status_message = match subtitle::save_srt_file(&path, &subtitles, self.output_encoding) {
+            Ok(_) if failures.is_empty() => {
+                format!("Translated {} cues to {:?}", subtitles.len(), path.file_name().unwrap_or_default())
+            }
+            Ok(_) => format!("Translated {} of {} cues to {:?}; {} failed and kept the original text (cues {})",
+                subtitles.len() - failures.len(), subtitles.len(), path.file_name().unwrap_or_default(), failures.len(),
+                failures.iter().take(10).map(|(index, _)| index.to_string()).collect::<Vec<_>>().join(", ")),
+            Err(e) => format!("Failed to save translation: {}", e),
+        };
+    }
+    
+    /// 把合并后的字幕导出为 CSV 或 JSON（按选择的扩展名），供表格软件中翻译
+    fn export_cues(&mut self) {
+        let Some(srt_path) = self.merged_srt_path() else { return };
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("CSV", &["csv"])
+            .add_filter("JSON", &["json"])
+            .set_file_name(format!("{}.csv", srt_path.file_stem().unwrap_or_default().to_string_lossy()))
+            .save_file() else { return };
+        
+        let is_json = path.extension().is_some_and(|e| e.eq_ignore_ascii_case("json"));
+        let offsets = pipeline::cut_offsets(&self.audio_segments, self.stream_start());
+        let merge = self.merge_options();
+        let subtitles_dir = self.segment_subtitles_dir();
+        let result = subtitle::load_srt_file(&srt_path).and_then(|mut subtitles| {
+            if is_json {
+                // 用逐词时间识别的片段，词随 JSON 一起导出
+                subtitle::attach_words(&mut subtitles, &pipeline::segment_word_timings(&offsets, subtitles_dir.as_deref(), &merge)?);
+                subtitle::save_json_file(&path, &subtitles)?;
+            } else {
+                fs::write(&path, srt_merger::encode_text(&subtitle::export_csv(&subtitles), self.output_encoding)?)?;
+            }
+            Ok(subtitles.len())
+        });
+        
+        self.status_message = match result {
+            Ok(count) => format!("Exported {} cues to {:?}", count, path.file_name().unwrap_or_default()),
+            Err(e) => format!("Failed to export: {}", e),
+        };
+    }
+    
+    /// 把合并后的字幕导出为不带时间的纯文本稿，写到字幕旁边的同名 `.txt`
+    fn export_plain_transcript(&mut self) {
+        let Some(srt_path) = self.merged_srt_path() else { return };
+        let path = srt_path.with_extension("txt");
+        
+        let result = subtitle::load_srt_file(&srt_path).and_then(|subtitles| {
+            let join = if self.plaintext_per_cue { "\n" } else { subtitle::plaintext_join(&subtitles) };
+            fs::write(&path, srt_merger::encode_text(&subtitle::export_plaintext(&subtitles, join), self.output_encoding)?)?;
+            Ok(subtitles.len())
+        });
+        
+        self.status_message = match result {
+            Ok(count) => format!("Exported text of {} cues to {:?}", count, path.file_name().unwrap_or_default()),
+            Err(e) => format!("Failed to export text: {}", e),
+        };
+    }
+    
+    /// 用编辑过的 CSV 或 JSON 替换合并后的字幕
+    fn import_cues(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("CSV or JSON", &["csv", "json"])
+            .pick_file() else { return };
+        
+        let is_json = path.extension().is_some_and(|e| e.eq_ignore_ascii_case("json"));
+        let imported = if is_json {
+            subtitle::load_json_file(&path)
+        } else {
+            fs::read(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|bytes| subtitle::import_csv(&srt_merger::decode_text(&bytes)))
+        };
+        
+        match imported {
+            Ok(mut imported) => {
+                imported.sort_by(subtitle::SubtitleEntry::cue_order);
+                subtitle::reindex_subtitles(&mut imported);
+                self.edit_merged_subtitles(|subtitles| {
+                    *subtitles = imported;
+                    format!("Imported {} cues", subtitles.len())
+                });
+            }
+            Err(e) => {
+                self.status_message = format!("Failed to import: {}", e);
+            }
+        }
+    }
+    
+    /// 执行字幕右键菜单中的操作，`entry` 按时间和文本在合并字幕中定位
+    fn apply_cue_action(&mut self, entry: &subtitle::SubtitleEntry, action: CueAction) {
+        if action == CueAction::Rerecognize {
+            self.manual_start_time = Self::format_time(entry.start_time);
+            self.manual_end_time = Self::format_time(entry.end_time);
+            self.recognize_range();
+            return;
+        }
+        
+        let position = self.current_position;
+        self.edit_merged_subtitles(|subtitles| {
+            let Some(index) = subtitles.iter().position(|s| s.same_cue(entry)) else {
+                return "Cue no longer exists, search again".to_string();
+            };
+            let result = match action {
+                CueAction::Delete => subtitle::delete_at(subtitles, index)
+                    .map(|_| format!("Deleted cue {}", index + 1)),
+                CueAction::MergeWithNext => subtitle::merge_with_next(subtitles, index)
+                    .map(|_| format!("Merged cue {} with the next cue", index + 1)),
+                CueAction::SplitAtPlayhead => subtitle::split_at(subtitles, index, position)
+                    .map(|_| format!("Split cue {} at {}", index + 1, Self::format_time(position))),
+                CueAction::ToggleItalic => subtitle::toggle_style_at(subtitles, index, "i")
+                    .map(|_| format!("Toggled italic on cue {}", index + 1)),
+                CueAction::ToggleBold => subtitle::toggle_style_at(subtitles, index, "b")
+                    .map(|_| format!("Toggled bold on cue {}", index + 1)),
+                CueAction::Rerecognize => unreachable!(),
+            };
+            result.unwrap_or_else(|e| format!("❌ {}", e))
+        });
+    }
+    
+    /// 删除列表中勾选的字幕，锁定的字幕保留
+    fn delete_marked_cues(&mut self) {
+        let marked: Vec<subtitle::SubtitleEntry> = self.search_marked.iter()
+            .filter_map(|&i| self.search_results.get(i).cloned())
+            .collect();
+        
+        self.edit_merged_subtitles(|subtitles| {
+            let indices: Vec<usize> = marked.iter()
+                .filter_map(|entry| subtitles.iter().position(|s| s.same_cue(entry)))
+                .collect();
+            let (removed, skipped) = subtitle::delete_many(subtitles, &indices);
+            if skipped > 0 {
+                format!("Deleted {} cues, kept {} locked cues", removed, skipped)
+            } else {
+                format!("Deleted {} cues", removed)
+            }
+        });
+    }
+    
+    /// 删除与输入的时间段重叠的字幕，锁定的字幕保留
+    fn delete_cues_in_range(&mut self) {
+        let range = manual_cut::parse_time_string(&self.delete_range_start)
+            .and_then(|start| Ok((start, manual_cut::parse_time_string(&self.delete_range_end)?)));
+        let (start, end) = match range {
+            Ok((start, end)) if start < end => (start, end),
+            Ok(_) => {
+                self.status_message = "Start time must be less than end time".to_string();
+                return;
+            }
+            Err(e) => {
+                self.status_message = format!("Invalid time: {}", e);
+                return;
+            }
+        };
+        
+        self.edit_merged_subtitles(|subtitles| {
+            let removed = subtitle::remove_subtitles_in_range(subtitles, start, end);
+            subtitle::reindex_subtitles(subtitles);
+            format!("Deleted {} cues between {} and {}", removed, Self::format_time(start), Self::format_time(end))
+        });
+    }
+    
+    /// 按输入的两个同步点线性缩放并平移所有字幕的时间，修正逐渐累积的偏移
+    fn rescale_from_sync_points(&mut self) {
+        let parse = |text: &str| manual_cut::parse_time_string(text);
+        let [(actual_a, expected_a), (actual_b, expected_b)] = &self.sync_anchors;
+        let points = parse(actual_a).and_then(|src_a| {
+            Ok((src_a, parse(expected_a)?, parse(actual_b)?, parse(expected_b)?))
+        });
+        let (src_a, dst_a, src_b, dst_b) = match points {
+            Ok(points) => points,
+            Err(e) => {
+                self.status_message = format!("Invalid time: {}", e);
+                return;
+            }
+        };
+        
+        // 锁定的字幕也一起移动，记录新的时间，之后的编辑不会把它们放回原处
+        let mut rescaled_locked = None;
+        self.edit_merged_subtitles(|subtitles| {
+            match subtitle::rescale_from_anchors(subtitles, src_a, dst_a, src_b, dst_b) {
+                Ok((factor, offset)) => {
+                    rescaled_locked = Some(subtitles.iter().filter(|s| s.locked).cloned().collect());
+                    format!("Rescaled {} cues (×{:.6}, {:+.3}s)", subtitles.len(), factor, offset)
+                }
+                Err(e) => format!("Failed to rescale: {}", e),
+            }
+        });
+        if let Some(locked) = rescaled_locked {
+            self.locked_subtitles = locked;
+        }
+    }
+    
+    /// 把时间范围内的字幕导出为单独的 SRT，用于剪辑出的片段
+    fn export_range(&mut self) {
+        let range = manual_cut::parse_time_string(&self.export_range_start)
+            .and_then(|start| Ok((start, manual_cut::parse_time_string(&self.export_range_end)?)));
+        let (start, end) = match range {
+            Ok((start, end)) if start < end => (start, end),
+            Ok(_) => {
+                self.status_message = "Start time must be less than end time".to_string();
+                return;
+            }
+            Err(e) => {
+                self.status_message = format!("Invalid time: {}", e);
+                return;
+            }
+        };
+        
+        let Some(srt_path) = self.merged_srt_path() else { return };
+        let subtitles = match subtitle::load_srt_file(&srt_path) {
+            Ok(subtitles) => subtitle::slice_range(&subtitles, start, end, self.export_range_rebase, self.export_range_clip),
+            Err(e) => {
+                self.status_message = format!("Failed to load subtitles: {}", e);
+                return;
+            }
+        };
+        if subtitles.is_empty() {
+            self.status_message = format!("No cues between {} and {}", Self::format_time(start), Self::format_time(end));
+            return;
+        }
+        
+        let stem = srt_path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("SRT", &["srt"])
+            .set_file_name(format!("{}_{}-{}.srt", stem, start.round() as u64, end.round() as u64))
+            .save_file() else { return };
+        
+        self.status_message = match subtitle::save_srt_file(&path, &subtitles, self.output_encoding) {
+            Ok(_) => format!("Exported {} cues to {:?}", subtitles.len(), path.file_name().unwrap_or_default()),
+            Err(e) => format!("Failed to export: {}", e),
+        };
+    }
+    
+    /// 按搜索框内容重新查找合并后的字幕，搜索框为空时列出全部字幕
+    fn update_subtitle_search(&mut self) {
+        self.search_results.clear();
+        self.search_selected = None;
+        self.search_marked.clear();
+        
+        let Some(Ok(subtitles)) = self.merged_srt_path().map(|p| subtitle::load_srt_file(&p)) else { return };
+        let query = self.subtitle_search.trim();
+        self.search_results = if query.is_empty() {
+            subtitles
+        } else {
+            subtitle::search(&subtitles, query)
+                .into_iter()
+                .map(|i| subtitles[i].clone())
+                .collect()
+        };
+    }
+    
+    /// 选中第 `index` 个匹配并跳转播放位置
+    fn select_search_result(&mut self, index: usize) {
+        if let Some(entry) = self.search_results.get(index) {
+            self.search_selected = Some(index);
+            self.current_position = entry.start_time;
+            if let Some(player) = &mut self.audio_player {
+                player.seek(entry.start_time);
+            }
+        }
+    }
+    
+    /// 检查合并后的字幕是否覆盖到媒体结尾
+    /// 
+    /// 末尾长时间没有字幕通常说明最后的片段识别失败（合并仍会“成功”），
+    /// 此时重新检查缺失的片段，以便直接恢复识别
+    fn check_tail_coverage(&mut self) {
+        self.tail_gap_warning = None;
+        
+        let Some(srt_path) = self.merged_srt_path() else { return };
+        let Ok(subtitles) = subtitle::load_srt_file(&srt_path) else { return };
+        
+        let gap = subtitle::uncovered_tail(&subtitles, self.total_duration);
+        if gap > TAIL_GAP_WARNING_SECONDS {
+            self.check_missing_subtitles();
+            let secs = gap.round() as u64;
+            self.tail_gap_warning = Some(format!(
+                "⚠ No subtitles in the last {} min {:02} s, recognition may have failed",
+                secs / 60,
+                secs % 60
+            ));
+        }
+    }
+    
+    /// 重新合并后恢复锁定的字幕
+    fn restore_locked_subtitles(&mut self) {
+        if !self.locked_subtitles.is_empty() {
+            let count = self.locked_subtitles.len();
+            self.edit_merged_subtitles(|_| format!("Restored {} locked cues", count));
+        }
+    }
+    
+    /// 锁定或解锁播放位置所在的字幕
+    fn toggle_lock_at_playhead(&mut self) {
+        let position = self.current_position;
+        
+        // 已锁定的字幕直接解锁
+        if let Some(i) = subtitle::find_at(&self.locked_subtitles, position) {
+            self.locked_subtitles.remove(i);
+            self.status_message = format!("Unlocked cue at {}", Self::format_time(position));
+            return;
+        }
+        
+        let Some(srt_path) = self.merged_srt_path() else { return };
+        match subtitle::load_srt_file(&srt_path) {
+            Ok(subtitles) => match subtitle::find_at(&subtitles, position) {
+                Some(i) => {
+                    self.locked_subtitles.push(subtitle::SubtitleEntry { locked: true, ..subtitles[i].clone() });
+                    self.status_message = format!("Locked cue {} at {}", subtitles[i].index, Self::format_time(position));
+                }
+                None => {
+                    self.status_message = format!("No cue at {}", Self::format_time(position));
+                }
+            },
+            Err(e) => {
+                self.status_message = format!("Failed to load subtitles: {}", e);
+            }
+        }
+    }
+    
+    fn lock_all_subtitles(&mut self) {
+        let Some(srt_path) = self.merged_srt_path() else { return };
+        match subtitle::load_srt_file(&srt_path) {
+            Ok(subtitles) => {
+                self.locked_subtitles = subtitles.into_iter()
+                    .map(|s| subtitle::SubtitleEntry { locked: true, ..s })
+                    .collect();
+                self.status_message = format!("Locked {} cues", self.locked_subtitles.len());
+            }
+            Err(e) => {
+                self.status_message = format!("Failed to load subtitles: {}", e);
+            }
+        }
+    }
+    
+    fn open_workspace(&mut self) {
+        if self.media_switch_blocked() {
+            return;
+        }
+        if let Some(folder) = rfd::FileDialog::new().pick_folder() {
+            println!("Selected folder: {:?}", folder);
+            
+            // 检查是否存在工作区状态文件
+            if workspace::WorkspaceState::exists(&folder) {
+                println!("Found workspace_state.json, loading...");
+                
+                // 加载工作区
+                match workspace::WorkspaceState::load(&folder) {
+                    Ok(state) => {
+                        println!("Workspace loaded successfully!");
+                        println!("Video path: {:?}", state.video_path);
+                        println!("Audio segments: {}", state.audio_segments.len());
+                        println!("Completed segments: {:?}", state.completed_segments);
+                        
+                        self.workspace_dir = Some(folder.clone());
+                        self.video_path = state.video_path.clone();
+                        self.session_srt_path = None;
+                        self.audio_path = state.audio_path.clone();
+                        self.cut_points = state.cut_points.clone();
+                        self.audio_segments = state.audio_segments.clone();
+                        self.manual_segment = state.manual_segment.clone();
+                        self.manual_start_time = state.manual_start_time.clone();
+                        self.manual_end_time = state.manual_end_time.clone();
+                        self.total_duration = state.total_duration;
+                        self.locked_subtitles = state.locked_subtitles.clone();
+                        self.segment_failures = state.failed_segments.clone();
+                        if let Some(settings) = &state.recognition_settings {
+                            self.whisper_model = settings.model;
+                            self.whisper_language = settings.language.clone();
+                            self.custom_language_code = settings.custom_language_code.clone();
+                            self.whisper_threads = settings.threads;
+                            self.precise_timing = settings.precise_timing;
+                            self.whisper_options = settings.whisper_options.clone();
+                        }
+                        self.media_info = state.video_path.as_ref()
+                            .filter(|p| p.exists())
+                            .map(|p| ffmpeg::media_info(p).map_err(|e| e.to_string()));
+                        
+                        // 重新加载音频播放器
+                        if let Some(audio_path) = &state.audio_path {
+                            println!("Loading audio player from: {:?}", audio_path);
+                            if audio_path.exists() {
+                                match audio_player::AudioPlayer::new(audio_path) {
+                                    Ok(player) => {
+                                        println!("Audio player loaded successfully!");
+                                        self.audio_player = Some(player);
+                                        self.apply_playback_speed();
+                                        self.state = AppState::AudioExtracted;
+                                    }
+                                    Err(e) => {
+                                        println!("Failed to load audio player: {}", e);
+                                        self.state = AppState::AudioExtracted;  // 仍然设置状态
+                                    }
+                                }
+                            } else {
+                                println!("Audio file not found: {:?}", audio_path);
+                                self.state = AppState::AudioExtracted;  // 即使音频不存在也设置状态
+                            }
+                        }
+                        
+                        // 检测哪些片段缺失字幕
+                        self.migrate_segment_subtitles();
+                        self.check_missing_subtitles();
+                        
+                        let completed = self.completed_segments.len();
+                        let total = self.audio_segments.len();
+                        self.status_message = match self.failed_segments.len() {
+                            0 => format!("Workspace loaded! {}/{} segments completed.", completed, total),
+                            failed => format!("Workspace loaded! {}/{} segments completed, {} failed.", completed, total, failed),
+                        };
+                    }
+                    Err(e) => {
+                        self.status_message = format!("Failed to load workspace: {}", e);
+                        eprintln!("Error loading workspace: {}", e);
+                    }
+                }
+            } else {
+                println!("No workspace_state.json found, creating new workspace");
+                
+                // 创建新工作区
+                match workspace::create_workspace_structure(&folder) {
+                    Ok(_) => {
+                        self.workspace_dir = Some(folder.clone());
+                        self.status_message = format!("New workspace created: {:?}", folder);
+                    }
+                    Err(e) => {
+                        self.status_message = format!("Failed to create workspace: {}", e);
+                    }
+                }
+            }
+        }
+    }
+    
+    fn save_workspace(&mut self) {
+        // 推荐工作区路径（基于视频文件所在目录）
+        let default_dir = self.video_path.as_ref()
+            .and_then(|p| p.parent())
+            .map(|p| p.to_path_buf());
+        
+        // 每次保存都让用户选择或确认工作区位置
+        let mut dialog = rfd::FileDialog::new();
+        if let Some(dir) = default_dir {
+            dialog = dialog.set_directory(dir);
+        }
+        
+        if let Some(folder) = dialog.pick_folder() {
+            // 创建工作区结构（如果不存在）
+            let _ = workspace::create_workspace_structure(&folder);
+            
+            // 更新当前工作区路径
+            self.workspace_dir = Some(folder.clone());
+            
+            // 扫描已完成的片段
+            let subtitles_dir = self.segment_subtitles_dir();
+            let mut completed_segments = Vec::new();
+            for (i, segment) in self.audio_segments.iter().enumerate() {
+                if pipeline::segment_has_subtitles(&segment.path, subtitles_dir.as_deref()) {
+                    completed_segments.push(i);
+                }
+            }
+            
+            let mut state = workspace::WorkspaceState {
+                video_path: self.video_path.clone(),
+                audio_path: self.audio_path.clone(),
+                cut_points: self.cut_points.clone(),
+                audio_segments: self.audio_segments.clone(),
+                completed_segments,  // 保存已完成的片段信息
+                manual_segment: self.manual_segment.clone(),
+                manual_start_time: self.manual_start_time.clone(),
+                manual_end_time: self.manual_end_time.clone(),
+                total_duration: self.total_duration,
+                workspace_dir: folder.clone(),
+                locked_subtitles: self.locked_subtitles.clone(),
+                failed_segments: self.segment_failures.clone(),
+                recognition_settings: Some(workspace::RecognitionSettings {
+                    model: self.whisper_model,
+                    language: self.whisper_language.clone(),
+                    custom_language_code: self.custom_language_code.clone(),
+                    threads: self.whisper_threads,
+                    precise_timing: self.precise_timing,
+                    whisper_options: self.whisper_options.clone(),
+                }),
+            };
+            
+            // 按设置把媒体文件复制/移动到工作区
+            let import_result = workspace::import_media(&mut state, self.media_transfer);
+            // 部分文件可能已经移动，无论成功与否都同步最新路径
+            self.sync_media_paths(&state);
+            self.migrate_segment_subtitles();
+            
+            match (import_result, state.save(&folder)) {
+                (Ok(_), Ok(_)) => {
+                    self.status_message = format!("Workspace saved to: {:?}", folder);
+                }
+                (Err(e), Ok(_)) => {
+                    self.status_message = format!("Workspace saved, but failed to import media: {}", e);
+                }
+                (_, Err(e)) => {
+                    self.status_message = format!("Failed to save workspace: {}", e);
+                }
+            }
+        }
+    }
+    
+    /// 媒体文件导入工作区后同步路径，音频位置变化时重新加载播放器
+    fn sync_media_paths(&mut self, state: &workspace::WorkspaceState) {
+        self.audio_segments = state.audio_segments.clone();
+        self.manual_segment = state.manual_segment.clone();
+        
+        if state.audio_path != self.audio_path {
+            self.audio_path = state.audio_path.clone();
+            if let Some(audio_path) = &self.audio_path {
+                if let Ok(player) = audio_player::AudioPlayer::new(audio_path) {
+                    self.audio_player = Some(player);
+                    self.is_playing = false;
+                    self.apply_playback_speed();
+                }
+            }
+        }
+    }
+    
+    /// 按识别状态给片段分类：已完成、识别失败（记录了原因）、未识别
+    fn check_missing_subtitles(&mut self) {
+        self.missing_segments.clear();
+        self.failed_segments.clear();
+        self.completed_segments.clear();
+        self.can_resume = false;
+        
+        if self.audio_segments.is_empty() {
+            return;
+        }
+        
+        // 检查每个片段是否有完整的 SRT 文件（空文件或写入中断的视为缺失）
+        let subtitles_dir = self.segment_subtitles_dir();
+        for i in 0..self.audio_segments.len() {
+            match self.segment_status(i, subtitles_dir.as_deref()) {
+                SegmentStatus::Completed => self.completed_segments.push(i),
+                SegmentStatus::Failed(_) => self.failed_segments.push(i),
+                SegmentStatus::NotRecognized => self.missing_segments.push(i),
+            }
+        }
+        // 已经有字幕的片段不再保留旧的失败原因
+        let completed = &self.completed_segments;
+        self.segment_failures.retain(|i, _| *i < self.audio_segments.len() && !completed.contains(i));
+        
+        // 如果有缺失且有完成的，说明可以恢复
+        self.can_resume = !self.missing_segments.is_empty() 
+            && !self.completed_segments.is_empty();
+    }
+    
+    /// 把片段的失败原因写入已保存的工作区，下次打开时仍能看到
+    /// 
+    /// 工作区中的片段与当前不一致（重新切割后还没保存）时不写入
+    fn save_segment_failures(&self) {
+        let Some(dir) = &self.workspace_dir else { return };
+        let Ok(mut state) = workspace::WorkspaceState::load(dir) else { return };
+        if state.audio_segments != self.audio_segments || state.failed_segments == self.segment_failures {
+            return;
+        }
+        
+        state.failed_segments = self.segment_failures.clone();
+        if let Err(e) = state.save(dir) {
+            eprintln!("Failed to save segment failures: {}", e);
+        }
+    }
+    
+    /// 片段下拉框中的名称，附带上次检查的识别状态
+    fn segment_label(&self, index: usize) -> String {
+        if self.completed_segments.contains(&index) {
+            format!("Segment {} ✔", index + 1)
+        } else if self.failed_segments.contains(&index) {
+            format!("Segment {} ❌ failed", index + 1)
+        } else if self.missing_segments.contains(&index) {
+            format!("Segment {} (not recognized)", index + 1)
+        } else {
+            format!("Segment {}", index + 1)
+        }
+    }
+    
+    /// 第 `index` 个片段的识别状态：有完整字幕为已完成，否则看是否记录过失败原因
+    fn segment_status(&self, index: usize, subtitles_dir: Option<&Path>) -> SegmentStatus {
+        let Some(segment) = self.audio_segments.get(index) else { return SegmentStatus::NotRecognized };
+        if pipeline::segment_has_subtitles(&segment.path, subtitles_dir) {
+            SegmentStatus::Completed
+        } else if let Some(reason) = self.segment_failures.get(&index) {
+            SegmentStatus::Failed(reason.clone())
+        } else {
+            SegmentStatus::NotRecognized
+        }
+    }
+    
+    fn resume_recognition(&mut self) {
+        if self.missing_segments.is_empty() {
+            self.status_message = "No missing segments to recognize!".to_string();
+            return;
+        }
+        
+        self.recognize_remaining(self.missing_segments.clone());
+    }
+    
+    /// 重新识别上次失败的片段
+    fn retry_failed_segments(&mut self) {
+        if self.failed_segments.is_empty() {
+            return;
+        }
+        
+        self.recognize_remaining(self.failed_segments.clone());
+    }
+    
+    /// 识别 `missing_indices` 中的片段，其余片段视为已完成，最后合并所有字幕
+    fn recognize_remaining(&mut self, missing_indices: Vec<usize>) {
+        let Some(output_path) = self.output_srt_path() else { return };
+        
+        self.state = AppState::Processing;
+        self.tail_gap_warning = None;
+        
+        // 设置初始进度为已完成的百分比
+        let completed_count = self.audio_segments.len() - missing_indices.len();
+        self.processing_progress = completed_count as f32 / self.audio_segments.len() as f32;
+        self.processing_status = format!("Resuming from {}/{} segments...", completed_count, self.audio_segments.len());
+        self.recognition_results.clear();
+        
+        let all_segments = self.segment_paths();
+        let offsets = pipeline::cut_offsets(&self.audio_segments, self.stream_start());
+        let recognition = self.recognition_options();
+        let merge = self.merge_options();
+        
+        // 创建消息通道
+        let (tx, rx) = channel();
+        self.progress_receiver = Some(rx);
+        let cancel = self.new_cancel_token();
+        
+        std::thread::spawn(move || {
+            // 只识别缺失的片段，进度包含已完成的数量
+            pipeline::recognize_segments(&all_segments, &missing_indices, &recognition, &cancel, &tx);
+            
+            // 合并所有字幕
+            if !cancel.is_cancelled() {
+                merge_in_background(&offsets, recognition.subtitles_dir.as_deref(), &merge, &output_path, &tx);
+            }
+            
+            // 发送完成消息
+            let _ = tx.send(ProgressMessage::Completed);
+        });
+    }
+    
+    /// 设置模板：选择并应用、保存当前设置、删除
+    /// Whisper 解码参数：未设置的参数不传给 Whisper
+    fn whisper_options_ui(&mut self, ui: &mut egui::Ui) {
+        let options = &mut self.whisper_options;
+        ui.collapsing("Whisper options", |ui| {
+            ui.label("Initial prompt:");
+            ui.add(egui::TextEdit::multiline(&mut options.initial_prompt)
+                .desired_rows(2)
+                .hint_text("Names and terms to bias recognition, e.g. Kubernetes, etcd, kubelet"));
+            
+            ui.horizontal(|ui| {
+                ui.label("Beam size:");
+                optional_count_ui(ui, &mut options.beam_size);
+                ui.label("Best of:");
+                optional_count_ui(ui, &mut options.best_of);
+            });
+            
+            ui.horizontal(|ui| {
+                let mut fixed = options.temperature.is_some();
+                ui.checkbox(&mut fixed, "Temperature:")
+                    .on_hover_text("Sample at a fixed temperature instead of Whisper's default fallback sequence");
+                let mut temperature = options.temperature.unwrap_or(0.0);
+                ui.add_enabled(fixed, egui::DragValue::new(&mut temperature).range(0.0..=1.0).speed(0.05));
+                options.temperature = fixed.then_some(temperature);
+            });
+            
+            optional_flag_ui(ui, "Word timestamps:", &mut options.word_timestamps);
+            optional_flag_ui(ui, "Condition on previous text:", &mut options.condition_on_previous_text);
+        });
+    }
+    
+    fn templates_ui(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("📋 Templates").show(ui, |ui| {
+            if self.settings_templates.is_empty() {
+                ui.label("No templates yet. Save the current settings below.");
+            } else {
+                self.selected_template = self.selected_template.min(self.settings_templates.len() - 1);
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_salt("settings_template")
+                        .selected_text(&self.settings_templates[self.selected_template].name)
+                        .show_ui(ui, |ui| {
+                            for (i, template) in self.settings_templates.iter().enumerate() {
+                                let label = if template.file_pattern.is_empty() {
+                                    template.name.clone()
+                                } else {
+                                    format!("{}  ({})", template.name, template.file_pattern)
+                                };
+                                ui.selectable_value(&mut self.selected_template, i, label);
+                            }
+                        });
+                    if ui.button("Apply").clicked() {
+                        let template = self.settings_templates[self.selected_template].clone();
+                        self.apply_template(&template);
+                        self.template_name = template.name.clone();
+                        self.template_pattern = template.file_pattern.clone();
+                        self.status_message = format!("Template \"{}\" applied", template.name);
+                    }
+                    if ui.button("🗑").on_hover_text("Delete this template").clicked() {
+                        let removed = self.settings_templates.remove(self.selected_template);
+                        self.status_message = format!("Template \"{}\" deleted", removed.name);
+                    }
+                });
+            }
+            
+            ui.horizontal(|ui| {
+                ui.add(egui::TextEdit::singleline(&mut self.template_name).hint_text("name").desired_width(100.0));
+                ui.add(egui::TextEdit::singleline(&mut self.template_pattern).hint_text("auto-apply, e.g. *podcast*").desired_width(140.0))
+                    .on_hover_text("Apply automatically to dropped files whose name matches (* and ?, case-insensitive); leave empty to apply manually");
+                if ui.button("💾 Save").on_hover_text("Save model, language, threads, timing, VAD threshold, subtitle standard and output settings").clicked() {
+                    self.save_template();
+                }
+            });
+        });
+    }
+    
+    /// 左侧：文件区、播放器、切割点与片段管理
+    fn media_panel_ui(&mut self, ui: &mut egui::Ui) {
+        // Drop area
+        egui::Frame::default()
+            .fill(egui::Color32::from_rgb(40, 40, 50))
+            .stroke(egui::Stroke::new(2.0, egui::Color32::from_rgb(100, 100, 120)))
+            .inner_margin(20.0)
+            .show(ui, |ui| {
+                ui.set_min_height(100.0);
+                ui.vertical_centered(|ui| {
+                    if let Some(path) = &self.video_path {
+                        ui.label(format!("📹 {}", path.file_name().unwrap().to_string_lossy()));
                         
-                        let completed = self.completed_segments.len();
-                        let total = self.audio_segments.len();
-                        self.status_message = format!("Workspace loaded! {}/{} segments completed.", completed, total);
+                        // 媒体信息
+                        match &self.media_info {
+                            Some(Ok(info)) => {
+                                ui.label(info.summary());
+                            }
+                            Some(Err(e)) => {
+                                ui.colored_label(egui::Color32::from_rgb(220, 160, 60), format!("⚠ Failed to probe media: {}", e));
+                            }
+                            None => {}
+                        }
+                        
+                        // 未自动提取时手动提取
+                        if self.state == AppState::Idle {
+                            if self.extraction_receiver.is_some() {
+                                ui.horizontal(|ui| {
+                                    ui.spinner();
+                                    ui.label("Extracting audio...");
+                                });
+                            } else if ui.button("🎵 Extract Audio").clicked() {
+                                self.extract_audio();
+                            }
+                        }
+                    } else {
+                        ui.label("📂 Drag video file here");
                     }
-                    Err(e) => {
-                        self.status_message = format!("Failed to load workspace: {}", e);
-                        eprintln!("Error loading workspace: {}", e);
+                });
+            });
+        
+        // Open a network stream
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.stream_url)
+                .hint_text("https://... or .m3u8 stream URL")
+                .desired_width(360.0));
+            ui.label("Max length (s):");
+            ui.add(egui::TextEdit::singleline(&mut self.stream_max_seconds)
+                .hint_text("full")
+                .desired_width(50.0));
+            let busy = self.extraction_receiver.is_some() || self.state == AppState::Processing;
+            if ui.add_enabled(!busy, egui::Button::new("🌐 Open URL")).clicked() {
+                self.open_stream_url();
+            }
+        });
+        
+        ui.add_space(10.0);
+        
+        // Audio player
+        if self.state != AppState::Idle {
+            egui::Frame::default()
+                .fill(egui::Color32::from_rgb(30, 30, 40))
+                .inner_margin(15.0)
+                .show(ui, |ui| {
+                    ui.label("🎵 Audio Player");
+                    ui.separator();
+                    
+                    // Time display
+                    ui.horizontal(|ui| {
+                        ui.label(Self::format_time(self.scrub_position.unwrap_or(self.current_position)));
+                        ui.label("/");
+                        ui.label(Self::format_time(self.total_duration));
+                    });
+                    
+                    ui.add_space(5.0);
+                    
+                    // Time scale (5-minute intervals)
+                    ui.horizontal(|ui| {
+                        let interval = 300.0; // 5 minutes in seconds
+                        let num_intervals = (self.total_duration / interval).ceil() as usize;
+                        
+                        for i in 0..=num_intervals {
+                            let time = i as f64 * interval;
+                            if time > self.total_duration {
+                                break;
+                            }
+                            
+                            let minutes = (time / 60.0).floor() as u32;
+                            let button_text = format!("{}m", minutes);
+                            
+                            if ui.small_button(&button_text).clicked() {
+                                self.current_position = time;
+                                if let Some(player) = &mut self.audio_player {
+                                    player.seek(time);
+                                    player.pause();
+                                    self.is_playing = false;
+                                }
+                            }
+                            
+                            // 在按钮之间添加间隔
+                            if i < num_intervals {
+                                ui.add_space(3.0);
+                            }
+                        }
+                    });
+                    
+                    // Playback progress bar (full width)
+                    ui.add_space(5.0);
+                    self.request_waveform();
+                    // 先占住绘制顺序，波形画在滑块下面
+                    let waveform_shape = ui.painter().add(egui::Shape::Noop);
+                    let mut position = self.scrub_position.unwrap_or(self.current_position);
+                    // 使用进度条宽度等于左侧面板宽度减去边距
+                    ui.spacing_mut().slider_width = TIMELINE_WIDTH;
+                    let slider = ui.add(egui::Slider::new(&mut position, 0.0..=self.total_duration)
+                        .show_value(false));
+                    
+                    // Waveform and cut points behind the slider
+                    let waveform = self.audio_path.as_ref().and_then(|path| self.waveforms.get(path));
+                    if waveform.is_some_and(|w| !w.is_empty()) || !self.cut_points.is_empty() {
+                        let mut shapes = Vec::new();
+                        // 与 A/B 区间一致，按滑块轨道换算位置
+                        let rail = slider.rect.x_range().shrink(slider.rect.height() / 2.5);
+                        let center = slider.rect.center().y;
+                        let half_height = slider.rect.height() / 2.0;
+                        let buckets = waveform.map_or(0, Vec::len);
+                        for (i, &peak) in waveform.into_iter().flatten().enumerate() {
+                            let x = rail.min + (i as f32 + 0.5) / buckets as f32 * rail.span();
+                            let h = (peak * half_height).max(0.5);
+                            shapes.push(egui::Shape::line_segment(
+                                [egui::pos2(x, center - h), egui::pos2(x, center + h)],
+                                egui::Stroke::new(1.0, egui::Color32::from_rgb(70, 110, 150)),
+                            ));
+                        }
+                        if self.total_duration > 0.0 {
+                            for &cut in &self.cut_points {
+                                let x = rail.min + (cut / self.total_duration) as f32 * rail.span();
+                                shapes.push(egui::Shape::vline(x, slider.rect.y_range(), egui::Stroke::new(1.5, egui::Color32::from_rgb(230, 90, 70))));
+                            }
+                        }
+                        ui.painter().set(waveform_shape, egui::Shape::Vec(shapes));
+                    }
+                    // 点击或拖动波形：按住期间手柄跟随鼠标，松开时才 seek，拖动中不会反复重新解码
+                    let commit = if slider.is_pointer_button_down_on() {
+                        if slider.changed() {
+                            self.scrub_position = Some(position);
+                        }
+                        None
+                    } else if slider.changed() {
+                        Some(position)  // 键盘调整
+                    } else {
+                        self.scrub_position.take()
+                    };
+                    if let Some(position) = commit {
+                        self.scrub_position = None;
+                        self.current_position = position;
+                        if let Some(player) = &mut self.audio_player {
+                            player.seek(position);
+                        }
+                    }
+                    
+                    // A/B loop region overlay
+                    if self.total_duration > 0.0 && (self.loop_a.is_some() || self.loop_b.is_some()) {
+                        // 与 egui 滑块一致：轨道两端各留出半径为高度 / 2.5 的手柄空间
+                        let rail = slider.rect.x_range().shrink(slider.rect.height() / 2.5);
+                        let x_at = |time: f64| rail.min + (time / self.total_duration) as f32 * rail.span();
+                        let painter = ui.painter();
+                        if let Some((a, b)) = self.loop_range() {
+                            let alpha = if self.loop_enabled { 60 } else { 25 };
+                            painter.rect_filled(
+                                egui::Rect::from_x_y_ranges(x_at(a)..=x_at(b), slider.rect.y_range()),
+                                0.0,
+                                egui::Color32::from_rgba_unmultiplied(240, 200, 60, alpha),
+                            );
+                        }
+                        for time in [self.loop_a, self.loop_b].into_iter().flatten() {
+                            painter.vline(x_at(time), slider.rect.y_range(), egui::Stroke::new(2.0, egui::Color32::from_rgb(240, 200, 60)));
+                        }
+                    }
+                    
+                    // Speech regions overlay
+                    if !self.speech_regions.is_empty() && self.total_duration > 0.0 {
+                        let (rect, _) = ui.allocate_exact_size(egui::vec2(TIMELINE_WIDTH, 8.0), egui::Sense::hover());
+                        let painter = ui.painter_at(rect);
+                        painter.rect_filled(rect, 0.0, egui::Color32::from_rgb(50, 50, 60));
+                        for &(start, end) in &self.speech_regions {
+                            let x_start = rect.left() + (start / self.total_duration) as f32 * rect.width();
+                            let x_end = rect.left() + (end / self.total_duration) as f32 * rect.width();
+                            painter.rect_filled(
+                                egui::Rect::from_x_y_ranges(x_start..=x_end.max(x_start + 1.0), rect.y_range()),
+                                0.0,
+                                egui::Color32::from_rgb(80, 180, 100),
+                            );
+                        }
+                    }
+                    ui.add_space(5.0);
+                    
+                    // Speech detection preview
+                    ui.horizontal(|ui| {
+                        ui.label("VAD threshold:");
+                        ui.add(egui::Slider::new(&mut self.vad_threshold, 0.001..=0.2).logarithmic(true));
+                        if self.vad_preview_receiver.is_none() {
+                            if ui.button("🔍 Preview Speech").clicked() {
+                                self.preview_speech_regions();
+                            }
+                        } else {
+                            ui.spinner();
+                        }
+                    });
+                    ui.add_space(5.0);
+                    
+                    ui.horizontal(|ui| {
+                        // Play/Pause button
+                        if self.is_playing {
+                            if ui.button("⏸ Pause").clicked() {
+                                if let Some(player) = &mut self.audio_player {
+                                    player.pause();
+                                    self.is_playing = false;
+                                }
+                            }
+                        } else {
+                            if ui.button("▶ Play").clicked() {
+                                if let Some(player) = &mut self.audio_player {
+                                    player.play();
+                                    self.is_playing = true;
+                                }
+                            }
+                        }
+                        
+                        // Mark cut point button
+                        if ui.button("✂ Mark Cut Point").on_hover_text("Mark a cut point at the playhead ( M )").clicked() {
+                            self.add_cut_point();
+                        }
+                        let mut global_hotkey = self.global_hotkey.is_some();
+                        if ui.checkbox(&mut global_hotkey, "🌐")
+                            .on_hover_text(format!("Global hotkey: {} marks a cut point even when this window is not focused", hotkey::HOTKEY_LABEL))
+                            .changed()
+                        {
+                            self.set_global_hotkey(global_hotkey);
+                        }
+                        
+                        // A/B loop
+                        if ui.button("A").on_hover_text("Set loop start at the playhead ( [ )").clicked() {
+                            self.set_loop_a();
+                        }
+                        if ui.button("B").on_hover_text("Set loop end at the playhead ( ] )").clicked() {
+                            self.set_loop_b();
+                        }
+                        ui.add_enabled(self.loop_range().is_some(), egui::Checkbox::new(&mut self.loop_enabled, "🔁 Loop"))
+                            .on_hover_text("Jump back to A whenever playback passes B ( L )");
+                        if (self.loop_a.is_some() || self.loop_b.is_some()) && ui.small_button("✖").on_hover_text("Clear A/B").clicked() {
+                            self.clear_loop();
+                        }
+                        
+                        // Playback speed
+                        let mut speed_changed = false;
+                        egui::ComboBox::from_id_salt("playback_speed")
+                            .selected_text(format!("{}x", self.playback_speed))
+                            .width(60.0)
+                            .show_ui(ui, |ui| {
+                                for speed in PLAYBACK_SPEEDS {
+                                    speed_changed |= ui.selectable_value(&mut self.playback_speed, speed, format!("{}x", speed)).changed();
+                                }
+                            });
+                        speed_changed |= ui.checkbox(&mut self.preserve_pitch, "Keep pitch")
+                            .on_hover_text("Slow down without lowering the pitch. Uses FFmpeg, so seeking takes a moment")
+                            .changed();
+                        if ui.checkbox(&mut self.accurate_seek, "Accurate seek")
+                            .on_hover_text("Decode up to the exact seek position so what you hear matches the displayed time (e.g. VBR MP3). Slightly slower seeking")
+                            .changed()
+                        {
+                            if let Some(player) = &mut self.audio_player {
+                                player.set_accurate_seek(self.accurate_seek);
+                            }
+                        }
+                        if speed_changed {
+                            self.apply_playback_speed();
+                        }
+                        
+                        // Level meter
+                        if let Some(player) = &self.audio_player {
+                            let levels = player.levels();
+                            let (rect, _) = ui.allocate_exact_size(egui::vec2(120.0, 10.0), egui::Sense::hover());
+                            let painter = ui.painter_at(rect);
+                            painter.rect_filled(rect, 0.0, egui::Color32::from_rgb(50, 50, 60));
+                            let rms_rect = egui::Rect::from_min_size(rect.min, egui::vec2(rect.width() * levels.rms.min(1.0), rect.height()));
+                            painter.rect_filled(rms_rect, 0.0, egui::Color32::from_rgb(80, 180, 100));
+                            let peak_x = rect.left() + rect.width() * levels.peak.min(1.0);
+                            let peak_color = if levels.clipping { egui::Color32::RED } else { egui::Color32::YELLOW };
+                            painter.vline(peak_x, rect.y_range(), egui::Stroke::new(2.0, peak_color));
+                            
+                            if levels.clipping {
+                                ui.colored_label(egui::Color32::RED, "CLIP");
+                            } else if levels.peak > 0.0 {
+                                ui.label(format!("{:.1} dBFS", 20.0 * levels.peak.log10()));
+                            }
+                        }
+                        
+                        if self.clip_scan_receiver.is_none() {
+                            if ui.button("🔊 Scan Clipping").clicked() {
+                                self.scan_clipping();
+                            }
+                        } else {
+                            ui.spinner();
+                        }
+                        
+                        if self.loudest_receiver.is_none() {
+                            if ui.button("📢 Jump to Loudest").on_hover_text("Play the loudest part to check the file loaded correctly").clicked() {
+                                self.jump_to_loudest();
+                            }
+                        } else {
+                            ui.spinner();
+                        }
+                    });
+                    
+                    // 按目标时长切割
+                    ui.horizontal(|ui| {
+                        ui.label("Target segment length:");
+                        ui.add(egui::DragValue::new(&mut self.target_segment_minutes)
+                            .range(1.0..=120.0)
+                            .speed(0.5)
+                            .suffix(" min"));
+                        ui.label("snap within:");
+                        ui.add(egui::DragValue::new(&mut self.snap_window_seconds)
+                            .range(0.0..=120.0)
+                            .speed(1.0)
+                            .suffix(" s"));
+                        if ui.button("📏 Cut by Target Length")
+                            .on_hover_text("Replace the cut points with evenly spaced ones, each moved to the nearest silence found by Preview Speech within the snap range")
+                            .clicked() {
+                            self.cut_by_target_length();
+                        }
+                    });
+                    
+                    // Cut points list
+                    if !self.cut_points.is_empty() {
+                        ui.separator();
+                        ui.label(format!("Cut Points ({}):", self.cut_points.len()));
+                        
+                        egui::ScrollArea::vertical()
+                            .max_height(150.0)
+                            .show(ui, |ui| {
+                                let mut to_remove = None;
+                                let mut to_move = None;
+                                for (i, &point) in self.cut_points.iter().enumerate() {
+                                    ui.horizontal(|ui| {
+                                        match &mut self.editing_cut_point {
+                                            // 直接输入新的时间，Enter 或点击别处确认，Esc 取消
+                                            Some((index, input)) if *index == i => {
+                                                ui.label(format!("{}.", i + 1));
+                                                let response = ui.add(egui::TextEdit::singleline(input).desired_width(100.0));
+                                                if !response.has_focus() && !response.lost_focus() {
+                                                    response.request_focus();
+                                                }
+                                                if response.lost_focus() {
+                                                    if !ui.input(|input| input.key_pressed(egui::Key::Escape)) {
+                                                        to_move = Some((i, input.clone()));
+                                                    }
+                                                    self.editing_cut_point = None;
+                                                }
+                                            }
+                                            _ => {
+                                                let label = ui.add(egui::Label::new(format!("{}. {}", i + 1, Self::format_time(point)))
+                                                    .sense(egui::Sense::click()))
+                                                    .on_hover_text("Click to edit the time");
+                                                if label.clicked() {
+                                                    self.editing_cut_point = Some((i, Self::format_time(point)));
+                                                }
+                                            }
+                                        }
+                                        if ui.small_button("🗑").clicked() {
+                                            to_remove = Some(i);
+                                        }
+                                    });
+                                }
+                                if let Some((i, input)) = to_move {
+                                    self.move_cut_point(i, &input);
+                                }
+                                if let Some(i) = to_remove {
+                                    self.editing_cut_point = None;
+                                    self.remove_cut_point(i);
+                                }
+                            });
+                        
+                        ui.add_space(5.0);
+                        if self.cut_receiver.is_some() {
+                            ui.label(&self.cut_status);
+                            ui.add(egui::ProgressBar::new(self.cut_progress).show_percentage());
+                        } else if ui.button("🔪 Execute Cut").clicked() {
+                            self.cut_audio();
+                        }
+                    }
+                });
+        }
+        
+        ui.add_space(10.0);
+        
+        // Re-recognize section
+        if !self.audio_segments.is_empty() && self.state != AppState::Processing {
+            ui.separator();
+            ui.label("🔄 Re-recognize Segment");
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_label("Select segment")
+                    .selected_text(self.segment_label(self.selected_segment_index))
+                    .show_ui(ui, |ui| {
+                        for i in 0..self.audio_segments.len() {
+                            let label = self.segment_label(i);
+                            ui.selectable_value(&mut self.selected_segment_index, i, label);
+                        }
+                    });
+                
+                if ui.button("🎤 Re-recognize").clicked() {
+                    self.rerecognize_segment();
+                }
+            });
+            
+            // 识别失败的片段及原因，恢复识别时不会自动重试
+            if !self.failed_segments.is_empty() {
+                let failures: Vec<String> = self.failed_segments.iter()
+                    .filter_map(|i| self.segment_failures.get(i).map(|reason| format!("Segment {}: {}", i + 1, reason)))
+                    .collect();
+                ui.horizontal(|ui| {
+                    ui.colored_label(egui::Color32::from_rgb(220, 80, 80), format!("❌ {} segments failed", failures.len()));
+                    if ui.button("🔁 Retry Failed").on_hover_text("Resume skips failed segments; retry them explicitly").clicked() {
+                        self.retry_failed_segments();
                     }
+                });
+                egui::CollapsingHeader::new("Failure reasons")
+                    .id_salt("segment_failures")
+                    .show(ui, |ui| {
+                        for failure in failures {
+                            ui.label(failure);
+                        }
+                    });
+            }
+            
+            ui.add_space(5.0);
+            
+            // Cleanup button
+            if ui.button("🗑️ Clean Up Temp Files").clicked() {
+                self.cleanup_temp_files();
+            }
+        }
+        
+        ui.add_space(10.0);
+        
+        // Manual cut section
+        if self.state != AppState::Idle && self.state != AppState::Processing {
+            ui.separator();
+            ui.label("✂️ Manual Cut Segment");
+            
+            ui.horizontal(|ui| {
+                ui.label("Start:");
+                ui.text_edit_singleline(&mut self.manual_start_time);
+                ui.label("End:");
+                ui.text_edit_singleline(&mut self.manual_end_time);
+            });
+            ui.label("💡 Format: HH:MM:SS or MM:SS or SS");
+            
+            ui.add_space(5.0);
+            ui.horizontal(|ui| {
+                if ui.button("✂️ Cut Segment").clicked() {
+                    self.cut_manual_segment();
+                }
+                
+                if ui.button("🎯 Recognize Range").on_hover_text("Recognize this time range without saving a segment file").clicked() {
+                    self.recognize_range();
+                }
+                
+                if self.manual_segment.is_some() && ui.button("🎤 Recognize Segment").clicked() {
+                    self.recognize_manual_segment();
+                }
+            });
+        }
+        
+        ui.add_space(10.0);
+        
+        // Status message
+        ui.label(&self.status_message);
+        
+        if let Some(warning) = self.tail_gap_warning.clone() {
+            ui.horizontal(|ui| {
+                ui.colored_label(egui::Color32::from_rgb(220, 160, 60), warning);
+                if self.can_resume && self.state != AppState::Processing && ui.button("▶️ Resume").clicked() {
+                    self.resume_recognition();
+                }
+            });
+        }
+    }
+    
+    /// 右侧：识别设置、进度与结果
+    fn settings_panel_ui(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Settings");
+        ui.separator();
+        
+        self.templates_ui(ui);
+        
+        // Whisper model selection
+        ui.label("Whisper Model:");
+        let model_label = |model: WhisperModel, downloaded: &[WhisperModel]| {
+            if downloaded.contains(&model) {
+                format!("{} ✔", model.as_str())
+            } else {
+                format!("{} (not downloaded)", model.as_str())
+            }
+        };
+        egui::ComboBox::from_label("")
+            .selected_text(model_label(self.whisper_model, &self.downloaded_models))
+            .show_ui(ui, |ui| {
+                for model in WhisperModel::all() {
+                    ui.selectable_value(&mut self.whisper_model, model, model_label(model, &self.downloaded_models));
+                }
+            });
+        
+        if ui.button("📊 Estimate").on_hover_text("Estimate recognition time and disk usage for the loaded media").clicked() {
+            self.show_estimate = true;
+        }
+        
+        if self.model_download_receiver.is_some() {
+            ui.add(egui::ProgressBar::new(self.model_download_progress)
+                .show_percentage()
+                .text(format!("Downloading {}...", self.whisper_model.as_str())));
+        } else if !self.downloaded_models.contains(&self.whisper_model) {
+            ui.horizontal(|ui| {
+                ui.colored_label(egui::Color32::from_rgb(220, 160, 60), "⚠ Not downloaded, the first run needs internet");
+                if ui.button("⬇ Download Model").clicked() {
+                    self.download_model();
+                }
+            });
+        }
+        
+        ui.add_space(10.0);
+        
+        // Language selection
+        ui.label("Recognition Language:");
+        egui::ComboBox::from_label(" ")
+            .selected_text(self.whisper_language.as_str())
+            .show_ui(ui, |ui| {
+                for lang in WhisperLanguage::all() {
+                    ui.selectable_value(&mut self.whisper_language, lang.clone(), lang.as_str());
+                }
+            });
+        
+        // Custom language input (only show when Custom is selected)
+        if self.whisper_language == WhisperLanguage::Custom {
+            ui.add_space(5.0);
+            ui.horizontal(|ui| {
+                ui.label("Language code:");
+                ui.text_edit_singleline(&mut self.custom_language_code);
+            });
+            ui.label("💡 Examples: ko (Korean), ar (Arabic), hi (Hindi), pt (Portuguese)");
+        }
+        
+        ui.add_space(10.0);
+        
+        self.preview_ui(ui);
+        
+        ui.add_space(10.0);
+        
+        // Lossless mode
+        ui.checkbox(&mut self.lossless, "Lossless mode (无损模式)")
+            .on_hover_text("Keep the WAV segments and feed them to Whisper bit-exact, skipping the MP3 conversion for both auto and manual cuts. Segments take about 7x the disk space; WAV has no priming delay");
+        ui.checkbox(&mut self.accurate_cut, "Accurate cutting (精确切割)")
+            .on_hover_text("Re-encode segments instead of copying the audio stream, so cuts land on the exact sample. Stream copy is faster but snaps MP3/AAC sources to frame boundaries, starting segments early. Makes no difference for WAV");
+        
+        // Priming offset correction
+        ui.horizontal(|ui| {
+            ui.label("Priming offset (ms):");
+            ui.add(egui::TextEdit::singleline(&mut self.priming_delay_ms)
+                .hint_text("auto")
+                .desired_width(80.0));
+        });
+        ui.label("💡 Leave empty to detect per segment: 0 for WAV and tagged MP3, LAME priming for untagged MP3");
+        
+        ui.add_space(10.0);
+        
+        // CPU thread limit
+        let max_threads = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1);
+        ui.horizontal(|ui| {
+            ui.label("Threads:");
+            ui.add(egui::DragValue::new(&mut self.whisper_threads)
+                .range(0..=max_threads)
+                .custom_formatter(|n, _| if n == 0.0 { "All".to_string() } else { format!("{}", n) }));
+        });
+        ui.label("💡 Limit CPU usage of Whisper, 0 = all cores");
+        
+        ui.horizontal(|ui| {
+            ui.label("Parallel segments:");
+            let auto = pipeline::default_workers();
+            ui.add(egui::DragValue::new(&mut self.recognition_workers)
+                .range(0..=max_threads)
+                .custom_formatter(move |n, _| if n == 0.0 { format!("Auto ({})", auto) } else { format!("{}", n) }))
+                .on_hover_text("Number of Whisper processes recognizing segments at the same time. With Threads = All, the cores are shared between them. Each process loads its own copy of the model, so lower this for large models if memory runs out");
+        });
+        
+        ui.checkbox(&mut self.precise_timing, "Precise timing (Whisper JSON)")
+            .on_hover_text("Read segment times from Whisper's JSON output instead of the millisecond-rounded SRT; times are only rounded when the merged subtitles are written");
+        
+        self.whisper_options_ui(ui);
+        
+        ui.add_space(10.0);
+        
+        // Output encoding selection
+        ui.label("Output Encoding:");
+        egui::ComboBox::from_id_salt("output_encoding")
+            .selected_text(self.output_encoding.as_str())
+            .show_ui(ui, |ui| {
+                for encoding in srt_merger::OutputEncoding::all() {
+                    ui.selectable_value(&mut self.output_encoding, encoding, encoding.as_str());
+                }
+            });
+        ui.checkbox(&mut self.keep_previous_srt, "Keep previous subtitles")
+            .on_hover_text("Don't overwrite an existing video.srt from an earlier run; write video.1.srt, video.2.srt, … instead. Runs within this session keep updating the same file");
+        ui.horizontal(|ui| {
+            ui.label("Round to frame rate (fps):");
+            ui.add(egui::TextEdit::singleline(&mut self.export_fps)
+                .hint_text("off")
+                .desired_width(60.0));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Start index:");
+            ui.add(egui::TextEdit::singleline(&mut self.export_start_index)
+                .hint_text("1")
+                .desired_width(60.0));
+        });
+        ui.checkbox(&mut self.dedupe_boundaries, "Remove duplicate cues at cut points")
+            .on_hover_text("When a cut splits a sentence, both segments may transcribe the same words");
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.strip_nonspeech, "Remove non-speech tags")
+                .on_hover_text("Drop tags like [Music] or (laughs) when merging; cues with nothing else are deleted");
+            ui.add_enabled(self.strip_nonspeech, egui::TextEdit::singleline(&mut self.nonspeech_patterns)
+                .desired_width(160.0))
+                .on_hover_text("Space-separated patterns, * matches any text within a line");
+        });
+        ui.checkbox(&mut self.export_segment_subtitles, "Also export per-segment SRTs")
+            .on_hover_text("Writes {name}_segNN.srt with global timestamps to the workspace subtitles/ folder");
+        
+        ui.add_space(10.0);
+        
+        // Workspace media handling
+        ui.label("Workspace Media:");
+        egui::ComboBox::from_id_salt("media_transfer")
+            .selected_text(self.media_transfer.as_str())
+            .show_ui(ui, |ui| {
+                for transfer in workspace::MediaTransfer::all() {
+                    ui.selectable_value(&mut self.media_transfer, transfer, transfer.as_str());
+                }
+            });
+        
+        ui.add_space(10.0);
+        ui.checkbox(&mut self.auto_extract, "Extract audio when a video is dropped");
+        ui.checkbox(&mut self.whisper_audio, "Extract as 16 kHz mono (提取为 16kHz 单声道)")
+            .on_hover_text("Extract video audio in the format Whisper uses internally: about 1/5 of the disk space and faster to extract and cut, with the same recognition results. Playback sounds duller. Audio files are always used as they are");
+        ui.horizontal(|ui| {
+            ui.label("FFmpeg path:");
+            let response = ui.add(egui::TextEdit::singleline(&mut self.ffmpeg_path)
+                .hint_text("ffmpeg (from PATH)")
+                .desired_width(200.0))
+                .on_hover_text("Full path to the ffmpeg binary if it is not on PATH; ffprobe is looked up next to it");
+            if response.changed() {
+                self.apply_ffmpeg_path();
+            }
+            if response.lost_focus() && self.ffmpeg_missing {
+                self.ffmpeg_missing = !ffmpeg::check_available();
+            }
+        });
+        
+        ui.add_space(20.0);
+        ui.separator();
+        
+        // Recognition section
+        ui.label("🎤 Recognition");
+        ui.add_space(5.0);
+        
+        if !self.audio_segments.is_empty() {
+            ui.label(format!("✅ Audio segments: {}", self.audio_segments.len()));
+            ui.add_space(10.0);
+            
+            if self.state != AppState::Processing {
+                if ui.button("🎤 Start Recognition").clicked() {
+                    self.start_recognition();
                 }
             } else {
-                println!("No workspace_state.json found, creating new workspace");
+                ui.label("🔄 Recognizing...");
+                ui.label(&self.processing_status);
+                ui.add_space(5.0);
+                ui.add(egui::ProgressBar::new(self.processing_progress).show_percentage());
+                ui.add_space(5.0);
+                if ui.button("🛑 Stop Recognition & Kill Processes").clicked() {
+                    self.stop_recognition();
+                }
+            }
+            
+            ui.add_space(10.0);
+            
+            // Recognition results
+            if !self.recognition_results.is_empty() {
+                ui.label("📝 Results:");
+                ui.add_space(5.0);
                 
-                // 创建新工作区
-                match workspace::create_workspace_structure(&folder) {
-                    Ok(_) => {
-                        self.workspace_dir = Some(folder.clone());
-                        self.status_message = format!("New workspace created: {:?}", folder);
-                    }
-                    Err(e) => {
-                        self.status_message = format!("Failed to create workspace: {}", e);
+                egui::ScrollArea::vertical()
+                    .max_height(180.0)
+                    .show(ui, |ui| {
+                        for result in &self.recognition_results {
+                            egui::Frame::default()
+                                .fill(egui::Color32::from_rgb(35, 35, 45))
+                                .inner_margin(8.0)
+                                .show(ui, |ui| {
+                                    ui.label(result);
+                                });
+                            ui.add_space(5.0);
+                        }
+                    });
+            }
+        } else {
+            ui.label("⚠️ Please cut audio first");
+        }
+        
+        // 立体声访谈：每个声道一个说话人
+        if self.is_stereo() && self.audio_path.is_some() {
+            ui.add_space(5.0);
+            if self.channel_receiver.is_some() {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label(&self.processing_status);
+                    if ui.button("🛑 Stop").clicked() {
+                        self.stop_recognition();
                     }
-                }
+                });
+            } else if self.state != AppState::Processing
+                && ui.button("🎧 Per-Channel Recognition")
+                    .on_hover_text("Recognize the left and right channels separately and label each cue with its speaker. For interviews with one speaker per channel")
+                    .clicked() {
+                self.recognize_channels();
             }
         }
-    }
-    
-    fn save_workspace(&mut self) {
-        // 推荐工作区路径（基于视频文件所在目录）
-        let default_dir = self.video_path.as_ref()
-            .and_then(|p| p.parent())
-            .map(|p| p.to_path_buf());
         
-        // 每次保存都让用户选择或确认工作区位置
-        let mut dialog = rfd::FileDialog::new();
-        if let Some(dir) = default_dir {
-            dialog = dialog.set_directory(dir);
+        ui.add_space(10.0);
+        
+        // Save Plain Text button
+        if !self.recognition_results.is_empty() {
+            ui.separator();
+            if ui.button("💾 Save Plain Text").clicked() {
+                self.save_plain_text();
+            }
         }
         
-        if let Some(folder) = dialog.pick_folder() {
-            // 创建工作区结构（如果不存在）
-            let _ = workspace::create_workspace_structure(&folder);
+        // Subtitle cleanup (operates on the merged SRT)
+        let has_merged_srt = self.merged_srt_path().is_some_and(|p| p.exists());
+        if has_merged_srt && self.state != AppState::Processing {
+            ui.add_space(10.0);
+            ui.separator();
+            ui.label("🧹 Subtitle Cleanup");
+            ui.add_space(5.0);
             
-            // 更新当前工作区路径
-            self.workspace_dir = Some(folder.clone());
+            // 字幕列表，可搜索（Ctrl+F）；每条字幕都可以单独播放，右键打开操作菜单
+            let mut jump_to = None;
+            ui.horizontal(|ui| {
+                let response = ui.add(egui::TextEdit::singleline(&mut self.subtitle_search)
+                    .hint_text("🔍 Search subtitles (Ctrl+F)")
+                    .desired_width(220.0));
+                if ui.input(|i| i.modifiers.command && i.key_pressed(egui::Key::F)) {
+                    response.request_focus();
+                }
+                if response.changed() {
+                    self.update_subtitle_search();
+                }
+                let enter_pressed = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                
+                let count = self.search_results.len();
+                if self.subtitle_search.trim().is_empty() {
+                    ui.label(format!("{} cues", count));
+                } else if count > 0 {
+                    let current = self.search_selected;
+                    ui.label(format!("{}/{}", current.map_or(0, |i| i + 1), count));
+                    if ui.small_button("⏶").clicked() {
+                        jump_to = Some(current.map_or(count - 1, |i| (i + count - 1) % count));
+                    }
+                    if ui.small_button("⏷").clicked() || enter_pressed {
+                        jump_to = Some(current.map_or(0, |i| (i + 1) % count));
+                    }
+                } else {
+                    ui.label("No matches");
+                }
+            });
             
-            // 扫描已完成的片段
-            let mut completed_segments = Vec::new();
-            for (i, segment) in self.audio_segments.iter().enumerate() {
-                let srt_path = segment.with_extension("srt");
-                if srt_path.exists() {
-                    completed_segments.push(i);
+            let mut cue_action = None;
+            let mut play_range = None;
+            if !self.search_results.is_empty() {
+                let can_rerecognize = self.audio_path.is_some() && self.state != AppState::Processing;
+                let query = self.subtitle_search.trim().to_string();
+                let text_color = ui.visuals().text_color();
+                let overlap_color = egui::Color32::from_rgb(230, 80, 80);
+                // 每行高度固定，长字幕文件只布局可见的行
+                let row_height = ui.spacing().interact_size.y;
+                let mut scroll_area = egui::ScrollArea::vertical()
+                    .id_salt("subtitle_cue_list")
+                    .max_height(200.0);
+                if let Some(index) = jump_to {
+                    // 不可见的行没有布局，按行号直接滚动到跳转的匹配
+                    scroll_area = scroll_area.vertical_scroll_offset(index as f32 * (row_height + ui.spacing().item_spacing.y));
+                }
+                scroll_area.show_rows(ui, row_height, self.search_results.len(), |ui, rows| {
+                    for i in rows {
+                        let entry = &self.search_results[i];
+                        let line = format!("{} {}", Self::format_time(entry.start_time), entry.text.replace('\n', " / "));
+                        let overlapping = self.overlaps.iter().any(|(a, b)| a.same_cue(entry) || b.same_cue(entry));
+                        let job = highlight_matches(&line, &query, if overlapping { overlap_color } else { text_color });
+                        let selected = self.search_selected == Some(i);
+                        let response = ui.horizontal(|ui| {
+                            ui.set_height(row_height);
+                            if ui.add_enabled(self.audio_player.is_some(), egui::Button::new("▶").small())
+                                .on_hover_text("Play only this cue")
+                                .clicked() {
+                                play_range = Some((entry.start_time, entry.end_time));
+                            }
+                            let mut marked = self.search_marked.contains(&i);
+                            if ui.checkbox(&mut marked, "").changed() {
+                                if marked {
+                                    self.search_marked.insert(i);
+                                } else {
+                                    self.search_marked.remove(&i);
+                                }
+                            }
+                            ui.selectable_label(selected, job)
+                        }).inner;
+                        if response.clicked() {
+                            // Shift+点击勾选从当前选中项到这一项的范围
+                            if ui.input(|input| input.modifiers.shift) {
+                                let anchor = self.search_selected.unwrap_or(i);
+                                self.search_marked.extend(anchor.min(i)..=anchor.max(i));
+                            }
+                            jump_to = Some(i);
+                        }
+                        response.context_menu(|ui| {
+                            let mut action = None;
+                            if ui.button("🗑 Delete").clicked() {
+                                action = Some(CueAction::Delete);
+                            }
+                            if ui.button("⤵ Merge with Next").clicked() {
+                                action = Some(CueAction::MergeWithNext);
+                            }
+                            if ui.button("✂ Split at Playhead").clicked() {
+                                action = Some(CueAction::SplitAtPlayhead);
+                            }
+                            ui.horizontal(|ui| {
+                                if ui.button(egui::RichText::new("I").italics()).on_hover_text("Toggle <i> italic").clicked() {
+                                    action = Some(CueAction::ToggleItalic);
+                                }
+                                if ui.button(egui::RichText::new("B").strong()).on_hover_text("Toggle <b> bold").clicked() {
+                                    action = Some(CueAction::ToggleBold);
+                                }
+                            });
+                            if ui.button("📋 Copy Text").clicked() {
+                                ui.ctx().copy_text(entry.text.clone());
+                                ui.close_menu();
+                            }
+                            if ui.add_enabled(can_rerecognize, egui::Button::new("🔄 Re-recognize This Cue")).clicked() {
+                                action = Some(CueAction::Rerecognize);
+                            }
+                            if let Some(action) = action {
+                                cue_action = Some((entry.clone(), action));
+                                ui.close_menu();
+                            }
+                        });
+                    }
+                });
+            }
+            
+            if let Some(index) = jump_to {
+                self.select_search_result(index);
+            }
+            if let Some((start, end)) = play_range {
+                self.play_cue(start, end);
+            }
+            if let Some((entry, action)) = cue_action {
+                self.apply_cue_action(&entry, action);
+            }
+            
+            // 批量删除
+            if !self.search_results.is_empty() {
+                ui.horizontal(|ui| {
+                    let marked = self.search_marked.len();
+                    if ui.add_enabled(marked > 0, egui::Button::new(format!("🗑 Delete Selected ({})", marked))).clicked() {
+                        self.delete_marked_cues();
+                    }
+                    if ui.small_button("Select All").clicked() {
+                        self.search_marked = (0..self.search_results.len()).collect();
+                    }
+                    if ui.small_button("Clear").clicked() {
+                        self.search_marked.clear();
+                    }
+                });
+            }
+            ui.horizontal(|ui| {
+                ui.label("Delete cues from");
+                ui.add(egui::TextEdit::singleline(&mut self.delete_range_start).hint_text("00:00:00").desired_width(70.0));
+                ui.label("to");
+                ui.add(egui::TextEdit::singleline(&mut self.delete_range_end).hint_text("00:00:00").desired_width(70.0));
+                if ui.button("🗑 Delete in Range").clicked() {
+                    self.delete_cues_in_range();
                 }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Export cues from");
+                ui.add(egui::TextEdit::singleline(&mut self.export_range_start).hint_text("00:00:00").desired_width(70.0));
+                ui.label("to");
+                ui.add(egui::TextEdit::singleline(&mut self.export_range_end).hint_text("00:00:00").desired_width(70.0));
+                ui.checkbox(&mut self.export_range_clip, "Clip")
+                    .on_hover_text("Trim cues that cross the range boundaries");
+                ui.checkbox(&mut self.export_range_rebase, "Start at 0")
+                    .on_hover_text("Shift times so the range starts at 00:00:00 and renumber from 1");
+                if ui.button("📤 Export Range...").clicked() {
+                    self.export_range();
+                }
+            });
+            
+            // 两点同步
+            for (i, (actual, expected)) in self.sync_anchors.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.label(format!("Sync point {}: cue at (实际是)", i + 1));
+                    ui.add(egui::TextEdit::singleline(actual).hint_text("00:00:00").desired_width(70.0));
+                    ui.label("should be at (应该是)");
+                    ui.add(egui::TextEdit::singleline(expected).hint_text("00:00:00").desired_width(70.0));
+                });
+            }
+            if ui.button("⏱ Rescale Times")
+                .on_hover_text("Stretch and shift all cue times so both sync points line up, e.g. for 25 vs 23.976 fps drift")
+                .clicked() {
+                self.rescale_from_sync_points();
             }
             
-            let state = workspace::WorkspaceState {
-                video_path: self.video_path.clone(),
-                audio_path: self.audio_path.clone(),
-                cut_points: self.cut_points.clone(),
-                audio_segments: self.audio_segments.clone(),
-                completed_segments,  // 保存已完成的片段信息
-                manual_segment: self.manual_segment.clone(),
-                manual_start_time: self.manual_start_time.clone(),
-                manual_end_time: self.manual_end_time.clone(),
-                total_duration: self.total_duration,
-                workspace_dir: folder.clone(),
-            };
+            ui.add_space(5.0);
             
-            match state.save(&folder) {
-                Ok(_) => {
-                    self.status_message = format!("Workspace saved to: {:?}", folder);
+            ui.horizontal(|ui| {
+                ui.label("Max cue duration (s):");
+                ui.add(egui::DragValue::new(&mut self.max_cue_seconds).range(1.0..=60.0).speed(0.1));
+                if ui.button("✂ Split Long Cues").clicked() {
+                    self.split_long_duration_cues();
                 }
-                Err(e) => {
-                    self.status_message = format!("Failed to save workspace: {}", e);
+            });
+            if self.speech_regions.is_empty() {
+                ui.label("💡 Run Preview Speech first to split at silences");
+            }
+            ui.horizontal(|ui| {
+                ui.label("Max cue characters:");
+                ui.add(egui::DragValue::new(&mut self.max_cue_chars).range(5..=200));
+                if ui.button("✂ Split Long Text")
+                    .on_hover_text("Split cues with more characters than this, sharing the duration by character count")
+                    .clicked() {
+                    self.split_long_text_cues();
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Max gap (s):");
+                ui.add(egui::DragValue::new(&mut self.merge_max_gap).range(0.0..=5.0).speed(0.01));
+                if ui.button("🔗 Merge Short Cues (合并短字幕)")
+                    .on_hover_text("Merge neighbouring cues closer than the gap, keeping each merged cue under the max cue duration")
+                    .clicked() {
+                    self.merge_short_cues();
+                }
+            });
+            
+            ui.horizontal(|ui| {
+                ui.label("Pad lead (s):");
+                ui.add(egui::DragValue::new(&mut self.pad_lead).range(0.0..=2.0).speed(0.01));
+                ui.label("tail (s):");
+                ui.add(egui::DragValue::new(&mut self.pad_tail).range(0.0..=2.0).speed(0.01));
+                if ui.add_enabled(self.pad_lead > 0.0 || self.pad_tail > 0.0, egui::Button::new("↔ Pad Cues"))
+                    .on_hover_text("Start cues earlier and end them later without overlapping neighbours")
+                    .clicked() {
+                    self.pad_cues();
+                }
+            });
+            
+            if ui.button(format!("✂ Split Cue at Playhead ({})", Self::format_time(self.current_position))).clicked() {
+                self.split_cue_at_playhead();
+            }
+            
+            // 锁定手动校对过的字幕
+            ui.add_space(5.0);
+            ui.horizontal(|ui| {
+                if ui.button("🔒 Lock/Unlock Cue at Playhead").clicked() {
+                    self.toggle_lock_at_playhead();
+                }
+                if ui.button("Lock All").clicked() {
+                    self.lock_all_subtitles();
+                }
+                if ui.button("Unlock All").clicked() {
+                    self.locked_subtitles.clear();
+                    self.status_message = "Unlocked all cues".to_string();
+                }
+            });
+            if !self.locked_subtitles.is_empty() {
+                ui.label(format!("🔒 {} locked cues (kept when re-recognizing)", self.locked_subtitles.len()));
+            }
+            
+            // 字幕规范检查
+            ui.add_space(5.0);
+            ui.horizontal(|ui| {
+                ui.label("Standard:");
+                egui::ComboBox::from_id_salt("subtitle_standard")
+                    .selected_text(self.subtitle_standard.as_str())
+                    .show_ui(ui, |ui| {
+                        for standard in subtitle::SubtitleStandard::all() {
+                            ui.selectable_value(&mut self.subtitle_standard, standard, standard.as_str());
+                        }
+                    });
+                if ui.button("✔ Check").clicked() {
+                    self.check_subtitle_standard();
+                }
+                if ui.button("↵ Apply Line Breaks").clicked() {
+                    self.apply_standard_line_breaks();
+                }
+            });
+            
+            if !self.standard_violations.is_empty() {
+                let mut seek_to = None;
+                egui::ScrollArea::vertical()
+                    .id_salt("standard_violations")
+                    .max_height(150.0)
+                    .show(ui, |ui| {
+                        for violation in &self.standard_violations {
+                            ui.horizontal(|ui| {
+                                if ui.small_button("⏵").clicked() {
+                                    seek_to = Some(violation.start_time);
+                                }
+                                ui.colored_label(egui::Color32::from_rgb(220, 160, 60),
+                                    format!("#{} {}", violation.index, violation.reason));
+                            });
+                        }
+                    });
+                
+                if let Some(time) = seek_to {
+                    self.current_position = time;
+                    if let Some(player) = &mut self.audio_player {
+                        player.seek(time);
+                    }
+                }
+            }
+            
+            // 时间重叠
+            ui.add_space(5.0);
+            ui.horizontal(|ui| {
+                if ui.button("⚠ Find Overlaps").clicked() {
+                    self.check_overlaps();
+                }
+                if ui.add_enabled(!self.overlaps.is_empty(), egui::Button::new("🩹 Fix Overlaps (修复重叠)"))
+                    .on_hover_text("End each overlapping cue where the next one starts")
+                    .clicked() {
+                    self.fix_overlaps();
+                }
+            });
+            if !self.overlaps.is_empty() {
+                let mut seek_to = None;
+                egui::ScrollArea::vertical()
+                    .id_salt("overlapping_cues")
+                    .max_height(100.0)
+                    .show(ui, |ui| {
+                        for (a, b) in &self.overlaps {
+                            ui.horizontal(|ui| {
+                                if ui.small_button("⏵").clicked() {
+                                    seek_to = Some(a.start_time.max(b.start_time));
+                                }
+                                ui.colored_label(egui::Color32::from_rgb(230, 80, 80),
+                                    format!("#{} and #{} overlap by {:.2}s", a.index, b.index,
+                                        a.end_time.min(b.end_time) - a.start_time.max(b.start_time)));
+                            });
+                        }
+                    });
+                
+                if let Some(time) = seek_to {
+                    self.current_position = time;
+                    if let Some(player) = &mut self.audio_player {
+                        player.seek(time);
+                    }
+                }
+            }
+            
+            // 文本规范化
+            ui.add_space(5.0);
+            ui.horizontal_wrapped(|ui| {
+                ui.checkbox(&mut self.normalize_rules.trim_spaces, "Trim spaces");
+                ui.checkbox(&mut self.normalize_rules.collapse_spaces, "Collapse double spaces");
+                ui.checkbox(&mut self.normalize_rules.cjk_punctuation, "Fullwidth punctuation in CJK");
+                ui.checkbox(&mut self.normalize_rules.capitalize_sentences, "Capitalize sentences");
+            });
+            ui.horizontal(|ui| {
+                if ui.button("🔤 Normalize Text").clicked() {
+                    self.normalize_subtitle_text();
+                }
+                if ui.button("🔇 Remove Non-speech Tags").on_hover_text("Remove tags matching the non-speech patterns from the merged subtitles").clicked() {
+                    self.strip_nonspeech_tags();
+                }
+                if ui.button("🎨 Normalize ASS File...").on_hover_text("Apply the rules to a styled .ass file, keeping styles and override tags").clicked() {
+                    self.normalize_ass_file();
+                }
+                if ui.button("✏ Edit ASS File...").on_hover_text("Edit the dialogue text and timing of a styled .ass file").clicked() {
+                    self.open_ass_editor();
+                }
+            });
+            
+            // 表格导入导出
+            ui.add_space(5.0);
+            ui.horizontal(|ui| {
+                if ui.button("📤 Export CSV/JSON...").clicked() {
+                    self.export_cues();
+                }
+                if ui.button("📥 Import CSV/JSON...").on_hover_text("Replace the merged subtitles with an edited CSV or JSON file").clicked() {
+                    self.import_cues();
+                }
+            });
+            ui.horizontal(|ui| {
+                if ui.button("📝 Export Text (导出文本)").on_hover_text("Write the transcript without timestamps to a .txt next to the video").clicked() {
+                    self.export_plain_transcript();
+                }
+                ui.checkbox(&mut self.plaintext_per_cue, "One line per cue");
+            });
+            
+            // 外部命令翻译
+            ui.add_space(5.0);
+            ui.horizontal(|ui| {
+                ui.label("Translate with:");
+                ui.add(egui::TextEdit::singleline(&mut self.translate_command)
+                    .hint_text("trans -b :zh")
+                    .desired_width(160.0))
+                    .on_hover_text("Shell command that reads one cue from stdin and prints the translation");
+                ui.label("Suffix:");
+                ui.add(egui::TextEdit::singleline(&mut self.translate_suffix).desired_width(60.0));
+                ui.label("Timeout (s):");
+                ui.add(egui::DragValue::new(&mut self.translate_timeout).range(1.0..=600.0).speed(1.0))
+                    .on_hover_text("Stop the command and count the cue as failed if it takes longer than this");
+                let busy = self.state == AppState::Processing;
+                if ui.add_enabled(!busy, egui::Button::new("🌍 Translate")).on_hover_text("Write a translated copy with the same timing; the source subtitles are not changed").clicked() {
+                    self.translate_subtitles();
+                }
+            });
+        }
+    }
+}
+
+/// 以 GB/MB 显示字节数
+/// 可选的正整数参数，0 显示为 "Default" 并表示不设置
+fn optional_count_ui(ui: &mut egui::Ui, value: &mut Option<u32>) {
+    let mut count = value.unwrap_or(0);
+    ui.add(egui::DragValue::new(&mut count)
+        .range(0..=20)
+        .custom_formatter(|n, _| if n == 0.0 { "Default".to_string() } else { format!("{}", n) }));
+    *value = (count > 0).then_some(count);
+}
+
+/// 可选的开关参数：Default（不设置）/ On / Off
+fn optional_flag_ui(ui: &mut egui::Ui, label: &str, value: &mut Option<bool>) {
+    ui.horizontal(|ui| {
+        ui.label(label);
+        ui.selectable_value(value, None, "Default");
+        ui.selectable_value(value, Some(true), "On");
+        ui.selectable_value(value, Some(false), "Off");
+    });
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const MB: f64 = 1024.0 * 1024.0;
+    let bytes = bytes as f64;
+    if bytes >= 1024.0 * MB {
+        format!("{:.1} GB", bytes / (1024.0 * MB))
+    } else {
+        format!("{:.0} MB", bytes / MB)
+    }
+}
+
+/// 以小时/分钟显示预估耗时
+fn format_eta(seconds: f64) -> String {
+    let minutes = (seconds / 60.0).round() as u64;
+    if minutes >= 60 {
+        format!("{}h {:02}m", minutes / 60, minutes % 60)
+    } else {
+        format!("{} min", minutes.max(1))
+    }
+}
+
+/// 高亮文本中与 `query` 匹配的部分
+fn highlight_matches(text: &str, query: &str, text_color: egui::Color32) -> egui::text::LayoutJob {
+    let normal = egui::TextFormat {
+        color: text_color,
+        ..Default::default()
+    };
+    let highlight = egui::TextFormat {
+        color: egui::Color32::BLACK,
+        background: egui::Color32::from_rgb(230, 200, 80),
+        ..Default::default()
+    };
+    
+    let mut job = egui::text::LayoutJob::default();
+    let mut last = 0;
+    for range in subtitle::find_matches(text, query) {
+        job.append(&text[last..range.start], 0.0, normal.clone());
+        job.append(&text[range.clone()], 0.0, highlight.clone());
+        last = range.end;
+    }
+    job.append(&text[last..], 0.0, normal);
+    job
+}
+
+/// 在识别线程中合并字幕，失败时通过进度通道报告
+fn merge_in_background(
+    segments: &[(PathBuf, f64)],
+    subtitles_dir: Option<&Path>,
+    options: &pipeline::MergeOptions,
+    output_path: &Path,
+    tx: &Sender<ProgressMessage>,
+) {
+    match pipeline::merge_subtitles(segments, subtitles_dir, options, output_path) {
+        Ok(_) => {
+            println!("Subtitles merged successfully: {:?}", output_path);
+        }
+        Err(e) => {
+            eprintln!("Failed to merge subtitles: {}", e);
+            let _ = tx.send(ProgressMessage::Error(format!("Failed to merge: {}", e)));
+        }
+    }
+}
+
+impl eframe::App for WhisperApp {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, AUTO_EXTRACT_KEY, &self.auto_extract);
+        eframe::set_value(storage, KEEP_PREVIOUS_SRT_KEY, &self.keep_previous_srt);
+        eframe::set_value(storage, SETTINGS_TEMPLATES_KEY, &self.settings_templates);
+        eframe::set_value(storage, TRANSLATE_COMMAND_KEY, &self.translate_command);
+        eframe::set_value(storage, TRANSLATE_TIMEOUT_KEY, &self.translate_timeout);
+        eframe::set_value(storage, LOSSLESS_KEY, &self.lossless);
+        eframe::set_value(storage, WHISPER_AUDIO_KEY, &self.whisper_audio);
+        eframe::set_value(storage, ACCURATE_CUT_KEY, &self.accurate_cut);
+        eframe::set_value(storage, RECOGNITION_WORKERS_KEY, &self.recognition_workers);
+        eframe::set_value(storage, STRIP_NONSPEECH_KEY, &self.strip_nonspeech);
+        eframe::set_value(storage, NONSPEECH_PATTERNS_KEY, &self.nonspeech_patterns);
+        eframe::set_value(storage, FFMPEG_PATH_KEY, &self.ffmpeg_path);
+        eframe::set_value(storage, WHISPER_OPTIONS_KEY, &self.whisper_options);
+    }
+    
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // 处理识别进度消息
+        let mut should_complete = false;
+        if let Some(rx) = &self.progress_receiver {
+            while let Ok(msg) = rx.try_recv() {
+                match msg {
+                    ProgressMessage::Progress { current, total } => {
+                        self.processing_status = if self.translate_receiver.is_some() {
+                            format!("Translating cue {}/{}", current, total)
+                        } else {
+                            format!("Recognizing segment {}/{}", current, total)
+                        };
+                        self.processing_progress = current as f32 / total as f32;
+                    }
+                    ProgressMessage::Result { segment, text } => {
+                        if let Some(index) = segment.checked_sub(1) {
+                            self.segment_failures.remove(&index);
+                        }
+                        let result = format!("Segment {}: {}", segment, text);
+                        self.recognition_results.push(result);
+                    }
+                    ProgressMessage::RealtimeOutput(output) => {
+                        // 实时输出信息
+                        self.recognition_results.push(output);
+                    }
+                    ProgressMessage::Completed => {
+                        should_complete = true;
+                    }
+                    ProgressMessage::Error(err) => {
+                        self.recognition_results.push(format!("❌ Error: {}", err));
+                    }
+                    ProgressMessage::SegmentFailed { segment, error } => {
+                        self.recognition_results.push(format!("❌ Error: Failed to recognize segment {}: {}", segment, error));
+                        self.segment_failures.insert(segment - 1, error);
+                    }
+                }
+            }
+        }
+        
+        // 处理切割进度和结果
+        if let Some(rx) = &self.cut_progress_receiver {
+            while let Ok(msg) = rx.try_recv() {
+                match msg {
+                    ProgressMessage::Progress { current, total } => {
+                        self.cut_progress = current as f32 / total as f32;
+                    }
+                    ProgressMessage::RealtimeOutput(status) => {
+                        self.cut_status = status;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        if let Some(rx) = &self.cut_receiver {
+            if let Ok(result) = rx.try_recv() {
+                self.cut_receiver = None;
+                self.cut_progress_receiver = None;
+                self.state = AppState::AudioExtracted;
+                match result {
+                    Ok(segments) => {
+                        // 以实际切割的范围为准，合并时的偏移与片段一致
+                        self.cut_points = pipeline::cut_points_of(&segments);
+                        self.audio_segments = segments;
+                        self.segment_failures.clear();
+                        self.failed_segments.clear();
+                        self.status_message = format!("Audio cut completed, {} segments", self.audio_segments.len());
+                    }
+                    Err(e) => {
+                        self.status_message = format!("Failed to cut audio: {}", e);
+                    }
+                }
+            }
+        }
+        
+        // 处理分声道识别结果
+        if let Some(rx) = &self.channel_receiver {
+            if let Ok(result) = rx.try_recv() {
+                self.channel_receiver = None;
+                self.progress_receiver = None;
+                self.state = AppState::AudioExtracted;
+                match result {
+                    Ok(subtitles) => self.save_channel_subtitles(subtitles),
+                    Err(e) => {
+                        self.status_message = format!("Per-channel recognition failed: {}", e);
+                    }
                 }
             }
         }
-    }
-    
-    fn check_missing_subtitles(&mut self) {
-        self.missing_segments.clear();
-        self.completed_segments.clear();
-        self.can_resume = false;
         
-        if self.audio_segments.is_empty() {
-            return;
+        // 处理字幕翻译结果
+        if let Some(rx) = &self.translate_receiver {
+            if let Ok(result) = rx.try_recv() {
+                self.translate_receiver = None;
+                self.progress_receiver = None;
+                self.state = AppState::AudioExtracted;
+                match result {
+                    Ok(translation) => self.save_translation(translation),
+                    Err(e) => {
+                        self.status_message = format!("Translation failed: {}", e);
+                    }
+                }
+            }
         }
         
-        // 检查每个片段是否有对应的 SRT 文件
-        for (i, segment) in self.audio_segments.iter().enumerate() {
-            let srt_path = segment.with_extension("srt");
-            if srt_path.exists() {
-                self.completed_segments.push(i);
-            } else {
-                self.missing_segments.push(i);
+        // 处理时间段识别结果
+        if let Some(rx) = &self.range_receiver {
+            if let Ok(result) = rx.try_recv() {
+                self.range_receiver = None;
+                self.progress_receiver = None;
+                self.state = AppState::AudioExtracted;
+                match result {
+                    Ok((start, end, subtitles)) => self.splice_range_subtitles(start, end, subtitles),
+                    Err(e) => {
+                        self.status_message = format!("Failed to recognize range: {}", e);
+                    }
+                }
             }
         }
         
-        // 如果有缺失且有完成的，说明可以恢复
-        self.can_resume = !self.missing_segments.is_empty() 
-            && !self.completed_segments.is_empty();
-    }
-    
-    fn resume_recognition(&mut self) {
-        if self.missing_segments.is_empty() {
-            self.status_message = "No missing segments to recognize!".to_string();
-            return;
+        // 处理波形概览结果
+        if let Some(rx) = &self.waveform_receiver {
+            if let Ok((path, result)) = rx.try_recv() {
+                // 失败时缓存空波形，不反复重试
+                let waveform = result.unwrap_or_else(|e| {
+                    println!("Failed to compute waveform for {:?}: {}", path, e);
+                    Vec::new()
+                });
+                self.waveforms.insert(path, waveform);
+                self.waveform_receiver = None;
+            }
         }
         
-        self.state = AppState::Processing;
-        
-        // 设置初始进度为已完成的百分比
-        let completed_count = self.audio_segments.len() - self.missing_segments.len();
-        self.processing_progress = completed_count as f32 / self.audio_segments.len() as f32;
-        self.processing_status = format!("Resuming from {}/{} segments...", completed_count, self.audio_segments.len());
-        self.recognition_results.clear();
-        
-        let segments: Vec<_> = self.missing_segments.iter()
-            .filter_map(|&i| self.audio_segments.get(i).cloned())
-            .collect();
-        let missing_indices = self.missing_segments.clone();
-        let all_segments = self.audio_segments.clone();
-        let model = self.whisper_model;
-        let language = self.whisper_language.clone();
-        let custom_lang = self.custom_language_code.clone();
-        let cut_points = self.cut_points.clone();
-        let video_path = self.video_path.clone().unwrap();
-        
-        // 创建消息通道
-        let (tx, rx) = channel();
-        self.progress_receiver = Some(rx);
+        // 处理语音区域预览结果
+        if let Some(rx) = &self.vad_preview_receiver {
+            if let Ok(result) = rx.try_recv() {
+                match result {
+                    Ok(regions) => {
+                        self.status_message = format!("Detected {} speech regions", regions.len());
+                        self.speech_regions = regions;
+                    }
+                    Err(e) => {
+                        self.status_message = format!("Failed to detect speech: {}", e);
+                    }
+                }
+                self.vad_preview_receiver = None;
+            }
+        }
         
-        std::thread::spawn(move || {
-            let total_segments = all_segments.len();
-            let completed_count = total_segments - missing_indices.len();  // 已完成的数量
-            
-            for (idx, segment) in segments.iter().enumerate() {
-                let segment_index = missing_indices[idx];
-                
-                // 确定要使用的语言代码
-                let lang_code = match language {
-                    WhisperLanguage::Unknown => None,
-                    WhisperLanguage::Japanese => Some("ja"),
-                    WhisperLanguage::English => Some("en"),
-                    WhisperLanguage::Chinese => Some("zh"),
-                    WhisperLanguage::French => Some("fr"),
-                    WhisperLanguage::German => Some("de"),
-                    WhisperLanguage::Spanish => Some("es"),
-                    WhisperLanguage::Italian => Some("it"),
-                    WhisperLanguage::Russian => Some("ru"),
-                    WhisperLanguage::Custom => {
-                        if custom_lang.is_empty() {
-                            None
-                        } else {
-                            Some(custom_lang.as_str())
+        // 处理音频提取结果
+        if let Some(rx) = &self.extraction_receiver {
+            if let Ok(result) = rx.try_recv() {
+                self.extraction_receiver = None;
+                match result {
+                    Ok((audio_path, timing_warning)) => {
+                        // 提取期间换了文件则丢弃结果
+                        if self.video_path.as_ref().is_some_and(|v| v.with_extension("wav") == audio_path) {
+                            if self.stream_source.is_some() {
+                                self.media_info = Some(ffmpeg::media_info(&audio_path).map_err(|e| e.to_string()));
+                            }
+                            self.load_audio_file(audio_path);
+                            if self.audio_player.is_some() {
+                                self.status_message = match timing_warning {
+                                    Some(warning) => format!("⚠ Audio extracted. {}", warning),
+                                    None => "Audio extracted successfully!".to_string(),
+                                };
+                            }
                         }
                     }
-                };
-                
-                // 使用新的实时输出版本
-                match whisper::recognize_audio_realtime(segment, model, lang_code, tx.clone(), segment_index + 1, total_segments) {
-                    Ok((_srt_path, text)) => {
-                        let _ = tx.send(ProgressMessage::Result { 
-                            segment: segment_index + 1, 
-                            text 
-                        });
-                        // 发送进度（识别完成后）- 包含已完成的数量
-                        let _ = tx.send(ProgressMessage::Progress { 
-                            current: completed_count + idx + 1,  // 已完成 + 当前进度
-                            total: total_segments  // 总片段数
-                        });
-                    }
                     Err(e) => {
-                        let error_msg = format!("Failed to recognize segment {}: {}", segment_index + 1, e);
-                        eprintln!("{}", error_msg);
-                        let _ = tx.send(ProgressMessage::Error(error_msg));
+                        self.status_message = format!("Failed to extract audio: {}", e);
                     }
                 }
             }
-            
-            // 合并所有字幕
-            let mut srt_files = Vec::new();
-            for seg in &all_segments {
-                let srt = seg.with_extension("srt");
-                if srt.exists() {
-                    srt_files.push(srt);
-                }
+        }
+        
+        // 处理爆音检测结果
+        if let Some(rx) = &self.clip_scan_receiver {
+            if let Ok(result) = rx.try_recv() {
+                self.status_message = match result {
+                    Ok(0.0) => "No clipping detected.".to_string(),
+                    Ok(ratio) => format!("⚠ {:.3}% of samples are clipped, consider re-recording or de-clipping", ratio * 100.0),
+                    Err(e) => format!("Failed to scan for clipping: {}", e),
+                };
+                self.clip_scan_receiver = None;
             }
-            
-            if !srt_files.is_empty() {
-                let output_path = video_path.with_extension("srt");
-                match srt_merger::merge_srt_files(&srt_files, &cut_points, &output_path) {
-                    Ok(_) => {
-                        println!("Subtitles merged successfully: {:?}", output_path);
+        }
+        
+        // 处理模型下载进度
+        if let Some(rx) = &self.model_download_receiver {
+            while let Ok(msg) = rx.try_recv() {
+                match msg {
+                    ModelDownloadMessage::Progress(progress) => {
+                        self.model_download_progress = progress;
                     }
-                    Err(e) => {
-                        eprintln!("Failed to merge subtitles: {}", e);
-                        let _ = tx.send(ProgressMessage::Error(format!("Failed to merge: {}", e)));
+                    ModelDownloadMessage::Finished(result) => {
+                        self.model_download_receiver = None;
+                        self.downloaded_models = whisper::downloaded_models();
+                        self.status_message = match result {
+                            Ok(()) => "Model downloaded!".to_string(),
+                            Err(e) => format!("❌ {}", e),
+                        };
+                        break;
                     }
                 }
             }
-            
-            // 发送完成消息
-            let _ = tx.send(ProgressMessage::Completed);
-        });
-    }
-}
-
-impl eframe::App for WhisperApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        // 处理识别进度消息
-        let mut should_complete = false;
-        if let Some(rx) = &self.progress_receiver {
+        }
+        
+        // 处理最响区域查找结果
+        if let Some(rx) = &self.join_receiver {
+            if let Ok(result) = rx.try_recv() {
+                self.join_receiver = None;
+                self.status_message = match result {
+                    Ok((path, None)) => format!("Joined segments into {:?}", path),
+                    Ok((path, Some(warning))) => format!("⚠ Joined segments into {:?}, but the formats differ: {}", path, warning),
+                    Err(e) => format!("Failed to join segments: {}", e),
+                };
+            }
+        }
+        
+        if let Some(rx) = &self.mux_progress_receiver {
             while let Ok(msg) = rx.try_recv() {
-                match msg {
-                    ProgressMessage::Progress { current, total } => {
-                        self.processing_status = format!("Recognizing segment {}/{}", current, total);
-                        self.processing_progress = current as f32 / total as f32;
+                if let ProgressMessage::Progress { current, total } = msg {
+                    self.mux_progress = current as f32 / total.max(1) as f32;
+                }
+            }
+        }
+        
+        if let Some(rx) = &self.mux_receiver {
+            if let Ok(result) = rx.try_recv() {
+                self.mux_receiver = None;
+                self.mux_progress_receiver = None;
+                self.status_message = match result {
+                    Ok(path) => format!("Exported video with subtitles to {:?}", path),
+                    Err(e) => format!("Failed to export video: {}", e),
+                };
+            }
+        }
+        
+        if let Some(rx) = &self.quick_receiver {
+            if let Ok(result) = rx.try_recv() {
+                self.quick_receiver = None;
+                self.quick_result = Some(result);
+            }
+        }
+        
+        if let Some(rx) = &self.preview_receiver {
+            if let Ok(result) = rx.try_recv() {
+                self.preview_receiver = None;
+                self.preview_result = Some(result);
+            }
+        }
+        
+        if let Some(rx) = &self.benchmark_receiver {
+            if let Ok(result) = rx.try_recv() {
+                self.benchmark_receiver = None;
+                match result {
+                    Ok(benchmark) => {
+                        self.benchmark_factor = Some(benchmark);
+                        self.status_message = "Benchmark finished".to_string();
                     }
-                    ProgressMessage::Result { segment, text } => {
-                        let result = format!("Segment {}: {}", segment, text);
-                        self.recognition_results.push(result);
+                    Err(e) => {
+                        self.status_message = format!("Benchmark failed: {}", e);
                     }
-                    ProgressMessage::RealtimeOutput(output) => {
-                        // 实时输出信息
-                        self.recognition_results.push(output);
+                }
+            }
+        }
+        
+        if let Some(rx) = &self.loudest_receiver {
+            if let Ok(result) = rx.try_recv() {
+                self.loudest_receiver = None;
+                match result {
+                    Ok(Some((start, end))) => {
+                        self.current_position = start;
+                        if let Some(player) = &mut self.audio_player {
+                            player.seek(start);
+                            player.play();
+                            self.is_playing = true;
+                        }
+                        self.status_message = format!("Playing loudest region {} - {}", Self::format_time(start), Self::format_time(end));
                     }
-                    ProgressMessage::Completed => {
-                        should_complete = true;
+                    Ok(None) => {
+                        self.status_message = "⚠ The audio is completely silent".to_string();
                     }
-                    ProgressMessage::Error(err) => {
-                        self.recognition_results.push(format!("❌ Error: {}", err));
+                    Err(e) => {
+                        self.status_message = format!("Failed to analyze audio: {}", e);
                     }
                 }
             }
@@ -1030,21 +4335,45 @@ impl eframe::App for WhisperApp {
         
         if should_complete {
             self.state = AppState::AudioExtracted;
-            self.status_message = "Recognition completed!".to_string();
             self.progress_receiver = None;
+            self.restore_locked_subtitles();
+            self.status_message = if self.locked_subtitles.is_empty() {
+                "Recognition completed!".to_string()
+            } else {
+                format!("Recognition completed! {} locked cues preserved.", self.locked_subtitles.len())
+            };
+            if let Some(name) = self.session_srt_path.as_ref().and_then(|p| p.file_name()) {
+                self.status_message.push_str(&format!(" Saved to {}", name.to_string_lossy()));
+            }
+            self.check_tail_coverage();
+            self.check_missing_subtitles();
+            self.save_segment_failures();
+            self.update_subtitle_search();
+            self.downloaded_models = whisper::downloaded_models();  // 首次使用的模型会在识别时下载
         }
         
         // Update current playback position
         if let Some(player) = &self.audio_player {
             self.current_position = player.position();
         }
+        self.handle_player_shortcuts(ctx);
+        self.enforce_loop();
+        self.enforce_play_until();
         
         // Handle dropped files
         ctx.input(|i| {
             if !i.raw.dropped_files.is_empty() {
                 if let Some(file) = i.raw.dropped_files.first() {
                     if let Some(path) = &file.path {
-                        self.handle_dropped_file(path.clone());
+                        // 快速识别窗口打开时，拖入的文件直接识别
+                        if self.show_quick_transcribe {
+                            if self.quick_receiver.is_none() {
+                                self.quick_path = path.display().to_string();
+                                self.quick_transcribe();
+                            }
+                        } else {
+                            self.handle_dropped_file(path.clone());
+                        }
                     }
                 }
             }
@@ -1056,333 +4385,111 @@ impl eframe::App for WhisperApp {
                 
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                     // 只要有视频加载就显示保存按钮
-                    if self.video_path.is_some() {
-                        if ui.button("💾 Save Workspace").clicked() {
-                            self.save_workspace();
-                        }
+                    if self.video_path.is_some() && ui.button("💾 Save Workspace").clicked() {
+                        self.save_workspace();
                     }
                     
-                    if ui.button("📁 Open Folder").clicked() {
+                    if ui.add_enabled(self.state != AppState::Processing, egui::Button::new("📁 Open Folder"))
+                        .on_disabled_hover_text("Wait for the running job or stop it first")
+                        .clicked()
+                    {
                         self.open_workspace();
                     }
                     
-                    // Resume 按钮（在加载工作区后，如果有缺失的字幕）
-                    if self.can_resume && self.state != AppState::Processing {
-                        if ui.button("▶️ Resume").clicked() {
-                            self.resume_recognition();
-                        }
-                    }
-                });
-            });
-            ui.separator();
-            
-            ui.horizontal(|ui| {
-                // Left panel: Drop area and player
-                ui.vertical(|ui| {
-                    ui.set_width(700.0);
-                    
-                    // Drop area
-                    egui::Frame::default()
-                        .fill(egui::Color32::from_rgb(40, 40, 50))
-                        .stroke(egui::Stroke::new(2.0, egui::Color32::from_rgb(100, 100, 120)))
-                        .inner_margin(20.0)
-                        .show(ui, |ui| {
-                            ui.set_min_height(100.0);
-                            ui.vertical_centered(|ui| {
-                                if let Some(path) = &self.video_path {
-                                    ui.label(format!("📹 {}", path.file_name().unwrap().to_string_lossy()));
-                                } else {
-                                    ui.label("📂 Drag video file here");
-                                }
-                            });
-                        });
-                    
-                    ui.add_space(10.0);
-                    
-                    // Audio player
-                    if self.state != AppState::Idle {
-                        egui::Frame::default()
-                            .fill(egui::Color32::from_rgb(30, 30, 40))
-                            .inner_margin(15.0)
-                            .show(ui, |ui| {
-                                ui.label("🎵 Audio Player");
-                                ui.separator();
-                                
-                                // Time display
-                                ui.horizontal(|ui| {
-                                    ui.label(Self::format_time(self.current_position));
-                                    ui.label("/");
-                                    ui.label(Self::format_time(self.total_duration));
-                                });
-                                
-                                ui.add_space(5.0);
-                                
-                                // Time scale (5-minute intervals)
-                                ui.horizontal(|ui| {
-                                    let interval = 300.0; // 5 minutes in seconds
-                                    let num_intervals = (self.total_duration / interval).ceil() as usize;
-                                    
-                                    for i in 0..=num_intervals {
-                                        let time = i as f64 * interval;
-                                        if time > self.total_duration {
-                                            break;
-                                        }
-                                        
-                                        let minutes = (time / 60.0).floor() as u32;
-                                        let button_text = format!("{}m", minutes);
-                                        
-                                        if ui.small_button(&button_text).clicked() {
-                                            self.current_position = time;
-                                            if let Some(player) = &mut self.audio_player {
-                                                player.seek(time);
-                                                player.pause();
-                                                self.is_playing = false;
-                                            }
-                                        }
-                                        
-                                        // 在按钮之间添加间隔
-                                        if i < num_intervals {
-                                            ui.add_space(3.0);
-                                        }
-                                    }
-                                });
-                                
-                                // Playback progress bar (full width)
-                                ui.add_space(5.0);
-                                let mut position = self.current_position;
-                                // 使用进度条宽度等于左侧面板宽度减去边距
-                                ui.spacing_mut().slider_width = 640.0;
-                                if ui.add(egui::Slider::new(&mut position, 0.0..=self.total_duration)
-                                    .show_value(false)).changed() {
-                                    self.current_position = position;
-                                    if let Some(player) = &mut self.audio_player {
-                                        player.seek(position);
-                                    }
-                                }
-                                ui.add_space(5.0);
-                                
-                                ui.horizontal(|ui| {
-                                    // Play/Pause button
-                                    if self.is_playing {
-                                        if ui.button("⏸ Pause").clicked() {
-                                            if let Some(player) = &mut self.audio_player {
-                                                player.pause();
-                                                self.is_playing = false;
-                                            }
-                                        }
-                                    } else {
-                                        if ui.button("▶ Play").clicked() {
-                                            if let Some(player) = &mut self.audio_player {
-                                                player.play();
-                                                self.is_playing = true;
-                                            }
-                                        }
-                                    }
-                                    
-                                    // Mark cut point button
-                                    if ui.button("✂ Mark Cut Point").clicked() {
-                                        self.add_cut_point();
-                                    }
-                                });
-                                
-                                // Cut points list
-                                if !self.cut_points.is_empty() {
-                                    ui.separator();
-                                    ui.label(format!("Cut Points ({}):", self.cut_points.len()));
-                                    
-                                    egui::ScrollArea::vertical()
-                                        .max_height(150.0)
-                                        .show(ui, |ui| {
-                                            let mut to_remove = None;
-                                            for (i, &point) in self.cut_points.iter().enumerate() {
-                                                ui.horizontal(|ui| {
-                                                    ui.label(format!("{}. {}", i + 1, Self::format_time(point)));
-                                                    if ui.small_button("🗑").clicked() {
-                                                        to_remove = Some(i);
-                                                    }
-                                                });
-                                            }
-                                            if let Some(i) = to_remove {
-                                                self.remove_cut_point(i);
-                                            }
-                                        });
-                                    
-                                    ui.add_space(5.0);
-                                    if ui.button("🔪 Execute Cut").clicked() {
-                                        self.cut_audio();
-                                    }
-                                }
-                            });
+                    if ui.button("⚡ Quick Transcribe").on_hover_text("Transcribe a short clip without creating a project").clicked() {
+                        self.show_quick_transcribe = true;
                     }
                     
-                    ui.add_space(10.0);
-                    
-                    // Re-recognize section
+                    // 把片段拼回完整音频，用于核对切割结果
                     if !self.audio_segments.is_empty() && self.state != AppState::Processing {
-                        ui.separator();
-                        ui.label("🔄 Re-recognize Segment");
-                        ui.horizontal(|ui| {
-                            egui::ComboBox::from_label("Select segment")
-                                .selected_text(format!("Segment {}", self.selected_segment_index + 1))
-                                .show_ui(ui, |ui| {
-                                    for i in 0..self.audio_segments.len() {
-                                        ui.selectable_value(&mut self.selected_segment_index, i, format!("Segment {}", i + 1));
-                                    }
-                                });
-                            
-                            if ui.button("🎤 Re-recognize").clicked() {
-                                self.rerecognize_segment();
-                            }
-                        });
-                        
-                        ui.add_space(5.0);
-                        
-                        // Cleanup button
-                        if ui.button("🗑️ Clean Up Temp Files").clicked() {
-                            self.cleanup_temp_files();
+                        if self.join_receiver.is_some() {
+                            ui.spinner();
+                        } else if ui.button("🧩 Join Segments").on_hover_text("Concatenate all segments into one file to check nothing was dropped").clicked() {
+                            self.join_segments();
                         }
                     }
                     
-                    ui.add_space(10.0);
-                    
-                    // Manual cut section
-                    if self.state != AppState::Idle && self.state != AppState::Processing {
-                        ui.separator();
-                        ui.label("✂️ Manual Cut Segment");
-                        
-                        ui.horizontal(|ui| {
-                            ui.label("Start:");
-                            ui.text_edit_singleline(&mut self.manual_start_time);
-                            ui.label("End:");
-                            ui.text_edit_singleline(&mut self.manual_end_time);
-                        });
-                        ui.label("💡 Format: HH:MM:SS or MM:SS or SS");
-                        
-                        ui.add_space(5.0);
-                        ui.horizontal(|ui| {
-                            if ui.button("✂️ Cut Segment").clicked() {
-                                self.cut_manual_segment();
-                            }
-                            
-                            if self.manual_segment.is_some() {
-                                if ui.button("🎤 Recognize Segment").clicked() {
-                                    self.recognize_manual_segment();
+                    // 把字幕嵌入视频（软字幕轨或硬字幕）
+                    let has_video = self.video_path.as_ref().is_some_and(|path| !pipeline::is_audio_file(path));
+                    let has_subtitles = self.merged_srt_path().is_some_and(|path| path.exists());
+                    if self.mux_receiver.is_some() {
+                        ui.add(egui::ProgressBar::new(self.mux_progress)
+                            .show_percentage()
+                            .desired_width(120.0));
+                    } else if has_video && has_subtitles && self.state != AppState::Processing {
+                        ui.menu_button("🎬 Export Video with Subtitles", |ui| {
+                            for mode in ffmpeg::MuxMode::all() {
+                                if ui.button(mode.as_str()).clicked() {
+                                    ui.close_menu();
+                                    self.export_subtitled_video(mode);
                                 }
                             }
                         });
                     }
                     
-                    ui.add_space(10.0);
-                    
-                    // Status message
-                    ui.label(&self.status_message);
-                });
-                
-                ui.separator();
-                
-                // Right panel: Settings
-                ui.vertical(|ui| {
-                    ui.set_width(400.0);
-                    
-                    ui.heading("Settings");
-                    ui.separator();
-                    
-                    // Whisper model selection
-                    ui.label("Whisper Model:");
-                    egui::ComboBox::from_label("")
-                        .selected_text(self.whisper_model.as_str())
-                        .show_ui(ui, |ui| {
-                            for model in WhisperModel::all() {
-                                ui.selectable_value(&mut self.whisper_model, model, model.as_str());
-                            }
-                        });
-                    
-                    ui.add_space(10.0);
-                    
-                    // Language selection
-                    ui.label("Recognition Language:");
-                    egui::ComboBox::from_label(" ")
-                        .selected_text(self.whisper_language.as_str())
-                        .show_ui(ui, |ui| {
-                            for lang in WhisperLanguage::all() {
-                                ui.selectable_value(&mut self.whisper_language, lang.clone(), lang.as_str());
-                            }
-                        });
-                    
-                    // Custom language input (only show when Custom is selected)
-                    if self.whisper_language == WhisperLanguage::Custom {
-                        ui.add_space(5.0);
-                        ui.horizontal(|ui| {
-                            ui.label("Language code:");
-                            ui.text_edit_singleline(&mut self.custom_language_code);
-                        });
-                        ui.label("💡 Examples: ko (Korean), ar (Arabic), hi (Hindi), pt (Portuguese)");
+                    // 只合并已有字幕（不重新识别）
+                    if !self.audio_segments.is_empty() && self.state != AppState::Processing && ui.button("🔗 Merge Subtitles").clicked() {
+                        self.merge_existing_subtitles();
                     }
                     
-                    ui.add_space(20.0);
-                    ui.separator();
-                    
-                    // Recognition section
-                    ui.label("🎤 Recognition");
-                    ui.add_space(5.0);
-                    
-                    if !self.audio_segments.is_empty() {
-                        ui.label(format!("✅ Audio segments: {}", self.audio_segments.len()));
-                        ui.add_space(10.0);
-                        
-                        if self.state != AppState::Processing {
-                            if ui.button("🎤 Start Recognition").clicked() {
-                                self.start_recognition();
-                            }
-                        } else {
-                            ui.label("🔄 Recognizing...");
-                            ui.label(&self.processing_status);
-                            ui.add_space(5.0);
-                            ui.add(egui::ProgressBar::new(self.processing_progress).show_percentage());
-                            ui.add_space(5.0);
-                            if ui.button("🛑 Stop Recognition & Kill Processes").clicked() {
-                                self.stop_recognition();
-                            }
-                        }
-                        
-                        ui.add_space(10.0);
-                        
-                        // Recognition results
-                        if !self.recognition_results.is_empty() {
-                            ui.label("📝 Results:");
-                            ui.add_space(5.0);
-                            
-                            egui::ScrollArea::vertical()
-                                .max_height(180.0)
-                                .show(ui, |ui| {
-                                    for result in &self.recognition_results {
-                                        egui::Frame::default()
-                                            .fill(egui::Color32::from_rgb(35, 35, 45))
-                                            .inner_margin(8.0)
-                                            .show(ui, |ui| {
-                                                ui.label(result);
-                                            });
-                                        ui.add_space(5.0);
-                                    }
-                                });
-                        }
-                    } else {
-                        ui.label("⚠️ Please cut audio first");
+                    // 崩溃后片段和片段字幕还在，但切割点丢失
+                    if self.audio_path.is_some() && self.audio_segments.is_empty() && self.state != AppState::Processing
+                        && ui.button("🩹 Recover from Segments").on_hover_text("After a crash: rebuild the segment list and cut points from the numbered segment files next to the audio and merge their subtitles, without re-recognizing").clicked() {
+                        self.recover_from_segments();
                     }
                     
-                    ui.add_space(10.0);
-                    
-                    // Save Plain Text button
-                    if !self.recognition_results.is_empty() {
-                        ui.separator();
-                        if ui.button("💾 Save Plain Text").clicked() {
-                            self.save_plain_text();
-                        }
+                    // Resume 按钮（在加载工作区后，如果有缺失的字幕）
+                    if self.can_resume && self.state != AppState::Processing && ui.button("▶️ Resume").clicked() {
+                        self.resume_recognition();
                     }
                 });
             });
+            ui.separator();
+            
+            if self.ffmpeg_missing {
+                ui.horizontal(|ui| {
+                    ui.colored_label(egui::Color32::from_rgb(230, 80, 80),
+                        "⚠ FFmpeg not found (未找到 ffmpeg，请先安装并加入 PATH). Install it, or set FFmpeg path in Settings");
+                    if ui.small_button("✖").on_hover_text("Dismiss").clicked() {
+                        self.ffmpeg_missing = false;
+                    }
+                });
+                ui.separator();
+            }
+            
+            if ui.available_width() < MIN_SIDE_BY_SIDE_WIDTH {
+                // 窗口太窄时上下堆叠
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    self.media_panel_ui(ui);
+                    ui.separator();
+                    self.settings_panel_ui(ui);
+                });
+            } else {
+                // Left panel: Drop area and player（宽度可拖动，随 egui 状态保存）
+                egui::SidePanel::left("media_panel")
+                    .resizable(true)
+                    .default_width(700.0)
+                    .width_range(400.0..=ui.available_width() - 300.0)
+                    .show_inside(ui, |ui| {
+                        egui::ScrollArea::vertical().show(ui, |ui| {
+                            self.media_panel_ui(ui);
+                        });
+                    });
+                
+                // Right panel: Settings
+                egui::CentralPanel::default().show_inside(ui, |ui| {
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        self.settings_panel_ui(ui);
+                    });
+                });
+            }
         });
         
+        self.estimate_window(ctx);
+        self.long_run_window(ctx);
+        self.quick_transcribe_window(ctx);
+        self.ass_editor_window(ctx);
+        
         // Continuously refresh UI to update playback position
         ctx.request_repaint();
     }
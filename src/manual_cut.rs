@@ -1,15 +1,19 @@
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use anyhow::{Result, anyhow};
-use crate::ffmpeg;
+use crate::error::{command_output, WhisperError};
+use crate::{config, ffmpeg};
 
 /// 手动切割音频片段
 /// 
-/// 注意：切割后会将 WAV 片段转换为 MP3 格式，并删除 WAV 片段
+/// 注意：切割后会将 WAV 片段转换为 MP3 格式，并删除 WAV 片段；`lossless` 为 true 时保留 WAV 片段。
+/// `accurate` 为 true 时重新编码以精确切割，见 [`ffmpeg::cut_codec_args`]
 pub fn cut_audio_segment(
     audio_path: &Path,
     start_time: f64,
     end_time: f64,
+    lossless: bool,
+    accurate: bool,
 ) -> Result<PathBuf> {
     if start_time >= end_time {
         return Err(anyhow!("Start time must be less than end time"));
@@ -27,22 +31,25 @@ pub fn cut_audio_segment(
     
     println!("🔪 手动切割音频片段 ({:.2}s - {:.2}s)...", start_time, end_time);
     
-    let output = Command::new("ffmpeg")
+    let output = command_output(Command::new(config::ffmpeg())
         .arg("-i")
         .arg(audio_path)
         .arg("-ss")
         .arg(start_time.to_string())
         .arg("-t")
         .arg(duration.to_string())
-        .arg("-acodec")
-        .arg("copy")
+        .args(ffmpeg::cut_codec_args(accurate))
         .arg("-y")
-        .arg(&wav_output_path)
-        .output()?;
+        .arg(&wav_output_path))?;
     
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(anyhow!("Failed to cut audio segment: {}", stderr));
+        return Err(WhisperError::process_failed("Failed to cut audio segment", stderr).into());
+    }
+    
+    if lossless {
+        println!("✅ 手动切割完成（无损）: {:?}", wav_output_path);
+        return Ok(wav_output_path);
     }
     
     // 转换为 MP3
@@ -0,0 +1,914 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Sender};
+use crate::error::{Result, WhisperError};
+use crate::{ffmpeg, srt_merger, subtitle, whisper, ProgressMessage, WhisperModel};
+use crate::whisper::CancelToken;
+
+/// 可直接识别、无需提取的音频格式
+const AUDIO_EXTENSIONS: &[&str] = &["wav", "mp3", "m4a", "flac", "ogg", "opus"];
+
+/// 识别参数
+#[derive(Debug, Clone, Default)]
+pub struct RecognitionOptions {
+    pub model: WhisperModel,
+    pub language: Option<String>,  // None 表示自动检测
+    pub threads: usize,  // 0 表示使用全部核心
+    pub subtitles_dir: Option<PathBuf>,  // 片段字幕的目录，识别时写入、合并时读取；None 时在片段旁边
+    pub precise_timing: bool,  // 用 Whisper 的 JSON 输出保留未取整的时间
+    pub lossless: bool,  // 切割后不转换为 MP3，直接识别原格式（WAV）片段
+    pub whisper_audio: bool,  // 从视频提取为 16kHz 单声道 WAV，见 `ffmpeg::extract_audio_for_whisper`
+    pub accurate_cut: bool,  // 切割时重新编码，切割点精确到样本，见 `ffmpeg::cut_codec_args`
+    pub workers: usize,  // 同时识别的片段数，0 表示 CPU 核心数的一半
+    pub whisper: whisper::WhisperOptions,  // 束搜索、温度、提示词等解码参数
+}
+
+impl RecognitionOptions {
+    /// 同时识别的片段数，至少为 1
+    pub fn worker_count(&self) -> usize {
+        match self.workers {
+            0 => default_workers(),
+            n => n,
+        }
+    }
+}
+
+/// 默认同时识别的片段数：CPU 核心数的一半
+pub fn default_workers() -> usize {
+    (cpu_count() / 2).max(1)
+}
+
+fn cpu_count() -> usize {
+    std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+}
+
+/// 合并字幕参数
+#[derive(Debug, Clone, Default)]
+pub struct MergeOptions {
+    pub priming_delay: Option<f64>,  // 所有片段统一扣除的编码延迟；None 时按每个片段的格式推算，见 `ffmpeg::priming_delay`
+    pub encoding: srt_merger::OutputEncoding,
+    pub export_segments_to: Option<PathBuf>,  // 同时把每段字幕（全局时间）导出到该目录
+    pub fps: Option<f64>,  // 设置后时间戳取整到帧边界
+    pub start_index: Option<usize>,  // 字幕起始编号，None 时从 1 开始
+    pub dedupe_boundaries: bool,  // 去掉切割点两侧重复识别的字幕
+    pub nonspeech_patterns: Vec<String>,  // 要去掉的非语音标注，见 `subtitle::strip_nonspeech`；为空时保留
+}
+
+/// 是否为可以直接使用的音频文件
+pub fn is_audio_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|s| s.to_str())
+        .is_some_and(|ext| AUDIO_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// 准备用于切割和播放的音频：音频文件直接使用，视频文件提取音频轨道
+/// 
+/// `whisper_audio` 为 true 时提取为 16kHz 单声道，否则为 44.1kHz 立体声。
+/// 视频为可变帧率或音频时间戳不连续时，提取时重建时间轴（见 `ffmpeg::extract_audio_retimed`），
+/// 并返回提示用户时间已修正的说明
+pub fn prepare_audio(media_path: &Path, whisper_audio: bool) -> Result<(PathBuf, Option<String>)> {
+    if is_audio_file(media_path) {
+        return Ok((media_path.to_path_buf(), None));
+    }
+    
+    // 检查失败（如 ffprobe 无法读取包信息）时按正常文件提取
+    let issues = ffmpeg::timestamp_issues(media_path).ok().flatten();
+    let audio_path = if whisper_audio {
+        ffmpeg::extract_audio_for_whisper(media_path, issues.is_some())?
+    } else if issues.is_some() {
+        ffmpeg::extract_audio_retimed(media_path)?
+    } else {
+        ffmpeg::extract_audio(media_path)?
+    };
+    let warning = issues.map(|issues| format!("Source has {}; audio timing was corrected during extraction", issues));
+    Ok((audio_path, warning))
+}
+
+/// 切割点与开头、结尾以及相邻切割点的最小距离（秒），更近会产生空片段
+pub const MIN_SEGMENT_SECONDS: f64 = 0.05;
+
+/// 排序并去掉会产生空片段的切割点：位于开头、位于结尾（总时长已知时）或与前一个切割点重合
+/// 
+/// 空片段识别不出字幕，会被当成"缺失字幕"的片段，还会打乱合并时的偏移
+pub fn valid_cut_points(cut_points: &[f64], total_duration: Option<f64>) -> Vec<f64> {
+    let mut sorted: Vec<f64> = cut_points.iter()
+        .copied()
+        .filter(|&point| point >= MIN_SEGMENT_SECONDS)
+        .filter(|&point| total_duration.is_none_or(|total| point <= total - MIN_SEGMENT_SECONDS))
+        .collect();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    
+    let mut valid: Vec<f64> = Vec::with_capacity(sorted.len());
+    for point in sorted {
+        if valid.last().is_none_or(|&last| point - last >= MIN_SEGMENT_SECONDS) {
+            valid.push(point);
+        }
+    }
+    valid
+}
+
+/// 按切割点切割音频，返回片段及其时间范围；`lossless` 为 true 时不转换为 MP3，`accurate` 为 true 时重新编码以精确切割
+pub fn cut_audio(audio_path: &Path, cut_points: &[f64], lossless: bool, accurate: bool) -> Result<Vec<ffmpeg::Segment>> {
+    Ok(ffmpeg::cut_audio(audio_path, cut_points, lossless, accurate)?)
+}
+
+/// 按切割点切割音频，并把每段的切割和转换进度发送到 `tx`
+pub fn cut_audio_with_progress(audio_path: &Path, cut_points: &[f64], lossless: bool, accurate: bool, tx: &Sender<ProgressMessage>) -> Result<Vec<ffmpeg::Segment>> {
+    Ok(ffmpeg::cut_audio_with_progress(audio_path, cut_points, lossless, accurate, Some(tx))?)
+}
+
+/// 片段字幕的路径：`subtitles_dir` 中与片段同名的 `.srt`，未指定目录时在片段旁边
+/// 
+/// 旧项目的字幕还在片段旁边（尚未迁移）时返回旧位置
+pub fn segment_srt_path(segment: &Path, subtitles_dir: Option<&Path>) -> PathBuf {
+    let beside = segment.with_extension("srt");
+    let Some(dir) = subtitles_dir else { return beside };
+    let in_dir = dir.join(beside.file_name().unwrap_or_default());
+    if !in_dir.exists() && beside.exists() {
+        beside
+    } else {
+        in_dir
+    }
+}
+
+/// 片段是否已有完整的字幕（空文件或写入中断的字幕视为未完成）
+pub fn segment_has_subtitles(segment: &Path, subtitles_dir: Option<&Path>) -> bool {
+    subtitle::is_complete_srt(&segment_srt_path(segment, subtitles_dir))
+}
+
+/// 切割结果中每个片段的路径和在播放时间轴上的起始时间（秒）
+/// 
+/// 片段和时间段识别一样用 `-ss` 从原音频提取，按同样的方式修正原音频的起始时间戳，见 [`range_offset`]
+pub fn cut_offsets(segments: &[ffmpeg::Segment], stream_start: f64) -> Vec<(PathBuf, f64)> {
+    segments.iter().map(|segment| (segment.path.clone(), range_offset(stream_start, segment.start))).collect()
+}
+
+/// 切割结果中各片段的起始时间对应的切割点（去掉第一段的 0）
+pub fn cut_points_of(segments: &[ffmpeg::Segment]) -> Vec<f64> {
+    segments.iter().skip(1).map(|segment| segment.start).collect()
+}
+
+/// 崩溃后从磁盘上的片段恢复：找到 `audio_path` 旁边按 `{文件名}_NNN.扩展名` 命名的片段，
+/// 按各片段的时长推算各自的时间范围
+/// 
+/// 片段编号必须从 000 开始连续，缺少中间的片段时无法推算后面的偏移，返回错误
+pub fn recover_segments(audio_path: &Path) -> Result<Vec<ffmpeg::Segment>> {
+    let segments = find_numbered_segments(audio_path)?;
+    if segments.is_empty() {
+        return Err(WhisperError::InvalidInput(format!("No segments ({}_000.*) found next to {:?}",
+            audio_path.file_stem().unwrap_or_default().to_string_lossy(), audio_path)));
+    }
+    
+    // 最后一段的时长不影响切割点
+    let mut durations = Vec::new();
+    for segment in &segments[..segments.len() - 1] {
+        durations.push(ffmpeg::get_audio_duration(segment)?);
+    }
+    Ok(ffmpeg::segments_from_cut_points(segments, &cut_points_from_durations(&durations))?)
+}
+
+/// `audio_path` 旁边按编号排好的片段，同一编号同时有 MP3 和其他格式时（转换 MP3 中途崩溃）取未转换的原片段
+fn find_numbered_segments(audio_path: &Path) -> Result<Vec<PathBuf>> {
+    let dir = audio_path.parent().unwrap_or(Path::new("."));
+    let prefix = format!("{}_", audio_path.file_stem().unwrap_or_default().to_string_lossy());
+    
+    let mut numbered: std::collections::BTreeMap<usize, PathBuf> = std::collections::BTreeMap::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if !is_audio_file(&path) {
+            continue;
+        }
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+        let Some(number) = stem.strip_prefix(&prefix) else { continue };
+        if number.len() < 3 || !number.chars().all(|c| c.is_ascii_digit()) {
+            continue;
+        }
+        let Ok(index) = number.parse::<usize>() else { continue };
+        let is_mp3 = |p: &Path| p.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("mp3"));
+        if numbered.get(&index).is_none_or(|existing| is_mp3(existing)) {
+            numbered.insert(index, path);
+        }
+    }
+    
+    for (expected, index) in numbered.keys().enumerate() {
+        if *index != expected {
+            return Err(WhisperError::InvalidInput(format!("Segment {}{:03} is missing, cannot infer the cut points after it", prefix, expected)));
+        }
+    }
+    Ok(numbered.into_values().collect())
+}
+
+/// 由前面各片段的时长累加出切割点
+fn cut_points_from_durations(durations: &[f64]) -> Vec<f64> {
+    durations.iter()
+        .scan(0.0, |start, duration| {
+            *start += duration;
+            Some(*start)
+        })
+        .collect()
+}
+
+/// 识别单个片段，返回字幕文件路径和识别文本
+/// 
+/// `current`/`total` 只用于实时输出中的进度显示。字幕写到 `options.subtitles_dir`
+pub fn recognize_segment(
+    segment_path: &Path,
+    current: usize,
+    total: usize,
+    options: &RecognitionOptions,
+    cancel: &CancelToken,
+    tx: &Sender<ProgressMessage>,
+) -> Result<(PathBuf, String)> {
+    let output_dir = match &options.subtitles_dir {
+        Some(dir) => {
+            std::fs::create_dir_all(dir)?;
+            dir.clone()
+        }
+        None => segment_path.parent().map(Path::to_path_buf).unwrap_or_default(),
+    };
+    
+    Ok(whisper::recognize_audio_realtime(
+        segment_path,
+        &output_dir,
+        options.model,
+        options.language.as_deref(),
+        options.threads,
+        options.precise_timing,
+        &options.whisper,
+        tx.clone(),
+        current,
+        total,
+        cancel,
+    )?)
+}
+
+/// 识别 `segments` 中下标为 `indices` 的片段，返回成功的数量
+/// 
+/// 按 `options.worker_count()` 同时启动多个 Whisper 进程，每个进程按顺序领取下一个片段。
+/// 每个片段完成后发送 `Result` 和 `Progress`（已完成数，与完成顺序无关），失败时发送
+/// `SegmentFailed` 并继续下一段。进度把不在 `indices` 中的片段视为已完成，便于断点续传。
+/// `cancel` 取消后终止所有进程并停止
+pub fn recognize_segments(
+    segments: &[PathBuf],
+    indices: &[usize],
+    options: &RecognitionOptions,
+    cancel: &CancelToken,
+    tx: &Sender<ProgressMessage>,
+) -> usize {
+    let total = segments.len();
+    let completed_before = total.saturating_sub(indices.len());
+    let workers = options.worker_count().min(indices.len()).max(1);
+    
+    // "全部核心"由各进程平分，避免多个进程争抢同一批核心
+    let options = if workers > 1 && options.threads == 0 {
+        RecognitionOptions { threads: (cpu_count() / workers).max(1), ..options.clone() }
+    } else {
+        options.clone()
+    };
+    
+    let next = AtomicUsize::new(0);
+    let completed = AtomicUsize::new(0);
+    let succeeded = AtomicUsize::new(0);
+    let stopped = AtomicBool::new(false);  // 缺少 Whisper/FFmpeg，其余片段也会失败
+    
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                if cancel.is_cancelled() || stopped.load(Ordering::SeqCst) {
+                    break;
+                }
+                let Some(&i) = indices.get(next.fetch_add(1, Ordering::SeqCst)) else { break };
+                let Some(segment) = segments.get(i) else { continue };
+                
+                match recognize_segment(segment, i + 1, total, &options, cancel, tx) {
+                    Ok((_srt_path, text)) => {
+                        succeeded.fetch_add(1, Ordering::SeqCst);
+                        let _ = tx.send(ProgressMessage::Result {
+                            segment: i + 1,
+                            text,
+                        });
+                        let _ = tx.send(ProgressMessage::Progress {
+                            current: completed_before + completed.fetch_add(1, Ordering::SeqCst) + 1,
+                            total,
+                        });
+                    }
+                    Err(WhisperError::Cancelled) => break,
+                    Err(_) if cancel.is_cancelled() => break,
+                    // 只报告一次
+                    Err(e @ WhisperError::ToolNotFound { .. }) => {
+                        if !stopped.swap(true, Ordering::SeqCst) {
+                            let _ = tx.send(ProgressMessage::Error(e.to_string()));
+                        }
+                        break;
+                    }
+                    Err(e) => {
+                        eprintln!("Failed to recognize segment {}: {}", i + 1, e);
+                        completed.fetch_add(1, Ordering::SeqCst);
+                        let _ = tx.send(ProgressMessage::SegmentFailed {
+                            segment: i + 1,
+                            error: e.to_string(),
+                        });
+                    }
+                }
+            });
+        }
+    });
+    
+    succeeded.into_inner()
+}
+
+/// 本次调用专用的临时目录
+/// 
+/// 预览、基准测试和时间段识别可能同时运行，目录名加上序号，各自只删除自己的目录
+fn unique_temp_dir(prefix: &str) -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    std::env::temp_dir().join(format!("{}_{}_{}", prefix, std::process::id(), COUNTER.fetch_add(1, Ordering::Relaxed)))
+}
+
+/// 识别音频中的一个时间段，返回换算成全局时间的字幕
+/// 
+/// 时间段先提取到临时目录，识别后连同字幕一起删除，不在工作区留下片段文件
+pub fn recognize_range(
+    audio_path: &Path,
+    start_time: f64,
+    end_time: f64,
+    options: &RecognitionOptions,
+    cancel: &CancelToken,
+    tx: &Sender<ProgressMessage>,
+) -> Result<Vec<subtitle::SubtitleEntry>> {
+    let temp_dir = unique_temp_dir("whisper_range");
+    std::fs::create_dir_all(&temp_dir)?;
+    let range_path = temp_dir.join(format!("range_{:.2}_{:.2}.wav", start_time, end_time));
+    
+    // 字幕也写在临时目录，随后一起删除
+    let options = RecognitionOptions { subtitles_dir: None, ..options.clone() };
+    let result = (|| -> Result<Vec<subtitle::SubtitleEntry>> {
+        ffmpeg::extract_range(audio_path, start_time, end_time, &range_path)?;
+        let (srt_path, _text) = recognize_segment(&range_path, 1, 1, &options, cancel, tx)?;
+        Ok(subtitle::load_segment_subtitles(&srt_path)?)
+    })();
+    let _ = std::fs::remove_dir_all(&temp_dir);
+    
+    let mut subtitles = result?;
+    let offset = range_offset(stream_start_time(audio_path), start_time);
+    for entry in subtitles.iter_mut() {
+        entry.start_time += offset;
+        entry.end_time += offset;
+    }
+    Ok(subtitles)
+}
+
+/// 快速识别一个短音频文件，直接返回文本
+/// 
+/// 不提取、不切割、不写工作区：字幕写在临时目录，读出文本后删除
+pub fn quick_transcribe(
+    audio_path: &Path,
+    options: &RecognitionOptions,
+    cancel: &CancelToken,
+    tx: &Sender<ProgressMessage>,
+) -> Result<String> {
+    let temp_dir = unique_temp_dir("whisper_quick");
+    let options = RecognitionOptions { subtitles_dir: Some(temp_dir.clone()), ..options.clone() };
+    let result = recognize_segment(audio_path, 1, 1, &options, cancel, tx);
+    let _ = std::fs::remove_dir_all(&temp_dir);
+    
+    let (_srt_path, text) = result?;
+    Ok(text)
+}
+
+/// 分声道识别时各声道的说话人标签
+pub const CHANNEL_LABELS: [&str; 2] = ["Left", "Right"];
+
+/// 分声道识别：左右声道分别提取为单声道并识别，字幕加上说话人标签后按时间交错合并
+/// 
+/// 适合每个说话人各占一个声道的访谈录音。中间文件放在临时目录，识别后连同字幕一起删除
+pub fn recognize_channels(
+    audio_path: &Path,
+    options: &RecognitionOptions,
+    cancel: &CancelToken,
+    tx: &Sender<ProgressMessage>,
+) -> Result<Vec<subtitle::SubtitleEntry>> {
+    let temp_dir = unique_temp_dir("whisper_channels");
+    std::fs::create_dir_all(&temp_dir)?;
+    let options = RecognitionOptions { subtitles_dir: None, ..options.clone() };
+    
+    let result = (|| -> Result<Vec<subtitle::SubtitleEntry>> {
+        let mut tracks = Vec::new();
+        for (channel, label) in CHANNEL_LABELS.iter().enumerate() {
+            if cancel.is_cancelled() {
+                return Err(WhisperError::Cancelled);
+            }
+            let channel_path = temp_dir.join(format!("channel_{}.wav", label.to_lowercase()));
+            ffmpeg::extract_channel(audio_path, channel, &channel_path)?;
+            let (srt_path, _text) = recognize_segment(&channel_path, channel + 1, CHANNEL_LABELS.len(), &options, cancel, tx)?;
+            tracks.push((label.to_string(), subtitle::load_segment_subtitles(&srt_path)?));
+        }
+        Ok(subtitle::interleave_tracks(tracks))
+    })();
+    let _ = std::fs::remove_dir_all(&temp_dir);
+    
+    result
+}
+
+/// 从原音频 `start_time` 处提取的片段，其 0 秒在原音频播放时间轴上的位置
+/// 
+/// FFmpeg 的 `-ss` 按时间戳定位，而播放器从第一个样本开始计时。音频流的起始时间戳不为 0 时
+/// （如 LAME 编码的 MP3 去掉编码延迟后从 0.025s 开始，AAC 常见 0.021s），
+/// 片段实际从 `start_time - 起始时间戳` 开始。`stream_start` 由 [`stream_start_time`] 读取。
+/// 自动切割的片段（[`cut_offsets`]）、时间段识别和手动片段都用这里的修正，合并时彼此对齐
+pub fn range_offset(stream_start: f64, start_time: f64) -> f64 {
+    (start_time - stream_start).max(0.0)
+}
+
+/// 原音频中音频流的起始时间戳（秒），无法读取时按 0 处理
+pub fn stream_start_time(audio_path: &Path) -> f64 {
+    ffmpeg::media_info(audio_path)
+        .ok()
+        .and_then(|info| info.audio_start_time)
+        .unwrap_or(0.0)
+}
+
+/// 提取的 WAV（44.1kHz、16 位、立体声）每秒字节数
+const WAV_BYTES_PER_SECOND: f64 = 44100.0 * 2.0 * 2.0;
+/// 192 kbps MP3 片段每秒字节数
+const MP3_BYTES_PER_SECOND: f64 = 192_000.0 / 8.0;
+/// 基准测试识别的音频长度（秒）
+pub const BENCHMARK_SECONDS: f64 = 30.0;
+/// 预览默认识别的开头时长（秒）
+pub const PREVIEW_SECONDS: f64 = 30.0;
+
+/// 识别耗时和磁盘占用的预估
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Estimate {
+    pub recognition_seconds: f64,
+    pub wav_bytes: u64,  // 从视频提取的完整 WAV
+    pub segment_bytes: u64,  // 切割后的 MP3 片段
+    pub peak_bytes: u64,  // 切割过程中 WAV 片段尚未转换为 MP3 时的峰值
+}
+
+/// 在 CPU 上识别每秒音频的大致耗时（秒），只作粗略参考，实际取决于硬件
+pub fn realtime_factor(model: WhisperModel) -> f64 {
+    match model {
+        WhisperModel::Tiny => 0.15,
+        WhisperModel::Base => 0.3,
+        WhisperModel::Small => 0.8,
+        WhisperModel::Medium => 2.0,
+        WhisperModel::Large => 4.0,
+        WhisperModel::Turbo => 1.2,
+    }
+}
+
+/// 按媒体时长预估识别耗时和磁盘占用
+/// 
+/// `measured_factor` 为基准测试得到的每秒音频耗时，None 时使用 `realtime_factor` 的经验值。
+/// 磁盘占用按视频提取 WAV、再切割并转换为 MP3 片段的流程计算
+pub fn estimate(duration: f64, model: WhisperModel, measured_factor: Option<f64>) -> Estimate {
+    let duration = duration.max(0.0);
+    let factor = measured_factor.unwrap_or_else(|| realtime_factor(model));
+    let wav_bytes = (duration * WAV_BYTES_PER_SECOND) as u64;
+    let segment_bytes = (duration * MP3_BYTES_PER_SECOND) as u64;
+    
+    Estimate {
+        recognition_seconds: duration * factor,
+        wav_bytes,
+        segment_bytes,
+        peak_bytes: wav_bytes * 2 + segment_bytes,
+    }
+}
+
+/// 识别音频中间的 30 秒，返回每秒音频的实际耗时（秒），用于 `estimate`
+/// 
+/// 计时包含每段识别都要付出的模型加载时间。样本文件识别后即删除
+pub fn benchmark(
+    audio_path: &Path,
+    duration: f64,
+    options: &RecognitionOptions,
+    cancel: &CancelToken,
+) -> Result<f64> {
+    let length = BENCHMARK_SECONDS.min(duration);
+    if length <= 0.0 {
+        return Err(WhisperError::InvalidInput("Audio is empty".to_string()));
+    }
+    let start_time = ((duration - length) / 2.0).max(0.0);
+    
+    let started = std::time::Instant::now();
+    recognize_range(audio_path, start_time, start_time + length, options, cancel, &channel().0)?;
+    Ok(started.elapsed().as_secs_f64() / length)
+}
+
+/// 只识别开头 `seconds` 秒（不超过 `duration`），用于在识别长文件前快速确认语言和模型设置
+/// 
+/// 不切割、不写工作区，临时文件识别后即删除，见 [`recognize_range`]
+pub fn preview(
+    media_path: &Path,
+    seconds: f64,
+    duration: Option<f64>,
+    options: &RecognitionOptions,
+    cancel: &CancelToken,
+) -> Result<Vec<subtitle::SubtitleEntry>> {
+    let end_time = duration.map_or(seconds, |duration| seconds.min(duration));
+    if end_time <= 0.0 {
+        return Err(WhisperError::InvalidInput("Preview length must be positive".to_string()));
+    }
+    recognize_range(media_path, 0.0, end_time, options, cancel, &channel().0)
+}
+
+/// 自检时合成朗读的文字
+const SELF_TEST_TEXT: &str = "Hello. This is a short self test of the speech recognition pipeline.";
+
+/// 端到端自检：提取音频 → 切割 → 用 `tiny` 模型识别 → 合并，检查输出的字幕完整有效，返回字幕条数
+/// 
+/// `sample` 为 None 时用 FFmpeg 合成一段带提示音的语音（需要 libflite），否则使用给定的短音视频。
+/// 所有文件放在临时目录，结束后删除。用于确认 FFmpeg 和 Whisper 的安装可用
+pub fn self_test(sample: Option<&Path>) -> Result<usize> {
+    let temp_dir = std::env::temp_dir().join(format!("whisper_self_test_{}", std::process::id()));
+    std::fs::create_dir_all(&temp_dir)?;
+    let result = run_self_test(sample, &temp_dir);
+    let _ = std::fs::remove_dir_all(&temp_dir);
+    result
+}
+
+fn run_self_test(sample: Option<&Path>, temp_dir: &Path) -> Result<usize> {
+    // 复制到临时目录，提取的音频和字幕都不会写到样本旁边
+    let (media_path, language) = match sample {
+        Some(sample) => {
+            let name = sample.file_name()
+                .ok_or_else(|| WhisperError::InvalidInput(format!("Invalid sample path: {}", sample.display())))?;
+            let media_path = temp_dir.join(name);
+            std::fs::copy(sample, &media_path)?;
+            (media_path, None)
+        }
+        None => {
+            // 使用视频容器，以便经过提取音频这一步
+            let media_path = temp_dir.join("self_test.mkv");
+            println!("🔧 Synthesizing test speech...");
+            ffmpeg::synthesize_test_media(SELF_TEST_TEXT, &media_path).map_err(|e| match WhisperError::from(e) {
+                WhisperError::ProcessFailed { action, stderr } => WhisperError::process_failed(
+                    format!("{} (FFmpeg may lack the flite filter; pass a short speech clip: --self-test <file>)", action),
+                    stderr,
+                ),
+                other => other,
+            })?;
+            (media_path, Some("en".to_string()))
+        }
+    };
+    
+    let duration = ffmpeg::media_info(&media_path)?.duration
+        .ok_or_else(|| WhisperError::ParseError("Sample has no duration".to_string()))?;
+    let recognition = RecognitionOptions {
+        model: WhisperModel::Tiny,
+        language,
+        ..Default::default()
+    };
+    
+    println!("🔧 Running extraction, cutting, recognition and merge ({:.1}s of audio)...", duration);
+    let output_path = run(&media_path, &[duration / 2.0], &recognition, &MergeOptions::default(), None)?;
+    check_srt(&std::fs::read_to_string(&output_path)?)
+}
+
+/// 检查 SRT 内容非空且格式正确：每块都有序号、时间和文本，序号连续，时间不倒退。返回字幕条数
+fn check_srt(content: &str) -> Result<usize> {
+    let subtitles = subtitle::parse_srt_content(content);
+    if subtitles.is_empty() {
+        return Err(WhisperError::ParseError("Merged subtitles are empty".to_string()));
+    }
+    if subtitle::to_srt_string(&subtitles) != content.trim_start_matches('\u{feff}') {
+        return Err(WhisperError::ParseError("Merged subtitles contain malformed blocks".to_string()));
+    }
+    
+    for (i, entry) in subtitles.iter().enumerate() {
+        if entry.index != i + 1 {
+            return Err(WhisperError::ParseError(format!("Cue {} is numbered {}", i + 1, entry.index)));
+        }
+        if entry.end_time < entry.start_time {
+            return Err(WhisperError::ParseError(format!("Cue {} ends before it starts", entry.index)));
+        }
+        if i > 0 && entry.start_time < subtitles[i - 1].start_time {
+            return Err(WhisperError::ParseError(format!("Cue {} starts before the previous cue", entry.index)));
+        }
+    }
+    
+    Ok(subtitles.len())
+}
+
+/// 不覆盖已有文件的输出路径：`path` 不存在时原样返回，否则依次尝试 `video.1.srt`、`video.2.srt`……
+pub fn versioned_path(path: &Path) -> PathBuf {
+    if !path.exists() {
+        return path.to_path_buf();
+    }
+    
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+    (1..)
+        .map(|n| path.with_file_name(format!("{}.{}{}", stem, n, extension)))
+        .find(|candidate| !candidate.exists())
+        .unwrap()
+}
+
+/// 合并已识别片段的字幕，返回合并的片段数
+/// 
+/// `segments` 为音频片段及其起始时间，字幕见 `segment_srt_path`，尚未识别的片段跳过。
+/// `subtitles_dir` 与识别时的 `RecognitionOptions::subtitles_dir` 相同。设置了 `export_segments_to` 时还会导出每段的字幕，见 `export_segment_subtitles`
+pub fn merge_subtitles(
+    segments: &[(PathBuf, f64)],
+    subtitles_dir: Option<&Path>,
+    options: &MergeOptions,
+    output_path: &Path,
+) -> Result<usize> {
+    let mut subtitles: Vec<(PathBuf, f64, f64)> = segments.iter()
+        .filter(|(segment, _)| segment_has_subtitles(segment, subtitles_dir))
+        .map(|(segment, start_time)| (
+            segment_srt_path(segment, subtitles_dir),
+            *start_time,
+            merge_priming_delay(segment, options),
+        ))
+        .collect();
+    
+    if subtitles.is_empty() {
+        return Err(WhisperError::InvalidInput("No segment subtitles found to merge".to_string()));
+    }
+    
+    subtitles.sort_by(|a, b| a.1.total_cmp(&b.1));
+    
+    srt_merger::merge_srt_files_with_offsets(&subtitles, options.encoding, options.dedupe_boundaries, output_path)?;
+    finalize_srt_file(output_path, options)?;
+    
+    if let Some(export_dir) = &options.export_segments_to {
+        let basename = output_path.file_stem().unwrap_or_default().to_string_lossy();
+        export_segment_subtitles(&subtitles, options, export_dir, &basename)?;
+    }
+    
+    Ok(subtitles.len())
+}
+
+/// 合并时片段扣除的编码延迟：设置中指定的值，否则按该片段的格式推算
+fn merge_priming_delay(segment: &Path, options: &MergeOptions) -> f64 {
+    options.priming_delay.unwrap_or_else(|| ffmpeg::priming_delay(segment))
+}
+
+/// 各片段 Whisper JSON 中的逐词时间，换算为原音频中的时间（与合并字幕相同的偏移和编码延迟）
+/// 
+/// 没有 JSON 或未开启逐词时间的片段不产生词
+pub fn segment_word_timings(
+    segments: &[(PathBuf, f64)],
+    subtitles_dir: Option<&Path>,
+    options: &MergeOptions,
+) -> Result<Vec<(f64, f64, String)>> {
+    let mut words = Vec::new();
+    for (segment, time_offset) in segments {
+        let json_path = segment_srt_path(segment, subtitles_dir).with_extension("json");
+        if !json_path.exists() {
+            continue;
+        }
+        let content = std::fs::read_to_string(&json_path)?;
+        let parsed = whisper::parse_word_timestamps(&content)
+            .map_err(|e| WhisperError::ParseError(format!("{:?}: {}", json_path, e)))?;
+        let priming_delay = merge_priming_delay(segment, options);
+        words.extend(parsed.into_iter().map(|(start, end, word)| (
+            srt_merger::to_global_time(start, priming_delay, *time_offset),
+            srt_merger::to_global_time(end, priming_delay, *time_offset),
+            word,
+        )));
+    }
+    Ok(words)
+}
+
+/// 按设置对写出的字幕文件做后处理：时间戳取整到帧边界、从指定编号开始编号
+fn finalize_srt_file(path: &Path, options: &MergeOptions) -> Result<()> {
+    if options.fps.is_none() && options.start_index.is_none() && options.nonspeech_patterns.is_empty() {
+        return Ok(());
+    }
+    
+    let mut subtitles = subtitle::load_srt_file(path)?;
+    if !options.nonspeech_patterns.is_empty() {
+        subtitle::strip_nonspeech(&mut subtitles, &options.nonspeech_patterns);
+    }
+    if let Some(fps) = options.fps {
+        subtitle::quantize_to_fps(&mut subtitles, fps);
+    }
+    if let Some(start) = options.start_index {
+        subtitle::reindex_from(&mut subtitles, start);
+    }
+    subtitle::save_srt_file(path, &subtitles, options.encoding)?;
+    Ok(())
+}
+
+/// 把每段字幕换算成全局时间后单独导出为 `{basename}_seg{NN}.srt`
+/// 
+/// `subtitles` 为字幕文件及其起始时间、编码延迟，已按时间排序；NN 从 01 开始
+fn export_segment_subtitles(
+    subtitles: &[(PathBuf, f64, f64)],
+    options: &MergeOptions,
+    export_dir: &Path,
+    basename: &str,
+) -> Result<()> {
+    std::fs::create_dir_all(export_dir)?;
+    
+    for (i, segment) in subtitles.iter().enumerate() {
+        let path = export_dir.join(format!("{}_seg{:02}.srt", basename, i + 1));
+        srt_merger::merge_srt_files_with_offsets(std::slice::from_ref(segment), options.encoding, false, &path)?;
+        finalize_srt_file(&path, options)?;
+    }
+    
+    Ok(())
+}
+
+/// 完整流程：准备音频 → 切割 → 识别 → 合并，返回合并后的字幕路径
+/// 
+/// 字幕写到媒体文件同目录的同名 `.srt`。`progress` 为 None 时不报告进度
+pub fn run(
+    media_path: &Path,
+    cut_points: &[f64],
+    recognition: &RecognitionOptions,
+    merge: &MergeOptions,
+    progress: Option<Sender<ProgressMessage>>,
+) -> Result<PathBuf> {
+    let tx = progress.unwrap_or_else(|| channel().0);
+    
+    let (audio_path, timing_warning) = prepare_audio(media_path, recognition.whisper_audio)?;
+    if let Some(warning) = timing_warning {
+        println!("⚠️ {}", warning);
+    }
+    let total_duration = ffmpeg::media_info(&audio_path).ok().and_then(|info| info.duration);
+    let cut_points = &valid_cut_points(cut_points, total_duration);
+    let cut = cut_audio_with_progress(&audio_path, cut_points, recognition.lossless, recognition.accurate_cut, &tx)?;
+    let offsets = cut_offsets(&cut, stream_start_time(&audio_path));
+    let segments: Vec<PathBuf> = offsets.iter().map(|(path, _)| path.clone()).collect();
+    
+    let indices: Vec<usize> = (0..segments.len()).collect();
+    if recognize_segments(&segments, &indices, recognition, &CancelToken::default(), &tx) == 0 {
+        return Err(WhisperError::process_failed("Recognition failed", "No segment was recognized"));
+    }
+    
+    let output_path = media_path.with_extension("srt");
+    merge_subtitles(&offsets, recognition.subtitles_dir.as_deref(), merge, &output_path)?;
+    
+    let _ = tx.send(ProgressMessage::Completed);
+    Ok(output_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_estimate() {
+        // 1 小时的 base 模型：经验值每秒 0.3 秒
+        let hour = estimate(3600.0, WhisperModel::Base, None);
+        assert!((hour.recognition_seconds - 1080.0).abs() < 1e-6);
+        assert_eq!(hour.wav_bytes, 635_040_000);
+        assert_eq!(hour.segment_bytes, 86_400_000);
+        assert_eq!(hour.peak_bytes, 635_040_000 * 2 + 86_400_000);
+        
+        // 基准测试结果优先于经验值
+        let measured = estimate(100.0, WhisperModel::Large, Some(0.5));
+        assert!((measured.recognition_seconds - 50.0).abs() < 1e-6);
+    }
+    
+    #[test]
+    fn test_parallel_missing_whisper() {
+        // 指向一定不存在的程序，不受本机是否安装 Whisper 影响
+        let dir = unique_temp_dir("whisper_missing_test");
+        crate::config::set_whisper_path(Some(dir.join("no-such-whisper")));
+        let segments: Vec<PathBuf> = (0..6).map(|i| PathBuf::from(format!("/tmp/missing_{:03}.wav", i))).collect();
+        let indices: Vec<usize> = (0..segments.len()).collect();
+        let options = RecognitionOptions { workers: 3, ..Default::default() };
+        let (tx, rx) = channel();
+        
+        assert_eq!(recognize_segments(&segments, &indices, &options, &CancelToken::default(), &tx), 0);
+        crate::config::set_whisper_path(None);
+        drop(tx);
+        // 多个进程同时发现缺少 Whisper 也只报告一次
+        let errors = rx.iter().filter(|msg| matches!(msg, ProgressMessage::Error(_))).count();
+        assert_eq!(errors, 1);
+        assert!(RecognitionOptions::default().worker_count() >= 1);
+    }
+    
+    #[test]
+    fn test_valid_cut_points() {
+        // 开头、结尾和重复的切割点被去掉，其余排序
+        let points = [30.0, 0.0, 10.0, 0.01, 10.02, 59.99, 60.0];
+        assert_eq!(valid_cut_points(&points, Some(60.0)), vec![10.0, 30.0]);
+        
+        // 总时长未知时不过滤结尾
+        assert_eq!(valid_cut_points(&points, None), vec![10.0, 30.0, 59.99]);
+        assert!(valid_cut_points(&[], Some(60.0)).is_empty());
+    }
+    
+    #[test]
+    fn test_segment_offsets_and_merge() {
+        let dir = std::env::temp_dir().join(format!("whisper_pipeline_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        
+        let segments: Vec<PathBuf> = (0..3).map(|i| dir.join(format!("talk_{:03}.wav", i))).collect();
+        let cut = ffmpeg::segments_from_cut_points(segments.clone(), &[10.0, 20.0]).unwrap();
+        let offsets = cut_offsets(&cut, 0.0);
+        assert_eq!(offsets[2], (segments[2].clone(), 20.0));
+        // 原音频起始时间戳不为 0 时与时间段识别一样修正，第一段仍从 0 开始
+        let shifted = cut_offsets(&cut, 0.025);
+        assert_eq!(shifted[0].1, 0.0);
+        assert!((shifted[2].1 - 19.975).abs() < 1e-9);
+        assert_eq!(cut_points_of(&cut), vec![10.0, 20.0]);
+        
+        // 第二段尚未识别，不影响第三段的偏移
+        std::fs::write(segments[0].with_extension("srt"), "1\n00:00:01,000 --> 00:00:02,000\nfirst\n\n").unwrap();
+        std::fs::write(segments[2].with_extension("srt"), "1\n00:00:01,000 --> 00:00:02,000\nthird\n\n").unwrap();
+        
+        let output = dir.join("talk.srt");
+        let merged = merge_subtitles(&offsets, None, &MergeOptions::default(), &output).unwrap();
+        assert_eq!(merged, 2);
+        
+        // 无损模式的 WAV 片段没有编码延迟，偏移与切割点完全一致
+        assert_eq!(ffmpeg::priming_delay(&segments[0]), 0.0);
+        assert!((ffmpeg::lame_priming_delay(16000) - 1105.0 / 16000.0).abs() < 1e-12);
+        assert!((ffmpeg::lame_priming_delay(44100) - 0.025057).abs() < 1e-6);
+        let content = std::fs::read_to_string(&output).unwrap();
+        assert!(content.contains("00:00:21,000 --> 00:00:22,000\nthird"));
+        
+        // 同时导出每段字幕
+        let export_dir = dir.join("subtitles");
+        let options = MergeOptions { export_segments_to: Some(export_dir.clone()), ..Default::default() };
+        merge_subtitles(&offsets, None, &options, &output).unwrap();
+        let second = std::fs::read_to_string(export_dir.join("talk_seg02.srt")).unwrap();
+        assert!(second.starts_with("1\n00:00:21,000 --> 00:00:22,000\nthird"));
+        
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+    
+    #[test]
+    fn test_recover_segments() {
+        let dir = std::env::temp_dir().join(format!("whisper_recover_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let audio = dir.join("talk.wav");
+        for name in ["talk.wav", "talk_000.mp3", "talk_001.wav", "talk_001.mp3", "talk_002.mp3", "talk_000.srt", "talk_manual_1.00_2.00.wav", "other_003.mp3"] {
+            std::fs::write(dir.join(name), b"").unwrap();
+        }
+        
+        // 转换中途崩溃时取未转换的片段，手动片段和其他文件的片段不计入
+        assert_eq!(find_numbered_segments(&audio).unwrap(),
+            vec![dir.join("talk_000.mp3"), dir.join("talk_001.wav"), dir.join("talk_002.mp3")]);
+        assert_eq!(cut_points_from_durations(&[10.5, 20.0]), vec![10.5, 30.5]);
+        
+        // 编号不连续
+        std::fs::remove_file(dir.join("talk_001.wav")).unwrap();
+        std::fs::remove_file(dir.join("talk_001.mp3")).unwrap();
+        assert!(find_numbered_segments(&audio).unwrap_err().to_string().contains("talk_001"));
+        
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+    
+    #[test]
+    fn test_segment_srt_path() {
+        let dir = std::env::temp_dir().join(format!("whisper_srt_path_test_{}", std::process::id()));
+        let subtitles_dir = dir.join("subtitles");
+        std::fs::create_dir_all(&subtitles_dir).unwrap();
+        let segment = dir.join("talk_000.mp3");
+        
+        assert_eq!(segment_srt_path(&segment, None), dir.join("talk_000.srt"));
+        assert_eq!(segment_srt_path(&segment, Some(&subtitles_dir)), subtitles_dir.join("talk_000.srt"));
+        
+        // 旧项目的字幕还在片段旁边
+        std::fs::write(dir.join("talk_000.srt"), "1\n00:00:01,000 --> 00:00:02,000\nold\n\n").unwrap();
+        assert_eq!(segment_srt_path(&segment, Some(&subtitles_dir)), dir.join("talk_000.srt"));
+        assert!(segment_has_subtitles(&segment, Some(&subtitles_dir)));
+        
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+    
+    #[test]
+    fn test_check_srt() {
+        let good = "1\n00:00:01,000 --> 00:00:02,000\nHello\n\n2\n00:00:02,500 --> 00:00:04,000\nworld\n\n";
+        assert_eq!(check_srt(good).unwrap(), 2);
+        
+        assert!(check_srt("").is_err());
+        // 截断的块
+        assert!(check_srt(&good[..good.len() - 8]).is_err());
+        // 时间倒退
+        assert!(check_srt("1\n00:00:03,000 --> 00:00:04,000\na\n\n2\n00:00:01,000 --> 00:00:02,000\nb\n\n").is_err());
+        assert!(check_srt("1\n00:00:03,000 --> 00:00:02,000\na\n\n").is_err());
+    }
+    
+    #[test]
+    fn test_versioned_path() {
+        let dir = std::env::temp_dir().join(format!("whisper_versioned_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let srt = dir.join("talk.srt");
+        
+        assert_eq!(versioned_path(&srt), srt);
+        std::fs::write(&srt, "").unwrap();
+        assert_eq!(versioned_path(&srt), dir.join("talk.1.srt"));
+        std::fs::write(dir.join("talk.1.srt"), "").unwrap();
+        assert_eq!(versioned_path(&srt), dir.join("talk.2.srt"));
+        
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+    
+    #[test]
+    fn test_unique_temp_dir() {
+        // 同一进程内并发的调用不能共用（并删除）同一个目录
+        let a = unique_temp_dir("whisper_range");
+        let b = unique_temp_dir("whisper_range");
+        assert_ne!(a, b);
+        assert!(a.file_name().unwrap().to_string_lossy().starts_with(&format!("whisper_range_{}_", std::process::id())));
+    }
+}
@@ -1,188 +1,416 @@
 use std::path::Path;
 use std::fs::File;
-use std::io::{BufRead, BufReader, Write};
+use std::io::Write;
 use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use crate::subtitle::{self, SubtitleEntry};
 
-#[derive(Debug, Clone)]
-struct SubtitleEntry {
-    index: usize,
-    start_time: String,
-    end_time: String,
-    text: Vec<String>,
+/// 输出文件编码
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum OutputEncoding {
+    #[default]
+    Utf8,
+    Utf8Bom,
+    Gbk,
 }
 
-/// 解析 SRT 时间字符串为秒数
-fn parse_srt_time(time_str: &str) -> Result<f64> {
-    // 格式: HH:MM:SS,mmm
-    let time_str = time_str.trim();
-    
-    if time_str.is_empty() {
-        return Err(anyhow!("Empty time string"));
-    }
-    
-    let parts: Vec<&str> = time_str.split(',').collect();
-    if parts.len() != 2 {
-        return Err(anyhow!("Invalid time format: {} (expected HH:MM:SS,mmm)", time_str));
+impl OutputEncoding {
+    pub fn as_str(&self) -> &str {
+        match self {
+            OutputEncoding::Utf8 => "UTF-8",
+            OutputEncoding::Utf8Bom => "UTF-8 with BOM",
+            OutputEncoding::Gbk => "GBK",
+        }
     }
     
-    let time_parts: Vec<&str> = parts[0].split(':').collect();
-    if time_parts.len() != 3 {
-        return Err(anyhow!("Invalid time format: {} (expected HH:MM:SS,mmm)", time_str));
+    pub fn all() -> Vec<OutputEncoding> {
+        vec![
+            OutputEncoding::Utf8,
+            OutputEncoding::Utf8Bom,
+            OutputEncoding::Gbk,
+        ]
     }
-    
-    let hours: f64 = time_parts[0].trim().parse()
-        .map_err(|_| anyhow!("Invalid hour value: {}", time_parts[0]))?;
-    let minutes: f64 = time_parts[1].trim().parse()
-        .map_err(|_| anyhow!("Invalid minute value: {}", time_parts[1]))?;
-    let seconds: f64 = time_parts[2].trim().parse()
-        .map_err(|_| anyhow!("Invalid second value: {}", time_parts[2]))?;
-    let milliseconds: f64 = parts[1].trim().parse()
-        .map_err(|_| anyhow!("Invalid millisecond value: {}", parts[1]))?;
-    
-    Ok(hours * 3600.0 + minutes * 60.0 + seconds + milliseconds / 1000.0)
 }
 
-/// 将秒数转换为 SRT 时间格式
-fn format_srt_time(seconds: f64) -> String {
-    let hours = (seconds / 3600.0).floor() as u32;
-    let minutes = ((seconds % 3600.0) / 60.0).floor() as u32;
-    let secs = (seconds % 60.0).floor() as u32;
-    let millis = ((seconds % 1.0) * 1000.0).floor() as u32;
-    
-    format!("{:02}:{:02}:{:02},{:03}", hours, minutes, secs, millis)
-}
-
-/// 解析单个 SRT 文件
-fn parse_srt_file(path: &Path) -> Result<Vec<SubtitleEntry>> {
-    let file = File::open(path)?;
-    let reader = BufReader::new(file);
-    let mut entries = Vec::new();
-    
-    let mut lines = reader.lines();
-    let mut current_entry: Option<SubtitleEntry> = None;
-    
-    while let Some(line) = lines.next() {
-        let line = line?;
-        let line = line.trim();
-        
-        if line.is_empty() {
-            if let Some(entry) = current_entry.take() {
-                // 只添加有效的条目（有时间和文本）
-                if !entry.start_time.is_empty() && !entry.end_time.is_empty() {
-                    entries.push(entry);
-                }
-            }
-            continue;
+/// 按指定编码转换文本
+/// 
+/// 旧编码（GBK）无法表示的字符会返回错误并列出这些字符，而不是写出乱码
+pub fn encode_text(text: &str, encoding: OutputEncoding) -> Result<Vec<u8>> {
+    match encoding {
+        OutputEncoding::Utf8 => Ok(text.as_bytes().to_vec()),
+        OutputEncoding::Utf8Bom => {
+            let mut bytes = vec![0xEF, 0xBB, 0xBF];
+            bytes.extend_from_slice(text.as_bytes());
+            Ok(bytes)
         }
-        
-        // 尝试解析序号
-        if let Ok(index) = line.parse::<usize>() {
-            current_entry = Some(SubtitleEntry {
-                index,
-                start_time: String::new(),
-                end_time: String::new(),
-                text: Vec::new(),
-            });
-            continue;
-        }
-        
-        // 尝试解析时间行
-        if line.contains("-->") {
-            let time_parts: Vec<&str> = line.split("-->").collect();
-            if time_parts.len() == 2 {
-                if let Some(ref mut entry) = current_entry {
-                    let start = time_parts[0].trim();
-                    let end = time_parts[1].trim();
-                    // 验证时间格式非空
-                    if !start.is_empty() && !end.is_empty() {
-                        entry.start_time = start.to_string();
-                        entry.end_time = end.to_string();
-                    }
-                }
+        OutputEncoding::Gbk => {
+            let (bytes, _, had_errors) = encoding_rs::GBK.encode(text);
+            if had_errors {
+                let mut unmappable: Vec<char> = text.chars()
+                    .filter(|c| encoding_rs::GBK.encode(&c.to_string()).2)
+                    .collect();
+                unmappable.dedup();
+                let chars: String = unmappable.into_iter().take(10).collect();
+                return Err(anyhow!("Characters cannot be represented in GBK: {} (use UTF-8 instead)", chars));
             }
-            continue;
+            Ok(bytes.into_owned())
         }
-        
-        // 字幕文本
-        if let Some(ref mut entry) = current_entry {
-            if !entry.start_time.is_empty() {
-                entry.text.push(line.to_string());
-            }
+    }
+}
+
+/// 读入字幕文件时依次尝试的旧编码，得分相同时取靠前的
+const LEGACY_ENCODINGS: [&encoding_rs::Encoding; 3] = [encoding_rs::GBK, encoding_rs::BIG5, encoding_rs::SHIFT_JIS];
+
+/// 把字幕文件的字节解码为文本
+/// 
+/// 有 BOM 时按 BOM（UTF-8/UTF-16）解码，有效的 UTF-8 直接使用；否则依次尝试 GBK、Big5、Shift-JIS，
+/// 取解码无误且常用字比例最高的一种。都无法解码时按 UTF-8 解码，无效字节替换为 U+FFFD
+pub fn decode_text(bytes: &[u8]) -> String {
+    if let Some((encoding, bom_length)) = encoding_rs::Encoding::for_bom(bytes) {
+        return encoding.decode_without_bom_handling(&bytes[bom_length..]).0.into_owned();
+    }
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return text.to_string();
+    }
+    
+    let mut best: Option<(f64, String)> = None;
+    for encoding in LEGACY_ENCODINGS {
+        let Some(text) = encoding.decode_without_bom_handling_and_without_replacement(bytes) else { continue };
+        let score = common_char_ratio(&text, encoding);
+        if best.as_ref().is_none_or(|(best_score, _)| score > *best_score) {
+            best = Some((score, text.into_owned()));
         }
     }
+    best.map(|(_, text)| text)
+        .unwrap_or_else(|| String::from_utf8_lossy(bytes).into_owned())
+}
+
+/// 非 ASCII 字符中属于该编码常用区的比例：GB2312 的符号和汉字、Big5 的符号和常用字、
+/// Shift-JIS 的符号、假名和第一水准汉字。用错编码解码出的多是生僻字，比例明显偏低
+fn common_char_ratio(text: &str, encoding: &'static encoding_rs::Encoding) -> f64 {
+    let mut buffer = [0; 4];
+    let (mut common, mut total) = (0, 0);
     
-    // 添加最后一个条目
-    if let Some(entry) = current_entry {
-        if !entry.start_time.is_empty() && !entry.end_time.is_empty() {
-            entries.push(entry);
+    for c in text.chars().filter(|c| !c.is_ascii()) {
+        total += 1;
+        let (bytes, _, had_errors) = encoding.encode(c.encode_utf8(&mut buffer));
+        let (lead, trail) = match *bytes {
+            [lead, trail] if !had_errors => (lead, trail),
+            _ => continue,
+        };
+        let is_common = if encoding == encoding_rs::GBK {
+            matches!(lead, 0xA1..=0xA9 | 0xB0..=0xF7) && trail >= 0xA1
+        } else if encoding == encoding_rs::BIG5 {
+            matches!(lead, 0xA1..=0xC6)
+        } else {
+            matches!(lead, 0x81..=0x98)
+        };
+        if is_common {
+            common += 1;
         }
     }
     
-    Ok(entries)
+    if total == 0 { 1.0 } else { common as f64 / total as f64 }
+}
+
+/// 只检查结束/开始时间距切割点不超过该值（秒）的字幕是否重复
+const BOUNDARY_WINDOW_SECONDS: f64 = 2.0;
+/// 文本相似度（0~1）达到该值视为同一句话
+const DUPLICATE_SIMILARITY: f64 = 0.8;
+/// 一条字幕包含另一条时，较短的一条至少要有这么多字符才视为重复
+const MIN_CONTAINED_CHARS: usize = 4;
+
+/// 片段内的时间换算为原音频中的时间：扣除编码延迟后加上片段的起始时间
+pub fn to_global_time(time: f64, priming_delay: f64, time_offset: f64) -> f64 {
+    (time - priming_delay).max(0.0) + time_offset
 }
 
 /// 合并多个 SRT 文件，根据切割点调整时间戳
+/// 
+/// 所有片段扣除相同的 `priming_delay`（秒），各片段格式不同时使用 `merge_srt_files_with_offsets`
 pub fn merge_srt_files(
     srt_files: &[std::path::PathBuf],
     cut_points: &[f64],
+    priming_delay: f64,
+    encoding: OutputEncoding,
+    dedupe: bool,
     output_path: &Path,
 ) -> Result<()> {
-    let mut merged_entries = Vec::new();
-    let mut global_index = 1;
-    
     // 计算每段的起始时间
     let mut segment_start_times = vec![0.0];
     segment_start_times.extend(cut_points.iter().copied());
     
+    let segments: Vec<(std::path::PathBuf, f64, f64)> = srt_files.iter()
+        .cloned()
+        .zip(segment_start_times)
+        .map(|(srt_path, start_time)| (srt_path, start_time, priming_delay))
+        .collect();
+    
+    merge_srt_files_with_offsets(&segments, encoding, dedupe, output_path)
+}
+
+/// 合并多个 SRT 文件，每个文件使用显式给出的起始时间和编码延迟（秒）
+/// 
+/// `segments` 为（字幕文件，片段起始时间，片段的 priming 延迟），延迟见 `ffmpeg::priming_delay`。
+/// 片段的起始时间来自绝对切割点，延迟不会跨片段累积，每段只减去自身的延迟，
+/// 因此手动与自动切割、WAV 与 MP3 片段混合时也能各自修正。
+/// 适用于部分片段缺失字幕的情况，此时无法从切割点按顺序推算偏移。
+/// `dedupe` 为 true 时去掉切割点两侧重复识别的字幕，见 `dedupe_boundary_cues`
+pub fn merge_srt_files_with_offsets(
+    segments: &[(std::path::PathBuf, f64, f64)],
+    encoding: OutputEncoding,
+    dedupe: bool,
+    output_path: &Path,
+) -> Result<()> {
+    let mut merged_entries = Vec::new();
+    let mut segment_of_entry = Vec::new();  // 每条字幕来自第几个片段
+    let mut global_index = 1;
+    
     // 处理每个 SRT 文件
-    for (segment_idx, srt_path) in srt_files.iter().enumerate() {
-        let entries = parse_srt_file(srt_path)?;
-        let time_offset = segment_start_times[segment_idx];
+    for (segment, (srt_path, time_offset, priming_delay)) in segments.iter().enumerate() {
+        let entries = subtitle::load_segment_subtitles(srt_path)?;
+        let (time_offset, priming_delay) = (*time_offset, *priming_delay);
         
         for entry in entries {
-            // 解析原始时间
-            let start_seconds = parse_srt_time(&entry.start_time)?;
-            let end_seconds = parse_srt_time(&entry.end_time)?;
-            
-            // 添加时间偏移
-            let adjusted_start = start_seconds + time_offset;
-            let adjusted_end = end_seconds + time_offset;
+            let global = |time: f64| to_global_time(time, priming_delay, time_offset);
             
             // 创建新的条目
             merged_entries.push(SubtitleEntry {
                 index: global_index,
-                start_time: format_srt_time(adjusted_start),
-                end_time: format_srt_time(adjusted_end),
-                text: entry.text.clone(),
+                start_time: global(entry.start_time),
+                end_time: global(entry.end_time),
+                text: entry.text,
+                locked: false,
+                words: entry.words.into_iter().map(|(start, end, word)| (global(start), global(end), word)).collect(),
             });
+            segment_of_entry.push(segment);
             
             global_index += 1;
         }
     }
     
-    // 按时间排序（以防万一）
-    merged_entries.sort_by(|a, b| {
-        let a_time = parse_srt_time(&a.start_time).unwrap_or(0.0);
-        let b_time = parse_srt_time(&b.start_time).unwrap_or(0.0);
-        a_time.partial_cmp(&b_time).unwrap()
-    });
+    // 按时间排序（以防万一），开始时间相同时按结束时间，再相同时保持原顺序
+    let mut merged: Vec<(SubtitleEntry, usize)> = merged_entries.into_iter().zip(segment_of_entry).collect();
+    merged.sort_by(|(a, _), (b, _)| SubtitleEntry::cue_order(a, b));
+    
+    if dedupe {
+        let boundaries: Vec<f64> = segments.iter().skip(1).map(|(_, offset, _)| *offset).collect();
+        let removed = dedupe_boundary_cues(&mut merged, &boundaries);
+        if removed > 0 {
+            println!("🧹 去除切割点两侧重复的字幕 {} 条", removed);
+        }
+    }
+    let mut merged_entries: Vec<SubtitleEntry> = merged.into_iter().map(|(entry, _)| entry).collect();
     
     // 重新编号
     for (i, entry) in merged_entries.iter_mut().enumerate() {
         entry.index = i + 1;
     }
     
-    // 写入合并后的 SRT 文件
-    let mut output_file = File::create(output_path)?;
+    // 与编辑字幕使用同一个 SRT 写出函数，按指定编码写入文件
+    let bytes = encode_text(&subtitle::to_srt_string(&merged_entries), encoding)?;
+    File::create(output_path)?.write_all(&bytes)?;
+    
+    Ok(())
+}
+
+/// 去掉切割点两侧重复识别的字幕
+/// 
+/// 切割点落在句子中间时，前一段的结尾和后一段的开头可能识别出同一句话。
+/// 只比较来自相邻片段、分别在切割点前后 `BOUNDARY_WINDOW_SECONDS` 内结束和开始的两条字幕，
+/// 文本相似或一条包含另一条时保留较长的文本，时间范围合并为两者的并集。
+/// 同一片段内的重复（真正重复说的话）不受影响。返回删除的条数
+fn dedupe_boundary_cues(entries: &mut Vec<(SubtitleEntry, usize)>, boundaries: &[f64]) -> usize {
+    let near = |time: f64, boundary: f64| (time - boundary).abs() <= BOUNDARY_WINDOW_SECONDS;
+    
+    let mut removed = 0;
+    let mut i = 0;
+    while i < entries.len() {
+        let duplicate = (i + 1..entries.len()).find(|&j| {
+            let ((a, seg_a), (b, seg_b)) = (&entries[i], &entries[j]);
+            *seg_b == seg_a + 1
+                && boundaries.get(*seg_a).is_some_and(|&boundary| near(a.end_time, boundary) && near(b.start_time, boundary))
+                && is_duplicate_text(&a.text, &b.text)
+        });
+        
+        match duplicate {
+            Some(j) => {
+                let (b, _) = entries.remove(j);
+                let a = &mut entries[i].0;
+                if b.text.chars().count() > a.text.chars().count() {
+                    a.text = b.text;
+                }
+                a.end_time = a.end_time.max(b.end_time);
+                removed += 1;
+            }
+            None => i += 1,
+        }
+    }
+    
+    removed
+}
+
+/// 忽略大小写、空白和标点比较两段文本是否为同一句话
+fn is_duplicate_text(a: &str, b: &str) -> bool {
+    let normalize = |text: &str| -> Vec<char> {
+        text.chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(|c| c.to_lowercase())
+            .collect()
+    };
+    let (a, b) = (normalize(a), normalize(b));
+    if a.is_empty() || b.is_empty() {
+        return false;
+    }
+    
+    let (shorter, longer) = if a.len() <= b.len() { (&a, &b) } else { (&b, &a) };
+    if shorter.len() >= MIN_CONTAINED_CHARS && longer.windows(shorter.len()).any(|w| w == shorter.as_slice()) {
+        return true;
+    }
+    
+    let similarity = 1.0 - edit_distance(&a, &b) as f64 / longer.len() as f64;
+    similarity >= DUPLICATE_SIMILARITY
+}
+
+/// 字符级编辑距离
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
     
-    for entry in merged_entries {
-        writeln!(output_file, "{}", entry.index)?;
-        writeln!(output_file, "{} --> {}", entry.start_time, entry.end_time)?;
-        for line in entry.text {
-            writeln!(output_file, "{}", line)?;
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
         }
-        writeln!(output_file)?;  // 空行
+        previous = current;
     }
     
-    Ok(())
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_encode_text() {
+        assert_eq!(encode_text("中文", OutputEncoding::Utf8).unwrap(), "中文".as_bytes());
+        assert_eq!(&encode_text("a", OutputEncoding::Utf8Bom).unwrap(), &[0xEF, 0xBB, 0xBF, b'a']);
+        assert_eq!(encode_text("中文", OutputEncoding::Gbk).unwrap(), vec![0xD6, 0xD0, 0xCE, 0xC4]);
+        
+        // GBK 无法表示的字符
+        assert!(encode_text("字幕🎵", OutputEncoding::Gbk).is_err());
+    }
+    
+    #[test]
+    fn test_decode_text() {
+        assert_eq!(decode_text("中文字幕".as_bytes()), "中文字幕");
+        assert_eq!(decode_text(&[0xEF, 0xBB, 0xBF, b'a']), "a");
+        assert_eq!(decode_text(&[0xFF, 0xFE, b'a', 0, 0x2D, 0x4E]), "a中");
+        
+        // 旧编码按常用字比例识别
+        for (encoding, text) in [
+            (encoding_rs::GBK, "1\n00:00:01,000 --> 00:00:02,000\n你好，这是下载的中文字幕。\n"),
+            (encoding_rs::BIG5, "1\n00:00:01,000 --> 00:00:02,000\n這是繁體中文字幕，謝謝觀看。\n"),
+            (encoding_rs::SHIFT_JIS, "1\n00:00:01,000 --> 00:00:02,000\nこれは日本語の字幕です。\n"),
+        ] {
+            let (bytes, _, had_errors) = encoding.encode(text);
+            assert!(!had_errors);
+            assert_eq!(decode_text(&bytes), text, "{}", encoding.name());
+        }
+    }
+    
+    #[test]
+    fn test_merge_uses_whisper_json_times() {
+        let dir = std::env::temp_dir().join(format!("srt_merger_json_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let srt = dir.join("part_001.srt");
+        let output = dir.join("merged.srt");
+        std::fs::write(&srt, "1\n00:00:01,000 --> 00:00:02,000\nHello\n\n").unwrap();
+        
+        // 只有 SRT 时两次取整：1.000 + 10.0004 -> 11,000
+        merge_srt_files_with_offsets(&[(srt.clone(), 10.0004, 0.0)], OutputEncoding::Utf8, false, &output).unwrap();
+        assert!(std::fs::read_to_string(&output).unwrap().contains("00:00:11,000 --> 00:00:12,000"));
+        
+        // 有 JSON 时只在写出时取整：1.0004 + 10.0004 -> 11,001
+        std::fs::write(srt.with_extension("json"), r#"{"segments": [{"start": 1.0004, "end": 2.0004, "text": " Hello"}]}"#).unwrap();
+        merge_srt_files_with_offsets(&[(srt.clone(), 10.0004, 0.0)], OutputEncoding::Utf8, false, &output).unwrap();
+        assert!(std::fs::read_to_string(&output).unwrap().contains("00:00:11,001 --> 00:00:12,001\nHello"));
+        
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    
+    #[test]
+    fn test_merge_per_segment_priming_delay() {
+        let dir = std::env::temp_dir().join(format!("srt_merger_priming_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mp3_srt = dir.join("part_000.srt");
+        let wav_srt = dir.join("part_001.srt");
+        let output = dir.join("merged.srt");
+        std::fs::write(&mp3_srt, "1\n00:00:01,025 --> 00:00:02,025\nmp3\n\n").unwrap();
+        std::fs::write(&wav_srt, "1\n00:00:01,000 --> 00:00:02,000\nwav\n\n").unwrap();
+        
+        // 每段只扣除自身的延迟，WAV 片段不受 MP3 片段影响
+        let segments = [(mp3_srt, 0.0, 0.025), (wav_srt, 10.0, 0.0)];
+        merge_srt_files_with_offsets(&segments, OutputEncoding::Utf8, false, &output).unwrap();
+        let content = std::fs::read_to_string(&output).unwrap();
+        assert!(content.contains("00:00:01,000 --> 00:00:02,000\nmp3"));
+        assert!(content.contains("00:00:11,000 --> 00:00:12,000\nwav"));
+        
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    
+    #[test]
+    fn test_merge_orders_cues_sharing_a_start_time() {
+        let dir = std::env::temp_dir().join(format!("srt_merger_order_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let srt = dir.join("part_000.srt");
+        let output = dir.join("merged.srt");
+        std::fs::write(&srt, "1\n00:00:01,000 --> 00:00:04,000\nlong\n\n2\n00:00:01,000 --> 00:00:02,000\nshort\n\n\
+            3\n00:00:05,000 --> 00:00:06,000\nfirst\n\n4\n00:00:05,000 --> 00:00:06,000\nsecond\n\n").unwrap();
+        
+        // 开始时间相同按结束时间排序，时间完全相同保持原顺序
+        merge_srt_files_with_offsets(&[(srt.clone(), 0.0, 0.0)], OutputEncoding::Utf8, false, &output).unwrap();
+        let texts: Vec<String> = subtitle::parse_srt_content(&std::fs::read_to_string(&output).unwrap())
+            .into_iter().map(|entry| entry.text).collect();
+        assert_eq!(texts, ["short", "long", "first", "second"]);
+        
+        // NaN 排在最后，不会 panic
+        let cue = |start: f64| SubtitleEntry { index: 0, start_time: start, end_time: 1.0, text: String::new(), locked: false, words: Vec::new() };
+        let mut cues = [cue(f64::NAN), cue(2.0), cue(-f64::NAN), cue(1.0)];
+        cues.sort_by(SubtitleEntry::cue_order);
+        assert_eq!(cues[0].start_time, 1.0);
+        assert_eq!(cues[1].start_time, 2.0);
+        assert!(cues[2].start_time.is_nan() && cues[3].start_time.is_nan());
+        
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    
+    #[test]
+    fn test_dedupe_boundary_cues() {
+        let cue = |start: f64, end: f64, text: &str| SubtitleEntry {
+            index: 0,
+            start_time: start,
+            end_time: end,
+            text: text.to_string(),
+            locked: false,
+            words: Vec::new(),
+        };
+        let mut entries = vec![
+            (cue(1.0, 3.0, "Thank you."), 0),
+            (cue(8.0, 10.0, "and that is why we"), 0),
+            (cue(10.1, 12.0, "And that is why we left."), 1),
+            (cue(13.0, 14.0, "Thank you."), 1),
+        ];
+        
+        assert_eq!(dedupe_boundary_cues(&mut entries, &[10.0]), 1);
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[1].0.text, "And that is why we left.");
+        assert_eq!(entries[1].0.end_time, 12.0);
+        // 远离切割点的重复保留
+        assert_eq!(entries[2].0.text, "Thank you.");
+        
+        assert!(is_duplicate_text("我们今天讨论", "我们今天讨论。"));
+        assert!(!is_duplicate_text("yes", "yes, and"));
+    }
 }
@@ -0,0 +1,2333 @@
+use std::path::Path;
+use std::fs;
+use anyhow::{Result, anyhow};
+use serde::{Serialize, Deserialize};
+use crate::srt_merger::{self, OutputEncoding};
+
+/// 拆分文本时切分点吸附到空格的最大距离（字符数）
+const WORD_SNAP_CHARS: usize = 10;
+
+/// 判定两条字幕为同一条的时间容差（秒），SRT 精度为毫秒
+const SAME_CUE_TOLERANCE: f64 = 0.001;
+
+/// 内存中的字幕条目（时间以秒为单位）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubtitleEntry {
+    pub index: usize,
+    pub start_time: f64,
+    pub end_time: f64,
+    pub text: String,
+    #[serde(default)]
+    pub locked: bool,  // 手动校对过的字幕，重新识别时保留
+    /// 逐词时间 (开始, 结束, 词)，来自 Whisper 的 `--word_timestamps`；SRT 不保存，只在 JSON 导出中使用
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub words: Vec<(f64, f64, String)>,
+}
+
+impl SubtitleEntry {
+    pub fn duration(&self) -> f64 {
+        self.end_time - self.start_time
+    }
+    
+    /// 时间和文本都相同（忽略序号和锁定状态）
+    pub fn same_cue(&self, other: &SubtitleEntry) -> bool {
+        (self.start_time - other.start_time).abs() < SAME_CUE_TOLERANCE
+            && (self.end_time - other.end_time).abs() < SAME_CUE_TOLERANCE
+            && self.text == other.text
+    }
+    
+    fn overlaps(&self, start: f64, end: f64) -> bool {
+        self.start_time < end && self.end_time > start
+    }
+    
+    /// 字幕的排序：按开始时间，相同时按结束时间，NaN 排在最后
+    /// 
+    /// 配合稳定排序（`sort_by`）使用，时间完全相同的字幕保持原来的顺序
+    pub fn cue_order(a: &SubtitleEntry, b: &SubtitleEntry) -> std::cmp::Ordering {
+        let time_order = |x: f64, y: f64| match (x.is_nan(), y.is_nan()) {
+            (false, false) => x.total_cmp(&y),
+            (nan_x, nan_y) => nan_x.cmp(&nan_y),
+        };
+        time_order(a.start_time, b.start_time).then_with(|| time_order(a.end_time, b.end_time))
+    }
+
+    /// 将秒数转换为 SRT 时间格式
+    pub fn format_srt_time(seconds: f64) -> String {
+        // 先四舍五入到整毫秒再拆分，59.9996 进位为 00:01:00,000 而不是截断成 59,999
+        let total_millis = (seconds.max(0.0) * 1000.0).round() as u64;
+        let hours = total_millis / 3_600_000;
+        let minutes = total_millis / 60_000 % 60;
+        let secs = total_millis / 1000 % 60;
+        let millis = total_millis % 1000;
+        
+        format!("{:02}:{:02}:{:02},{:03}", hours, minutes, secs, millis)
+    }
+}
+
+/// 解析 SRT 时间字符串（HH:MM:SS,mmm）为秒数
+fn parse_srt_time(time_str: &str) -> Option<f64> {
+    let (hms, millis) = time_str.trim().split_once(',')?;
+    let parts: Vec<&str> = hms.split(':').collect();
+    if parts.len() != 3 {
+        return None;
+    }
+    
+    let hours: f64 = parts[0].trim().parse().ok()?;
+    let minutes: f64 = parts[1].trim().parse().ok()?;
+    let seconds: f64 = parts[2].trim().parse().ok()?;
+    let millis: f64 = millis.trim().parse().ok()?;
+    
+    Some(hours * 3600.0 + minutes * 60.0 + seconds + millis / 1000.0)
+}
+
+/// 把 SRT 文本按空行拆分为字幕块，每块为去掉首尾空白的非空行
+/// 
+/// 去掉 BOM，兼容 CRLF 换行和只含空格的"空行"
+fn srt_blocks(content: &str) -> Vec<Vec<&str>> {
+    let mut blocks = Vec::new();
+    let mut current = Vec::new();
+    
+    for line in content.trim_start_matches('\u{feff}').lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            if !current.is_empty() {
+                blocks.push(std::mem::take(&mut current));
+            }
+        } else {
+            current.push(line);
+        }
+    }
+    if !current.is_empty() {
+        blocks.push(current);
+    }
+    
+    blocks
+}
+
+/// 解析 SRT 文本内容，多行文本以 `\n` 连接
+/// 
+/// 与 [`to_srt_string`] 配对使用：合并片段和编辑字幕都经过这一对读写函数，
+/// 读入再写出的结果逐字节不变。
+/// 缺少序号或序号不是数字（如 `#3`）的字幕块只要时间行有效仍会读入，序号接着上一条编号
+pub fn parse_srt_content(content: &str) -> Vec<SubtitleEntry> {
+    let mut entries: Vec<SubtitleEntry> = Vec::new();
+    
+    for lines in srt_blocks(content) {
+        // 时间行在第一行（没有序号）或第二行
+        let Some((time_line, (start, end))) = lines.iter().take(2).enumerate()
+            .find_map(|(i, line)| Some((i, parse_time_line(line)?))) else {
+            continue;
+        };
+        
+        let next_index = entries.last().map_or(1, |entry| entry.index + 1);
+        let index = match time_line {
+            1 => lines[0].parse::<usize>().unwrap_or(next_index),
+            _ => next_index,
+        };
+        
+        entries.push(SubtitleEntry {
+            index,
+            start_time: start,
+            end_time: end,
+            text: lines[time_line + 1..].join("\n"),
+            locked: false,
+            words: Vec::new(),
+        });
+    }
+    
+    entries
+}
+
+/// 解析 `00:00:01,000 --> 00:00:02,500` 形式的时间行
+fn parse_time_line(line: &str) -> Option<(f64, f64)> {
+    let (start, end) = line.split_once("-->")?;
+    Some((parse_srt_time(start)?, parse_srt_time(end)?))
+}
+
+/// 读取 SRT 文件，非 UTF-8 的文件按 `srt_merger::decode_text` 识别编码（GBK、Big5、Shift-JIS）
+pub fn load_srt_file(path: &Path) -> Result<Vec<SubtitleEntry>> {
+    let content = srt_merger::decode_text(&fs::read(path)?);
+    Ok(parse_srt_content(&content))
+}
+
+/// 解析 Whisper 的 JSON 输出（`--output_format json`）中的 `segments`
+/// 
+/// 时间保留 JSON 中的完整精度，不像 SRT 那样取整到毫秒；空白文本的片段跳过。
+/// 用 `--word_timestamps True` 识别时片段带有 `words`，逐词时间保存在 `SubtitleEntry::words`
+pub fn parse_whisper_json(content: &str) -> Result<Vec<SubtitleEntry>> {
+    let value: serde_json::Value = serde_json::from_str(content.trim_start_matches('\u{feff}'))?;
+    let segments = value.get("segments").and_then(|s| s.as_array())
+        .ok_or_else(|| anyhow!("Whisper JSON has no segments"))?;
+    
+    let mut entries = Vec::new();
+    for segment in segments {
+        let (Some(start), Some(end)) = (segment["start"].as_f64(), segment["end"].as_f64()) else {
+            return Err(anyhow!("Whisper JSON segment has no start/end time"));
+        };
+        let text = segment["text"].as_str().unwrap_or_default().trim();
+        if text.is_empty() {
+            continue;
+        }
+        let words = segment["words"].as_array().map(Vec::as_slice).unwrap_or_default().iter()
+            .filter_map(|word| {
+                let text = word["word"].as_str()?.trim();
+                if text.is_empty() {
+                    return None;
+                }
+                Some((word["start"].as_f64()?, word["end"].as_f64()?, text.to_string()))
+            })
+            .collect();
+        entries.push(SubtitleEntry {
+            index: entries.len() + 1,
+            start_time: start,
+            end_time: end,
+            text: text.to_string(),
+            locked: false,
+            words,
+        });
+    }
+    
+    Ok(entries)
+}
+
+/// 读取片段字幕：同目录下有同名的 Whisper JSON 时使用其中未取整的时间，否则读取 SRT
+pub fn load_segment_subtitles(srt_path: &Path) -> Result<Vec<SubtitleEntry>> {
+    let json_path = srt_path.with_extension("json");
+    if json_path.exists() {
+        return parse_whisper_json(&fs::read_to_string(&json_path)?);
+    }
+    load_srt_file(srt_path)
+}
+
+/// SRT 文件是否完整：文件可读，且每个字幕块都有序号、时间和文本
+/// 
+/// 与 [`load_srt_file`] 一样用 [`srt_merger::decode_text`] 解码，GBK 等编码的字幕同样视为可读。
+/// 没有字幕块的文件（静音片段识别不出文字）也是完整的结果。
+/// 磁盘写满等原因中断写入的文件会在最后一块截断，断点续传时不应视为已完成
+pub fn is_complete_srt(path: &Path) -> bool {
+    let Ok(bytes) = fs::read(path) else { return false };
+    let content = srt_merger::decode_text(&bytes);
+    
+    let blocks = srt_blocks(&content).len();
+    let entries = parse_srt_content(&content);
+    
+    entries.len() == blocks && entries.iter().all(|e| !e.text.is_empty())
+}
+
+/// 生成 SRT 文本
+/// 
+/// 每条字幕以一个空行结束；文本中的空行会被跳过，否则播放器会把它当作字幕块的结尾
+pub fn to_srt_string(subtitles: &[SubtitleEntry]) -> String {
+    let mut output = String::new();
+    
+    for entry in subtitles {
+        output.push_str(&format!("{}\n", entry.index));
+        output.push_str(&format!("{} --> {}\n",
+            SubtitleEntry::format_srt_time(entry.start_time),
+            SubtitleEntry::format_srt_time(entry.end_time)));
+        for line in entry.text.lines().map(str::trim_end).filter(|l| !l.trim().is_empty()) {
+            output.push_str(line);
+            output.push('\n');
+        }
+        output.push('\n');
+    }
+    
+    output
+}
+
+/// 按指定编码保存 SRT 文件
+pub fn save_srt_file(path: &Path, subtitles: &[SubtitleEntry], encoding: OutputEncoding) -> Result<()> {
+    let bytes = srt_merger::encode_text(&to_srt_string(subtitles), encoding)?;
+    fs::write(path, bytes)?;
+    Ok(())
+}
+
+/// CSV 的表头
+const CSV_HEADER: &str = "index,start,end,text";
+
+/// JSON 导出的单条字幕，时间为秒数
+#[derive(Serialize, Deserialize)]
+struct JsonCue {
+    index: usize,
+    start: f64,
+    end: f64,
+    text: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    words: Vec<JsonWord>,
+}
+
+/// JSON 导出中的逐词时间
+#[derive(Serialize, Deserialize)]
+struct JsonWord {
+    start: f64,
+    end: f64,
+    word: String,
+}
+
+/// 导出为 CSV（index, start, end, text），便于在表格软件中翻译
+/// 
+/// 时间为 `HH:MM:SS.mmm`（SRT 时间的逗号会与分隔符冲突）。
+/// 包含逗号、引号或换行的字段按 RFC 4180 用引号包裹，引号写成两个引号
+pub fn export_csv(subtitles: &[SubtitleEntry]) -> String {
+    let mut output = format!("{}\r\n", CSV_HEADER);
+    
+    for entry in subtitles {
+        output.push_str(&format!("{},{},{},{}\r\n",
+            entry.index,
+            SubtitleEntry::format_srt_time(entry.start_time).replace(',', "."),
+            SubtitleEntry::format_srt_time(entry.end_time).replace(',', "."),
+            csv_field(&entry.text)));
+    }
+    
+    output
+}
+
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// 拆分 CSV 记录，引号内的逗号和换行属于字段内容
+fn parse_csv_records(content: &str) -> Result<Vec<Vec<String>>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = content.chars().peekable();
+    
+    while let Some(c) = chars.next() {
+        match (c, in_quotes) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            ('"', true) => in_quotes = false,
+            ('"', false) if field.is_empty() => in_quotes = true,
+            (',', false) => record.push(std::mem::take(&mut field)),
+            ('\r', false) => {}
+            ('\n', false) => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            _ => field.push(c),
+        }
+    }
+    
+    if in_quotes {
+        return Err(anyhow!("Unterminated quoted field in CSV"));
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    
+    records.retain(|r| !(r.len() == 1 && r[0].trim().is_empty()));
+    Ok(records)
+}
+
+/// 从 CSV 导入字幕，时间可以是 `HH:MM:SS.mmm`、SRT 格式或秒数；表头行可有可无
+pub fn import_csv(content: &str) -> Result<Vec<SubtitleEntry>> {
+    let content = content.trim_start_matches('\u{feff}');
+    let mut subtitles = Vec::new();
+    
+    for (row, record) in parse_csv_records(content)?.into_iter().enumerate() {
+        if row == 0 && record.first().is_some_and(|f| f.trim().eq_ignore_ascii_case("index")) {
+            continue;
+        }
+        if record.len() != 4 {
+            return Err(anyhow!("CSV row {}: expected 4 columns (index, start, end, text), found {}", row + 1, record.len()));
+        }
+        
+        let time = |value: &str| parse_srt_time(&value.replace('.', ","))
+            .or_else(|| value.trim().parse::<f64>().ok())
+            .ok_or_else(|| anyhow!("CSV row {}: invalid time {:?}", row + 1, value));
+        
+        subtitles.push(SubtitleEntry {
+            index: record[0].trim().parse().unwrap_or(subtitles.len() + 1),
+            start_time: time(&record[1])?,
+            end_time: time(&record[2])?,
+            text: record[3].clone(),
+            locked: false,
+            words: Vec::new(),
+        });
+    }
+    
+    Ok(subtitles)
+}
+
+/// 导出为 JSON 数组，每条字幕为 `{index, start, end, text}`，时间为秒数
+/// 
+/// 有逐词时间的字幕另有 `words: [{start, end, word}]`
+pub fn export_json(subtitles: &[SubtitleEntry]) -> Result<String> {
+    let cues: Vec<JsonCue> = subtitles.iter()
+        .map(|entry| JsonCue {
+            index: entry.index,
+            start: entry.start_time,
+            end: entry.end_time,
+            text: entry.text.clone(),
+            words: entry.words.iter()
+                .map(|(start, end, word)| JsonWord { start: *start, end: *end, word: word.clone() })
+                .collect(),
+        })
+        .collect();
+    Ok(serde_json::to_string_pretty(&cues)?)
+}
+
+/// 从 `export_json` 格式的 JSON 导入字幕
+pub fn import_json(content: &str) -> Result<Vec<SubtitleEntry>> {
+    let cues: Vec<JsonCue> = serde_json::from_str(content.trim_start_matches('\u{feff}'))?;
+    Ok(cues.into_iter()
+        .map(|cue| SubtitleEntry {
+            index: cue.index,
+            start_time: cue.start,
+            end_time: cue.end,
+            text: cue.text,
+            locked: false,
+            words: cue.words.into_iter().map(|word| (word.start, word.end, word.word)).collect(),
+        })
+        .collect())
+}
+
+/// 把全局时间的逐词时间分配给字幕：词的中点落在哪条字幕的时间内就归哪条，并截到该字幕的时间范围
+/// 
+/// 字幕经过编辑、拆分或合并后仍按时间对应。原有的逐词时间被替换，返回分配到的词数
+pub fn attach_words(subtitles: &mut [SubtitleEntry], words: &[(f64, f64, String)]) -> usize {
+    let mut attached = 0;
+    for entry in subtitles.iter_mut() {
+        entry.words = words.iter()
+            .filter(|(start, end, _)| (start + end) / 2.0 >= entry.start_time && (start + end) / 2.0 < entry.end_time)
+            .map(|(start, end, word)| (
+                start.clamp(entry.start_time, entry.end_time),
+                end.clamp(entry.start_time, entry.end_time),
+                word.clone(),
+            ))
+            .collect();
+        attached += entry.words.len();
+    }
+    attached
+}
+
+/// 把字幕保存为 JSON 文件（UTF-8），格式同 [`export_json`]：
+/// `[{index, start, end, text, words?}]`，时间为秒数，不取整到毫秒
+/// 
+/// 便于用脚本处理识别结果或比较两次识别，用 [`load_json_file`] 读回
+pub fn save_json_file(path: &Path, subtitles: &[SubtitleEntry]) -> Result<()> {
+    fs::write(path, export_json(subtitles)?)?;
+    Ok(())
+}
+
+/// 读取 [`save_json_file`] 保存的 JSON 文件，编辑器另存的非 UTF-8 文件按 `srt_merger::decode_text` 识别编码
+pub fn load_json_file(path: &Path) -> Result<Vec<SubtitleEntry>> {
+    import_json(&srt_merger::decode_text(&fs::read(path)?))
+}
+
+/// 导出为不带时间的纯文本稿
+/// 
+/// 去掉样式标签，字幕内的换行和同一句话的相邻字幕用 `join` 连接，句末标点后另起一行。
+/// 中日文用 `""` 连接，不产生多余空格；英文等用 `" "`；`"\n"` 则每条字幕一行，不合并句子
+pub fn export_plaintext(subtitles: &[SubtitleEntry], join: &str) -> String {
+    let mut output = String::new();
+    
+    for entry in subtitles {
+        let text = strip_tags(&entry.text).lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join(join);
+        if text.is_empty() {
+            continue;
+        }
+        
+        if !output.is_empty() {
+            output.push_str(if ends_sentence(&output) { "\n" } else { join });
+        }
+        output.push_str(&text);
+    }
+    
+    if !output.is_empty() {
+        output.push('\n');
+    }
+    output
+}
+
+/// 导出纯文本时默认的连接符：以中日韩文字为主时不加空格，否则为一个空格
+pub fn plaintext_join(subtitles: &[SubtitleEntry]) -> &'static str {
+    let text: String = subtitles.iter().map(|entry| entry.text.as_str()).collect();
+    if is_cjk_text(&text) { "" } else { " " }
+}
+
+/// 从 1 开始重新编号
+pub fn reindex_subtitles(subtitles: &mut [SubtitleEntry]) {
+    reindex_from(subtitles, 1);
+}
+
+/// 从 `start` 开始重新编号，便于把导出的片段拼接到其他字幕文件之后
+pub fn reindex_from(subtitles: &mut [SubtitleEntry], start: usize) {
+    for (i, entry) in subtitles.iter_mut().enumerate() {
+        entry.index = start + i;
+    }
+}
+
+/// 按比例把文本切成若干段
+/// 
+/// `weights` 为每段所占比例，按字符数分配；附近有空格时切分点吸附到空格，避免截断单词
+fn split_text_proportionally(text: &str, weights: &[f64]) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mask = tag_mask(&chars);
+    // 按显示的字符分配比例，样式标签不计入
+    let visible: Vec<usize> = (0..chars.len()).filter(|&j| !mask[j]).collect();
+    let total_weight: f64 = weights.iter().sum();
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    let mut accumulated = 0.0;
+    
+    for (i, weight) in weights.iter().enumerate() {
+        if i == weights.len() - 1 {
+            pieces.push(chars[start..].iter().collect::<String>().trim().to_string());
+            break;
+        }
+        
+        accumulated += weight;
+        let target = ((accumulated / total_weight) * visible.len() as f64).round() as usize;
+        let mut end = visible.get(target).copied().unwrap_or(chars.len()).clamp(start, chars.len());
+        
+        // 吸附到附近的空格
+        if let Some(space) = (start..chars.len())
+            .filter(|&j| chars[j].is_whitespace() && !mask[j])
+            .min_by_key(|&j| j.abs_diff(end))
+            .filter(|&j| j.abs_diff(end) <= WORD_SNAP_CHARS)
+        {
+            end = space;
+        }
+        
+        // 紧挨在切分点前的开始标签归到后一段
+        while end > start && mask[end - 1] {
+            let Some(tag_start) = (start..end).rev().find(|&j| tag_len(&chars[j..]) == Some(end - j)) else { break };
+            if chars.get(tag_start + 1) == Some(&'/') {
+                break;
+            }
+            end = tag_start;
+        }
+        
+        pieces.push(chars[start..end].iter().collect::<String>().trim().to_string());
+        start = end;
+    }
+    
+    balance_tags(pieces)
+}
+
+/// 拆分时长超过 `max_seconds` 的字幕
+/// 
+/// 优先在字幕时间范围内的静音区域中点拆分，没有静音时平均拆分；
+/// 文本按各段时长比例分配字符数。锁定的字幕和时间无效（时长不是有限值）的字幕不拆分。
+/// 拆分后重新编号，返回被拆分的字幕数
+pub fn split_long_duration(
+    subtitles: &mut Vec<SubtitleEntry>,
+    max_seconds: f64,
+    silence_regions: &[(f64, f64)],
+) -> usize {
+    if max_seconds <= 0.0 {
+        return 0;
+    }
+    
+    let mut result = Vec::with_capacity(subtitles.len());
+    let mut split_count = 0;
+    
+    for entry in subtitles.drain(..) {
+        if entry.locked || !entry.duration().is_finite() || entry.duration() <= max_seconds {
+            result.push(entry);
+            continue;
+        }
+        
+        // 计算拆分点
+        let mut bounds = vec![entry.start_time, entry.end_time];
+        loop {
+            let longest = (0..bounds.len() - 1)
+                .max_by(|&a, &b| (bounds[a + 1] - bounds[a]).total_cmp(&(bounds[b + 1] - bounds[b])))
+                .unwrap();
+            let (piece_start, piece_end) = (bounds[longest], bounds[longest + 1]);
+            if piece_end - piece_start <= max_seconds {
+                break;
+            }
+            
+            // 选择最接近中间的静音点
+            let middle = (piece_start + piece_end) / 2.0;
+            let silence_point = silence_regions.iter()
+                .map(|(s, e)| (s + e) / 2.0)
+                .filter(|&t| t > piece_start && t < piece_end)
+                .min_by(|a, b| (a - middle).abs().total_cmp(&(b - middle).abs()));
+            
+            match silence_point {
+                Some(t) => bounds.insert(longest + 1, t),
+                None => {
+                    // 平均拆分
+                    let parts = ((piece_end - piece_start) / max_seconds).ceil() as usize;
+                    let step = (piece_end - piece_start) / parts as f64;
+                    for k in (1..parts).rev() {
+                        bounds.insert(longest + 1, piece_start + step * k as f64);
+                    }
+                }
+            }
+        }
+        
+        let weights: Vec<f64> = bounds.windows(2).map(|w| w[1] - w[0]).collect();
+        let texts = split_text_proportionally(&entry.text, &weights);
+        
+        for (window, text) in bounds.windows(2).zip(texts) {
+            result.push(SubtitleEntry {
+                index: 0,
+                start_time: window[0],
+                end_time: window[1],
+                text,
+                locked: false,
+                words: Vec::new(),
+            });
+        }
+        split_count += 1;
+    }
+    
+    *subtitles = result;
+    reindex_subtitles(subtitles);
+    split_count
+}
+
+/// 拆分字数超过 `max_chars` 的字幕
+/// 
+/// 按字数平均切成尽量少的几段（中日韩文字每字算 1，样式标签不计入），
+/// 原时长按各段字数比例分配。锁定的字幕不拆分。拆分后重新编号，返回被拆分的字幕数
+pub fn split_long_cues(subtitles: &mut Vec<SubtitleEntry>, max_chars: usize) -> usize {
+    if max_chars == 0 {
+        return 0;
+    }
+    
+    let mut result = Vec::with_capacity(subtitles.len());
+    let mut split_count = 0;
+    
+    for entry in subtitles.drain(..) {
+        let chars = visible_len(&entry.text);
+        if entry.locked || chars <= max_chars {
+            result.push(entry);
+            continue;
+        }
+        
+        let parts = chars.div_ceil(max_chars);
+        let texts: Vec<String> = split_text_proportionally(&entry.text, &vec![1.0; parts])
+            .into_iter()
+            .filter(|text| !strip_tags(text).trim().is_empty())
+            .collect();
+        
+        let lengths: Vec<usize> = texts.iter().map(|text| visible_len(text).max(1)).collect();
+        let total: usize = lengths.iter().sum();
+        let mut start = entry.start_time;
+        let mut counted = 0;
+        for (i, (text, length)) in texts.into_iter().zip(&lengths).enumerate() {
+            counted += length;
+            let end = if i == lengths.len() - 1 {
+                entry.end_time
+            } else {
+                entry.start_time + entry.duration() * counted as f64 / total as f64
+            };
+            result.push(SubtitleEntry {
+                index: 0,
+                start_time: start,
+                end_time: end,
+                text,
+                locked: false,
+                words: Vec::new(),
+            });
+            start = end;
+        }
+        split_count += 1;
+    }
+    
+    *subtitles = result;
+    reindex_subtitles(subtitles);
+    split_count
+}
+
+/// 查找文本中与 `query` 匹配的位置（不区分大小写），返回不重叠的字节范围
+pub fn find_matches(text: &str, query: &str) -> Vec<std::ops::Range<usize>> {
+    let fold = |c: char| c.to_lowercase().next().unwrap_or(c);
+    let query: Vec<char> = query.chars().map(fold).collect();
+    if query.is_empty() {
+        return Vec::new();
+    }
+    
+    let chars: Vec<(usize, char)> = text.char_indices().map(|(i, c)| (i, fold(c))).collect();
+    let mut matches = Vec::new();
+    let mut i = 0;
+    
+    while i + query.len() <= chars.len() {
+        if chars[i..i + query.len()].iter().map(|(_, c)| *c).eq(query.iter().copied()) {
+            let end = chars.get(i + query.len()).map(|(b, _)| *b).unwrap_or(text.len());
+            matches.push(chars[i].0..end);
+            i += query.len();
+        } else {
+            i += 1;
+        }
+    }
+    
+    matches
+}
+
+/// 文本包含 `query` 的字幕下标（不区分大小写）
+pub fn search(subtitles: &[SubtitleEntry], query: &str) -> Vec<usize> {
+    subtitles.iter()
+        .enumerate()
+        .filter(|(_, s)| !find_matches(&s.text, query).is_empty())
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// 查找时间点所在的字幕
+pub fn find_at(subtitles: &[SubtitleEntry], time: f64) -> Option<usize> {
+    subtitles.iter().position(|s| time >= s.start_time && time <= s.end_time)
+}
+
+/// 字幕规范预设
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum SubtitleStandard {
+    #[default]
+    Netflix,
+    Ebu,
+    YouTube,
+}
+
+/// 字幕规范的各项限制
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StandardLimits {
+    pub max_lines: usize,
+    pub max_chars_per_line: usize,
+    pub max_cps: f64,  // 每秒字符数（不含空白）
+    pub min_duration: f64,
+    pub max_duration: f64,
+}
+
+impl SubtitleStandard {
+    pub fn as_str(&self) -> &str {
+        match self {
+            SubtitleStandard::Netflix => "Netflix",
+            SubtitleStandard::Ebu => "EBU",
+            SubtitleStandard::YouTube => "YouTube",
+        }
+    }
+    
+    pub fn all() -> Vec<SubtitleStandard> {
+        vec![
+            SubtitleStandard::Netflix,
+            SubtitleStandard::Ebu,
+            SubtitleStandard::YouTube,
+        ]
+    }
+    
+    /// 各规范的限制（拉丁文字）
+    /// 
+    /// Netflix：2 行 42 字符，20 CPS，5/6 秒 ~ 7 秒；
+    /// EBU（EBU-TT 指南）：2 行 37 字符，约 15 CPS，1 ~ 7 秒；
+    /// YouTube：没有硬性规定，按常用的 2 行 42 字符、25 CPS，最长 10 秒
+    pub fn limits(&self) -> StandardLimits {
+        match self {
+            SubtitleStandard::Netflix => StandardLimits {
+                max_lines: 2,
+                max_chars_per_line: 42,
+                max_cps: 20.0,
+                min_duration: 5.0 / 6.0,
+                max_duration: 7.0,
+            },
+            SubtitleStandard::Ebu => StandardLimits {
+                max_lines: 2,
+                max_chars_per_line: 37,
+                max_cps: 15.0,
+                min_duration: 1.0,
+                max_duration: 7.0,
+            },
+            SubtitleStandard::YouTube => StandardLimits {
+                max_lines: 2,
+                max_chars_per_line: 42,
+                max_cps: 25.0,
+                min_duration: 0.7,
+                max_duration: 10.0,
+            },
+        }
+    }
+}
+
+/// 不符合规范的字幕
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    pub index: usize,  // 字幕序号（从 1 开始）
+    pub start_time: f64,
+    pub reason: String,
+}
+
+/// 按规范检查字幕的行数、每行字符数、阅读速度和时长
+pub fn check_standard(subtitles: &[SubtitleEntry], standard: SubtitleStandard) -> Vec<Violation> {
+    let limits = standard.limits();
+    let mut violations = Vec::new();
+    
+    for entry in subtitles {
+        let mut flag = |reason: String| violations.push(Violation {
+            index: entry.index,
+            start_time: entry.start_time,
+            reason,
+        });
+        
+        // 样式标签不显示，不计入字符数
+        let text = strip_tags(&entry.text);
+        let lines: Vec<&str> = text.lines().collect();
+        if lines.len() > limits.max_lines {
+            flag(format!("{} lines (max {})", lines.len(), limits.max_lines));
+        }
+        
+        // 以中日韩文字为主的字幕按显示宽度计数（全角字符算 2），与折行一致
+        let width_of = |l: &str| if is_cjk_text(&text) { display_width(l) } else { l.chars().count() };
+        if let Some(longest) = lines.iter().map(|l| width_of(l)).max() {
+            if longest > limits.max_chars_per_line {
+                flag(format!("{} chars per line (max {})", longest, limits.max_chars_per_line));
+            }
+        }
+        
+        let duration = entry.duration();
+        if duration > 0.0 {
+            let cps = text.chars().filter(|c| !c.is_whitespace()).count() as f64 / duration;
+            if cps > limits.max_cps {
+                flag(format!("{:.1} chars/s (max {:.0})", cps, limits.max_cps));
+            }
+        }
+        
+        if duration < limits.min_duration {
+            flag(format!("{:.2}s too short (min {:.2}s)", duration, limits.min_duration));
+        } else if duration > limits.max_duration {
+            flag(format!("{:.2}s too long (max {:.0}s)", duration, limits.max_duration));
+        }
+    }
+    
+    violations
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x3040..=0x30FF     // 平假名、片假名
+        | 0x3400..=0x4DBF   // CJK 扩展 A
+        | 0x4E00..=0x9FFF   // CJK 统一汉字
+        | 0xAC00..=0xD7AF   // 韩文
+        | 0xF900..=0xFAFF   // CJK 兼容汉字
+        | 0x3000..=0x303F   // CJK 标点
+        | 0xFF00..=0xFFEF)  // 全角字符
+}
+
+/// SRT 样式标签名（`<i>`、`<b>`、`<u>`、`<s>`、`<font ...>`），大小写不敏感
+const SRT_TAGS: &[&str] = &["i", "b", "u", "s", "font"];
+
+/// `chars` 以样式标签开头时返回标签的字符数
+/// 
+/// 识别 SRT 标签（含结束标签）和 ASS 覆盖标签 `{\an8}`，其余的 `<...>`、`{...}` 视为普通文本
+fn tag_len(chars: &[char]) -> Option<usize> {
+    let close = match chars.first()? {
+        '<' => '>',
+        '{' if chars.get(1) == Some(&'\\') => '}',
+        _ => return None,
+    };
+    let len = chars.iter().position(|&c| c == close)? + 1;
+    
+    if close == '>' {
+        let inner: String = chars[1..len - 1].iter().collect();
+        let name = inner.trim_start_matches('/').split_whitespace().next().unwrap_or("").to_ascii_lowercase();
+        if !SRT_TAGS.contains(&name.as_str()) {
+            return None;
+        }
+    }
+    Some(len)
+}
+
+/// 标记每个字符是否属于样式标签，标签不显示、不计入字符数，编辑时不能拆开
+fn tag_mask(chars: &[char]) -> Vec<bool> {
+    let mut mask = vec![false; chars.len()];
+    let mut i = 0;
+    while i < chars.len() {
+        match tag_len(&chars[i..]) {
+            Some(len) => {
+                mask[i..i + len].fill(true);
+                i += len;
+            }
+            None => i += 1,
+        }
+    }
+    mask
+}
+
+/// 去掉样式标签后显示的文本
+pub fn strip_tags(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mask = tag_mask(&chars);
+    chars.iter().zip(&mask).filter(|(_, &tag)| !tag).map(|(&c, _)| c).collect()
+}
+
+/// 显示的字符数（不含样式标签）
+fn visible_len(text: &str) -> usize {
+    strip_tags(text).chars().count()
+}
+
+/// 显示宽度（不含样式标签），中日韩文字和全角字符算 2，半角片假名等算 1
+fn display_width(text: &str) -> usize {
+    strip_tags(text).chars()
+        .map(|c| if is_cjk(c) && !matches!(c as u32, 0xFF61..=0xFFDC) { 2 } else { 1 })
+        .sum()
+}
+
+/// 以中日韩文字为主：至少一半的可见非空白字符是中日韩文字
+fn is_cjk_text(text: &str) -> bool {
+    let visible: Vec<char> = strip_tags(text).chars().filter(|c| !c.is_whitespace()).collect();
+    let cjk = visible.iter().filter(|&&c| is_cjk(c)).count();
+    cjk > 0 && cjk * 2 >= visible.len()
+}
+
+/// 中文断行优先选择的标点，断在标点之后
+const BREAK_PUNCTUATION: &[char] = &['，', '。', '！', '？', '、', '；', '：', '…', ',', '!', '?', ';'];
+
+fn is_break_punct(token: &str) -> bool {
+    let mut chars = token.chars();
+    matches!((chars.next(), chars.next()), (Some(c), None) if BREAK_PUNCTUATION.contains(&c))
+}
+
+/// 文本中的全部样式标签
+fn tags_in(text: &str) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tags = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        match tag_len(&chars[i..]) {
+            Some(len) => {
+                tags.push(chars[i..i + len].iter().collect());
+                i += len;
+            }
+            None => i += 1,
+        }
+    }
+    tags
+}
+
+/// SRT 标签的名称（小写）及是否为结束标签，ASS 覆盖标签返回 None
+fn srt_tag_name(tag: &str) -> Option<(String, bool)> {
+    let inner = tag.strip_prefix('<')?.strip_suffix('>')?;
+    let closing = inner.starts_with('/');
+    let name = inner.trim_start_matches('/').split_whitespace().next()?.to_ascii_lowercase();
+    Some((name, closing))
+}
+
+/// 在 `open` 的基础上处理 `text` 中的标签，返回文本结尾仍未关闭的 SRT 开始标签
+fn open_tags(text: &str, mut open: Vec<String>) -> Vec<String> {
+    for tag in tags_in(text) {
+        match srt_tag_name(&tag) {
+            Some((name, true)) => {
+                if let Some(pos) = open.iter().rposition(|t| srt_tag_name(t).is_some_and(|(n, _)| n == name)) {
+                    open.remove(pos);
+                }
+            }
+            Some((_, false)) => open.push(tag),
+            None => {}
+        }
+    }
+    open
+}
+
+/// 补全拆分后各段的样式标签
+/// 
+/// 上一段未关闭的标签在段尾关闭、在下一段开头重新打开；
+/// 原文开头的 ASS 覆盖标签（如 `{\an8}` 位置）复制到每一段
+fn balance_tags(pieces: Vec<String>) -> Vec<String> {
+    let leading: String = pieces.first()
+        .map(|first| tags_in(first).into_iter()
+            .scan(first.as_str(), |rest, tag| {
+                let next = rest.strip_prefix(tag.as_str()).filter(|_| tag.starts_with('{'))?;
+                *rest = next;
+                Some(tag)
+            })
+            .collect())
+        .unwrap_or_default();
+    
+    let mut carried: Vec<String> = Vec::new();
+    pieces.into_iter().enumerate().map(|(k, piece)| {
+        let mut balanced = String::new();
+        if k > 0 && !piece.starts_with(&leading) {
+            balanced.push_str(&leading);
+        }
+        carried.iter().for_each(|tag| balanced.push_str(tag));
+        balanced.push_str(&piece);
+        
+        carried = open_tags(&piece, carried.clone());
+        for tag in carried.iter().rev() {
+            if let Some((name, _)) = srt_tag_name(tag) {
+                balanced.push_str(&format!("</{}>", name));
+            }
+        }
+        balanced
+    }).collect()
+}
+
+/// 给整条字幕加上或去掉 SRT 样式标签（如斜体 `i`、粗体 `b`）
+/// 
+/// 整条文本已被该标签包住时去掉标签，否则加上；开头的 ASS 覆盖标签保留在最前面
+pub fn toggle_style(text: &str, tag: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut prefix_len = 0;
+    while chars.get(prefix_len) == Some(&'{') {
+        match tag_len(&chars[prefix_len..]) {
+            Some(len) => prefix_len += len,
+            None => break,
+        }
+    }
+    let prefix: String = chars[..prefix_len].iter().collect();
+    let body: String = chars[prefix_len..].iter().collect();
+    
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let inner = body.strip_prefix(&open).and_then(|b| b.strip_suffix(&close))
+        .filter(|inner| open_tags(inner, Vec::new()).is_empty());
+    match inner {
+        Some(inner) => format!("{}{}", prefix, inner),
+        None => format!("{}{}{}{}", prefix, open, body, close),
+    }
+}
+
+/// 切换一条字幕的样式标签，锁定的字幕不能修改
+pub fn toggle_style_at(subtitles: &mut [SubtitleEntry], index: usize, tag: &str) -> Result<()> {
+    let entry = subtitles.get_mut(index)
+        .ok_or_else(|| anyhow!("Subtitle {} does not exist", index + 1))?;
+    
+    if entry.locked {
+        return Err(anyhow!("Subtitle {} is locked", entry.index));
+    }
+    
+    entry.text = toggle_style(&entry.text, tag);
+    Ok(())
+}
+
+/// 按每行最大字符数重新折行
+/// 
+/// 在空格处断行，中日韩文字可在任意字符间断行；超长的词按字符数硬切。
+/// 合并原有的行时，两侧都是中日韩文字则不加空格。
+/// 以中日韩文字为主的文本按显示宽度计数（全角字符算 2），优先在最后一个逗号、句号等标点后断行，
+/// 一行内没有标点时才按宽度硬切，标点不放在行首
+pub fn wrap_text(text: &str, max_chars_per_line: usize) -> String {
+    let max_chars = max_chars_per_line.max(1);
+    let cjk = is_cjk_text(text);
+    let width_of = |s: &str| if cjk { display_width(s) } else { visible_len(s) };
+    
+    // 拆成可断行的单元：(内容, 与前一单元之间是否需要空格)
+    let mut tokens: Vec<(String, bool)> = Vec::new();
+    for word in split_words(text) {
+        let mut first = true;
+        let mut run = String::new();
+        let chars: Vec<char> = word.chars().collect();
+        let mask = tag_mask(&chars);
+        for (&c, &tag) in chars.iter().zip(&mask) {
+            if is_cjk(c) && !tag {
+                if !run.is_empty() {
+                    tokens.push((std::mem::take(&mut run), first));
+                    first = false;
+                }
+                let after_cjk = tokens.last().is_some_and(|(t, _)| t.chars().last().is_some_and(is_cjk));
+                tokens.push((c.to_string(), first && !after_cjk));
+                first = false;
+            } else {
+                run.push(c);
+            }
+        }
+        if !run.is_empty() {
+            tokens.push((run, first));
+        }
+    }
+    
+    let mut lines: Vec<String> = Vec::new();
+    let mut current = String::new();
+    // 当前行最后一个断行标点之后的位置（字节）
+    let mut last_break: Option<usize> = None;
+    
+    for (token, space_before) in tokens {
+        // 带样式标签的词不硬切，避免拆开标签
+        let chunks: Vec<String> = if token.contains(['<', '{']) {
+            vec![token]
+        } else {
+            token.chars().collect::<Vec<_>>().chunks(max_chars).map(|c| c.iter().collect()).collect()
+        };
+        for (k, chunk) in chunks.into_iter().enumerate() {
+            let needs_space = !current.is_empty() && space_before && k == 0;
+            let width = width_of(&current) + usize::from(needs_space) + width_of(&chunk);
+            
+            if width_of(&current) > 0 && width > max_chars {
+                let rest = match last_break.take() {
+                    Some(pos) if cjk && pos < current.len() => current.split_off(pos).trim_start().to_string(),
+                    // 没有可用的标点时硬切，但不让标点出现在行首：把前一个字带到下一行
+                    _ if cjk && is_break_punct(&chunk) => match current.chars().last() {
+                        Some(last) if is_cjk(last) && current.chars().count() > 1 => {
+                            current.pop();
+                            last.to_string()
+                        }
+                        _ => String::new(),
+                    },
+                    _ => String::new(),
+                };
+                lines.push(std::mem::take(&mut current));
+                if !rest.is_empty() && width_of(&rest) + width_of(&chunk) > max_chars {
+                    lines.push(rest);
+                } else {
+                    current = rest;
+                }
+                current.push_str(&chunk);
+            } else {
+                if needs_space {
+                    current.push(' ');
+                }
+                current.push_str(&chunk);
+            }
+            
+            if cjk && is_break_punct(&chunk) {
+                last_break = Some(current.len());
+            }
+        }
+    }
+    
+    if !current.is_empty() {
+        lines.push(current);
+    }
+    
+    lines.join("\n")
+}
+
+/// 按空白拆分单词，样式标签内的空格（如 `<font color="red">`）不拆分
+fn split_words(text: &str) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mask = tag_mask(&chars);
+    let mut words = Vec::new();
+    let mut word = String::new();
+    
+    for (&c, &tag) in chars.iter().zip(&mask) {
+        if c.is_whitespace() && !tag {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+        } else {
+            word.push(c);
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    
+    words
+}
+
+/// 按规范的每行字符数重新折行（锁定的字幕不变），返回修改的条数
+pub fn apply_line_breaks(subtitles: &mut [SubtitleEntry], standard: SubtitleStandard) -> usize {
+    let max_chars = standard.limits().max_chars_per_line;
+    let mut changed = 0;
+    
+    for entry in subtitles.iter_mut().filter(|s| !s.locked) {
+        let wrapped = wrap_text(&entry.text, max_chars);
+        if wrapped != entry.text {
+            entry.text = wrapped;
+            changed += 1;
+        }
+    }
+    
+    changed
+}
+
+/// 文本规范化规则，每条可单独开关
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct NormalizeRules {
+    pub trim_spaces: bool,  // 去掉行首行尾的空格
+    pub collapse_spaces: bool,  // 连续空格合并为一个
+    pub cjk_punctuation: bool,  // 中日韩文字后的半角标点改为全角
+    pub capitalize_sentences: bool,  // 英文句首字母大写（不含中日韩文字的行）
+}
+
+impl Default for NormalizeRules {
+    fn default() -> Self {
+        Self {
+            trim_spaces: true,
+            collapse_spaces: true,
+            cjk_punctuation: true,
+            capitalize_sentences: true,
+        }
+    }
+}
+
+/// 半角标点及对应的全角标点
+const FULLWIDTH_PUNCTUATION: &[(char, char)] = &[
+    (',', '，'),
+    ('.', '。'),
+    ('?', '？'),
+    ('!', '！'),
+    (':', '：'),
+    (';', '；'),
+];
+
+/// Whisper 常在无人声处输出的非语音标注，`*` 匹配同一行内的任意文字，不含 `*` 的模式按原文匹配
+pub const DEFAULT_NONSPEECH_PATTERNS: &[&str] = &["[*]", "(*)", "（*）", "【*】", "♪", "♫"];
+
+/// 按空白拆分用户填写的非语音模式
+pub fn parse_nonspeech_patterns(text: &str) -> Vec<String> {
+    text.split_whitespace().map(String::from).collect()
+}
+
+/// 去掉字幕中与 `patterns` 匹配的非语音标注（如 `[音乐]`、`(笑)`、`♪♪`），只剩标注的字幕整条删除
+/// 
+/// 标注和正文混在一起时只去掉标注，保留正文。锁定的字幕不修改。
+/// 返回 (去掉了标注的字幕数, 其中删除的字幕数)，删除后重新编号
+pub fn strip_nonspeech(subtitles: &mut Vec<SubtitleEntry>, patterns: &[String]) -> (usize, usize) {
+    let mut cleaned = 0;
+    let before = subtitles.len();
+    
+    subtitles.retain_mut(|entry| {
+        if entry.locked {
+            return true;
+        }
+        let stripped = patterns.iter().fold(entry.text.clone(), |text, pattern| remove_nonspeech(&text, pattern));
+        if stripped == entry.text {
+            return true;
+        }
+        
+        cleaned += 1;
+        // 去掉标注后留下的多余空格和空行
+        entry.text = stripped.lines()
+            .map(|line| line.split(' ').filter(|word| !word.is_empty()).collect::<Vec<_>>().join(" "))
+            .filter(|line| !strip_tags(line).trim().is_empty())
+            .collect::<Vec<_>>()
+            .join("\n");
+        !strip_tags(&entry.text).trim().is_empty()
+    });
+    
+    let removed = before - subtitles.len();
+    if removed > 0 {
+        reindex_subtitles(subtitles);
+    }
+    (cleaned, removed)
+}
+
+/// 去掉文本中所有与模式匹配的部分，`*` 不跨行
+fn remove_nonspeech(text: &str, pattern: &str) -> String {
+    let (open, close) = pattern.split_once('*').unwrap_or((pattern, ""));
+    if open.is_empty() {
+        return text.to_string();
+    }
+    
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(open) {
+        let after = &rest[start + open.len()..];
+        let end = if close.is_empty() {
+            Some(0)
+        } else {
+            after.find(close)
+                .filter(|&end| !after[..end].contains('\n'))
+                .map(|end| end + close.len())
+        };
+        match end {
+            Some(end) => {
+                result.push_str(&rest[..start]);
+                rest = &after[end..];
+            }
+            None => {
+                result.push_str(&rest[..start + open.len()]);
+                rest = after;
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// 按规则规范化所有字幕的文本，锁定的字幕不修改，返回改动的字幕数
+/// 
+/// 句首判断跨字幕延续：上一条字幕没有以句末标点结尾时，下一条的首字母不大写
+pub fn normalize_text(subtitles: &mut [SubtitleEntry], rules: NormalizeRules) -> usize {
+    let mut changed = 0;
+    let mut sentence_start = true;
+    
+    for entry in subtitles.iter_mut() {
+        let mut at_start = sentence_start;
+        let normalized: Vec<String> = entry.text.lines()
+            .map(|line| normalize_line(line, rules, &mut at_start))
+            .collect();
+        let normalized = normalized.join("\n");
+        
+        if !entry.locked && normalized != entry.text {
+            entry.text = normalized;
+            changed += 1;
+        }
+        sentence_start = ends_sentence(&entry.text);
+    }
+    
+    changed
+}
+
+/// 规范化单行文本，`sentence_start` 记录下一个字母是否位于句首
+fn normalize_line(line: &str, rules: NormalizeRules, sentence_start: &mut bool) -> String {
+    let mut text = line.to_string();
+    
+    if rules.collapse_spaces {
+        let mut collapsed = String::with_capacity(text.len());
+        for c in text.chars() {
+            if !(c == ' ' && collapsed.ends_with(' ')) {
+                collapsed.push(c);
+            }
+        }
+        text = collapsed;
+    }
+    
+    if rules.trim_spaces {
+        text = text.trim().to_string();
+    }
+    
+    let has_cjk = text.chars().any(is_cjk);
+    
+    if rules.cjk_punctuation && has_cjk {
+        text = to_fullwidth_punctuation(&text);
+    }
+    
+    if rules.capitalize_sentences && !has_cjk {
+        let chars: Vec<char> = text.chars().collect();
+        let mask = tag_mask(&chars);
+        let mut capitalized = String::with_capacity(text.len());
+        for (i, &c) in chars.iter().enumerate() {
+            // 样式标签（如 <i>）原样保留
+            if mask[i] {
+                capitalized.push(c);
+                continue;
+            }
+            if c.is_alphanumeric() {
+                if *sentence_start {
+                    capitalized.extend(c.to_uppercase());
+                } else {
+                    capitalized.push(c);
+                }
+                *sentence_start = false;
+                continue;
+            }
+            // 句末标点后接空格或行尾才算句子结束，避免 3.5 之类的数字
+            if matches!(c, '.' | '?' | '!') && chars.get(i + 1).is_none_or(|n| n.is_whitespace()) {
+                *sentence_start = true;
+            }
+            capitalized.push(c);
+        }
+        text = capitalized;
+    } else {
+        *sentence_start = ends_sentence(&text) || (*sentence_start && text.is_empty());
+    }
+    
+    text
+}
+
+/// 把紧跟在中日韩文字后的半角标点改为全角，并去掉其后多余的空格
+/// 
+/// 省略号等连续的句点保持不变
+fn to_fullwidth_punctuation(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mask = tag_mask(&chars);
+    let mut result = String::with_capacity(text.len());
+    let mut skip_spaces = false;
+    
+    for (i, &c) in chars.iter().enumerate() {
+        if mask[i] {
+            result.push(c);
+            continue;
+        }
+        if skip_spaces && c == ' ' {
+            continue;
+        }
+        skip_spaces = false;
+        
+        let after_cjk = i > 0 && is_cjk(chars[i - 1]);
+        let ellipsis = c == '.' && (chars.get(i + 1) == Some(&'.') || (i > 0 && chars[i - 1] == '.'));
+        match FULLWIDTH_PUNCTUATION.iter().find(|(half, _)| *half == c) {
+            Some(&(_, full)) if after_cjk && !ellipsis => {
+                result.push(full);
+                skip_spaces = true;
+            }
+            _ => result.push(c),
+        }
+    }
+    
+    result
+}
+
+/// 文本是否以句末标点结尾
+fn ends_sentence(text: &str) -> bool {
+    text.trim_end().ends_with(['.', '?', '!', '。', '？', '！'])
+}
+
+/// 在指定时间点把一条字幕拆成两条
+/// 
+/// 两条字幕共享拆分时间点，文本按时长比例在最近的词/字边界处拆分。
+/// 时间点不在字幕范围内或字幕已锁定时返回错误
+pub fn split_at(subtitles: &mut Vec<SubtitleEntry>, index: usize, time: f64) -> Result<()> {
+    let entry = subtitles.get(index)
+        .ok_or_else(|| anyhow!("Subtitle {} does not exist", index + 1))?
+        .clone();
+    
+    if entry.locked {
+        return Err(anyhow!("Subtitle {} is locked", entry.index));
+    }
+    
+    if time <= entry.start_time || time >= entry.end_time {
+        return Err(anyhow!("Split point {:.3}s is outside subtitle {} ({:.3}s - {:.3}s)",
+            time, entry.index, entry.start_time, entry.end_time));
+    }
+    
+    let texts = split_text_proportionally(&entry.text, &[time - entry.start_time, entry.end_time - time]);
+    
+    subtitles[index] = SubtitleEntry {
+        end_time: time,
+        text: texts[0].clone(),
+        ..entry.clone()
+    };
+    subtitles.insert(index + 1, SubtitleEntry {
+        start_time: time,
+        text: texts[1].clone(),
+        ..entry
+    });
+    
+    reindex_subtitles(subtitles);
+    Ok(())
+}
+
+/// 删除一条字幕并重新编号，锁定的字幕不能删除
+pub fn delete_at(subtitles: &mut Vec<SubtitleEntry>, index: usize) -> Result<SubtitleEntry> {
+    let entry = subtitles.get(index)
+        .ok_or_else(|| anyhow!("Subtitle {} does not exist", index + 1))?;
+    
+    if entry.locked {
+        return Err(anyhow!("Subtitle {} is locked", entry.index));
+    }
+    
+    let removed = subtitles.remove(index);
+    reindex_subtitles(subtitles);
+    Ok(removed)
+}
+
+/// 一次删除多条字幕（`indices` 为下标），最后只重新编号一次
+/// 
+/// 锁定的字幕保留，返回 (删除的条数, 因锁定跳过的条数)
+pub fn delete_many(subtitles: &mut Vec<SubtitleEntry>, indices: &[usize]) -> (usize, usize) {
+    let mut requested = vec![false; subtitles.len()];
+    for &index in indices {
+        if let Some(flag) = requested.get_mut(index) {
+            *flag = true;
+        }
+    }
+    
+    let skipped = subtitles.iter().zip(&requested).filter(|(s, &r)| r && s.locked).count();
+    let before = subtitles.len();
+    let mut flags = requested.into_iter();
+    subtitles.retain(|s| !flags.next().unwrap_or(false) || s.locked);
+    
+    let removed = before - subtitles.len();
+    if removed > 0 {
+        reindex_subtitles(subtitles);
+    }
+    (removed, skipped)
+}
+
+/// 把一条字幕与下一条合并：时间覆盖两条，文本用空格连接（两侧都是中日韩文字时直接连接）
+/// 
+/// 没有下一条或任一条已锁定时返回错误
+pub fn merge_with_next(subtitles: &mut Vec<SubtitleEntry>, index: usize) -> Result<()> {
+    if index + 1 >= subtitles.len() {
+        return Err(anyhow!("Subtitle {} has no next subtitle", index + 1));
+    }
+    if let Some(locked) = subtitles[index..=index + 1].iter().find(|s| s.locked) {
+        return Err(anyhow!("Subtitle {} is locked", locked.index));
+    }
+    
+    let next = subtitles.remove(index + 1);
+    absorb(&mut subtitles[index], &next);
+    
+    reindex_subtitles(subtitles);
+    Ok(())
+}
+
+/// 把间隔小于 `max_gap` 秒的相邻字幕合并，合并后的时长不超过 `max_duration` 秒
+/// 
+/// 用于 VAD 模式产生的大量单词短字幕。文本连接方式同 `merge_with_next`，锁定的字幕不参与合并。
+/// 返回合并掉的字幕数，有合并时重新编号
+pub fn merge_short_cues(subtitles: &mut Vec<SubtitleEntry>, max_gap: f64, max_duration: f64) -> usize {
+    let before = subtitles.len();
+    let mut result: Vec<SubtitleEntry> = Vec::with_capacity(before);
+    
+    for entry in subtitles.drain(..) {
+        if let Some(last) = result.last_mut() {
+            let gap = entry.start_time - last.end_time;
+            let merged_duration = entry.end_time.max(last.end_time) - last.start_time.min(entry.start_time);
+            if !last.locked && !entry.locked && gap < max_gap && merged_duration < max_duration {
+                absorb(last, &entry);
+                continue;
+            }
+        }
+        result.push(entry);
+    }
+    
+    *subtitles = result;
+    let merged = before - subtitles.len();
+    if merged > 0 {
+        reindex_subtitles(subtitles);
+    }
+    merged
+}
+
+/// 把 `next` 并入 `entry`：时间覆盖两条，文本用空格连接（两侧都是中日韩文字时直接连接）
+fn absorb(entry: &mut SubtitleEntry, next: &SubtitleEntry) {
+    let first = entry.text.trim_end();
+    let second = next.text.trim_start();
+    let joined_without_space = first.chars().last().is_some_and(is_cjk) && second.chars().next().is_some_and(is_cjk);
+    entry.text = if first.is_empty() || second.is_empty() || joined_without_space {
+        format!("{}{}", first, second)
+    } else {
+        format!("{} {}", first, second)
+    };
+    entry.start_time = entry.start_time.min(next.start_time);
+    entry.end_time = entry.end_time.max(next.end_time);
+}
+
+/// 把开始和结束时间取整到最近的帧边界（`round(t * fps) / fps`）
+/// 
+/// 取整后时长为 0 的字幕至少保留一帧
+pub fn quantize_to_fps(subtitles: &mut [SubtitleEntry], fps: f64) {
+    if fps <= 0.0 {
+        return;
+    }
+    
+    let to_frame = |t: f64| (t * fps).round() / fps;
+    for entry in subtitles.iter_mut() {
+        entry.start_time = to_frame(entry.start_time);
+        entry.end_time = to_frame(entry.end_time).max(entry.start_time + 1.0 / fps);
+    }
+}
+
+/// 时间重叠的字幕，返回下标对 (i, j)，i < j，按 i、j 排序
+/// 
+/// 首尾恰好相接（结束时间等于下一条的开始时间）不算重叠
+pub fn find_overlaps(subtitles: &[SubtitleEntry]) -> Vec<(usize, usize)> {
+    let mut order: Vec<usize> = (0..subtitles.len()).collect();
+    order.sort_by(|&a, &b| SubtitleEntry::cue_order(&subtitles[a], &subtitles[b]));
+    
+    let mut pairs = Vec::new();
+    for (k, &a) in order.iter().enumerate() {
+        // 按开始时间排序后，开始时间不早于 a 结束的字幕都不会与 a 重叠
+        for &b in order[k + 1..].iter().take_while(|&&b| subtitles[b].start_time < subtitles[a].end_time) {
+            if subtitles[b].overlaps(subtitles[a].start_time, subtitles[a].end_time) {
+                pairs.push((a.min(b), a.max(b)));
+            }
+        }
+    }
+    
+    pairs.sort_unstable();
+    pairs
+}
+
+/// 修复重叠：把较早开始的字幕的结束时间截到较晚字幕的开始时间
+/// 
+/// 两条同时开始（截断后时长为 0）或较早的字幕已锁定时不修改。返回修改的字幕数
+pub fn fix_overlaps(subtitles: &mut [SubtitleEntry]) -> usize {
+    let mut trimmed = std::collections::BTreeSet::new();
+    
+    for (i, j) in find_overlaps(subtitles) {
+        let (earlier, later) = if SubtitleEntry::cue_order(&subtitles[i], &subtitles[j]).is_le() { (i, j) } else { (j, i) };
+        let later_start = subtitles[later].start_time;
+        let entry = &mut subtitles[earlier];
+        if entry.locked || later_start <= entry.start_time || later_start >= entry.end_time {
+            continue;
+        }
+        entry.end_time = later_start;
+        trimmed.insert(earlier);
+    }
+    
+    trimmed.len()
+}
+
+/// 所有时间乘以 `factor`，修正帧率不一致（如 25 与 23.976 fps）造成的逐渐偏移
+/// 
+/// 这是整体的同步修正，锁定的字幕也一起缩放
+pub fn rescale_time(subtitles: &mut [SubtitleEntry], factor: f64) {
+    for entry in subtitles.iter_mut() {
+        entry.start_time *= factor;
+        entry.end_time *= factor;
+    }
+}
+
+/// 按两个同步点线性修正时间：`src_a` 处的字幕应在 `dst_a`，`src_b` 处的应在 `dst_b`
+/// 
+/// 时间变为 `t * factor + offset`，早于 0 的截到 0。两个同步点相同或换算后顺序颠倒时返回错误，
+/// 否则返回 (factor, offset)
+pub fn rescale_from_anchors(
+    subtitles: &mut [SubtitleEntry],
+    src_a: f64,
+    dst_a: f64,
+    src_b: f64,
+    dst_b: f64,
+) -> Result<(f64, f64)> {
+    if src_a == src_b {
+        return Err(anyhow!("The two sync points must be at different times"));
+    }
+    let factor = (dst_b - dst_a) / (src_b - src_a);
+    if !factor.is_finite() || factor <= 0.0 {
+        return Err(anyhow!("The sync points would reverse the subtitle order"));
+    }
+    let offset = dst_a - src_a * factor;
+    
+    rescale_time(subtitles, factor);
+    for entry in subtitles.iter_mut() {
+        entry.start_time = (entry.start_time + offset).max(0.0);
+        entry.end_time = (entry.end_time + offset).max(0.0);
+    }
+    Ok((factor, offset))
+}
+
+/// 把每条字幕的开始提前 `lead` 秒、结束推迟 `tail` 秒，避免首尾的字被截掉
+/// 
+/// 字幕需按开始时间排序。不会早于 0，也不会与相邻字幕重叠：
+/// 两条字幕之间的间隔不够时按 `tail`、`lead` 的比例分配。锁定的字幕不变，返回修改的条数
+pub fn pad(subtitles: &mut [SubtitleEntry], lead: f64, tail: f64) -> usize {
+    let (lead, tail) = (lead.max(0.0), tail.max(0.0));
+    if lead == 0.0 && tail == 0.0 {
+        return 0;
+    }
+    
+    // 间隔不够时按比例分给前一条的结尾和后一条的开头
+    let share = |gap: f64, want: f64, other: f64| {
+        if want + other > gap { gap * want / (want + other) } else { want }
+    };
+    let lead_of = |entry: &SubtitleEntry| if entry.locked { 0.0 } else { lead };
+    let tail_of = |entry: &SubtitleEntry| if entry.locked { 0.0 } else { tail };
+    
+    let mut starts: Vec<f64> = subtitles.iter().map(|s| s.start_time).collect();
+    let mut ends: Vec<f64> = subtitles.iter().map(|s| s.end_time).collect();
+    
+    for (i, entry) in subtitles.iter().enumerate() {
+        if entry.locked {
+            continue;
+        }
+        
+        starts[i] -= match i.checked_sub(1).map(|p| &subtitles[p]) {
+            None => lead.min(entry.start_time.max(0.0)),
+            Some(prev) => share((entry.start_time - prev.end_time).max(0.0), lead, tail_of(prev)),
+        };
+        ends[i] += match subtitles.get(i + 1) {
+            None => tail,
+            Some(next) => share((next.start_time - entry.end_time).max(0.0), tail, lead_of(next)),
+        };
+    }
+    
+    let mut changed = 0;
+    for (entry, (start, end)) in subtitles.iter_mut().zip(starts.into_iter().zip(ends)) {
+        if start != entry.start_time || end != entry.end_time {
+            entry.start_time = start;
+            entry.end_time = end;
+            changed += 1;
+        }
+    }
+    changed
+}
+
+/// 最后一条字幕结束到媒体结尾之间没有字幕的时长（秒）
+/// 
+/// 没有字幕时返回整个时长
+pub fn uncovered_tail(subtitles: &[SubtitleEntry], total_duration: f64) -> f64 {
+    let last_end = subtitles.iter().map(|s| s.end_time).fold(0.0, f64::max);
+    (total_duration - last_end).max(0.0)
+}
+
+/// 删除与时间范围重叠的字幕，锁定的字幕保留，返回删除的条数
+pub fn remove_subtitles_in_range(subtitles: &mut Vec<SubtitleEntry>, start: f64, end: f64) -> usize {
+    let before = subtitles.len();
+    subtitles.retain(|s| s.locked || !s.overlaps(start, end));
+    before - subtitles.len()
+}
+
+/// 取出与时间范围重叠的字幕，用于导出片段字幕
+/// 
+/// `clip` 为 true 时把跨越边界的字幕截到范围内；`rebase` 为 true 时所有时间减去 `start`
+/// （截断前开始得更早的字幕不会变成负数）并从 1 重新编号。原字幕不变
+pub fn slice_range(subtitles: &[SubtitleEntry], start: f64, end: f64, rebase: bool, clip: bool) -> Vec<SubtitleEntry> {
+    let mut sliced: Vec<SubtitleEntry> = subtitles.iter()
+        .filter(|s| s.overlaps(start, end))
+        .cloned()
+        .collect();
+    
+    for entry in &mut sliced {
+        if clip {
+            entry.start_time = entry.start_time.max(start);
+            entry.end_time = entry.end_time.min(end);
+        }
+        if rebase {
+            entry.start_time = (entry.start_time - start).max(0.0);
+            entry.end_time = (entry.end_time - start).max(0.0);
+        }
+    }
+    if rebase {
+        reindex_subtitles(&mut sliced);
+    }
+    
+    sliced
+}
+
+/// 把多条字幕轨按开始时间交错合并，每条字幕前加上所属轨道的标签（如 `Left: ...`）
+/// 
+/// 开始时间相同时保持轨道顺序
+pub fn interleave_tracks(tracks: Vec<(String, Vec<SubtitleEntry>)>) -> Vec<SubtitleEntry> {
+    let mut merged: Vec<SubtitleEntry> = tracks.into_iter()
+        .flat_map(|(label, entries)| entries.into_iter().map(move |mut entry| {
+            entry.text = format!("{}: {}", label, entry.text);
+            entry
+        }))
+        .collect();
+    
+    merged.sort_by(|a, b| a.start_time.total_cmp(&b.start_time));
+    reindex_subtitles(&mut merged);
+    merged
+}
+
+/// 插入字幕并按开始时间排序、重新编号
+/// 
+/// 未锁定的新字幕如果与已锁定的字幕重叠则跳过，返回实际插入的条数
+pub fn insert_subtitles(subtitles: &mut Vec<SubtitleEntry>, new_subtitles: Vec<SubtitleEntry>) -> usize {
+    let mut inserted = 0;
+    
+    for entry in new_subtitles {
+        let blocked = !entry.locked && subtitles.iter()
+            .any(|s| s.locked && s.overlaps(entry.start_time, entry.end_time));
+        if !blocked {
+            subtitles.push(entry);
+            inserted += 1;
+        }
+    }
+    
+    subtitles.sort_by(SubtitleEntry::cue_order);
+    reindex_subtitles(subtitles);
+    inserted
+}
+
+/// 把锁定的字幕应用到重新生成的字幕上
+/// 
+/// 与锁定字幕相同的条目标记为锁定；与锁定字幕重叠的其他条目被删除，
+/// 缺失的锁定字幕重新插入
+pub fn apply_locked(subtitles: &mut Vec<SubtitleEntry>, locked: &[SubtitleEntry]) {
+    for entry in subtitles.iter_mut() {
+        entry.locked = locked.iter().any(|l| l.same_cue(entry));
+    }
+    
+    for cue in locked {
+        remove_subtitles_in_range(subtitles, cue.start_time, cue.end_time);
+    }
+    
+    let missing: Vec<SubtitleEntry> = locked.iter()
+        .filter(|l| !subtitles.iter().any(|s| s.same_cue(l)))
+        .map(|l| SubtitleEntry { locked: true, ..l.clone() })
+        .collect();
+    insert_subtitles(subtitles, missing);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn entry(start: f64, end: f64, text: &str) -> SubtitleEntry {
+        SubtitleEntry { index: 0, start_time: start, end_time: end, text: text.to_string(), locked: false, words: Vec::new() }
+    }
+
+    #[test]
+    fn test_format_srt_time_rounds() {
+        assert_eq!(SubtitleEntry::format_srt_time(5.9996), "00:00:06,000");
+        assert_eq!(SubtitleEntry::format_srt_time(59.9995), "00:01:00,000");
+        assert_eq!(SubtitleEntry::format_srt_time(3599.9999), "01:00:00,000");
+        assert_eq!(SubtitleEntry::format_srt_time(1.2344), "00:00:01,234");
+        assert_eq!(SubtitleEntry::format_srt_time(0.0), "00:00:00,000");
+    }
+    
+    #[test]
+    fn test_delete_many() {
+        let mut subs = vec![entry(0.0, 1.0, "a"), entry(1.0, 2.0, "b"), entry(2.0, 3.0, "c"), entry(3.0, 4.0, "d")];
+        reindex_subtitles(&mut subs);
+        subs[2].locked = true;
+        
+        // 重复和越界的下标忽略，锁定的字幕保留
+        assert_eq!(delete_many(&mut subs, &[0, 2, 3, 3, 9]), (2, 1));
+        assert_eq!(subs.iter().map(|s| s.text.as_str()).collect::<Vec<_>>(), ["b", "c"]);
+        assert_eq!(subs.iter().map(|s| s.index).collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(delete_many(&mut subs, &[]), (0, 0));
+    }
+    
+    #[test]
+    fn test_tagged_cues() {
+        let content = "1\n00:00:01,000 --> 00:00:05,000\n{\\an8}<i>hello there.</i>\n\n2\n00:00:05,000 --> 00:00:07,000\n<font color=\"#ff0000\">很好</font>\n\n";
+        let mut subs = parse_srt_content(content);
+        assert_eq!(subs[0].text, "{\\an8}<i>hello there.</i>");
+        assert_eq!(to_srt_string(&subs), content);
+        
+        // 规范化不改动标签
+        normalize_text(&mut subs, NormalizeRules::default());
+        assert_eq!(subs[0].text, "{\\an8}<i>Hello there.</i>");
+        assert_eq!(strip_tags(&subs[1].text), "很好");
+        
+        // 折行不拆开标签，标签不计入宽度
+        assert_eq!(wrap_text("<font color=\"red\">one two</font>", 7), "<font color=\"red\">one two</font>");
+        assert_eq!(wrap_text("<i>one two three</i>", 9), "<i>one two\nthree</i>");
+        
+        // 拆分后每段的标签都是完整的，位置标签复制到后一段
+        split_at(&mut subs, 0, 3.0).unwrap();
+        assert_eq!(subs[0].text, "{\\an8}<i>Hello</i>");
+        assert_eq!(subs[1].text, "{\\an8}<i>there.</i>");
+        
+        // 切换斜体/粗体
+        assert_eq!(toggle_style("{\\an8}<i>Hello</i>", "i"), "{\\an8}Hello");
+        assert_eq!(toggle_style("Hello", "b"), "<b>Hello</b>");
+        assert_eq!(toggle_style("<i>a</i> b <i>c</i>", "i"), "<i><i>a</i> b <i>c</i></i>");
+        
+        // 检查规范时按显示的字符计数
+        let tagged = vec![entry(0.0, 5.0, &format!("<font color=\"#ffffff\">{}</font>", "a".repeat(40)))];
+        assert!(check_standard(&tagged, SubtitleStandard::Netflix).is_empty());
+    }
+    
+    #[test]
+    fn test_parse_whisper_json() {
+        let json = r#"{"text": " Hello. World", "segments": [
+            {"id": 0, "start": 0.0004, "end": 1.23456, "text": " Hello."},
+            {"id": 1, "start": 1.5, "end": 2.0, "text": "  "},
+            {"id": 2, "start": 2.0, "end": 3.9996, "text": " World"}
+        ], "language": "en"}"#;
+        let subs = parse_whisper_json(json).unwrap();
+        assert_eq!(subs.len(), 2);
+        assert_eq!(subs[0].start_time, 0.0004);
+        assert_eq!(subs[0].end_time, 1.23456);
+        assert_eq!(subs[1].text, "World");
+        assert_eq!(subs[1].index, 2);
+        assert!(subs[0].words.is_empty());
+        
+        // --word_timestamps True 的输出
+        let json = r#"{"segments": [{"start": 0.5, "end": 1.5, "text": " Hello world", "words": [
+            {"word": " Hello", "start": 0.5, "end": 0.9, "probability": 0.98},
+            {"word": " world", "start": 0.9, "end": 1.5, "probability": 0.95}
+        ]}]}"#;
+        let subs = parse_whisper_json(json).unwrap();
+        assert_eq!(subs[0].words, [(0.5, 0.9, "Hello".to_string()), (0.9, 1.5, "world".to_string())]);
+        
+        assert!(parse_whisper_json(r#"{"text": ""}"#).is_err());
+        assert!(parse_whisper_json(r#"{"segments": [{"text": "x"}]}"#).is_err());
+    }
+    
+    #[test]
+    fn test_slice_range() {
+        let mut subs = vec![entry(8.0, 11.0, "a"), entry(12.0, 14.0, "b"), entry(19.0, 22.0, "c"), entry(25.0, 26.0, "d")];
+        reindex_subtitles(&mut subs);
+        
+        let sliced = slice_range(&subs, 10.0, 20.0, false, false);
+        assert_eq!(sliced.iter().map(|s| s.text.as_str()).collect::<Vec<_>>(), ["a", "b", "c"]);
+        assert_eq!((sliced[0].index, sliced[0].start_time), (1, 8.0));
+        
+        let clip = slice_range(&subs, 10.0, 20.0, true, true);
+        assert_eq!(clip.iter().map(|s| (s.index, s.start_time, s.end_time)).collect::<Vec<_>>(),
+            [(1, 0.0, 1.0), (2, 2.0, 4.0), (3, 9.0, 10.0)]);
+        
+        // 不截断时提前开始的字幕从 0 开始
+        let rebased = slice_range(&subs, 10.0, 20.0, true, false);
+        assert_eq!((rebased[0].start_time, rebased[0].end_time), (0.0, 1.0));
+        assert_eq!(rebased[2].end_time, 12.0);
+        assert_eq!(subs[0].start_time, 8.0);
+    }
+    
+    #[test]
+    fn test_pad() {
+        let mut subs = vec![entry(0.1, 1.0, "a"), entry(1.1, 2.0, "b"), entry(5.0, 6.0, "c"), entry(6.0, 7.0, "d")];
+        assert_eq!(pad(&mut subs, 0.0, 0.0), 0);
+        
+        assert_eq!(pad(&mut subs, 0.2, 0.3), 4);
+        // 开头不早于 0
+        assert_eq!(subs[0].start_time, 0.0);
+        // 0.1 秒的间隔按 3:2 分给前一条结尾和后一条开头
+        assert!((subs[0].end_time - 1.06).abs() < 1e-9);
+        assert!((subs[1].start_time - 1.06).abs() < 1e-9);
+        // 间隔足够时完整延长
+        assert!((subs[1].end_time - 2.3).abs() < 1e-9);
+        assert!((subs[2].start_time - 4.8).abs() < 1e-9);
+        // 紧挨着的字幕不变
+        assert_eq!(subs[2].end_time, 6.0);
+        assert_eq!(subs[3].start_time, 6.0);
+        assert!((subs[3].end_time - 7.3).abs() < 1e-9);
+        
+        // 锁定的字幕不变，相邻字幕可以用满间隔
+        let mut subs = vec![entry(0.0, 1.0, "a"), entry(1.1, 2.0, "b")];
+        subs[1].locked = true;
+        assert_eq!(pad(&mut subs, 0.2, 0.3), 1);
+        assert!((subs[0].end_time - 1.1).abs() < 1e-9);
+        assert_eq!(subs[1].start_time, 1.1);
+    }
+    
+    #[test]
+    fn test_split_long_duration() {
+        // 在静音处拆分
+        let mut subs = vec![entry(0.0, 20.0, "one two three four"), entry(20.0, 22.0, "short")];
+        let count = split_long_duration(&mut subs, 12.0, &[(9.0, 11.0)]);
+        assert_eq!(count, 1);
+        assert_eq!(subs.len(), 3);
+        assert_eq!(subs[0].end_time, 10.0);
+        assert_eq!(subs[1].start_time, 10.0);
+        assert_eq!(subs[0].text, "one two");
+        assert_eq!(subs[1].text, "three four");
+        assert_eq!(subs[2].index, 3);
+        
+        // 没有静音时平均拆分
+        let mut subs = vec![entry(0.0, 30.0, "一二三四五六")];
+        split_long_duration(&mut subs, 10.0, &[]);
+        assert_eq!(subs.len(), 3);
+        assert_eq!(subs[1].start_time, 10.0);
+        assert_eq!(subs[2].text, "五六");
+        
+        // 时间无效的字幕原样保留
+        let mut subs = vec![entry(0.0, f64::INFINITY, "broken"), entry(f64::NAN, 30.0, "also broken")];
+        assert_eq!(split_long_duration(&mut subs, 10.0, &[]), 0);
+        assert_eq!(subs.len(), 2);
+        assert_eq!(subs[0].text, "broken");
+    }
+    
+    #[test]
+    fn test_split_at() {
+        let mut subs = vec![entry(0.0, 4.0, "hello big world"), entry(4.0, 6.0, "next")];
+        split_at(&mut subs, 0, 1.0).unwrap();
+        assert_eq!(subs.len(), 3);
+        assert_eq!(subs[0].end_time, 1.0);
+        assert_eq!(subs[1].start_time, 1.0);
+        assert_eq!(subs[0].text, "hello");
+        assert_eq!(subs[1].text, "big world");
+        assert_eq!(subs[2].index, 3);
+        
+        // 拆分点不在字幕范围内
+        assert!(split_at(&mut subs, 2, 3.0).is_err());
+    }
+    
+    #[test]
+    fn test_delete_and_merge_with_next() {
+        let mut subs = vec![entry(0.0, 1.0, "hello"), entry(1.2, 2.0, "world"), entry(2.0, 3.0, "你好"), entry(3.0, 4.0, "世界")];
+        merge_with_next(&mut subs, 0).unwrap();
+        assert_eq!(subs[0].text, "hello world");
+        assert_eq!((subs[0].start_time, subs[0].end_time), (0.0, 2.0));
+        merge_with_next(&mut subs, 1).unwrap();
+        assert_eq!(subs[1].text, "你好世界");
+        assert_eq!(subs[1].index, 2);
+        
+        // 最后一条没有下一条可合并
+        assert!(merge_with_next(&mut subs, 1).is_err());
+        
+        assert_eq!(delete_at(&mut subs, 0).unwrap().text, "hello world");
+        assert_eq!(subs.len(), 1);
+        assert_eq!(subs[0].index, 1);
+        
+        subs[0].locked = true;
+        assert!(delete_at(&mut subs, 0).is_err());
+    }
+    
+    #[test]
+    fn test_split_long_cues() {
+        let mut subs = vec![
+            entry(0.0, 6.0, "一二三四五六七八九十一二三四五六七八九十一二三四五六七八九十"),
+            entry(6.0, 8.0, "<i>short</i>"),
+        ];
+        assert_eq!(split_long_cues(&mut subs, 12), 1);
+        let texts: Vec<&str> = subs.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(texts, ["一二三四五六七八九十", "一二三四五六七八九十", "一二三四五六七八九十", "<i>short</i>"]);
+        assert_eq!(subs.iter().map(|s| (s.start_time, s.end_time)).collect::<Vec<_>>(),
+            [(0.0, 2.0), (2.0, 4.0), (4.0, 6.0), (6.0, 8.0)]);
+        assert_eq!(subs.iter().map(|s| s.index).collect::<Vec<_>>(), [1, 2, 3, 4]);
+        
+        // 英文在空格处切分，时长按字数分配
+        let mut subs = vec![entry(0.0, 3.0, "aaaa bbbbbbbbbbbbb")];
+        assert_eq!(split_long_cues(&mut subs, 10), 1);
+        assert_eq!(subs[0].text, "aaaa");
+        assert_eq!(subs[1].text, "bbbbbbbbbbbbb");
+        assert!((subs[0].end_time - 3.0 * 4.0 / 17.0).abs() < 1e-9);
+        
+        subs[1].locked = true;
+        assert_eq!(split_long_cues(&mut subs, 5), 0);
+    }
+    
+    #[test]
+    fn test_find_and_fix_overlaps() {
+        let mut subs = vec![
+            entry(0.0, 2.0, "a"),
+            entry(2.0, 3.0, "touches a"),
+            entry(2.5, 5.0, "overlaps b"),
+            entry(4.0, 4.5, "inside c"),
+            entry(6.0, 7.0, "same start"),
+            entry(6.0, 6.5, "same start"),
+        ];
+        assert_eq!(find_overlaps(&subs), [(1, 2), (2, 3), (4, 5)]);
+        
+        // c 被截到 d 开始；同时开始的 e、f 不修改
+        assert_eq!(fix_overlaps(&mut subs), 2);
+        assert_eq!(subs[1].end_time, 2.5);
+        assert_eq!(subs[2].end_time, 4.0);
+        assert_eq!(find_overlaps(&subs), [(4, 5)]);
+        
+        let mut subs = vec![entry(0.0, 2.0, "locked"), entry(1.0, 3.0, "b")];
+        subs[0].locked = true;
+        assert_eq!(fix_overlaps(&mut subs), 0);
+    }
+    
+    #[test]
+    fn test_rescale_time() {
+        let mut subs = vec![entry(10.0, 12.0, "a"), entry(100.0, 101.0, "b")];
+        rescale_time(&mut subs, 25.0 / 23.976);
+        assert!((subs[1].start_time - 100.0 * 25.0 / 23.976).abs() < 1e-9);
+        
+        // 10 秒处应为 11 秒，100 秒处应为 105.5 秒：factor 1.05，offset 0.5
+        let mut subs = vec![entry(0.0, 1.0, "a"), entry(10.0, 12.0, "b"), entry(100.0, 101.0, "c")];
+        let (factor, offset) = rescale_from_anchors(&mut subs, 10.0, 11.0, 100.0, 105.5).unwrap();
+        assert!((factor - 1.05).abs() < 1e-9 && (offset - 0.5).abs() < 1e-9);
+        assert!((subs[1].start_time - 11.0).abs() < 1e-9);
+        assert!((subs[2].end_time - 106.55).abs() < 1e-9);
+        assert!((subs[0].start_time - 0.5).abs() < 1e-9);
+        
+        // 偏移为负时不早于 0
+        let (_, offset) = rescale_from_anchors(&mut subs, 11.0, 10.0, 105.5, 104.5).unwrap();
+        assert_eq!(offset, -1.0);
+        assert_eq!(subs[0].start_time, 0.0);
+        
+        assert!(rescale_from_anchors(&mut subs, 5.0, 6.0, 5.0, 7.0).is_err());
+        assert!(rescale_from_anchors(&mut subs, 5.0, 20.0, 10.0, 15.0).is_err());
+    }
+    
+    #[test]
+    fn test_merge_short_cues() {
+        let mut subs = vec![
+            entry(0.0, 0.4, "So"),
+            entry(0.5, 0.9, "what"),
+            entry(1.0, 1.6, "happened?"),
+            entry(3.0, 3.5, "Well,"),  // 间隔太大
+            entry(3.6, 9.0, "it's a long story"),  // 合并后超过最大时长
+            entry(9.1, 9.5, "我们"),
+            entry(9.6, 10.0, "走吧"),
+        ];
+        subs[6].locked = true;
+        
+        assert_eq!(merge_short_cues(&mut subs, 0.3, 5.0), 2);
+        let texts: Vec<&str> = subs.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(texts, ["So what happened?", "Well,", "it's a long story", "我们", "走吧"]);
+        assert_eq!((subs[0].start_time, subs[0].end_time), (0.0, 1.6));
+        assert_eq!(subs.iter().map(|s| s.index).collect::<Vec<_>>(), [1, 2, 3, 4, 5]);
+        
+        subs[4].locked = false;
+        assert_eq!(merge_short_cues(&mut subs, 0.3, 5.0), 1);
+        assert_eq!(subs[3].text, "我们走吧");
+    }
+    
+    #[test]
+    fn test_interleave_tracks() {
+        let left = vec![entry(0.0, 2.0, "question"), entry(5.0, 6.0, "thanks")];
+        let right = vec![entry(2.5, 4.5, "answer"), entry(5.0, 5.5, "sure")];
+        let merged = interleave_tracks(vec![("Left".to_string(), left), ("Right".to_string(), right)]);
+        
+        let texts: Vec<&str> = merged.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(texts, vec!["Left: question", "Right: answer", "Left: thanks", "Right: sure"]);
+        assert_eq!(merged[3].index, 4);
+    }
+    
+    #[test]
+    fn test_apply_locked() {
+        let corrected = SubtitleEntry { locked: true, ..entry(2.0, 4.0, "corrected") };
+        
+        // 重新识别生成的字幕覆盖了校对过的时间段
+        let mut subs = vec![entry(0.0, 2.0, "a"), entry(1.5, 3.0, "wrong"), entry(3.5, 5.0, "also wrong"), entry(5.0, 6.0, "b")];
+        apply_locked(&mut subs, std::slice::from_ref(&corrected));
+        assert_eq!(subs.len(), 3);
+        assert_eq!(subs[1].text, "corrected");
+        assert!(subs[1].locked);
+        assert_eq!(subs[2].index, 3);
+        
+        // 锁定的字幕不会被拆分或被重叠的新字幕覆盖
+        assert!(split_at(&mut subs, 1, 3.0).is_err());
+        assert_eq!(split_long_duration(&mut subs, 1.0, &[]), 1);
+        assert!(subs.iter().any(|s| s.same_cue(&corrected)));
+        assert_eq!(insert_subtitles(&mut subs, vec![entry(3.0, 3.5, "new")]), 0);
+    }
+    
+    #[test]
+    fn test_check_standard() {
+        let mut subs = vec![
+            entry(0.0, 3.0, "short line"),
+            entry(3.0, 3.5, "this cue is far too fast to read comfortably"),
+            entry(4.0, 12.0, "one\ntwo\nthree"),
+        ];
+        reindex_subtitles(&mut subs);
+        
+        let violations = check_standard(&subs, SubtitleStandard::Netflix);
+        assert!(violations.iter().all(|v| v.index != 1));
+        assert!(violations.iter().any(|v| v.index == 2 && v.reason.contains("chars per line")));
+        assert!(violations.iter().any(|v| v.index == 2 && v.reason.contains("chars/s")));
+        assert!(violations.iter().any(|v| v.index == 2 && v.reason.contains("too short")));
+        assert!(violations.iter().any(|v| v.index == 3 && v.reason.contains("3 lines")));
+        assert!(violations.iter().any(|v| v.index == 3 && v.reason.contains("too long")));
+    }
+    
+    #[test]
+    fn test_wrap_text() {
+        assert_eq!(wrap_text("the quick brown fox jumps", 10), "the quick\nbrown fox\njumps");
+        assert_eq!(wrap_text("一二三\n四五", 8), "一二三四\n五");
+        assert_eq!(wrap_text("short", 42), "short");
+        assert_eq!(wrap_text("我用Rust写", 6), "我用Rust\n写");
+    }
+    
+    #[test]
+    fn test_wrap_cjk_long_line() {
+        // 全角字符算 2：宽度 8 每行 4 个汉字
+        assert_eq!(wrap_text("一二三四五六", 8), "一二三四\n五六");
+        
+        // 优先在最后一个标点后断行
+        let sentence = "今天我们来讨论一下字幕的断行问题，这句话没有空格而且非常长，需要按标点拆开才好读。";
+        let wrapped = wrap_text(sentence, 42);
+        assert_eq!(wrapped, "今天我们来讨论一下字幕的断行问题，\n这句话没有空格而且非常长，\n需要按标点拆开才好读。");
+        assert!(wrapped.lines().all(|l| display_width(l) <= 42));
+        assert_eq!(wrapped.replace('\n', ""), sentence);
+        
+        // 没有标点时按宽度硬切，标点不放在行首
+        assert_eq!(wrap_text("一二三四五六七八九十", 8), "一二三四\n五六七八\n九十");
+        assert_eq!(wrap_text("一二三四。五六", 8), "一二三\n四。五六");
+        
+        // 以中文为主的字幕按显示宽度检查
+        let subs = vec![SubtitleEntry { index: 1, start_time: 0.0, end_time: 6.0, text: "字".repeat(22), locked: false, words: Vec::new() }];
+        assert!(check_standard(&subs, SubtitleStandard::Netflix).iter().any(|v| v.reason.contains("44 chars per line")));
+    }
+    
+    #[test]
+    fn test_search() {
+        assert_eq!(find_matches("Hello hello HELLO", "hello"), vec![0..5, 6..11, 12..17]);
+        assert_eq!(find_matches("字幕测试字幕", "字幕"), vec![0..6, 12..18]);
+        assert!(find_matches("abc", "").is_empty());
+        
+        let subs = vec![entry(0.0, 1.0, "The Rust book"), entry(1.0, 2.0, "nothing"), entry(2.0, 3.0, "rusty\nnail")];
+        assert_eq!(search(&subs, "RUST"), vec![0, 2]);
+    }
+    
+    #[test]
+    fn test_quantize_to_fps() {
+        let mut subs = vec![entry(1.01, 2.49, "a"), entry(3.0, 3.001, "b")];
+        quantize_to_fps(&mut subs, 25.0);
+        assert!((subs[0].start_time - 1.0).abs() < 1e-9);
+        assert!((subs[0].end_time - 2.48).abs() < 1e-9);
+        // 不足一帧的字幕保留一帧
+        assert!((subs[1].end_time - 3.04).abs() < 1e-9);
+    }
+    
+    #[test]
+    fn test_reindex_from() {
+        let mut subs = vec![entry(0.0, 1.0, "a"), entry(1.0, 2.0, "b")];
+        reindex_from(&mut subs, 101);
+        assert_eq!(subs.iter().map(|e| e.index).collect::<Vec<_>>(), vec![101, 102]);
+    }
+    
+    #[test]
+    fn test_uncovered_tail() {
+        let subs = vec![entry(0.0, 10.0, "a"), entry(5.0, 30.0, "b"), entry(20.0, 25.0, "c")];
+        assert!((uncovered_tail(&subs, 100.0) - 70.0).abs() < 1e-9);
+        assert_eq!(uncovered_tail(&subs, 20.0), 0.0);
+        assert_eq!(uncovered_tail(&[], 42.0), 42.0);
+    }
+    
+    #[test]
+    fn test_strip_nonspeech() {
+        let patterns: Vec<String> = DEFAULT_NONSPEECH_PATTERNS.iter().map(|p| p.to_string()).collect();
+        let mut subs = vec![
+            entry(0.0, 2.0, "[音乐]"),
+            entry(2.0, 4.0, "[音乐] 你好"),
+            entry(4.0, 6.0, "Hello (laughs) there"),
+            entry(6.0, 8.0, "♪♪"),
+            entry(8.0, 9.0, "(笑)\n真的吗"),
+            entry(9.0, 10.0, "<i>[Music]</i>"),
+            entry(10.0, 11.0, "a [b\nc] d"),
+        ];
+        let mut locked = entry(11.0, 12.0, "[掌声]");
+        locked.locked = true;
+        subs.push(locked);
+        reindex_subtitles(&mut subs);
+        
+        // 混合字幕只去掉标注，只有标注的字幕整条删除，锁定的字幕不变
+        assert_eq!(strip_nonspeech(&mut subs, &patterns), (6, 3));
+        let texts: Vec<&str> = subs.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(texts, ["你好", "Hello there", "真的吗", "a [b\nc] d", "[掌声]"]);
+        assert_eq!(subs.last().unwrap().index, 5);
+        
+        // 不填模式时保留所有标注
+        let mut kept = vec![entry(0.0, 1.0, "[音乐]")];
+        assert_eq!(strip_nonspeech(&mut kept, &[]), (0, 0));
+        assert_eq!(parse_nonspeech_patterns(" [*]  ♪ "), vec!["[*]", "♪"]);
+    }
+    
+    #[test]
+    fn test_normalize_text() {
+        let mut subs = vec![
+            entry(0.0, 1.0, "  hello   world.  it costs 3.5 dollars "),
+            entry(1.0, 2.0, "and more"),
+            entry(2.0, 3.0, "你好, 世界!价格是3.5元..."),
+        ];
+        assert_eq!(normalize_text(&mut subs, NormalizeRules::default()), 2);
+        assert_eq!(subs[0].text, "Hello world. It costs 3.5 dollars");
+        // 上一条没有句末标点，不是句首
+        assert_eq!(subs[1].text, "and more");
+        assert_eq!(subs[2].text, "你好，世界！价格是3.5元...");
+        
+        // 锁定的字幕不修改
+        let mut locked = vec![SubtitleEntry { locked: true, ..entry(0.0, 1.0, "a  b") }];
+        assert_eq!(normalize_text(&mut locked, NormalizeRules::default()), 0);
+        assert_eq!(locked[0].text, "a  b");
+    }
+    
+    #[test]
+    fn test_is_complete_srt() {
+        let dir = std::env::temp_dir().join(format!("whisper_srt_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("segment.srt");
+        let full = "1\n00:00:00,000 --> 00:00:01,000\nHello\n\n2\n00:00:01,000 --> 00:00:02,500\nWorld\n\n";
+        
+        fs::write(&path, full).unwrap();
+        assert!(is_complete_srt(&path));
+        
+        // 写到一半被截断
+        fs::write(&path, &full[..full.len() - 12]).unwrap();
+        assert!(!is_complete_srt(&path));
+        fs::write(&path, &full[..full.len() - 8]).unwrap();
+        assert!(!is_complete_srt(&path));
+        
+        // 非 UTF-8 编码的完整字幕
+        let (gbk, _, _) = encoding_rs::GBK.encode("1\n00:00:00,000 --> 00:00:01,000\n你好，世界\n\n");
+        fs::write(&path, &gbk).unwrap();
+        assert!(is_complete_srt(&path));
+        
+        // 静音片段没有字幕块，算作已完成；不存在的文件未完成
+        fs::write(&path, "").unwrap();
+        assert!(is_complete_srt(&path));
+        fs::write(&path, "\n\n").unwrap();
+        assert!(is_complete_srt(&path));
+        assert!(!is_complete_srt(&dir.join("missing.srt")));
+        
+        fs::remove_dir_all(&dir).unwrap();
+    }
+    
+    #[test]
+    fn test_srt_round_trip() {
+        let srt = "1\n00:00:01,000 --> 00:00:02,500\n<i>First line</i>\nSecond line\n\n2\n00:00:03,000 --> 00:00:04,000\n单行\n\n";
+        let subs = parse_srt_content(srt);
+        assert_eq!(subs.len(), 2);
+        assert_eq!(subs[0].text, "<i>First line</i>\nSecond line");
+        assert_eq!(to_srt_string(&subs), srt);
+        
+        // BOM、CRLF 和多余的空行读入后写出相同的内容
+        let messy = format!("\u{feff}{}\r\n \r\n", srt.replace('\n', "\r\n").replace("\r\n\r\n2", "\r\n \r\n\r\n2"));
+        assert_eq!(to_srt_string(&parse_srt_content(&messy)), srt);
+        
+        // 编辑后文本中的空行不会拆散字幕块
+        let mut edited = subs.clone();
+        edited[0].text = "First line\n\nSecond line\n".to_string();
+        let written = to_srt_string(&edited);
+        assert!(written.starts_with("1\n00:00:01,000 --> 00:00:02,500\nFirst line\nSecond line\n\n2\n"));
+        assert_eq!(to_srt_string(&parse_srt_content(&written)), written);
+    }
+    
+    #[test]
+    fn test_parse_srt_bom_crlf() {
+        // Windows 工具导出的 SRT：带 BOM、CRLF 换行
+        let subs = parse_srt_content("\u{FEFF}1\r\n00:00:01,000 --> 00:00:02,000\r\nHello\r\n");
+        assert_eq!(subs.len(), 1);
+        assert_eq!(subs[0].index, 1);
+        assert_eq!((subs[0].start_time, subs[0].end_time), (1.0, 2.0));
+        assert_eq!(subs[0].text, "Hello");
+    }
+    
+    #[test]
+    fn test_parse_srt_without_numbers() {
+        // 第一块缺少序号，第三块的序号不是数字
+        let srt = "00:00:01,000 --> 00:00:02,000\nNo number\n\n2\n00:00:03,000 --> 00:00:04,000\nNumbered\n\n#3\n00:00:05,000 --> 00:00:06,000\nMarker\n\n7\n00:00:07,000 --> 00:00:08,000\nKept index\n";
+        let subs = parse_srt_content(srt);
+        let summary: Vec<(usize, &str)> = subs.iter().map(|s| (s.index, s.text.as_str())).collect();
+        assert_eq!(summary, [(1, "No number"), (2, "Numbered"), (3, "Marker"), (7, "Kept index")]);
+        assert_eq!((subs[0].start_time, subs[0].end_time), (1.0, 2.0));
+        
+        // 没有有效时间行的块仍然跳过
+        assert!(parse_srt_content("1\nno time\ntext\n").is_empty());
+    }
+    
+    #[test]
+    fn test_csv_json_round_trip() {
+        let mut subs = vec![
+            SubtitleEntry { index: 1, ..entry(0.5, 1.25, "Hello, \"world\"") },
+            SubtitleEntry { index: 2, ..entry(61.0, 62.5, "两行\n字幕") },
+            SubtitleEntry { index: 3, ..entry(63.0, 64.0, "plain") },
+        ];
+        
+        let csv = export_csv(&subs);
+        assert!(csv.contains("1,00:00:00.500,00:00:01.250,\"Hello, \"\"world\"\"\"\r\n"));
+        assert_eq!(import_csv(&csv).unwrap(), subs);
+        
+        let json = export_json(&subs).unwrap();
+        assert!(!json.contains("words"));
+        assert_eq!(import_json(&json).unwrap(), subs);
+        
+        // 逐词时间按中点分到字幕，截到字幕范围内，并随 JSON 导出
+        let words = [(0.45, 0.8, "Hello,".to_string()), (0.8, 1.3, "world".to_string()), (61.2, 61.6, "两行".to_string())];
+        assert_eq!(attach_words(&mut subs, &words), 3);
+        assert_eq!(subs[0].words, [(0.5, 0.8, "Hello,".to_string()), (0.8, 1.25, "world".to_string())]);
+        assert!(subs[2].words.is_empty());
+        for entry in &subs {
+            assert!(entry.words.iter().all(|(start, end, _)| *start >= entry.start_time && *end <= entry.end_time));
+        }
+        let json = export_json(&subs).unwrap();
+        assert!(json.contains("\"word\": \"两行\""));
+        assert_eq!(import_json(&json).unwrap(), subs);
+        
+        // 保存到文件再读回，时间误差在 1ms 以内
+        let path = std::env::temp_dir().join(format!("whisper_json_test_{}.json", std::process::id()));
+        subs[1].start_time = 61.000_4;
+        save_json_file(&path, &subs).unwrap();
+        let loaded = load_json_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.len(), subs.len());
+        for (loaded, saved) in loaded.iter().zip(&subs) {
+            assert_eq!((loaded.index, &loaded.text), (saved.index, &saved.text));
+            assert!((loaded.start_time - saved.start_time).abs() < 0.001);
+            assert!((loaded.end_time - saved.end_time).abs() < 0.001);
+            assert_eq!(loaded.words.len(), saved.words.len());
+            for (a, b) in loaded.words.iter().zip(&saved.words) {
+                assert!((a.0 - b.0).abs() < 0.001 && (a.1 - b.1).abs() < 0.001 && a.2 == b.2);
+            }
+        }
+        
+        // 秒数形式的时间、无表头
+        let imported = import_csv("7,1.5,2,text").unwrap();
+        assert_eq!(imported, vec![SubtitleEntry { index: 7, ..entry(1.5, 2.0, "text") }]);
+        assert!(import_csv("1,0,1,\"unterminated").is_err());
+    }
+    
+    #[test]
+    fn test_export_plaintext() {
+        let latin = vec![
+            entry(0.0, 1.0, "<i>This is</i>"),
+            entry(1.0, 2.0, "one sentence."),
+            entry(2.0, 3.0, "  Another\nline  "),
+            entry(3.0, 4.0, ""),
+        ];
+        assert_eq!(plaintext_join(&latin), " ");
+        assert_eq!(export_plaintext(&latin, " "), "This is one sentence.\nAnother line\n");
+        
+        let cjk = vec![
+            entry(0.0, 1.0, "今天天气"),
+            entry(1.0, 2.0, "很好。"),
+            entry(2.0, 3.0, "我们出去\n走走吧"),
+        ];
+        assert_eq!(plaintext_join(&cjk), "");
+        assert_eq!(export_plaintext(&cjk, ""), "今天天气很好。\n我们出去走走吧\n");
+        // 每条字幕一行
+        assert_eq!(export_plaintext(&cjk, "\n"), "今天天气\n很好。\n我们出去\n走走吧\n");
+        assert_eq!(export_plaintext(&[], " "), "");
+    }
+}
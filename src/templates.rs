@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+use whisper_gui::{srt_merger, subtitle, whisper, WhisperLanguage, WhisperModel};
+
+/// 命名的设置模板：识别参数、语音检测阈值和字幕规范，一键应用
+/// 
+/// 随程序设置保存在 eframe 的配置目录中。`file_pattern` 非空时，拖入文件名匹配的文件会自动应用
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SettingsTemplate {
+    pub name: String,
+    pub file_pattern: String,  // 文件名通配符（`*`、`?`，不区分大小写），如 `*podcast*`
+    pub model: WhisperModel,
+    pub language: WhisperLanguage,
+    pub custom_language_code: String,
+    pub threads: usize,
+    pub precise_timing: bool,
+    pub whisper_options: whisper::WhisperOptions,
+    pub vad_threshold: f32,
+    pub subtitle_standard: subtitle::SubtitleStandard,
+    pub normalize_rules: subtitle::NormalizeRules,
+    pub max_cue_seconds: f64,
+    pub output_encoding: srt_merger::OutputEncoding,
+    pub dedupe_boundaries: bool,
+}
+
+/// 第一个 `file_pattern` 与文件名匹配的模板
+pub fn find_for_file<'a>(templates: &'a [SettingsTemplate], file_name: &str) -> Option<&'a SettingsTemplate> {
+    templates.iter()
+        .find(|template| !template.file_pattern.trim().is_empty() && matches_pattern(template.file_pattern.trim(), file_name))
+}
+
+/// 简单通配符匹配：`*` 匹配任意多个字符，`?` 匹配一个字符，不区分大小写
+fn matches_pattern(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    
+    // matched[j]：模板已处理的部分能否匹配 text 的前 j 个字符
+    let mut matched = vec![false; text.len() + 1];
+    matched[0] = true;
+    for p in pattern {
+        let mut next = vec![false; text.len() + 1];
+        for j in 0..=text.len() {
+            next[j] = match p {
+                '*' => matched[j] || (j > 0 && next[j - 1]),
+                '?' => j > 0 && matched[j - 1],
+                c => j > 0 && matched[j - 1] && text[j - 1] == c,
+            };
+        }
+        matched = next;
+    }
+    
+    matched[text.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_find_for_file() {
+        assert!(matches_pattern("*podcast*", "My Podcast EP12.mp3"));
+        assert!(matches_pattern("lecture_??.mp4", "Lecture_07.mp4"));
+        assert!(!matches_pattern("lecture_??.mp4", "lecture_7.mp4"));
+        assert!(!matches_pattern("*.mkv", "talk.mp4"));
+        
+        let template = |name: &str, pattern: &str| SettingsTemplate {
+            name: name.to_string(),
+            file_pattern: pattern.to_string(),
+            ..Default::default()
+        };
+        let templates = vec![template("Manual", ""), template("Lectures", "lecture*"), template("Any", "*")];
+        assert_eq!(find_for_file(&templates, "lecture_01.mp4").unwrap().name, "Lectures");
+        assert_eq!(find_for_file(&templates, "interview.wav").unwrap().name, "Any");
+        assert!(find_for_file(&templates[..2], "interview.wav").is_none());
+    }
+}
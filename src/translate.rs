@@ -0,0 +1,204 @@
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::Sender;
+use std::time::Duration;
+use crate::error::{Result, WhisperError};
+use crate::subtitle::SubtitleEntry;
+use crate::whisper::CancelToken;
+use crate::ProgressMessage;
+
+/// 翻译命令失败后第一次重试前的等待时间，之后每次加倍（应对限流）
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+/// 翻译一条字幕的默认超时时间
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// 翻译参数
+#[derive(Debug, Clone)]
+pub struct TranslateOptions {
+    /// 通过系统 shell 运行的命令，从标准输入读取一条字幕的文本，把译文写到标准输出，
+    /// 如 `trans -b :zh` 或调用本地大模型的脚本
+    pub command: String,
+    /// 每条字幕最多尝试的次数
+    pub max_attempts: usize,
+    /// 单次运行命令的超时时间，超时后终止命令，按失败处理
+    pub timeout: Duration,
+}
+
+impl Default for TranslateOptions {
+    fn default() -> Self {
+        Self {
+            command: String::new(),
+            max_attempts: 3,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+}
+
+/// 翻译结果
+#[derive(Debug, Clone)]
+pub struct Translation {
+    pub subtitles: Vec<SubtitleEntry>,  // 时间不变的译文副本，失败的字幕保留原文
+    pub failures: Vec<(usize, String)>,  // 翻译失败的字幕序号及原因
+}
+
+/// 逐条翻译字幕，返回时间不变的译文副本和失败的字幕
+/// 
+/// 某条字幕多次尝试仍失败时保留原文并继续翻译其余字幕，不会中断整批。
+/// 进度和每条的结果通过 `tx` 报告；取消时返回 `WhisperError::Cancelled`
+pub fn translate_subtitles(
+    subtitles: &[SubtitleEntry],
+    options: &TranslateOptions,
+    cancel: &CancelToken,
+    tx: &Sender<ProgressMessage>,
+) -> Result<Translation> {
+    if options.command.trim().is_empty() {
+        return Err(WhisperError::InvalidInput("Translation command is empty".to_string()));
+    }
+    
+    let mut translated = Vec::with_capacity(subtitles.len());
+    let mut failures = Vec::new();
+    
+    for (i, entry) in subtitles.iter().enumerate() {
+        if cancel.is_cancelled() {
+            return Err(WhisperError::Cancelled);
+        }
+        
+        let mut entry = entry.clone();
+        match translate_with_retry(&entry.text, options, cancel) {
+            Ok(text) => {
+                let _ = tx.send(ProgressMessage::RealtimeOutput(format!("{} → {}", entry.text.replace('\n', " "), text.replace('\n', " "))));
+                entry.text = text;
+            }
+            Err(WhisperError::Cancelled) => return Err(WhisperError::Cancelled),
+            Err(e) => {
+                let _ = tx.send(ProgressMessage::Error(format!("Failed to translate cue {}: {}", entry.index, e)));
+                failures.push((entry.index, e.to_string()));
+            }
+        }
+        translated.push(entry);
+        let _ = tx.send(ProgressMessage::Progress { current: i + 1, total: subtitles.len() });
+    }
+    
+    Ok(Translation { subtitles: translated, failures })
+}
+
+/// 翻译一条文本，失败时等待后重试，等待时间每次加倍
+fn translate_with_retry(text: &str, options: &TranslateOptions, cancel: &CancelToken) -> Result<String> {
+    let mut delay = RETRY_DELAY;
+    let mut attempt = 1;
+    loop {
+        match run_translator(&options.command, text, options.timeout, cancel) {
+            Ok(translation) => return Ok(translation),
+            // 命令本身不存在时重试没有意义
+            Err(e) if attempt >= options.max_attempts || matches!(e, WhisperError::ToolNotFound { .. } | WhisperError::Cancelled) => return Err(e),
+            Err(_) => {
+                std::thread::sleep(delay);
+                if cancel.is_cancelled() {
+                    return Err(WhisperError::Cancelled);
+                }
+                delay *= 2;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// 用系统 shell 运行翻译命令，文本写入标准输入，返回去掉首尾空白的标准输出
+/// 
+/// 命令交给 `cancel` 持有，取消时立即终止；超过 `timeout` 仍未结束时终止并返回错误
+fn run_translator(command: &str, text: &str, timeout: Duration, cancel: &CancelToken) -> Result<String> {
+    let (shell, flag) = if cfg!(windows) { ("cmd", "/C") } else { ("sh", "-c") };
+    let mut child = Command::new(shell)
+        .args([flag, command])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| WhisperError::spawn(shell, e))?;
+    
+    if let Some(mut stdin) = child.stdin.take() {
+        // 命令可能不读取输入就退出，写入失败不算错误，以退出状态为准
+        let _ = stdin.write_all(text.as_bytes());
+    }
+    
+    // 在后台读取输出，避免管道写满后命令卡住
+    let stdout = read_in_background(child.stdout.take());
+    let stderr = read_in_background(child.stderr.take());
+    let Some(status) = cancel.wait_timeout(child, timeout)? else {
+        return Err(WhisperError::process_failed("Translation command timed out",
+            format!("no result after {} s", timeout.as_secs_f64())));
+    };
+    
+    if !status.success() {
+        let stderr = stderr.join().unwrap_or_default();
+        return Err(WhisperError::process_failed("Translation command failed", String::from_utf8_lossy(&stderr)));
+    }
+    let stdout = stdout.join().unwrap_or_default();
+    let translation = String::from_utf8_lossy(&stdout).trim().to_string();
+    if translation.is_empty() {
+        return Err(WhisperError::ParseError("Translation command printed nothing".to_string()));
+    }
+    
+    Ok(translation)
+}
+
+/// 在后台线程读完管道的全部内容
+fn read_in_background(pipe: Option<impl Read + Send + 'static>) -> std::thread::JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut buffer = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buffer);
+        }
+        buffer
+    })
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::channel;
+    
+    #[test]
+    fn test_translate_subtitles() {
+        let cue = |index: usize, text: &str| SubtitleEntry {
+            index,
+            start_time: index as f64,
+            end_time: index as f64 + 0.5,
+            text: text.to_string(),
+            locked: false,
+            words: Vec::new(),
+        };
+        let subtitles = vec![cue(1, "hello\nworld"), cue(2, "skip me"), cue(3, "bye")];
+        let options = TranslateOptions {
+            // 第 2 条失败，其余转成大写
+            command: "input=$(cat); case \"$input\" in skip*) exit 3;; esac; echo \"$input\" | tr a-z A-Z".to_string(),
+            max_attempts: 1,
+            ..Default::default()
+        };
+        
+        let (tx, rx) = channel();
+        let translation = translate_subtitles(&subtitles, &options, &CancelToken::default(), &tx).unwrap();
+        let (translated, failures) = (translation.subtitles, translation.failures);
+        assert_eq!(translated[0].text, "HELLO\nWORLD");
+        assert_eq!(translated[0].start_time, 1.0);
+        assert_eq!(translated[1].text, "skip me");
+        assert_eq!(translated[2].text, "BYE");
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].0, 2);
+        assert!(rx.try_iter().any(|m| matches!(m, ProgressMessage::Progress { current: 3, total: 3 })));
+        
+        // 卡住的命令超时后终止，按失败处理
+        let hung = TranslateOptions {
+            command: "sleep 30".to_string(),
+            max_attempts: 1,
+            timeout: Duration::from_millis(200),
+        };
+        let start = std::time::Instant::now();
+        let translation = translate_subtitles(&subtitles[..1], &hung, &CancelToken::default(), &tx).unwrap();
+        assert!(translation.failures[0].1.contains("timed out"));
+        assert!(start.elapsed() < Duration::from_secs(5));
+        
+        let empty = TranslateOptions::default();
+        assert!(translate_subtitles(&subtitles, &empty, &CancelToken::default(), &tx).is_err());
+    }
+}
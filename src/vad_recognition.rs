@@ -0,0 +1,225 @@
+use rodio::{Decoder, Source};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use anyhow::Result;
+
+/// 语音区域列表 (开始秒数, 结束秒数)
+pub type SpeechRegions = Vec<(f64, f64)>;
+
+/// 默认能量阈值（归一化 RMS）
+pub const DEFAULT_THRESHOLD: f32 = 0.02;
+
+/// 每帧时长（秒）
+const FRAME_SECONDS: f64 = 0.03;
+/// 间隔小于该值的相邻语音区域合并（秒）
+const MIN_GAP_SECONDS: f64 = 0.3;
+/// 短于该值的语音区域视为噪声丢弃（秒）
+const MIN_REGION_SECONDS: f64 = 0.2;
+/// 所有帧都不超过该能量时视为完全静音
+const SILENCE_FLOOR: f32 = 0.001;
+/// 查找最响区域时的窗口时长（秒）
+const LOUDEST_WINDOW_SECONDS: f64 = 3.0;
+
+/// 基于能量的语音区域预览
+/// 
+/// 解码音频并按 30ms 分帧计算 RMS（归一化到 0~1），超过 `threshold` 的帧视为语音。
+/// 只是 Silero VAD 的粗略近似，用于在识别前快速调节阈值
+pub fn preview_speech_regions(audio_path: &Path, threshold: f32) -> Result<SpeechRegions> {
+    let energies = frame_energies(audio_path)?;
+    Ok(detect_regions(&energies, FRAME_SECONDS, threshold))
+}
+
+/// 查找平均能量最高的 3 秒区域，返回 (开始秒数, 结束秒数)
+/// 
+/// 用于加载后快速试听有代表性的音频；完全静音时返回 None
+pub fn loudest_region(audio_path: &Path) -> Result<Option<(f64, f64)>> {
+    let energies = frame_energies(audio_path)?;
+    Ok(find_loudest_window(&energies, FRAME_SECONDS, LOUDEST_WINDOW_SECONDS))
+}
+
+/// 解码音频并计算每帧的 RMS（归一化到 0~1）
+fn frame_energies(audio_path: &Path) -> Result<Vec<f32>> {
+    let file = File::open(audio_path)?;
+    let source = Decoder::new(BufReader::new(file))?;
+    
+    let channels = source.channels().max(1) as usize;
+    let sample_rate = source.sample_rate().max(1) as f64;
+    let samples_per_frame = ((sample_rate * FRAME_SECONDS) as usize).max(1) * channels;
+    
+    let mut energies = Vec::new();
+    let mut sum_squares = 0.0f64;
+    let mut count = 0usize;
+    
+    for sample in source {
+        let value = sample as f64 / i16::MAX as f64;
+        sum_squares += value * value;
+        count += 1;
+        
+        if count == samples_per_frame {
+            energies.push((sum_squares / count as f64).sqrt() as f32);
+            sum_squares = 0.0;
+            count = 0;
+        }
+    }
+    
+    if count > 0 {
+        energies.push((sum_squares / count as f64).sqrt() as f32);
+    }
+    
+    Ok(energies)
+}
+
+/// 滑动窗口查找能量总和最大的区域
+fn find_loudest_window(energies: &[f32], frame_seconds: f64, window_seconds: f64) -> Option<(f64, f64)> {
+    if energies.iter().all(|&e| e <= SILENCE_FLOOR) {
+        return None;
+    }
+    
+    let window = ((window_seconds / frame_seconds).round() as usize).clamp(1, energies.len());
+    let mut sum: f64 = energies[..window].iter().map(|&e| e as f64).sum();
+    let mut best = (sum, 0);
+    
+    for start in 1..=energies.len() - window {
+        sum += energies[start + window - 1] as f64 - energies[start - 1] as f64;
+        if sum > best.0 {
+            best = (sum, start);
+        }
+    }
+    
+    Some((best.1 as f64 * frame_seconds, (best.1 + window) as f64 * frame_seconds))
+}
+
+/// 由语音区域推算静音区域（语音区域之间以及首尾的空隙）
+pub fn silence_regions(speech_regions: &[(f64, f64)], total_duration: f64) -> SpeechRegions {
+    let mut silences = Vec::new();
+    let mut last_end = 0.0;
+    
+    for &(start, end) in speech_regions {
+        if start > last_end {
+            silences.push((last_end, start));
+        }
+        last_end = end;
+    }
+    
+    if total_duration > last_end && !speech_regions.is_empty() {
+        silences.push((last_end, total_duration));
+    }
+    
+    silences
+}
+
+/// 按目标片段时长放置切割点，每个切割点吸附到 `snap_window` 秒内离它最近的静音
+/// 
+/// 下一个切割点从上一个（吸附后的）切割点起算，片段时长保持在目标附近。
+/// 窗口内没有静音时按原位置切割；吸附到静音中点（静音超出窗口时取窗口内部分的中点）。
+/// 距结尾不足半个目标时长时不再切割，避免最后一段过短
+pub fn target_cut_points(total_duration: f64, target_length: f64, snap_window: f64, silences: &[(f64, f64)]) -> Vec<f64> {
+    let mut cut_points = Vec::new();
+    if target_length <= 0.0 {
+        return cut_points;
+    }
+    
+    let mut last = 0.0;
+    while total_duration - (last + target_length) >= target_length / 2.0 {
+        let ideal = last + target_length;
+        let (low, high) = ((ideal - snap_window).max(last), ideal + snap_window);
+        let snapped = silences.iter()
+            .filter(|&&(start, end)| start < high && end > low)
+            .map(|&(start, end)| (start.max(low) + end.min(high)) / 2.0)
+            .min_by(|a, b| (a - ideal).abs().total_cmp(&(b - ideal).abs()))
+            .unwrap_or(ideal);
+        cut_points.push(snapped);
+        last = snapped;
+    }
+    cut_points
+}
+
+/// 根据每帧能量检测语音区域
+fn detect_regions(energies: &[f32], frame_seconds: f64, threshold: f32) -> SpeechRegions {
+    let mut regions: Vec<(f64, f64)> = Vec::new();
+    let mut region_start: Option<usize> = None;
+    
+    for (i, &energy) in energies.iter().enumerate() {
+        match (energy > threshold, region_start) {
+            (true, None) => region_start = Some(i),
+            (false, Some(start)) => {
+                regions.push((start as f64 * frame_seconds, i as f64 * frame_seconds));
+                region_start = None;
+            }
+            _ => {}
+        }
+    }
+    
+    if let Some(start) = region_start {
+        regions.push((start as f64 * frame_seconds, energies.len() as f64 * frame_seconds));
+    }
+    
+    // 合并间隔很短的区域
+    let mut merged: Vec<(f64, f64)> = Vec::new();
+    for (start, end) in regions {
+        if let Some(last) = merged.last_mut() {
+            if start - last.1 < MIN_GAP_SECONDS {
+                last.1 = end;
+                continue;
+            }
+        }
+        merged.push((start, end));
+    }
+    
+    // 丢弃过短的区域
+    merged.retain(|(start, end)| end - start >= MIN_REGION_SECONDS);
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_detect_regions() {
+        // 0.1 秒帧：静音 0.5s，语音 1s（中间 0.1s 停顿），静音 0.5s，0.1s 噪声
+        let mut energies = vec![0.0; 5];
+        energies.extend(vec![0.5; 5]);
+        energies.push(0.0);
+        energies.extend(vec![0.5; 4]);
+        energies.extend(vec![0.0; 5]);
+        energies.push(0.5);
+        
+        let regions = detect_regions(&energies, 0.1, 0.1);
+        assert_eq!(regions.len(), 1);
+        assert!((regions[0].0 - 0.5).abs() < 1e-9);
+        assert!((regions[0].1 - 1.5).abs() < 1e-9);
+    }
+    
+    #[test]
+    fn test_target_cut_points() {
+        // 无静音时均匀切割，最后不足半个目标时长的余量并入最后一段
+        assert_eq!(target_cut_points(100.0, 30.0, 5.0, &[]), vec![30.0, 60.0]);
+        assert_eq!(target_cut_points(100.0, 25.0, 5.0, &[]), vec![25.0, 50.0, 75.0]);
+        
+        // 吸附到窗口内最近的静音中点，后续切割点从吸附后的位置起算；超出窗口的长静音取窗口内部分的中点
+        let silences = [(27.0, 28.0), (40.0, 41.0), (56.0, 57.0), (88.0, 100.0)];
+        assert_eq!(target_cut_points(130.0, 30.0, 5.0, &silences), vec![27.5, 56.5, 89.75]);
+        
+        assert!(target_cut_points(100.0, 0.0, 5.0, &silences).is_empty());
+    }
+    
+    #[test]
+    fn test_find_loudest_window() {
+        // 0.1 秒帧：静音 1s，较弱 1s，最响 0.5s，静音 1s
+        let mut energies = vec![0.0; 10];
+        energies.extend(vec![0.1; 10]);
+        energies.extend(vec![0.8; 5]);
+        energies.extend(vec![0.0; 10]);
+        
+        let (start, end) = find_loudest_window(&energies, 0.1, 0.5).unwrap();
+        assert!((start - 2.0).abs() < 1e-9);
+        assert!((end - 2.5).abs() < 1e-9);
+        
+        // 窗口比音频长时取整段
+        assert_eq!(find_loudest_window(&energies[..5], 0.1, 3.0), None);
+        let (start, end) = find_loudest_window(&energies[18..22], 0.1, 3.0).unwrap();
+        assert!(start.abs() < 1e-9 && (end - 0.4).abs() < 1e-9);
+    }
+}
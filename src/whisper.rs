@@ -1,11 +1,230 @@
 use std::path::{Path, PathBuf};
-use std::process::{Command, Stdio};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
 use std::fs;
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, Read};
 use anyhow::{Result, anyhow};
-use crate::{WhisperModel, ProgressMessage};
+use serde::{Deserialize, Serialize};
+use crate::{config, subtitle, WhisperModel, ProgressMessage};
+use crate::error::{command_output, WhisperError};
 use std::sync::mpsc::Sender;
 
+/// 等待可取消的子进程时检查状态的间隔
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// 识别失败时错误信息中保留的 Whisper 输出行数（足够容纳 Python 异常的调用栈末尾）
+const STDERR_TAIL_LINES: usize = 20;
+
+/// 传给 Whisper 的解码参数，None（或空的提示词）时不传对应参数，使用 Whisper 的默认值
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WhisperOptions {
+    pub beam_size: Option<u32>,
+    pub best_of: Option<u32>,
+    pub temperature: Option<f32>,
+    pub initial_prompt: String,  // 引导识别专有名词、术语的提示词
+    pub word_timestamps: Option<bool>,
+    pub condition_on_previous_text: Option<bool>,
+}
+
+impl WhisperOptions {
+    /// 对应的命令行参数（openai-whisper 的写法，布尔值为 `True`/`False`）
+    pub fn args(&self) -> Vec<String> {
+        let flag = |value: bool| if value { "True" } else { "False" }.to_string();
+        let mut args = Vec::new();
+        if let Some(beam_size) = self.beam_size {
+            args.extend(["--beam_size".to_string(), beam_size.to_string()]);
+        }
+        if let Some(best_of) = self.best_of {
+            args.extend(["--best_of".to_string(), best_of.to_string()]);
+        }
+        if let Some(temperature) = self.temperature {
+            args.extend(["--temperature".to_string(), temperature.to_string()]);
+        }
+        let prompt = self.initial_prompt.trim();
+        if !prompt.is_empty() {
+            args.extend(["--initial_prompt".to_string(), prompt.to_string()]);
+        }
+        if let Some(word_timestamps) = self.word_timestamps {
+            args.extend(["--word_timestamps".to_string(), flag(word_timestamps)]);
+        }
+        if let Some(condition) = self.condition_on_previous_text {
+            args.extend(["--condition_on_previous_text".to_string(), flag(condition)]);
+        }
+        args
+    }
+}
+
+/// 取消正在进行的识别
+/// 
+/// 克隆后在界面线程调用 `cancel`：终止所有正在运行的 Whisper 子进程，后续片段不再启动
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+    children: Arc<Mutex<Vec<Child>>>,  // 正在运行的 Whisper 进程，并行识别时有多个
+}
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        for child in self.children.lock().unwrap().iter_mut() {
+            let _ = child.kill();
+        }
+    }
+    
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+    
+    /// 交给令牌持有，返回进程号，用于之后取回
+    fn register(&self, child: Child) -> u32 {
+        let id = child.id();
+        self.children.lock().unwrap().push(child);
+        id
+    }
+    
+    /// 取回进程号为 `id` 的子进程
+    fn take(&self, id: u32) -> Option<Child> {
+        let mut children = self.children.lock().unwrap();
+        let position = children.iter().position(|child| child.id() == id)?;
+        Some(children.swap_remove(position))
+    }
+    
+    /// 交给令牌持有并等待子进程结束，超过 `timeout` 时终止它并返回 None
+    /// 
+    /// 等待期间取消会终止进程并返回 `WhisperError::Cancelled`
+    pub fn wait_timeout(&self, child: Child, timeout: Duration) -> crate::error::Result<Option<ExitStatus>> {
+        let id = self.register(child);
+        let deadline = Instant::now() + timeout;
+        loop {
+            let mut child = self.take(id).ok_or(WhisperError::Cancelled)?;
+            // 取回后才取消时令牌已经找不到这个进程，在这里终止
+            let cancelled = self.is_cancelled();
+            if let Some(status) = child.try_wait()? {
+                return if cancelled { Err(WhisperError::Cancelled) } else { Ok(Some(status)) };
+            }
+            if cancelled || Instant::now() >= deadline {
+                let _ = child.kill();
+                let _ = child.wait();
+                return if cancelled { Err(WhisperError::Cancelled) } else { Ok(None) };
+            }
+            self.register(child);
+            std::thread::sleep(WAIT_POLL_INTERVAL);
+        }
+    }
+}
+
+/// 下载模型时 Python 输出中表示没有网络的错误信息
+const OFFLINE_MARKERS: &[&str] = &[
+    "URLError",
+    "Temporary failure in name resolution",
+    "getaddrinfo failed",
+    "Network is unreachable",
+    "nodename nor servname",
+];
+
+/// 模型文件名（openai-whisper 的 `_MODELS`），`large` 实际使用 large-v3
+fn model_file_name(model: WhisperModel) -> &'static str {
+    match model {
+        WhisperModel::Tiny => "tiny.pt",
+        WhisperModel::Base => "base.pt",
+        WhisperModel::Small => "small.pt",
+        WhisperModel::Medium => "medium.pt",
+        WhisperModel::Large => "large-v3.pt",
+        WhisperModel::Turbo => "large-v3-turbo.pt",
+    }
+}
+
+/// Whisper 的模型缓存目录：`$XDG_CACHE_HOME/whisper`，默认 `~/.cache/whisper`
+pub fn model_cache_dir() -> Option<PathBuf> {
+    if let Some(cache) = std::env::var_os("XDG_CACHE_HOME").filter(|v| !v.is_empty()) {
+        return Some(PathBuf::from(cache).join("whisper"));
+    }
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(|home| PathBuf::from(home).join(".cache").join("whisper"))
+}
+
+/// 缓存目录中已经下载的模型
+pub fn downloaded_models() -> Vec<WhisperModel> {
+    model_cache_dir()
+        .map(|dir| models_in(&dir))
+        .unwrap_or_default()
+}
+
+fn models_in(dir: &Path) -> Vec<WhisperModel> {
+    WhisperModel::all()
+        .into_iter()
+        .filter(|model| dir.join(model_file_name(*model)).is_file())
+        .collect()
+}
+
+/// 通过 openai-whisper 的 Python 包下载模型到缓存目录
+/// 
+/// 下载进度（0~1）通过 `on_progress` 报告。没有网络时返回说明如何手动放置模型的错误，
+/// 而不是让第一次识别卡在下载上
+pub fn download_model(model: WhisperModel, mut on_progress: impl FnMut(f32)) -> Result<()> {
+    let cache_dir = model_cache_dir().ok_or_else(|| anyhow!("Cannot determine the Whisper cache directory"))?;
+    let python = if cfg!(windows) { "python" } else { "python3" };
+    let script = "import sys, whisper; whisper._download(whisper._MODELS[sys.argv[1]], sys.argv[2], False)";
+    
+    let mut child = Command::new(python)
+        .arg("-c")
+        .arg(script)
+        .arg(model.as_str())
+        .arg(&cache_dir)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| WhisperError::spawn(python, e))?;
+    
+    // tqdm 用 \r 刷新进度条，按 \r 和 \n 拆分
+    let mut output = String::new();
+    if let Some(stderr) = child.stderr.take() {
+        let mut buffer = Vec::new();
+        for byte in BufReader::new(stderr).bytes().map_while(Result::ok) {
+            if byte == b'\r' || byte == b'\n' {
+                let line = String::from_utf8_lossy(&buffer).to_string();
+                if let Some(percent) = parse_download_percent(&line) {
+                    on_progress(percent / 100.0);
+                } else if !line.trim().is_empty() {
+                    output.push_str(&line);
+                    output.push('\n');
+                }
+                buffer.clear();
+            } else {
+                buffer.push(byte);
+            }
+        }
+        output.push_str(&String::from_utf8_lossy(&buffer));
+    }
+    
+    if child.wait()?.success() {
+        return Ok(());
+    }
+    
+    if OFFLINE_MARKERS.iter().any(|marker| output.contains(marker)) {
+        Err(anyhow!(
+            "No network connection. Download {} on a machine with internet access and copy it to {:?}",
+            model_file_name(model),
+            cache_dir
+        ))
+    } else if output.contains("No module named") {
+        Err(anyhow!("The openai-whisper Python package is not installed for {}", python))
+    } else {
+        let last = output.lines().rev().find(|l| !l.trim().is_empty()).unwrap_or("unknown error");
+        Err(anyhow!("Model download failed: {}", last.trim()))
+    }
+}
+
+/// 解析 tqdm 进度条中的百分比，如 ` 45%|████▌     | 65.2M/139M`
+fn parse_download_percent(line: &str) -> Option<f32> {
+    let (before, _) = line.split_once("%|")?;
+    before.split_whitespace().last()?.parse().ok()
+}
+
 /// 使用 Whisper 识别音频（保留用于兼容性）
 #[allow(dead_code)]
 pub fn recognize_audio(
@@ -14,9 +233,13 @@ pub fn recognize_audio(
     language: Option<&str>,
 ) -> Result<(PathBuf, String)> {
     let output_dir = audio_path.parent().unwrap();
-    let output_name = audio_path.file_stem().unwrap().to_string_lossy();
+    let started = SystemTime::now();
     
-    let mut cmd = Command::new("whisper");
+    let mut cmd = Command::new(config::whisper());
+    // Whisper 用 ffmpeg 读取音频，ffmpeg 不在 PATH 中时使用设置的路径
+    if let Some(path) = config::search_path() {
+        cmd.env("PATH", path);
+    }
     
     cmd.arg(audio_path)
         .arg("--model")
@@ -31,19 +254,14 @@ pub fn recognize_audio(
         cmd.arg("--language").arg(lang);
     }
     
-    let output = cmd.output()?;
+    let output = command_output(&mut cmd)?;
     
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(anyhow!("Whisper recognition failed: {}", stderr));
+        return Err(WhisperError::process_failed("Whisper recognition failed", stderr).into());
     }
     
-    // Whisper 输出的 SRT 文件名
-    let srt_path = output_dir.join(format!("{}.srt", output_name));
-    
-    if !srt_path.exists() {
-        return Err(anyhow!("Subtitle file not found"));
-    }
+    let srt_path = find_output_srt(audio_path, output_dir, started)?;
     
     // 读取并提取文本内容
     let text = extract_text_from_srt(&srt_path)?;
@@ -52,24 +270,53 @@ pub fn recognize_audio(
 }
 
 /// 使用 Whisper 识别音频（实时输出版本）
+/// 
+/// `threads` 限制推理使用的 CPU 线程数，0 表示使用全部核心（默认行为）。
+/// 非 0 时会同时传入 `--threads` 并设置线程相关环境变量：
+/// - openai-whisper（PyTorch CLI）：`--threads` 调用 `torch.set_num_threads`，
+///   `OMP_NUM_THREADS`/`MKL_NUM_THREADS` 限制 numpy 等底层库
+/// - whisper.cpp 风格的后端：接受 `--threads`
+/// - 使用 GPU（CUDA/MPS）推理时线程数只影响 CPU 端的预处理
+/// 
+/// `options` 中设置了的解码参数原样追加到命令行，见 [`WhisperOptions::args`]。
+/// 
+/// 字幕写到 `output_dir`。`precise_timing` 为 true 或开启了逐词时间时让 Whisper 输出 JSON，
+/// 由 JSON 生成 SRT 并把 JSON 留在同目录，之后读取字幕时使用其中未取整的时间（见 `subtitle::load_segment_subtitles`）。
+/// 运行期间进程由 `cancel` 持有，取消后返回错误
+#[allow(clippy::too_many_arguments)]
 pub fn recognize_audio_realtime(
     audio_path: &Path,
+    output_dir: &Path,
     model: WhisperModel,
     language: Option<&str>,
+    threads: usize,
+    precise_timing: bool,
+    options: &WhisperOptions,
     tx: Sender<ProgressMessage>,
     current: usize,
     total: usize,
+    cancel: &CancelToken,
 ) -> Result<(PathBuf, String)> {
-    let output_dir = audio_path.parent().unwrap();
-    let output_name = audio_path.file_stem().unwrap().to_string_lossy();
+    if cancel.is_cancelled() {
+        return Err(WhisperError::Cancelled.into());
+    }
+    
+    let started = SystemTime::now();
+    // 逐词时间只在 JSON 输出中
+    let json_output = precise_timing || options.word_timestamps == Some(true);
+    let output_format = if json_output { "json" } else { "srt" };
     
-    let mut cmd = Command::new("whisper");
+    let mut cmd = Command::new(config::whisper());
+    // Whisper 用 ffmpeg 读取音频，ffmpeg 不在 PATH 中时使用设置的路径
+    if let Some(path) = config::search_path() {
+        cmd.env("PATH", path);
+    }
     
     cmd.arg(audio_path)
         .arg("--model")
         .arg(model.as_str())
         .arg("--output_format")
-        .arg("srt")
+        .arg(output_format)
         .arg("--output_dir")
         .arg(output_dir)
         .stdout(Stdio::piped())
@@ -80,58 +327,102 @@ pub fn recognize_audio_realtime(
         cmd.arg("--language").arg(lang);
     }
     
+    // 如果限制了线程数，添加线程参数
+    if threads > 0 {
+        cmd.arg("--threads").arg(threads.to_string())
+            .env("OMP_NUM_THREADS", threads.to_string())
+            .env("MKL_NUM_THREADS", threads.to_string());
+    }
+    
+    let extra_args = options.args();
+    cmd.args(&extra_args);
+    
     // 打印将要执行的命令（用于调试）
     println!("🚀 Starting Whisper recognition [{}/{}]", current, total);
     println!("   Model: {}", model.as_str());
     println!("   Language: {:?}", language);
+    println!("   Threads: {}", if threads > 0 { threads.to_string() } else { "all".to_string() });
     println!("   Audio: {:?}", audio_path);
-    println!("   Command: whisper {} --model {} --output_format srt --output_dir {:?} {}", 
+    println!("   Command: whisper {} --model {} --output_format {} --output_dir {:?} {} {} {}", 
         audio_path.display(),
         model.as_str(),
+        output_format,
         output_dir,
-        language.map(|l| format!("--language {}", l)).unwrap_or_default()
+        language.map(|l| format!("--language {}", l)).unwrap_or_default(),
+        if threads > 0 { format!("--threads {}", threads) } else { String::new() },
+        extra_args.join(" ")
     );
     
     let mut child = match cmd.spawn() {
         Ok(c) => c,
         Err(e) => {
             eprintln!("❌ Failed to spawn whisper process: {}", e);
-            return Err(anyhow!("Failed to spawn whisper: {}", e));
+            return Err(WhisperError::spawn("whisper", e).into());
         }
     };
     
     println!("   Process spawned with PID: {:?}", child.id());
     
-    // 读取 stderr（Whisper 将进度输出到 stderr）
-    if let Some(stderr) = child.stderr.take() {
+    // 交给取消令牌持有，读取输出期间可以从界面线程终止
+    let stderr = child.stderr.take();
+    let child_id = cancel.register(child);
+    if cancel.is_cancelled() {
+        cancel.cancel();  // 启动期间已经取消
+    }
+    
+    // 读取 stderr（Whisper 将进度输出到 stderr），保留最后几行用于错误信息。
+    // 错误信息不按下面的规则过滤，只去掉进度条，CUDA 显存不足、语言代码无效等原因都能原样看到
+    let mut stderr_tail: Vec<String> = Vec::new();
+    if let Some(stderr) = stderr {
+        // 按字节分行，非 UTF-8 的输出也继续读取，否则管道写满后 Whisper 会卡住
         let reader = BufReader::new(stderr);
-        for line in reader.lines() {
-            if let Ok(line) = line {
-                println!("   Whisper output: {}", line);  // 打印所有输出用于调试
-                // 只发送包含有用信息的行
-                if !line.trim().is_empty() && (line.contains("[") || line.contains("Detecting language")) {
-                    let msg = format!("[{}/{}] {}", current, total, line.trim());
-                    let _ = tx.send(ProgressMessage::RealtimeOutput(msg));
+        for line in reader.split(b'\n').map_while(Result::ok) {
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches('\r');
+            println!("   Whisper output: {}", line);  // 打印所有输出用于调试
+            if let Some(error_line) = error_line(line) {
+                if stderr_tail.len() == STDERR_TAIL_LINES {
+                    stderr_tail.remove(0);
                 }
+                stderr_tail.push(error_line);
+            }
+            // 进度只发送包含有用信息的行
+            if !line.trim().is_empty() && (line.contains("[") || line.contains("Detecting language")) {
+                let msg = format!("[{}/{}] {}", current, total, line.trim());
+                let _ = tx.send(ProgressMessage::RealtimeOutput(msg));
             }
         }
     }
     
+    // stderr 结束说明进程已退出（或被终止）
+    let mut child = cancel.take(child_id)
+        .ok_or_else(|| anyhow!("Whisper process handle lost"))?;
     let status = child.wait()?;
     
     println!("   Whisper process finished with status: {:?}", status);
     
+    if cancel.is_cancelled() {
+        return Err(WhisperError::Cancelled.into());
+    }
+    
     if !status.success() {
         eprintln!("❌ Whisper recognition failed with status: {:?}", status);
-        return Err(anyhow!("Whisper recognition failed"));
+        let details = if stderr_tail.is_empty() {
+            format!("{} without error output", status)
+        } else {
+            format!("{}\n{}", status, stderr_tail.join("\n"))
+        };
+        return Err(WhisperError::process_failed("Whisper recognition failed", details).into());
     }
     
-    // Whisper 输出的 SRT 文件名
-    let srt_path = output_dir.join(format!("{}.srt", output_name));
-    
-    if !srt_path.exists() {
-        return Err(anyhow!("Subtitle file not found"));
-    }
+    let srt_path = if json_output {
+        srt_from_json_output(audio_path, output_dir, started)?
+    } else {
+        let srt_path = find_output_srt(audio_path, output_dir, started)?;
+        // 之前用 JSON 识别留下的时间已经过期
+        let _ = fs::remove_file(srt_path.with_extension("json"));
+        srt_path
+    };
     
     // 读取并提取文本内容
     let text = extract_text_from_srt(&srt_path)?;
@@ -139,6 +430,76 @@ pub fn recognize_audio_realtime(
     Ok((srt_path, text))
 }
 
+/// Whisper JSON 输出（`--word_timestamps True`）中所有的逐词时间 (开始, 结束, 词)，按片段顺序排列
+pub fn parse_word_timestamps(content: &str) -> Result<Vec<(f64, f64, String)>> {
+    Ok(subtitle::parse_whisper_json(content)?
+        .into_iter()
+        .flat_map(|entry| entry.words)
+        .collect())
+}
+
+/// 把 Whisper 的 JSON 输出整理为 `{file_stem}.json`，并在旁边生成同名 SRT，返回 SRT 路径
+fn srt_from_json_output(audio_path: &Path, output_dir: &Path, started: SystemTime) -> Result<PathBuf> {
+    let found = find_output(audio_path, output_dir, started, "json")?;
+    let stem = audio_path.file_stem().unwrap_or_default().to_string_lossy();
+    let json_path = output_dir.join(format!("{}.json", stem));
+    if found != json_path {
+        fs::rename(&found, &json_path)?;
+    }
+    
+    let content = fs::read_to_string(&json_path)?;
+    let subtitles = subtitle::parse_whisper_json(&content)
+        .map_err(|e| WhisperError::ParseError(format!("{:?}: {}", json_path, e)))?;
+    let srt_path = json_path.with_extension("srt");
+    fs::write(&srt_path, subtitle::to_srt_string(&subtitles))?;
+    Ok(srt_path)
+}
+
+/// 判定输出文件为本次生成时允许的时间误差（部分文件系统的修改时间精度较低）
+const MTIME_SLACK: Duration = Duration::from_secs(2);
+
+/// 查找 Whisper 为 `audio_path` 生成的 SRT 文件
+fn find_output_srt(audio_path: &Path, output_dir: &Path, started: SystemTime) -> Result<PathBuf> {
+    find_output(audio_path, output_dir, started, "srt")
+}
+
+/// 查找 Whisper 为 `audio_path` 生成的输出文件（扩展名为 `extension`）
+/// 
+/// 通常为 `{file_stem}.srt`，但有的版本保留完整的文件名（如 `talk.v2.mp3` 输出 `talk.v2.mp3.srt`）。
+/// 找不到精确的文件名时，在 `output_dir` 中选择识别开始后修改、文件名以 `{file_stem}.` 开头的最新文件。
+/// 只比输入短的文件名（如 `talk.srt`）不接受，避免把共用目录中其他输入的旧字幕当成结果
+fn find_output(audio_path: &Path, output_dir: &Path, started: SystemTime, extension: &str) -> Result<PathBuf> {
+    let stem = audio_path.file_stem().unwrap_or_default().to_string_lossy();
+    
+    let exact = output_dir.join(format!("{}.{}", stem, extension));
+    if exact.exists() {
+        return Ok(exact);
+    }
+    
+    let since = started.checked_sub(MTIME_SLACK).unwrap_or(started);
+    let mut newest: Option<(SystemTime, PathBuf)> = None;
+    
+    for entry in fs::read_dir(output_dir)?.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some(extension) {
+            continue;
+        }
+        let candidate = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+        if !candidate.strip_prefix(stem.as_ref()).is_some_and(|rest| rest.starts_with('.')) {
+            continue;
+        }
+        let Ok(modified) = entry.metadata().and_then(|m| m.modified()) else { continue };
+        if modified >= since && newest.as_ref().is_none_or(|(time, _)| modified > *time) {
+            newest = Some((modified, path));
+        }
+    }
+    
+    newest.map(|(_, path)| path).ok_or_else(|| WhisperError::process_failed(
+        "Whisper finished but produced no subtitle file",
+        format!("no {} for {:?} in {:?}", extension.to_uppercase(), audio_path.file_name().unwrap_or_default(), output_dir),
+    ).into())
+}
+
 /// 从 SRT 文件中提取纯文本
 fn extract_text_from_srt(srt_path: &Path) -> Result<String> {
     let content = fs::read_to_string(srt_path)?;
@@ -158,3 +519,145 @@ fn extract_text_from_srt(srt_path: &Path) -> Result<String> {
     Ok(text_lines.join(" "))
 }
 
+/// 错误信息中保留的 stderr 行：tqdm 用 `\r` 刷新的行只取最后一次的内容，空行和进度条返回 None
+fn error_line(line: &str) -> Option<String> {
+    let line = line.rsplit('\r').find(|part| !part.trim().is_empty())?.trim();
+    if line.contains("%|") {
+        return None;
+    }
+    Some(line.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_whisper_options_args() {
+        assert!(WhisperOptions::default().args().is_empty());
+        
+        let options = WhisperOptions {
+            beam_size: Some(5),
+            temperature: Some(0.2),
+            initial_prompt: "  Kubernetes, etcd  ".to_string(),
+            condition_on_previous_text: Some(false),
+            ..Default::default()
+        };
+        assert_eq!(options.args(), [
+            "--beam_size", "5",
+            "--temperature", "0.2",
+            "--initial_prompt", "Kubernetes, etcd",
+            "--condition_on_previous_text", "False",
+        ]);
+        
+        // 只有空白的提示词不传
+        let blank = WhisperOptions { initial_prompt: "   ".to_string(), word_timestamps: Some(true), ..Default::default() };
+        assert_eq!(blank.args(), ["--word_timestamps", "True"]);
+    }
+    
+    #[test]
+    fn test_error_line() {
+        assert_eq!(error_line("  RuntimeError: CUDA out of memory.  "), Some("RuntimeError: CUDA out of memory.".to_string()));
+        assert_eq!(error_line("whisper: error: argument --language: invalid choice: 'xx'"), Some("whisper: error: argument --language: invalid choice: 'xx'".to_string()));
+        assert_eq!(error_line(" 45%|████▌     | 1350/3000 [00:05<00:06, 270frames/s]\r100%|██████████| 3000/3000 [00:10<00:00]"), None);
+        assert_eq!(error_line("Loading\rDetected language: English\r"), Some("Detected language: English".to_string()));
+        assert_eq!(error_line("   "), None);
+    }
+    
+    #[cfg(unix)]
+    #[test]
+    fn test_cancel_kills_child() {
+        let cancel = CancelToken::default();
+        let first = cancel.register(Command::new("sleep").arg("30").spawn().unwrap());
+        let second = cancel.register(Command::new("sleep").arg("30").spawn().unwrap());
+        
+        let start = std::time::Instant::now();
+        cancel.clone().cancel();
+        let first = cancel.take(first).unwrap().wait().unwrap();
+        let second = cancel.take(second).unwrap().wait().unwrap();
+        
+        assert!(cancel.is_cancelled());
+        assert!(!first.success() && !second.success());
+        assert!(cancel.take(0).is_none());
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+    }
+    
+    #[cfg(unix)]
+    #[test]
+    fn test_wait_timeout() {
+        let cancel = CancelToken::default();
+        let status = cancel.wait_timeout(Command::new("true").spawn().unwrap(), Duration::from_secs(5)).unwrap();
+        assert!(status.unwrap().success());
+        
+        // 超时后终止
+        let start = Instant::now();
+        let status = cancel.wait_timeout(Command::new("sleep").arg("30").spawn().unwrap(), Duration::from_millis(200)).unwrap();
+        assert!(status.is_none());
+        assert!(start.elapsed() < Duration::from_secs(5));
+        
+        // 等待期间取消
+        let waiting = cancel.clone();
+        let handle = std::thread::spawn(move || waiting.wait_timeout(Command::new("sleep").arg("30").spawn().unwrap(), Duration::from_secs(60)));
+        std::thread::sleep(Duration::from_millis(200));
+        cancel.cancel();
+        assert!(matches!(handle.join().unwrap(), Err(WhisperError::Cancelled)));
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+    
+    #[test]
+    fn test_find_output_srt_multi_dot() {
+        let dir = std::env::temp_dir().join(format!("whisper_output_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let audio = dir.join("talk.v2.mp3");
+        let started = SystemTime::now();
+        
+        // 没有输出文件
+        assert!(find_output_srt(&audio, &dir, started).is_err());
+        
+        // 保留完整文件名的输出
+        let full_name = dir.join("talk.v2.mp3.srt");
+        fs::write(&full_name, "").unwrap();
+        assert_eq!(find_output_srt(&audio, &dir, started).unwrap(), full_name);
+        
+        // 精确的文件名优先
+        let exact = dir.join("talk.v2.srt");
+        fs::write(&exact, "").unwrap();
+        assert_eq!(find_output_srt(&audio, &dir, started).unwrap(), exact);
+        
+        // 不相关的字幕不匹配
+        let other = dir.join("other.mp3");
+        assert!(find_output_srt(&other, &dir, started).is_err());
+        
+        fs::remove_dir_all(&dir).unwrap();
+    }
+    
+    #[test]
+    fn test_find_output_srt_rejects_shorter_names() {
+        let dir = std::env::temp_dir().join(format!("whisper_output_prefix_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let started = SystemTime::now();
+        
+        // 共用目录中其他输入的字幕：文件名只是输入的前缀，或输入只是文件名的前缀
+        fs::write(dir.join("talk.srt"), "").unwrap();
+        fs::write(dir.join("a.srt"), "").unwrap();
+        fs::write(dir.join("abcdef.srt"), "").unwrap();
+        assert!(find_output_srt(&dir.join("talk.v2.mp3"), &dir, started).is_err());
+        assert!(find_output_srt(&dir.join("abc.mp3"), &dir, started).is_err());
+        
+        fs::remove_dir_all(&dir).unwrap();
+    }
+    
+    #[test]
+    fn test_model_cache_helpers() {
+        assert_eq!(parse_download_percent(" 45%|████▌     | 65.2M/139M [00:03<00:04, 18.1MiB/s]"), Some(45.0));
+        assert_eq!(parse_download_percent("100%|██████████| 139M/139M"), Some(100.0));
+        assert_eq!(parse_download_percent("Downloading..."), None);
+        
+        let dir = std::env::temp_dir().join(format!("whisper_models_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("base.pt"), "").unwrap();
+        fs::write(dir.join("large-v3-turbo.pt"), "").unwrap();
+        assert_eq!(models_in(&dir), vec![WhisperModel::Base, WhisperModel::Turbo]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
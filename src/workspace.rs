@@ -1,14 +1,59 @@
 use std::path::{Path, PathBuf};
+use std::collections::BTreeMap;
 use std::fs;
 use serde::{Serialize, Deserialize};
-use anyhow::Result;
+use anyhow::{Result, anyhow};
+use crate::ffmpeg::Segment;
+use crate::subtitle::SubtitleEntry;
+use crate::whisper::WhisperOptions;
+use crate::{WhisperLanguage, WhisperModel};
+
+/// 保存工作区时如何处理引用的媒体文件
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum MediaTransfer {
+    #[default]
+    Keep,  // 保留在原位置，只记录路径
+    Copy,  // 复制到工作区
+    Move,  // 移动到工作区
+}
+
+impl MediaTransfer {
+    pub fn as_str(&self) -> &str {
+        match self {
+            MediaTransfer::Keep => "Keep in place",
+            MediaTransfer::Copy => "Copy into workspace",
+            MediaTransfer::Move => "Move into workspace",
+        }
+    }
+    
+    pub fn all() -> Vec<MediaTransfer> {
+        vec![
+            MediaTransfer::Keep,
+            MediaTransfer::Copy,
+            MediaTransfer::Move,
+        ]
+    }
+}
+
+/// 识别时使用的 Whisper 设置，随工作区保存，继续识别时与最初一致
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecognitionSettings {
+    pub model: WhisperModel,
+    pub language: WhisperLanguage,
+    pub custom_language_code: String,
+    pub threads: usize,  // 0 表示使用全部核心
+    pub precise_timing: bool,
+    #[serde(default)]  // 旧版本没有保存解码参数
+    pub whisper_options: WhisperOptions,  // 束搜索、温度、提示词等
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WorkspaceState {
     pub video_path: Option<PathBuf>,
     pub audio_path: Option<PathBuf>,
     pub cut_points: Vec<f64>,
-    pub audio_segments: Vec<PathBuf>,
+    pub audio_segments: Vec<Segment>,  // 旧版本只保存了路径，打开时按切割点补上时间范围
     #[serde(default)]  // 兼容旧的 workspace_state.json，如果没有这个字段就用空数组
     pub completed_segments: Vec<usize>,  // 已完成识别的片段索引
     pub manual_segment: Option<PathBuf>,
@@ -18,6 +63,12 @@ pub struct WorkspaceState {
     pub manual_end_time: String,
     pub total_duration: f64,
     pub workspace_dir: PathBuf,
+    #[serde(default)]  // 兼容旧版本
+    pub locked_subtitles: Vec<SubtitleEntry>,  // 锁定的字幕
+    #[serde(default)]  // 兼容旧版本
+    pub failed_segments: BTreeMap<usize, String>,  // 识别失败的片段索引及原因
+    #[serde(default)]  // 旧版本没有保存识别设置，打开时保持当前设置
+    pub recognition_settings: Option<RecognitionSettings>,
 }
 
 impl WorkspaceState {
@@ -31,7 +82,9 @@ impl WorkspaceState {
     pub fn load(workspace_dir: &Path) -> Result<Self> {
         let state_file = workspace_dir.join("workspace_state.json");
         let json = fs::read_to_string(state_file)?;
-        let mut state: WorkspaceState = serde_json::from_str(&json)?;
+        let mut value: serde_json::Value = serde_json::from_str(&json)?;
+        upgrade_segments(&mut value)?;
+        let mut state: WorkspaceState = serde_json::from_value(value)?;
         state.workspace_dir = workspace_dir.to_path_buf();
         Ok(state)
     }
@@ -41,6 +94,21 @@ impl WorkspaceState {
     }
 }
 
+/// 旧版本的 `audio_segments` 只有路径：按切割点换算成带时间范围的片段
+fn upgrade_segments(value: &mut serde_json::Value) -> Result<()> {
+    let Some(segments) = value.get("audio_segments").and_then(|v| v.as_array()) else { return Ok(()) };
+    if !segments.iter().any(|segment| segment.is_string()) {
+        return Ok(());
+    }
+    
+    let paths: Vec<PathBuf> = serde_json::from_value(value["audio_segments"].clone())?;
+    let cut_points: Vec<f64> = serde_json::from_value(value["cut_points"].clone())?;
+    let segments = crate::ffmpeg::segments_from_cut_points(paths, &cut_points)
+        .map_err(|e| anyhow!("Invalid workspace: {}", e))?;
+    value["audio_segments"] = serde_json::to_value(segments)?;
+    Ok(())
+}
+
 /// 创建工作区目录结构
 pub fn create_workspace_structure(base_dir: &Path) -> Result<()> {
     fs::create_dir_all(base_dir)?;
@@ -49,9 +117,217 @@ pub fn create_workspace_structure(base_dir: &Path) -> Result<()> {
     Ok(())
 }
 
+/// 把工作区引用的媒体文件复制或移动到工作区内，并改写 `state` 中的路径
+/// 
+/// 提取的音频放在工作区根目录，音频片段（连同已识别的 SRT）放在 `segments/`。
+/// 原视频/直接导入的音频不会被移动；已在工作区内或已不存在的文件保持不变
+pub fn import_media(state: &mut WorkspaceState, transfer: MediaTransfer) -> Result<()> {
+    if transfer == MediaTransfer::Keep {
+        return Ok(());
+    }
+    
+    let workspace_dir = state.workspace_dir.clone();
+    let segments_dir = workspace_dir.join("segments");
+    fs::create_dir_all(&segments_dir)?;
+    
+    // 提取的音频（直接导入的音频即源文件本身，不处理）
+    if let Some(audio_path) = state.audio_path.clone() {
+        if state.video_path.as_ref() != Some(&audio_path) {
+            state.audio_path = Some(import_file(&audio_path, &workspace_dir, &workspace_dir, transfer)?);
+        }
+    }
+    
+    for segment in state.audio_segments.iter_mut() {
+        segment.path = import_file(&segment.path, &segments_dir, &workspace_dir, transfer)?;
+    }
+    
+    if let Some(manual_segment) = state.manual_segment.clone() {
+        state.manual_segment = Some(import_file(&manual_segment, &segments_dir, &workspace_dir, transfer)?);
+    }
+    
+    Ok(())
+}
+
+/// 导入单个媒体文件及其同名 SRT，返回新路径
+fn import_file(path: &Path, target_dir: &Path, workspace_dir: &Path, transfer: MediaTransfer) -> Result<PathBuf> {
+    if is_in_workspace(path, workspace_dir) || !path.exists() {
+        return Ok(path.to_path_buf());
+    }
+    
+    let target = unique_target(target_dir, path)?;
+    transfer_file(path, &target, transfer)?;
+    
+    // 已识别的字幕（及精确时间的 JSON）跟随音频一起处理，保持同名以便断点续传检测
+    for extension in ["srt", "json"] {
+        let subtitle_path = path.with_extension(extension);
+        if subtitle_path.exists() {
+            transfer_file(&subtitle_path, &target.with_extension(extension), transfer)?;
+        }
+    }
+    
+    Ok(target)
+}
+
+/// 在目标目录中选择不冲突的文件名（同时检查同名 SRT）
+pub fn unique_target(target_dir: &Path, path: &Path) -> Result<PathBuf> {
+    let file_name = path.file_name()
+        .ok_or_else(|| anyhow!("Invalid media path: {:?}", path))?;
+    let stem = path.file_stem().unwrap().to_string_lossy();
+    let extension = path.extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    
+    let mut candidate = target_dir.join(file_name);
+    let mut n = 1;
+    while candidate.exists() || candidate.with_extension("srt").exists() {
+        candidate = target_dir.join(format!("{}_{}{}", stem, n, extension));
+        n += 1;
+    }
+    
+    Ok(candidate)
+}
+
+/// 把片段旁边的字幕（旧项目的布局）移动到 `subtitles_dir`，返回迁移的文件数
+/// 
+/// `subtitles_dir` 中已有同名字幕时保留新位置的文件。源目录只读时只复制，原文件留在原处
+pub fn migrate_segment_subtitles(segments: &[PathBuf], subtitles_dir: &Path) -> Result<usize> {
+    fs::create_dir_all(subtitles_dir)?;
+    let mut migrated = 0;
+    
+    for segment in segments {
+        let beside = segment.with_extension("srt");
+        let Some(file_name) = beside.file_name() else { continue };
+        let target = subtitles_dir.join(file_name);
+        if !beside.exists() || target.exists() {
+            continue;
+        }
+        
+        if transfer_file(&beside, &target, MediaTransfer::Move).is_err() {
+            transfer_file(&beside, &target, MediaTransfer::Copy)?;
+        }
+        // 精确时间的 JSON 与字幕放在一起
+        let json = beside.with_extension("json");
+        if json.exists() && transfer_file(&json, &target.with_extension("json"), MediaTransfer::Move).is_err() {
+            transfer_file(&json, &target.with_extension("json"), MediaTransfer::Copy)?;
+        }
+        migrated += 1;
+    }
+    
+    Ok(migrated)
+}
+
+/// 复制或移动文件；移动时先校验副本大小一致再删除原文件
+fn transfer_file(source: &Path, target: &Path, transfer: MediaTransfer) -> Result<()> {
+    if transfer == MediaTransfer::Move && fs::rename(source, target).is_ok() {
+        return Ok(());
+    }
+    
+    // 复制（跨文件系统移动也走这里）
+    fs::copy(source, target)?;
+    let source_len = fs::metadata(source)?.len();
+    let target_len = fs::metadata(target)?.len();
+    if source_len != target_len {
+        let _ = fs::remove_file(target);
+        return Err(anyhow!("Copy of {:?} is incomplete ({} of {} bytes)", source, target_len, source_len));
+    }
+    
+    if transfer == MediaTransfer::Move {
+        fs::remove_file(source)?;
+    }
+    
+    Ok(())
+}
+
 /// 检查路径是否在工作区内
-#[allow(dead_code)]
 pub fn is_in_workspace(path: &Path, workspace_dir: &Path) -> bool {
     path.starts_with(workspace_dir)
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_import_media_handles_collisions() {
+        let base = std::env::temp_dir().join(format!("whisper_ws_test_{}", std::process::id()));
+        let source_dir = base.join("source");
+        let workspace_dir = base.join("workspace");
+        fs::create_dir_all(&source_dir).unwrap();
+        create_workspace_structure(&workspace_dir).unwrap();
+        
+        let segment = source_dir.join("talk_000.mp3");
+        fs::write(&segment, b"audio").unwrap();
+        fs::write(segment.with_extension("srt"), b"1\n").unwrap();
+        // 工作区中已有同名文件
+        fs::write(workspace_dir.join("segments").join("talk_000.mp3"), b"other").unwrap();
+        
+        let mut state = WorkspaceState {
+            video_path: None,
+            audio_path: None,
+            cut_points: vec![],
+            audio_segments: vec![Segment { path: segment.clone(), start: 0.0, end: None }],
+            completed_segments: vec![],
+            manual_segment: None,
+            manual_start_time: String::new(),
+            manual_end_time: String::new(),
+            total_duration: 0.0,
+            workspace_dir: workspace_dir.clone(),
+            locked_subtitles: vec![],
+            failed_segments: BTreeMap::new(),
+            recognition_settings: None,
+        };
+        import_media(&mut state, MediaTransfer::Move).unwrap();
+        
+        let moved = workspace_dir.join("segments").join("talk_000_1.mp3");
+        assert_eq!(state.audio_segments[0].path, moved);
+        assert_eq!(fs::read(&moved).unwrap(), b"audio");
+        assert!(moved.with_extension("srt").exists());
+        assert!(!segment.exists());
+        
+        // 旧布局的字幕迁移到 subtitles/
+        let subtitles_dir = workspace_dir.join("subtitles");
+        assert_eq!(migrate_segment_subtitles(std::slice::from_ref(&moved), &subtitles_dir).unwrap(), 1);
+        assert!(subtitles_dir.join("talk_000_1.srt").exists());
+        assert!(!moved.with_extension("srt").exists());
+        assert_eq!(migrate_segment_subtitles(std::slice::from_ref(&moved), &subtitles_dir).unwrap(), 0);
+        
+        // 失败原因随工作区保存
+        state.failed_segments.insert(0, "Invalid data found when processing input".to_string());
+        state.save(&workspace_dir).unwrap();
+        let loaded = WorkspaceState::load(&workspace_dir).unwrap();
+        assert_eq!(loaded.failed_segments, state.failed_segments);
+        assert_eq!(loaded.recognition_settings, None);
+        
+        // 识别设置随工作区保存
+        state.recognition_settings = Some(RecognitionSettings {
+            model: WhisperModel::Small,
+            language: WhisperLanguage::Custom,
+            custom_language_code: "ko".to_string(),
+            threads: 4,
+            precise_timing: true,
+            whisper_options: WhisperOptions {
+                beam_size: Some(5),
+                initial_prompt: "Kubernetes, etcd".to_string(),
+                ..Default::default()
+            },
+        });
+        state.save(&workspace_dir).unwrap();
+        let loaded = WorkspaceState::load(&workspace_dir).unwrap();
+        assert_eq!(loaded.recognition_settings, state.recognition_settings);
+        assert_eq!(loaded.audio_segments, state.audio_segments);
+        
+        // 旧版本只保存了片段路径，按切割点补上时间范围；数量不符时拒绝打开
+        let state_file = workspace_dir.join("workspace_state.json");
+        let mut legacy: serde_json::Value = serde_json::from_str(&fs::read_to_string(&state_file).unwrap()).unwrap();
+        legacy["audio_segments"] = serde_json::json!(["a_000.mp3", "a_001.mp3"]);
+        legacy["cut_points"] = serde_json::json!([12.5]);
+        fs::write(&state_file, legacy.to_string()).unwrap();
+        let loaded = WorkspaceState::load(&workspace_dir).unwrap();
+        assert_eq!(loaded.audio_segments[1], Segment { path: PathBuf::from("a_001.mp3"), start: 12.5, end: None });
+        legacy["cut_points"] = serde_json::json!([]);
+        fs::write(&state_file, legacy.to_string()).unwrap();
+        assert!(WorkspaceState::load(&workspace_dir).is_err());
+        
+        let _ = fs::remove_dir_all(&base);
+    }
+}